@@ -0,0 +1,117 @@
+//! Bearer token authentication of the operator routes.
+//!
+//! The settlement history, its export and statistics, and the configuration reload are served
+//! only to requests with an `Authorization: Bearer <token>` header, checked by the
+//! [`require_admin_token`] middleware against the [`AdminToken`]. Without a token configured,
+//! those routes are not served at all.
+//!
+//! Environment:
+//! - `ADMIN_API_TOKEN` – token of the operator routes, which are disabled if unset
+
+use axum::Json;
+use axum::extract::{Request, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use crate::types::ErrorResponse;
+
+pub const ENV_ADMIN_API_TOKEN: &str = "ADMIN_API_TOKEN";
+
+/// The token operators authenticate with.
+#[derive(Clone)]
+pub struct AdminToken(String);
+
+impl AdminToken {
+    /// Fails on an empty token, which would let anyone in.
+    pub fn new(token: impl Into<String>) -> Result<Self, String> {
+        let token = token.into();
+        if token.trim().is_empty() {
+            return Err(format!(
+                "env {ENV_ADMIN_API_TOKEN}: token must not be empty"
+            ));
+        }
+        Ok(Self(token))
+    }
+
+    /// The token from `ADMIN_API_TOKEN`, or `None` if unset.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        std::env::var(ENV_ADMIN_API_TOKEN)
+            .ok()
+            .map(Self::new)
+            .transpose()
+    }
+
+    /// Whether `authorization` is a bearer header with this token.
+    ///
+    /// Compares in constant time over the token length, so that the token can not be guessed
+    /// from response times.
+    fn accepts(&self, authorization: Option<&HeaderValue>) -> bool {
+        let Some(presented) = authorization
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return false;
+        };
+        let (presented, expected) = (presented.trim().as_bytes(), self.0.as_bytes());
+        let difference = expected
+            .iter()
+            .enumerate()
+            .fold(0, |difference, (i, byte)| {
+                difference | (byte ^ presented.get(i).copied().unwrap_or(0))
+            });
+        difference == 0 && presented.len() == expected.len()
+    }
+}
+
+impl Debug for AdminToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AdminToken(<redacted>)")
+    }
+}
+
+/// Middleware answering `401 Unauthorized` to requests without the admin token.
+pub async fn require_admin_token(
+    State(token): State<Arc<AdminToken>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if token.accepts(request.headers().get(header::AUTHORIZATION)) {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            Json(ErrorResponse {
+                error: "Missing or invalid admin token".to_string(),
+            }),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_token_accepts_exact_bearer_token_only() {
+        let token = AdminToken::new("s3cret").unwrap();
+        let header = |value: &str| HeaderValue::from_str(value).unwrap();
+        assert!(token.accepts(Some(&header("Bearer s3cret"))));
+        for rejected in [
+            "Bearer s3cre",
+            "Bearer s3crett",
+            "Basic s3cret",
+            "s3cret",
+            "Bearer ",
+        ] {
+            assert!(!token.accepts(Some(&header(rejected))), "{rejected}");
+        }
+        assert!(!token.accepts(None));
+        assert!(AdminToken::new(" ").is_err());
+        assert!(!format!("{token:?}").contains("s3cret"));
+    }
+}
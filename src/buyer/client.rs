@@ -0,0 +1,381 @@
+//! HTTP client that pays for `402 Payment Required` responses automatically.
+//!
+//! [`X402Client`] sends a request and, if the server answers `402`, decodes the
+//! [`PaymentRequiredResponse`], picks the first accepted [`PaymentRequirements`] it has a
+//! [`Buyer`] for and that fits the configured [`SpendingLimit`]s, signs a payment and retries the
+//! request with the `X-PAYMENT` header. The `X-PAYMENT-RESPONSE` header of the paid response is
+//! decoded back into a [`SettleResponse`].
+//!
+//! ```ignore
+//! let client = X402Client::new(reqwest::Client::new())
+//!     .with_buyer(EvmBuyer::try_new(signer, Network::MonadTestnet, rpc_url)?)
+//!     .with_spending_limit(SpendingLimit {
+//!         network: Network::MonadTestnet,
+//!         asset: usdc,
+//!         max_per_request: TokenAmount::from(100_000u64),
+//!         session_budget: TokenAmount::from(1_000_000u64),
+//!     });
+//! let paid = client.get("https://example.com/weather".parse()?).await?;
+//! ```
+
+use reqwest::header::HeaderValue;
+use reqwest::{Client, Request, Response, StatusCode};
+use std::sync::Mutex;
+use url::Url;
+
+use crate::buyer::{Buyer, BuyerError};
+use crate::middleware::{X_PAYMENT, X_PAYMENT_RESPONSE};
+use crate::network::Network;
+use crate::types::{
+    Base64Bytes, MixedAddress, PaymentRequiredResponse, PaymentRequirements, SettleResponse,
+    TokenAmount,
+};
+
+/// Errors returned by [`X402Client`].
+#[derive(Debug, thiserror::Error)]
+pub enum X402ClientError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Request body can not be replayed for a paid retry")]
+    UnclonableRequest,
+    #[error("Invalid 402 response: {0}")]
+    InvalidPaymentRequired(String),
+    /// None of the accepted payment options has a buyer and fits the spending limits.
+    #[error("No acceptable payment option: {0}")]
+    NoAcceptablePayment(String),
+    #[error(transparent)]
+    Buyer(#[from] BuyerError),
+    /// The server answered the paid request with `402` again.
+    #[error("Payment rejected: {0}")]
+    PaymentRejected(String),
+    #[error("Invalid X-PAYMENT-RESPONSE header: {0}")]
+    InvalidPaymentResponse(String),
+}
+
+/// Limits payments in one asset on one network.
+///
+/// Payments are only made in assets that have a limit configured.
+#[derive(Clone, Debug)]
+pub struct SpendingLimit {
+    pub network: Network,
+    pub asset: MixedAddress,
+    /// Largest `maxAmountRequired` paid for a single request, facilitator fee included.
+    pub max_per_request: TokenAmount,
+    /// Largest total paid over the lifetime of the client.
+    pub session_budget: TokenAmount,
+}
+
+/// Response to a request, with the settlement if the request was paid for.
+#[derive(Debug)]
+pub struct PaidResponse {
+    pub response: Response,
+    /// Decoded `X-PAYMENT-RESPONSE` header; `None` if no payment was needed
+    /// or the server did not report the settlement.
+    pub settlement: Option<SettleResponse>,
+}
+
+/// An HTTP client that answers `402 Payment Required` with an x402 payment.
+pub struct X402Client {
+    http: Client,
+    buyers: Vec<Buyer>,
+    limits: Vec<SpendingLimit>,
+    /// Amount spent against each entry of `limits`.
+    spent: Mutex<Vec<TokenAmount>>,
+}
+
+impl X402Client {
+    pub fn new(http: Client) -> Self {
+        Self {
+            http,
+            buyers: Vec::new(),
+            limits: Vec::new(),
+            spent: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Adds a buyer paying on its network. The first buyer added for a network is used.
+    pub fn with_buyer(mut self, buyer: impl Into<Buyer>) -> Self {
+        self.buyers.push(buyer.into());
+        self
+    }
+
+    /// Allows payments in `limit.asset` on `limit.network`, within the limit.
+    pub fn with_spending_limit(mut self, limit: SpendingLimit) -> Self {
+        self.limits.push(limit);
+        self.spent
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(TokenAmount::from(0u64));
+        self
+    }
+
+    /// Total committed so far in `asset` on `network`.
+    pub fn spent(&self, network: Network, asset: &MixedAddress) -> TokenAmount {
+        let spent = self.spent.lock().unwrap_or_else(|e| e.into_inner());
+        self.limits
+            .iter()
+            .zip(spent.iter())
+            .filter(|(limit, _)| limit.network == network && limit.asset == *asset)
+            .map(|(_, spent)| *spent)
+            .fold(TokenAmount::from(0u64), |total, spent| total + spent)
+    }
+
+    /// Sends a `GET` request to `url`, paying for it if required.
+    pub async fn get(&self, url: Url) -> Result<PaidResponse, X402ClientError> {
+        let request = self.http.get(url).build()?;
+        self.execute(request).await
+    }
+
+    /// Sends `request`, paying for it if the server answers `402 Payment Required`.
+    ///
+    /// The request body must be replayable (not a stream) so it can be sent again with payment.
+    pub async fn execute(&self, request: Request) -> Result<PaidResponse, X402ClientError> {
+        let mut paid_request = request
+            .try_clone()
+            .ok_or(X402ClientError::UnclonableRequest)?;
+        let response = self.http.execute(request).await?;
+        if response.status() != StatusCode::PAYMENT_REQUIRED {
+            return Ok(PaidResponse {
+                response,
+                settlement: None,
+            });
+        }
+        let payment_required: PaymentRequiredResponse = response
+            .json()
+            .await
+            .map_err(|e| X402ClientError::InvalidPaymentRequired(e.to_string()))?;
+
+        let (requirements, buyer, limit_index) = self
+            .reserve(&payment_required.accepts)
+            .ok_or_else(|| X402ClientError::NoAcceptablePayment(payment_required.error))?;
+        let header = match payment_header(buyer, requirements).await {
+            Ok(header) => header,
+            Err(e) => {
+                self.release(limit_index, payment_cost(requirements));
+                return Err(e.into());
+            }
+        };
+        paid_request.headers_mut().insert(X_PAYMENT, header);
+
+        let response = self.http.execute(paid_request).await?;
+        if response.status() == StatusCode::PAYMENT_REQUIRED {
+            let error = match response.json::<PaymentRequiredResponse>().await {
+                Ok(payment_required) => payment_required.error,
+                Err(e) => e.to_string(),
+            };
+            return Err(X402ClientError::PaymentRejected(error));
+        }
+        let settlement = response
+            .headers()
+            .get(X_PAYMENT_RESPONSE)
+            .map(|header| decode_settlement(header.as_bytes()))
+            .transpose()?;
+        Ok(PaidResponse {
+            response,
+            settlement,
+        })
+    }
+
+    /// Picks the first accepted option with a buyer and room in its spending limit, and commits
+    /// its amount against the limit.
+    ///
+    /// The amount is committed before signing: once signed, the payment can be settled by anyone
+    /// holding it until it expires, whatever the server responds.
+    fn reserve<'a>(
+        &'a self,
+        accepts: &'a [PaymentRequirements],
+    ) -> Option<(&'a PaymentRequirements, &'a Buyer, usize)> {
+        let mut spent = self.spent.lock().unwrap_or_else(|e| e.into_inner());
+        for requirements in accepts {
+            let Some(buyer) = self
+                .buyers
+                .iter()
+                .find(|buyer| buyer.network() == requirements.network)
+            else {
+                continue;
+            };
+            let Some(index) = self.limits.iter().position(|limit| {
+                limit.network == requirements.network && limit.asset == requirements.asset
+            }) else {
+                continue;
+            };
+            let limit = &self.limits[index];
+            let amount = payment_cost(requirements);
+            if amount > limit.max_per_request {
+                continue;
+            }
+            match spent[index].checked_add(amount) {
+                Some(total) if total <= limit.session_budget => {
+                    spent[index] = total;
+                    return Some((requirements, buyer, index));
+                }
+                _ => continue,
+            }
+        }
+        None
+    }
+
+    /// Returns a reservation for a payment that was never sent.
+    fn release(&self, index: usize, amount: TokenAmount) {
+        let mut spent = self.spent.lock().unwrap_or_else(|e| e.into_inner());
+        spent[index] = spent[index]
+            .checked_sub(amount)
+            .unwrap_or(TokenAmount::from(0u64));
+    }
+}
+
+/// Signs a payment meeting `requirements`, encoded as an `X-Payment` header value.
+async fn payment_header(
+    buyer: &Buyer,
+    requirements: &PaymentRequirements,
+) -> Result<HeaderValue, BuyerError> {
+    let payment_payload = buyer.sign_payment(requirements).await?;
+    let payment_payload =
+        serde_json::to_vec(&payment_payload).map_err(|e| BuyerError::Signing(e.to_string()))?;
+    let header = Base64Bytes::encode(payment_payload);
+    HeaderValue::from_bytes(header.as_ref()).map_err(|e| BuyerError::Signing(e.to_string()))
+}
+
+/// Amount a payment meeting `requirements` spends: `maxAmountRequired` plus the facilitator fee.
+fn payment_cost(requirements: &PaymentRequirements) -> TokenAmount {
+    let fee = requirements
+        .facilitator_fee()
+        .map_or(TokenAmount::from(0u64), |fee| {
+            fee.amount(&requirements.asset, requirements.max_amount_required)
+        });
+    requirements.max_amount_required.saturating_add(fee)
+}
+
+fn decode_settlement(header: &[u8]) -> Result<SettleResponse, X402ClientError> {
+    let decoded = Base64Bytes::from(header)
+        .decode()
+        .map_err(|e| X402ClientError::InvalidPaymentResponse(e.to_string()))?;
+    serde_json::from_slice(&decoded)
+        .map_err(|e| X402ClientError::InvalidPaymentResponse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buyer::evm::EvmBuyer;
+    use crate::facilitator::Facilitator;
+    use crate::middleware::X402Layer;
+    use crate::network::USDCDeployment;
+    use crate::types::test_fixtures::settle_request;
+    use crate::types::{
+        SettleRequest, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+    };
+    use alloy::primitives::Address;
+    use alloy::signers::local::PrivateKeySigner;
+    use axum::Router;
+    use axum::routing::get;
+
+    struct AcceptingFacilitator;
+
+    impl Facilitator for AcceptingFacilitator {
+        type Error = String;
+
+        async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+            Ok(VerifyResponse::valid(
+                request.payment_requirements.pay_to.clone(),
+            ))
+        }
+
+        async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+            Ok(SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: request.payment_requirements.pay_to.clone(),
+                transaction: None,
+                network: request.payment_requirements.network,
+            })
+        }
+
+        async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+            Ok(SupportedPaymentKindsResponse { kinds: vec![] })
+        }
+    }
+
+    fn requirements() -> PaymentRequirements {
+        let network = Network::MonadTestnet;
+        PaymentRequirements {
+            network,
+            pay_to: MixedAddress::from(Address::repeat_byte(0x11)),
+            asset: USDCDeployment::by_network(network).asset.address.clone(),
+            ..settle_request(1_000).payment_requirements
+        }
+    }
+
+    async fn serve(requirements: PaymentRequirements) -> Url {
+        let router = Router::new()
+            .route("/paid", get(|| async { "paid content" }))
+            .layer(X402Layer::new(AcceptingFacilitator).with_requirements(requirements));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{address}/paid").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pays_within_session_budget() {
+        let requirements = requirements();
+        let url = serve(requirements.clone()).await;
+        // The USDC domain is known statically, so the RPC endpoint is never called.
+        let buyer = EvmBuyer::try_new(
+            PrivateKeySigner::random(),
+            requirements.network,
+            "http://localhost:8545".parse().unwrap(),
+        )
+        .unwrap();
+        let client = X402Client::new(Client::new())
+            .with_buyer(buyer)
+            .with_spending_limit(SpendingLimit {
+                network: requirements.network,
+                asset: requirements.asset.clone(),
+                max_per_request: TokenAmount::from(1_000u64),
+                session_budget: TokenAmount::from(1_500u64),
+            });
+
+        let paid = client.get(url.clone()).await.unwrap();
+        assert_eq!(paid.response.status(), StatusCode::OK);
+        assert!(paid.settlement.unwrap().success);
+        assert_eq!(
+            client.spent(requirements.network, &requirements.asset),
+            TokenAmount::from(1_000u64)
+        );
+
+        let error = client.get(url).await.unwrap_err();
+        assert!(matches!(error, X402ClientError::NoAcceptablePayment(_)));
+    }
+
+    #[tokio::test]
+    async fn test_releases_reservation_when_signing_fails() {
+        let requirements = PaymentRequirements {
+            asset: MixedAddress::from(Address::repeat_byte(0x22)),
+            ..requirements()
+        };
+        let url = serve(requirements.clone()).await;
+        // Nothing listens there, so resolving the token's EIP-712 domain fails.
+        let buyer = EvmBuyer::try_new(
+            PrivateKeySigner::random(),
+            requirements.network,
+            "http://127.0.0.1:1".parse().unwrap(),
+        )
+        .unwrap();
+        let client = X402Client::new(Client::new())
+            .with_buyer(buyer)
+            .with_spending_limit(SpendingLimit {
+                network: requirements.network,
+                asset: requirements.asset.clone(),
+                max_per_request: TokenAmount::from(1_000u64),
+                session_budget: TokenAmount::from(1_000u64),
+            });
+
+        let error = client.get(url).await.unwrap_err();
+        assert!(matches!(error, X402ClientError::Buyer(_)), "{error:?}");
+        assert_eq!(
+            client.spent(requirements.network, &requirements.asset),
+            TokenAmount::from(0u64)
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Buyer-side signing of ERC-3009 payments on EVM networks.
+
+use alloy::primitives::{Address, B256};
+use alloy::providers::RootProvider;
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol_types::{Eip712Domain, SolStruct};
+use url::Url;
+
+use crate::buyer::BuyerError;
+use crate::chain::FacilitatorLocalError;
+use crate::chain::evm::{EvmChain, EvmMetadataCache, NATIVE_COIN_ASSET, USDC, assert_domain};
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    EvmSignature, ExactEvmFeePayload, ExactEvmPayload, ExactEvmPayloadAuthorization,
+    ExactPaymentPayload, HexEncodedNonce, PaymentPayload, PaymentRequirements, Scheme, TokenAmount,
+    TransferWithAuthorization, X402Version,
+};
+
+/// How far `validAfter` is set in the past, to tolerate clock skew with the facilitator.
+const VALID_AFTER_SKEW_SECONDS: u64 = 600;
+
+/// Signs x402 `exact` payments on one EVM network with a local private key.
+///
+/// The RPC endpoint is only used to read the token's EIP-712 `name` and `version` when they are
+/// neither in the requirements' `extra` nor known for the network's USDC deployment.
+pub struct EvmBuyer {
+    signer: PrivateKeySigner,
+    chain: EvmChain,
+    provider: RootProvider,
+    metadata_cache: EvmMetadataCache,
+}
+
+impl EvmBuyer {
+    pub fn try_new(
+        signer: PrivateKeySigner,
+        network: Network,
+        rpc_url: Url,
+    ) -> Result<Self, BuyerError> {
+        let chain =
+            EvmChain::try_from(network).map_err(|_| BuyerError::UnsupportedNetwork(network))?;
+        Ok(Self {
+            signer,
+            chain,
+            provider: RootProvider::new_http(rpc_url),
+            metadata_cache: EvmMetadataCache::default(),
+        })
+    }
+
+    /// Resolves the EIP-712 domain of the `asset` token for `requirements`, the same way
+    /// verification does: `extra.name`/`extra.version`, then the known USDC deployment, and only
+    /// then the token contract itself.
+    async fn eip712_domain(
+        &self,
+        asset: Address,
+        requirements: &PaymentRequirements,
+    ) -> Result<Eip712Domain, FacilitatorLocalError> {
+        let token = USDC::new(asset, &self.provider);
+        assert_domain(&self.chain, &token, &asset, requirements, &self.metadata_cache).await
+    }
+
+    /// Address the payments are made from.
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    pub fn network(&self) -> Network {
+        self.chain.network()
+    }
+
+    /// Signs a `TransferWithAuthorization` of `max_amount_required` of `asset` to `pay_to`,
+    /// valid for `max_timeout_seconds` from now, and another one paying the facilitator fee of
+    /// the requirements, if any.
+    pub async fn sign_payment(
+        &self,
+        requirements: &PaymentRequirements,
+    ) -> Result<PaymentPayload, BuyerError> {
+        if requirements.network != self.network() {
+            return Err(BuyerError::NetworkMismatch {
+                expected: self.network(),
+                actual: requirements.network,
+            });
+        }
+        if requirements.scheme != Scheme::Exact {
+            return Err(BuyerError::UnsupportedScheme(requirements.scheme));
+        }
+        let asset: Address = requirements
+            .asset
+            .clone()
+            .try_into()
+            .map_err(|e| BuyerError::InvalidAddress(format!("{e:?}")))?;
+        if asset == NATIVE_COIN_ASSET {
+            return Err(BuyerError::UnsupportedAsset(
+                "native coin payments need a signed transaction, not an authorization".to_string(),
+            ));
+        }
+        let pay_to: Address = requirements
+            .pay_to
+            .clone()
+            .try_into()
+            .map_err(|e| BuyerError::InvalidAddress(format!("{e:?}")))?;
+        let domain = self.eip712_domain(asset, requirements).await?;
+
+        let now = UnixTimestamp::try_now()?;
+        let valid_after = UnixTimestamp(now.0.saturating_sub(VALID_AFTER_SKEW_SECONDS));
+        let valid_before = now + requirements.max_timeout_seconds;
+        let (signature, authorization) = self.authorize(
+            &domain,
+            pay_to,
+            requirements.max_amount_required,
+            valid_after,
+            valid_before,
+        )?;
+        // The facilitator fee is paid by a second authorization, with its own nonce
+        let fee = match requirements.facilitator_fee() {
+            Some(fee) => {
+                let amount = fee.amount(&requirements.asset, requirements.max_amount_required);
+                if amount == TokenAmount::from(0u64) {
+                    None
+                } else {
+                    let recipient: Address = fee
+                        .recipient
+                        .try_into()
+                        .map_err(|e| BuyerError::InvalidAddress(format!("{e:?}")))?;
+                    let (signature, authorization) =
+                        self.authorize(&domain, recipient, amount, valid_after, valid_before)?;
+                    Some(Box::new(ExactEvmFeePayload {
+                        signature,
+                        authorization,
+                    }))
+                }
+            }
+            None => None,
+        };
+
+        Ok(PaymentPayload {
+            x402_version: X402Version::V1,
+            scheme: requirements.scheme,
+            network: requirements.network,
+            payload: ExactPaymentPayload::Evm(ExactEvmPayload {
+                signature,
+                authorization,
+                fee,
+            }),
+        })
+    }
+
+    /// Signs a `TransferWithAuthorization` of `value` to `to`, with a random nonce.
+    fn authorize(
+        &self,
+        domain: &Eip712Domain,
+        to: Address,
+        value: TokenAmount,
+        valid_after: UnixTimestamp,
+        valid_before: UnixTimestamp,
+    ) -> Result<(EvmSignature, ExactEvmPayloadAuthorization), BuyerError> {
+        let nonce = B256::random();
+        let transfer_with_authorization = TransferWithAuthorization {
+            from: self.address(),
+            to,
+            value: value.into(),
+            validAfter: valid_after.into(),
+            validBefore: valid_before.into(),
+            nonce,
+        };
+        let hash = transfer_with_authorization.eip712_signing_hash(domain);
+        let signature = self
+            .signer
+            .sign_hash_sync(&hash)
+            .map_err(|e| BuyerError::Signing(format!("{e}")))?;
+        let authorization = ExactEvmPayloadAuthorization {
+            from: self.address().into(),
+            to: to.into(),
+            value,
+            valid_after,
+            valid_before,
+            nonce: HexEncodedNonce(nonce.0),
+        };
+        Ok((EvmSignature::from(signature.as_bytes()), authorization))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::USDCDeployment;
+    use crate::types::MixedAddress;
+    use alloy::primitives::Signature;
+
+    fn usdc_requirements(
+        network: Network,
+        extra: Option<serde_json::Value>,
+    ) -> PaymentRequirements {
+        PaymentRequirements {
+            scheme: Scheme::Exact,
+            network,
+            max_amount_required: TokenAmount::from(10_000u64),
+            resource: "https://example.com/paid".parse().unwrap(),
+            description: String::new(),
+            mime_type: "text/plain".to_string(),
+            output_schema: None,
+            pay_to: MixedAddress::from(Address::repeat_byte(0x11)),
+            max_timeout_seconds: 60,
+            asset: USDCDeployment::by_network(network).asset.address.clone(),
+            extra,
+        }
+    }
+
+    /// The signer of an `authorization` of the requirements' asset.
+    async fn signer(
+        buyer: &EvmBuyer,
+        requirements: &PaymentRequirements,
+        signature: &EvmSignature,
+        authorization: &ExactEvmPayloadAuthorization,
+    ) -> Address {
+        let asset: Address = requirements.asset.clone().try_into().unwrap();
+        let domain = buyer.eip712_domain(asset, requirements).await.unwrap();
+        let hash = TransferWithAuthorization {
+            from: authorization.from.into(),
+            to: authorization.to.into(),
+            value: authorization.value.into(),
+            validAfter: authorization.valid_after.into(),
+            validBefore: authorization.valid_before.into(),
+            nonce: B256::from(authorization.nonce.0),
+        }
+        .eip712_signing_hash(&domain);
+        let signature = Signature::try_from(signature.0.as_slice()).unwrap();
+        signature.recover_address_from_prehash(&hash).unwrap()
+    }
+
+    /// A buyer on `network`: its USDC domain is known statically, so the RPC endpoint is never
+    /// called.
+    fn buyer(network: Network) -> EvmBuyer {
+        EvmBuyer::try_new(
+            PrivateKeySigner::random(),
+            network,
+            "http://localhost:8545".parse().unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sign_payment_recovers_to_buyer() {
+        let network = Network::MonadTestnet;
+        let requirements = usdc_requirements(network, None);
+        let buyer = buyer(network);
+
+        let payload = buyer.sign_payment(&requirements).await.unwrap();
+        let ExactPaymentPayload::Evm(payload) = payload.payload else {
+            panic!("expected an ERC-3009 payload");
+        };
+        let authorization = payload.authorization;
+
+        assert_eq!(
+            signer(&buyer, &requirements, &payload.signature, &authorization).await,
+            buyer.address()
+        );
+        assert_eq!(Address::from(authorization.to), Address::repeat_byte(0x11));
+        assert!(payload.fee.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sign_payment_authorizes_the_facilitator_fee() {
+        let network = Network::MonadTestnet;
+        let recipient = Address::repeat_byte(0x22);
+        let fee = serde_json::json!({ "fee": { "recipient": recipient.to_string(), "bps": 100 } });
+        let requirements = usdc_requirements(network, Some(fee));
+        let buyer = buyer(network);
+
+        let payload = buyer.sign_payment(&requirements).await.unwrap();
+        let ExactPaymentPayload::Evm(payload) = payload.payload else {
+            panic!("expected an ERC-3009 payload");
+        };
+        let fee = payload.fee.expect("fee authorization");
+
+        assert_eq!(Address::from(fee.authorization.to), recipient);
+        assert_eq!(fee.authorization.value, TokenAmount::from(100u64));
+        assert_eq!(fee.authorization.from, payload.authorization.from);
+        assert_ne!(fee.authorization.nonce.0, payload.authorization.nonce.0);
+        assert_eq!(
+            signer(&buyer, &requirements, &fee.signature, &fee.authorization).await,
+            buyer.address()
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Buyer-side construction of signed x402 payments.
+//!
+//! Given the [`PaymentRequirements`] a seller returned with `402 Payment Required`, a buyer
+//! produces the [`PaymentPayload`] that goes into the `X-PAYMENT` header:
+//!
+//! - [`evm::EvmBuyer`] signs an ERC-3009 `TransferWithAuthorization` for the token, using the same
+//!   EIP-712 domain resolution as the facilitator.
+//! - [`solana::SolanaBuyer`] builds and partially signs a transfer transaction in the instruction
+//!   layout the facilitator verifies, leaving the fee payer signature to the facilitator.
+//!
+//! [`client::X402Client`] wraps an HTTP client that answers `402 Payment Required` responses with
+//! such a payment, within a configured spending policy.
+
+use std::time::SystemTimeError;
+
+use crate::buyer::evm::EvmBuyer;
+use crate::buyer::solana::SolanaBuyer;
+use crate::chain::FacilitatorLocalError;
+use crate::network::Network;
+use crate::types::{MixedAddress, PaymentPayload, PaymentRequirements, Scheme};
+
+pub mod client;
+pub mod evm;
+pub mod solana;
+
+/// Errors returned while building a payment.
+#[derive(Debug, thiserror::Error)]
+pub enum BuyerError {
+    #[error("Buyer for {expected} can not pay on {actual}")]
+    NetworkMismatch { expected: Network, actual: Network },
+    #[error("Unsupported network: {0}")]
+    UnsupportedNetwork(Network),
+    #[error("Unsupported scheme: {0}")]
+    UnsupportedScheme(Scheme),
+    #[error("Unsupported asset: {0}")]
+    UnsupportedAsset(String),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Invalid payment requirements: {0}")]
+    InvalidRequirements(String),
+    #[error("RPC call failed: {0}")]
+    Rpc(String),
+    #[error("Failed to sign payment: {0}")]
+    Signing(String),
+    #[error("Can not get system clock time: {0}")]
+    Clock(#[from] SystemTimeError),
+    #[error(transparent)]
+    Chain(#[from] FacilitatorLocalError),
+}
+
+/// A buyer able to pay on one network.
+pub enum Buyer {
+    Evm(EvmBuyer),
+    Solana(SolanaBuyer),
+}
+
+impl Buyer {
+    pub fn network(&self) -> Network {
+        match self {
+            Buyer::Evm(buyer) => buyer.network(),
+            Buyer::Solana(buyer) => buyer.network(),
+        }
+    }
+
+    /// Address the payments are made from.
+    pub fn address(&self) -> MixedAddress {
+        match self {
+            Buyer::Evm(buyer) => buyer.address().into(),
+            Buyer::Solana(buyer) => buyer.pubkey().into(),
+        }
+    }
+
+    pub async fn sign_payment(
+        &self,
+        requirements: &PaymentRequirements,
+    ) -> Result<PaymentPayload, BuyerError> {
+        match self {
+            Buyer::Evm(buyer) => buyer.sign_payment(requirements).await,
+            Buyer::Solana(buyer) => buyer.sign_payment(requirements).await,
+        }
+    }
+}
+
+impl From<EvmBuyer> for Buyer {
+    fn from(buyer: EvmBuyer) -> Self {
+        Buyer::Evm(buyer)
+    }
+}
+
+impl From<SolanaBuyer> for Buyer {
+    fn from(buyer: SolanaBuyer) -> Self {
+        Buyer::Solana(buyer)
+    }
+}
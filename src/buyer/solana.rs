@@ -0,0 +1,538 @@
+//! Buyer-side construction of Solana payment transactions.
+//!
+//! The transaction has exactly the layout the facilitator verifies:
+//!
+//! 1. `SetComputeUnitLimit`
+//! 2. `SetComputeUnitPrice`
+//! 3. `CreateIdempotent` for the recipient's associated token account, only if it does not exist
+//! 4. `TransferChecked` of `maxAmountRequired` to that account, plus the transfer fee of a
+//!    Token-2022 mint taking one
+//!
+//! If the requirements carry a facilitator fee, a last `TransferChecked` pays it to the fee
+//! recipient's associated token account, which must exist.
+//!
+//! Native SOL payments replace 3–4 with a single `SystemProgram::Transfer`, and pay the fee with
+//! another one. The facilitator's `feePayer` (from the requirements' `extra`) pays the
+//! transaction fee, so the buyer only signs as the transfer authority and the facilitator adds
+//! the fee payer signature on settlement.
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::{VersionedMessage, v0};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_system_interface::instruction::SystemInstruction;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use spl_token_2022::state::Mint;
+
+use crate::buyer::BuyerError;
+use crate::chain::solana::{
+    ATA_PROGRAM_PUBKEY, NATIVE_SOL_ASSET, TransactionInt, associated_token_address,
+};
+use crate::network::{Network, NetworkFamily};
+use crate::types::{
+    Base64Bytes, ExactPaymentPayload, ExactSolanaPayload, MixedAddress, PaymentPayload,
+    PaymentRequirements, Scheme, X402Version,
+};
+
+/// Compute unit limit requested by default; within the facilitator's default maximum on all networks.
+const DEFAULT_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+/// Compute unit price in micro-lamports used by default.
+const DEFAULT_COMPUTE_UNIT_PRICE: u64 = 1_000;
+
+/// Builds and signs x402 `exact` payment transactions on one Solana network.
+pub struct SolanaBuyer {
+    keypair: Keypair,
+    network: Network,
+    rpc_client: RpcClient,
+    compute_unit_limit: u32,
+    compute_unit_price: u64,
+}
+
+impl SolanaBuyer {
+    pub fn try_new(
+        keypair: Keypair,
+        network: Network,
+        rpc_url: String,
+    ) -> Result<Self, BuyerError> {
+        if !matches!(NetworkFamily::from(network), NetworkFamily::Solana) {
+            return Err(BuyerError::UnsupportedNetwork(network));
+        }
+        Ok(Self {
+            keypair,
+            network,
+            rpc_client: RpcClient::new(rpc_url),
+            compute_unit_limit: DEFAULT_COMPUTE_UNIT_LIMIT,
+            compute_unit_price: DEFAULT_COMPUTE_UNIT_PRICE,
+        })
+    }
+
+    /// Sets the compute unit limit. It must not exceed the facilitator's
+    /// `X402_SOLANA_MAX_COMPUTE_UNIT_LIMIT_*`.
+    pub fn with_compute_unit_limit(mut self, compute_unit_limit: u32) -> Self {
+        self.compute_unit_limit = compute_unit_limit;
+        self
+    }
+
+    /// Sets the compute unit price, e.g. from the facilitator's `recommendedComputeUnitPrice`.
+    /// It must not exceed the facilitator's `X402_SOLANA_MAX_COMPUTE_UNIT_PRICE_*`.
+    pub fn with_compute_unit_price(mut self, compute_unit_price: u64) -> Self {
+        self.compute_unit_price = compute_unit_price;
+        self
+    }
+
+    /// Address the payments are made from.
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Builds a transaction paying `max_amount_required` of `asset` to `pay_to`,
+    /// signed by the buyer and left for the facilitator's fee payer to co-sign.
+    pub async fn sign_payment(
+        &self,
+        requirements: &PaymentRequirements,
+    ) -> Result<PaymentPayload, BuyerError> {
+        if requirements.network != self.network {
+            return Err(BuyerError::NetworkMismatch {
+                expected: self.network,
+                actual: requirements.network,
+            });
+        }
+        if requirements.scheme != Scheme::Exact {
+            return Err(BuyerError::UnsupportedScheme(requirements.scheme));
+        }
+        let fee_payer = fee_payer(requirements)?;
+        let mint = solana_address(&requirements.asset)?;
+        let pay_to = solana_address(&requirements.pay_to)?;
+        let amount = u64::try_from(requirements.max_amount_required.0).map_err(|_| {
+            BuyerError::InvalidRequirements("maxAmountRequired does not fit in u64".to_string())
+        })?;
+        let fee = match requirements.facilitator_fee() {
+            Some(fee) => {
+                let amount = fee.amount(&requirements.asset, requirements.max_amount_required);
+                let amount = u64::try_from(amount.0).map_err(|_| {
+                    BuyerError::InvalidRequirements("fee does not fit in u64".to_string())
+                })?;
+                let recipient = solana_address(&fee.recipient)?;
+                (amount != 0).then_some((recipient, amount))
+            }
+            None => None,
+        };
+
+        let mut instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(self.compute_unit_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(self.compute_unit_price),
+        ];
+        if mint == NATIVE_SOL_ASSET {
+            instructions.push(Instruction::new_with_bincode(
+                solana_system_interface::program::ID,
+                &SystemInstruction::Transfer { lamports: amount },
+                vec![
+                    AccountMeta::new(self.pubkey(), true),
+                    AccountMeta::new(pay_to, false),
+                ],
+            ));
+            if let Some((recipient, fee)) = fee {
+                instructions.push(Instruction::new_with_bincode(
+                    solana_system_interface::program::ID,
+                    &SystemInstruction::Transfer { lamports: fee },
+                    vec![
+                        AccountMeta::new(self.pubkey(), true),
+                        AccountMeta::new(recipient, false),
+                    ],
+                ));
+            }
+        } else {
+            let mint_account = self
+                .rpc_client
+                .get_account(&mint)
+                .await
+                .map_err(|e| BuyerError::Rpc(format!("{e}")))?;
+            let token_program = mint_account.owner;
+            let mint_state = StateWithExtensions::<Mint>::unpack(&mint_account.data)
+                .map_err(|e| BuyerError::InvalidRequirements(format!("invalid mint: {e}")))?;
+            let decimals = mint_state.base.decimals;
+            // The facilitator matches what reaches `pay_to`: the transfer fee is paid on top
+            let amount = match mint_state.get_extension::<TransferFeeConfig>() {
+                Ok(transfer_fee_config) => {
+                    let epoch = self
+                        .rpc_client
+                        .get_epoch_info()
+                        .await
+                        .map_err(|e| BuyerError::Rpc(format!("{e}")))?
+                        .epoch;
+                    transfer_fee_config
+                        .calculate_inverse_epoch_fee(epoch, amount)
+                        .and_then(|transfer_fee| amount.checked_add(transfer_fee))
+                        .ok_or_else(|| {
+                            BuyerError::InvalidRequirements(
+                                "transfer fee overflows u64".to_string(),
+                            )
+                        })?
+                }
+                Err(_) => amount,
+            };
+            let destination = associated_token_address(&pay_to, &token_program, &mint);
+            let destination_exists = self
+                .rpc_client
+                .get_account_with_commitment(&destination, self.rpc_client.commitment())
+                .await
+                .map_err(|e| BuyerError::Rpc(format!("{e}")))?
+                .value
+                .is_some();
+            instructions.extend(token_transfer_instructions(
+                &self.pubkey(),
+                &pay_to,
+                &mint,
+                &token_program,
+                decimals,
+                amount,
+                !destination_exists,
+            )?);
+            if let Some((recipient, fee)) = fee {
+                instructions.extend(token_transfer_instructions(
+                    &self.pubkey(),
+                    &recipient,
+                    &mint,
+                    &token_program,
+                    decimals,
+                    fee,
+                    false,
+                )?);
+            }
+        }
+
+        let blockhash = self
+            .rpc_client
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| BuyerError::Rpc(format!("{e}")))?;
+        let message = v0::Message::try_compile(&fee_payer, &instructions, &[], blockhash)
+            .map_err(|e| BuyerError::Signing(format!("{e}")))?;
+        let message = VersionedMessage::V0(message);
+        let num_required = message.header().num_required_signatures as usize;
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default(); num_required],
+            message,
+        };
+        let transaction = TransactionInt::new(transaction)
+            .sign(&self.keypair)?
+            .into_inner();
+        let bytes =
+            bincode::serialize(&transaction).map_err(|e| BuyerError::Signing(format!("{e}")))?;
+        let encoded = Base64Bytes::encode(bytes);
+
+        Ok(PaymentPayload {
+            x402_version: X402Version::V1,
+            scheme: requirements.scheme,
+            network: requirements.network,
+            payload: ExactPaymentPayload::Solana(ExactSolanaPayload {
+                transaction: String::from_utf8_lossy(encoded.as_ref()).into_owned(),
+            }),
+        })
+    }
+}
+
+/// The facilitator's fee payer, advertised in `extra.feePayer` of the requirements.
+fn fee_payer(requirements: &PaymentRequirements) -> Result<Pubkey, BuyerError> {
+    let fee_payer = requirements
+        .extra
+        .as_ref()
+        .and_then(|extra| extra.get("feePayer"))
+        .cloned()
+        .ok_or_else(|| BuyerError::InvalidRequirements("missing extra.feePayer".to_string()))?;
+    let fee_payer: MixedAddress = serde_json::from_value(fee_payer)
+        .map_err(|e| BuyerError::InvalidAddress(format!("{e}")))?;
+    solana_address(&fee_payer)
+}
+
+fn solana_address(address: &MixedAddress) -> Result<Pubkey, BuyerError> {
+    match address {
+        MixedAddress::Solana(pubkey) => Ok(*pubkey),
+        other => Err(BuyerError::InvalidAddress(format!(
+            "expected Solana address, got {other}"
+        ))),
+    }
+}
+
+/// Instructions moving `amount` of `mint` from the buyer's associated token account to the
+/// recipient's, creating the latter first if `create_destination` is set.
+fn token_transfer_instructions(
+    buyer: &Pubkey,
+    pay_to: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    decimals: u8,
+    amount: u64,
+    create_destination: bool,
+) -> Result<Vec<Instruction>, BuyerError> {
+    let source = associated_token_address(buyer, token_program, mint);
+    let destination = associated_token_address(pay_to, token_program, mint);
+    let mut instructions = Vec::with_capacity(2);
+    if create_destination {
+        // The buyer funds the account rent: the fee payer must not appear in any instruction.
+        instructions.push(Instruction {
+            program_id: ATA_PROGRAM_PUBKEY,
+            accounts: vec![
+                AccountMeta::new(*buyer, true),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(*pay_to, false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(solana_system_interface::program::ID, false),
+                AccountMeta::new_readonly(*token_program, false),
+            ],
+            // AssociatedTokenAccountInstruction::CreateIdempotent
+            data: vec![1],
+        });
+    }
+    let transfer = spl_token_2022::instruction::transfer_checked(
+        token_program,
+        &source,
+        mint,
+        &destination,
+        buyer,
+        &[],
+        amount,
+        decimals,
+    )
+    .map_err(|e| BuyerError::InvalidRequirements(format!("{e}")))?;
+    instructions.push(transfer);
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::FacilitatorLocalError;
+    use crate::chain::rpc_pool::RpcEndpoint;
+    use crate::chain::solana::{MintExtensionPolicy, PriorityFeePolicy, SolanaProvider};
+    use crate::facilitator::Facilitator;
+    use crate::types::test_fixtures::transfer_fee_mint;
+    use crate::types::{FacilitatorFee, TokenAmount, VerifyRequest, VerifyResponse};
+    use solana_client::rpc_request::RpcRequest;
+    use solana_rpc_client::mock_sender::MocksMap;
+    use solana_sdk::hash::Hash;
+
+    #[test]
+    fn test_token_transfer_layout_matches_verification() {
+        let facilitator = SolanaProvider::try_new(
+            Keypair::new(),
+            &[RpcEndpoint::new("http://localhost:8899")],
+            Network::SolanaDevnet,
+            200_000,
+            100_000,
+            MintExtensionPolicy::default(),
+            PriorityFeePolicy::default(),
+        )
+        .unwrap();
+        let buyer = Pubkey::new_unique();
+        let pay_to = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let requirements = PaymentRequirements {
+            scheme: Scheme::Exact,
+            network: Network::SolanaDevnet,
+            max_amount_required: TokenAmount::from(1_000u64),
+            resource: "https://example.com/paid".parse().unwrap(),
+            description: String::new(),
+            mime_type: "text/plain".to_string(),
+            output_schema: None,
+            pay_to: MixedAddress::Solana(pay_to),
+            max_timeout_seconds: 60,
+            asset: MixedAddress::Solana(mint),
+            extra: None,
+        };
+
+        let mut instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(DEFAULT_COMPUTE_UNIT_LIMIT),
+            ComputeBudgetInstruction::set_compute_unit_price(DEFAULT_COMPUTE_UNIT_PRICE),
+        ];
+        instructions.extend(
+            token_transfer_instructions(&buyer, &pay_to, &mint, &spl_token::ID, 6, 1_000, true)
+                .unwrap(),
+        );
+        let fee_payer = facilitator.fee_payer();
+        let fee_payer = solana_address(&fee_payer).unwrap();
+        let message =
+            v0::Message::try_compile(&fee_payer, &instructions, &[], Hash::default()).unwrap();
+        let transaction = VersionedTransaction {
+            signatures: vec![],
+            message: VersionedMessage::V0(message),
+        };
+
+        let compute_units = facilitator
+            .verify_compute_limit_instruction(&transaction, 0)
+            .unwrap();
+        assert_eq!(compute_units, DEFAULT_COMPUTE_UNIT_LIMIT);
+        facilitator
+            .verify_compute_price_instruction(&transaction, 1)
+            .unwrap();
+        let transaction = TransactionInt::new(transaction);
+        facilitator
+            .verify_create_ata_instruction(&transaction, 2, &requirements)
+            .unwrap();
+        let transfer = facilitator
+            .decode_transfer_instruction(&transaction, 3, &requirements)
+            .unwrap();
+        assert_eq!(transfer.amount, 1_000);
+        assert_eq!(transfer.decimals, 6);
+        assert_eq!(transfer.mint, mint);
+        assert_eq!(transfer.token_program, spl_token::ID);
+        assert_eq!(
+            transfer.source,
+            associated_token_address(&buyer, &spl_token::ID, &mint)
+        );
+        assert_eq!(
+            transfer.destination,
+            associated_token_address(&pay_to, &spl_token::ID, &mint)
+        );
+        assert_eq!(transfer.authority, buyer);
+    }
+
+    #[tokio::test]
+    async fn test_native_sol_fee_passes_verification() {
+        let recipient = Pubkey::new_unique();
+        let facilitator = SolanaProvider::try_new(
+            Keypair::new(),
+            &[RpcEndpoint::new("http://localhost:8899")],
+            Network::SolanaDevnet,
+            200_000,
+            100_000,
+            MintExtensionPolicy::default(),
+            PriorityFeePolicy::default(),
+        )
+        .unwrap()
+        .with_rpc_client(RpcClient::new_mock("succeeds".to_string()))
+        .with_fee(FacilitatorFee {
+            recipient: MixedAddress::Solana(recipient),
+            bps: Some(100),
+            flat: Default::default(),
+            exempt: Vec::new(),
+        });
+        let buyer = SolanaBuyer {
+            keypair: Keypair::new(),
+            network: Network::SolanaDevnet,
+            rpc_client: RpcClient::new_mock("succeeds".to_string()),
+            compute_unit_limit: DEFAULT_COMPUTE_UNIT_LIMIT,
+            compute_unit_price: DEFAULT_COMPUTE_UNIT_PRICE,
+        };
+        let requirements = |bps: u16| PaymentRequirements {
+            scheme: Scheme::Exact,
+            network: Network::SolanaDevnet,
+            max_amount_required: TokenAmount::from(10_000u64),
+            resource: "https://example.com/paid".parse().unwrap(),
+            description: String::new(),
+            mime_type: "text/plain".to_string(),
+            output_schema: None,
+            pay_to: MixedAddress::Solana(Pubkey::new_unique()),
+            max_timeout_seconds: 60,
+            asset: MixedAddress::Solana(NATIVE_SOL_ASSET),
+            extra: Some(serde_json::json!({
+                "feePayer": facilitator.fee_payer(),
+                "fee": { "recipient": recipient.to_string(), "bps": bps },
+            })),
+        };
+        let verify = async |requirements: PaymentRequirements| {
+            let request = VerifyRequest {
+                x402_version: X402Version::V1,
+                payment_payload: buyer.sign_payment(&requirements).await.unwrap(),
+                payment_requirements: requirements,
+            };
+            facilitator.verify(&request).await
+        };
+
+        let response = verify(requirements(100)).await.unwrap();
+        assert!(matches!(
+            response,
+            VerifyResponse::Valid { payer, .. } if payer == MixedAddress::Solana(buyer.pubkey())
+        ));
+
+        // A buyer paying less than the facilitator charges is rejected
+        let error = verify(requirements(50)).await.unwrap_err();
+        assert!(
+            matches!(error, FacilitatorLocalError::InvalidFee(..)),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transfer_fee_mint_payment_covers_the_transfer_fee() {
+        let facilitator = SolanaProvider::try_new(
+            Keypair::new(),
+            &[RpcEndpoint::new("http://localhost:8899")],
+            Network::SolanaDevnet,
+            200_000,
+            100_000,
+            MintExtensionPolicy::default(),
+            PriorityFeePolicy::default(),
+        )
+        .unwrap();
+        let (mint, pay_to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // A mint taking a 1% transfer fee, then a recipient token account not created yet
+        let mint_data = Base64Bytes::encode(transfer_fee_mint(100, u64::MAX));
+        let mint_account = serde_json::json!({
+            "context": {"slot": 1},
+            "value": {
+                "lamports": 1_000_000,
+                "data": [String::from_utf8_lossy(mint_data.as_ref()), "base64"],
+                "owner": spl_token_2022::ID.to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+                "space": 0,
+            },
+        });
+        let no_account = serde_json::json!({"context": {"slot": 1}, "value": null});
+        let buyer = SolanaBuyer {
+            keypair: Keypair::new(),
+            network: Network::SolanaDevnet,
+            rpc_client: RpcClient::new_mock_with_mocks_map(
+                "succeeds",
+                MocksMap::from_iter([
+                    (RpcRequest::GetAccountInfo, mint_account),
+                    (RpcRequest::GetAccountInfo, no_account),
+                ]),
+            ),
+            compute_unit_limit: DEFAULT_COMPUTE_UNIT_LIMIT,
+            compute_unit_price: DEFAULT_COMPUTE_UNIT_PRICE,
+        };
+        let requirements = PaymentRequirements {
+            scheme: Scheme::Exact,
+            network: Network::SolanaDevnet,
+            max_amount_required: TokenAmount::from(10_000u64),
+            resource: "https://example.com/paid".parse().unwrap(),
+            description: String::new(),
+            mime_type: "text/plain".to_string(),
+            output_schema: None,
+            pay_to: MixedAddress::Solana(pay_to),
+            max_timeout_seconds: 60,
+            asset: MixedAddress::Solana(mint),
+            extra: Some(serde_json::json!({ "feePayer": facilitator.fee_payer() })),
+        };
+
+        let payload = buyer.sign_payment(&requirements).await.unwrap();
+        let ExactPaymentPayload::Solana(payload) = payload.payload else {
+            panic!("expected a Solana payload");
+        };
+        let transaction = Base64Bytes::from(payload.transaction.as_bytes())
+            .decode()
+            .unwrap();
+        let transaction: VersionedTransaction = bincode::deserialize(&transaction).unwrap();
+        let transaction = TransactionInt::new(transaction);
+        facilitator
+            .verify_create_ata_instruction(&transaction, 2, &requirements)
+            .unwrap();
+        // 10 102 less its 1% transfer fee, rounded up, credits `pay_to` with 10 000
+        let transfer = facilitator
+            .decode_transfer_instruction(&transaction, 3, &requirements)
+            .unwrap();
+        assert_eq!(transfer.amount, 10_102);
+        assert_eq!(transfer.token_program, spl_token_2022::ID);
+    }
+}
@@ -10,16 +10,29 @@
 //! - Target tokens implement ERC-3009 and support ERC-1271 for contract signers.
 //! - The validator contract exists at [`VALIDATOR_ADDRESS`] on supported chains.
 //!
+//! Native coin payments (`asset` set to [`NATIVE_COIN_ASSET`]) carry a raw transaction signed
+//! by the buyer instead of an ERC-3009 authorization. The facilitator checks chain id, recipient,
+//! value, nonce and balance, simulates the transfer, and relays the transaction unchanged, so
+//! the buyer pays the gas. Gas sponsorship (e.g. via EIP-7702 delegation) is not supported.
+//!
+//! When a [`FacilitatorFee`] is configured, ERC-3009 payments carry a second authorization
+//! paying the fee to its recipient. It is verified alongside the payment, and settled in the
+//! same Multicall3 batch. Native coin payments are not charged a fee.
+//!
 //! Invariants:
-//! - Settlement is atomic: deploy (if needed) + transfer happen in a single user flow.
+//! - Settlement is atomic: deploy (if needed) + transfer + fee happen in a single user flow.
 //! - Verification does not persist state.
 
+use alloy::consensus::transaction::SignerRecoverable;
+use alloy::consensus::{Transaction as _, TxEnvelope};
 use alloy::contract::SolCallBuilder;
 use alloy::dyn_abi::SolType;
+use alloy::eips::eip2718::Decodable2718;
+use alloy::eips::{BlockId, BlockNumberOrTag};
 use alloy::network::{
     Ethereum as AlloyEthereum, EthereumWallet, NetworkWallet, TransactionBuilder,
 };
-use alloy::primitives::{Address, Bytes, FixedBytes, U256, address};
+use alloy::primitives::{Address, B256, Bytes, FixedBytes, TxKind, U256, address};
 use alloy::providers::ProviderBuilder;
 use alloy::providers::bindings::IMulticall3;
 use alloy::providers::fillers::NonceManager;
@@ -29,32 +42,41 @@ use alloy::providers::fillers::{
 use alloy::providers::{
     Identity, MULTICALL3_ADDRESS, MulticallItem, Provider, RootProvider, WalletProvider,
 };
-use alloy::rpc::client::RpcClient;
+use alloy::rpc::client::{BatchRequest, RpcClient};
 use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
 use alloy::sol_types::{Eip712Domain, SolCall, SolStruct, eip712_domain};
 use alloy::{hex, sol};
 use async_trait::async_trait;
 use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::Counter;
 use std::future::{Future, IntoFuture};
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tracing::{Instrument, instrument};
 use tracing_core::Level;
 
+use crate::chain::rpc_pool::{EvmRpcTransport, RpcEndpoint};
 use crate::chain::{
-    FacilitatorLocalError, FromEnvByNetworkBuild, NetworkProviderOps, TransactionStatusQuery,
+    FacilitatorFeeQuery, FacilitatorLocalError, FinalityQuery, FromEnvByNetworkBuild,
+    NetworkProviderOps, PaymentAmount, PaymentAmountQuery, SettlementCostQuery, SignerBalanceQuery,
+    TransactionStatusQuery,
 };
 use crate::facilitator::Facilitator;
 use crate::from_env;
 use crate::network::{Network, USDCDeployment};
+use crate::pending_settlements;
 use crate::timestamp::UnixTimestamp;
 use crate::types::{
-    EvmAddress, EvmSignature, ExactPaymentPayload, FacilitatorErrorReason, HexEncodedNonce,
-    MixedAddress, PaymentPayload, PaymentRequirements, Scheme, SettleRequest, SettleResponse,
-    SupportedPaymentKind, SupportedPaymentKindsResponse, TokenAmount, TransactionHash,
-    TransactionStatus, TransactionStatusResponse, TransferWithAuthorization, VerifyRequest,
-    VerifyResponse, X402Version,
+    EvmAddress, EvmSignature, ExactEvmNativePayload, ExactEvmPayload, ExactPaymentPayload,
+    FacilitatorErrorReason, FacilitatorFee, Finality, HexEncodedNonce, MixedAddress,
+    PaymentPayload, PaymentRequirements, Scheme, SettleRequest, SettleResponse,
+    SupportedPaymentKind, SupportedPaymentKindExtra, SupportedPaymentKindsResponse, TokenAmount,
+    TransactionHash, TransactionStatus, TransactionStatusResponse, TransferWithAuthorization,
+    VerifyRequest, VerifyResponse, X402Version,
 };
 
 sol!(
@@ -80,6 +102,23 @@ sol! {
 const VALIDATOR_ADDRESS: alloy::primitives::Address =
     address!("0xdAcD51A54883eb67D95FAEb2BBfdC4a9a6BD2a3B");
 
+/// Value of [`PaymentRequirements::asset`] designating the chain's native coin (MON/ETH),
+/// following the common `0xEeee...EEeE` convention. Amounts are in wei.
+pub const NATIVE_COIN_ASSET: alloy::primitives::Address =
+    address!("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+/// How long token EIP-712 names and versions, and observed contract code, stay cached.
+const METADATA_CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// Lookups in [`EvmMetadataCache`], by `cache` (`eip712_name`, `eip712_version`,
+/// `code_presence`) and `result` (`hit`, `miss`).
+static METADATA_CACHE_LOOKUPS: Lazy<Counter<u64>> = Lazy::new(|| {
+    opentelemetry::global::meter(env!("CARGO_PKG_NAME"))
+        .u64_counter("x402.evm.metadata_cache.lookups")
+        .with_description("EVM metadata cache lookups, by cache and hit or miss")
+        .build()
+});
+
 /// Combined filler type for gas, blob gas, nonce, and chain ID.
 type InnerFiller = JoinFill<
     GasFiller,
@@ -156,6 +195,22 @@ pub struct ExactEvmPayment {
     pub signature: EvmSignature,
 }
 
+/// A verified, buyer-signed native coin transfer, ready to be relayed.
+pub struct NativeEvmPayment {
+    /// Recovered transaction signer (the payer).
+    pub from: Address,
+    /// Transfer recipient (`payTo`).
+    pub to: Address,
+    /// Transferred amount, in wei.
+    pub value: U256,
+    /// Hash of the signed transaction.
+    pub tx_hash: B256,
+    /// Nonce of the signed transaction, the next one of the payer.
+    pub nonce: u64,
+    /// Signed EIP-2718 transaction bytes, relayed unchanged.
+    pub raw_transaction: Bytes,
+}
+
 /// EVM implementation of the x402 facilitator.
 ///
 /// Holds a composed Alloy ethereum provider [`InnerProvider`],
@@ -174,13 +229,86 @@ pub struct EvmProvider {
     signer_cursor: Arc<AtomicUsize>,
     /// Nonce manager for resetting nonces on transaction failures.
     nonce_manager: PendingNonceManager,
+    /// Resolved EIP-712 domains and contract code presence.
+    metadata_cache: EvmMetadataCache,
+    /// Block confirmations a settlement transaction waits for.
+    confirmations: u64,
+    /// Service fee charged on ERC-3009 payments.
+    fee: Option<FacilitatorFee>,
+    /// Limits on settlement transactions, swapped on configuration reload.
+    policy: Arc<RwLock<EvmPolicy>>,
+}
+
+/// Limits an [`EvmProvider`] applies to settlement transactions. Reloadable at runtime, see
+/// [`EvmProvider::set_policy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EvmPolicy {
+    /// Highest gas price, in wei, a settlement transaction may pay: its `maxFeePerGas` on EIP-1559
+    /// networks. Settlements are refused, before broadcast, while the network asks for more.
+    pub max_gas_price: Option<u64>,
+}
+
+impl EvmPolicy {
+    /// Reads the policy of `network` from its `X402_EVM_MAX_GAS_PRICE_*` variable, as returned by
+    /// `lookup`. No ceiling applies if it is not set.
+    pub fn from_lookup(
+        network: Network,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let env_var = from_env::evm_max_gas_price_env_name(network);
+        let max_gas_price = match lookup(&env_var) {
+            Some(value) => Some(
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("env {env_var}: {e}"))?,
+            ),
+            None => None,
+        };
+        Ok(Self { max_gas_price })
+    }
+
+    /// Checks a gas price, in wei, against the ceiling.
+    fn assert_gas_price(&self, gas_price: u128) -> Result<(), FacilitatorLocalError> {
+        match self.max_gas_price {
+            Some(max_gas_price) if gas_price > u128::from(max_gas_price) => {
+                Err(FacilitatorLocalError::NotBroadcast(format!(
+                    "gas price of {gas_price} wei exceeds the ceiling of {max_gas_price} wei"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl EvmProvider {
     /// Build an [`EvmProvider`] from a pre-composed Alloy ethereum provider [`InnerProvider`].
+    ///
+    /// JSON-RPC calls are spread over `rpc_endpoints` with failover, see [`EvmRpcTransport`].
     pub async fn try_new(
         wallet: EthereumWallet,
-        rpc_url: &str,
+        rpc_endpoints: &[RpcEndpoint],
+        eip1559: bool,
+        network: Network,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let transport = EvmRpcTransport::try_new(rpc_endpoints)
+            .map_err(|e| format!("Failed to connect to {network}: {e}"))?;
+        let provider =
+            Self::with_client(wallet, RpcClient::new(transport, false), eip1559, network)?;
+        tracing::info!(network=%network, rpc=?rpc_endpoints, signers=?provider.signer_addresses, "Initialized provider");
+        Ok(provider)
+    }
+
+    /// An [`EvmProvider`] answered by `asserter`, signing with a random key.
+    #[cfg(test)]
+    pub(crate) fn mocked(network: Network, asserter: alloy::transports::mock::Asserter) -> Self {
+        let wallet = EthereumWallet::from(alloy::signers::local::PrivateKeySigner::random());
+        Self::with_client(wallet, RpcClient::mocked(asserter), true, network).unwrap()
+    }
+
+    fn with_client(
+        wallet: EthereumWallet,
+        client: RpcClient,
         eip1559: bool,
         network: Network,
     ) -> Result<Self, Box<dyn std::error::Error>> {
@@ -192,10 +320,6 @@ impl EvmProvider {
         }
         let signer_addresses = Arc::new(signer_addresses);
         let signer_cursor = Arc::new(AtomicUsize::new(0));
-        let client = RpcClient::builder()
-            .connect(rpc_url)
-            .await
-            .map_err(|e| format!("Failed to connect to {network}: {e}"))?;
 
         // Create nonce manager explicitly so we can store a reference for error handling
         let nonce_manager = PendingNonceManager::default();
@@ -218,8 +342,6 @@ impl EvmProvider {
             .wallet(wallet)
             .connect_client(client);
 
-        tracing::info!(network=%network, rpc=rpc_url, signers=?signer_addresses, "Initialized provider");
-
         Ok(Self {
             inner,
             eip1559,
@@ -227,9 +349,49 @@ impl EvmProvider {
             signer_addresses,
             signer_cursor,
             nonce_manager,
+            metadata_cache: EvmMetadataCache::default(),
+            confirmations: 1,
+            fee: None,
+            policy: Arc::new(RwLock::new(EvmPolicy::default())),
         })
     }
 
+    /// Applies `policy` to settlement transactions, see [`Self::set_policy`].
+    pub fn with_policy(self, policy: EvmPolicy) -> Self {
+        *self.policy.write().expect("policy lock poisoned") = policy;
+        self
+    }
+
+    /// Current limits on settlement transactions.
+    pub fn policy(&self) -> EvmPolicy {
+        *self.policy.read().expect("policy lock poisoned")
+    }
+
+    /// Swaps in a new policy, as on configuration reload. Settlements sent from now on are
+    /// checked against it.
+    pub fn set_policy(&self, policy: EvmPolicy) {
+        tracing::info!(network = %self.chain.network, policy = ?policy, "Updated EVM policy");
+        *self.policy.write().expect("policy lock poisoned") = policy;
+    }
+
+    /// Waits for `confirmations` blocks, 1 by default, before a settlement is reported.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+
+    /// Charges `fee` on ERC-3009 payments, with a second authorization settled along.
+    ///
+    /// Native coin payments are exempt, and advertised as such.
+    pub fn with_fee(mut self, mut fee: FacilitatorFee) -> Self {
+        let native: MixedAddress = NATIVE_COIN_ASSET.into();
+        if !fee.exempt.contains(&native) {
+            fee.exempt.push(native);
+        }
+        self.fee = Some(fee);
+        self
+    }
+
     /// Round-robin selection of next signer from wallet.
     fn next_signer_address(&self) -> Address {
         debug_assert!(!self.signer_addresses.is_empty());
@@ -254,6 +416,14 @@ pub trait MetaEvmProvider {
     fn inner(&self) -> &Self::Inner;
     /// Returns reference to chain descriptor.
     fn chain(&self) -> &EvmChain;
+    /// Returns the cache of token metadata and contract code for this chain.
+    fn metadata_cache(&self) -> &EvmMetadataCache;
+    /// Block confirmations a settlement transaction waits for.
+    fn confirmations(&self) -> u64;
+    /// Service fee charged on ERC-3009 payments, if any.
+    fn fee(&self) -> Option<&FacilitatorFee>;
+    /// Signer that settlement transactions are simulated from.
+    fn simulation_sender(&self) -> Address;
 
     /// Sends a meta-transaction to the network.
     fn send_transaction(
@@ -284,12 +454,32 @@ impl MetaEvmProvider for EvmProvider {
         &self.chain
     }
 
+    fn metadata_cache(&self) -> &EvmMetadataCache {
+        &self.metadata_cache
+    }
+
+    fn confirmations(&self) -> u64 {
+        self.confirmations
+    }
+
+    fn fee(&self) -> Option<&FacilitatorFee> {
+        self.fee.as_ref()
+    }
+
+    fn simulation_sender(&self) -> Address {
+        self.inner.default_signer_address()
+    }
+
     /// Send a meta-transaction with provided `to`, `calldata`, and automatically selected signer.
     ///
     /// This method constructs a transaction from the provided [`MetaTransaction`], automatically
     /// selects the next available signer using round-robin selection, and handles gas pricing
     /// based on whether the network supports EIP-1559.
     ///
+    /// The transaction is filled and signed by the provider's fillers, its nonce allocated by the
+    /// [`PendingNonceManager`]. Once broadcast, it is recorded as a pending settlement until the
+    /// receipt arrives or fails to, see [`pending_settlements`].
+    ///
     /// If the transaction fails at any point (during submission or receipt fetching), the nonce
     /// for the sending address is reset to force a fresh query on the next transaction. This
     /// ensures correctness even when transactions partially succeed (e.g., submitted but receipt
@@ -297,7 +487,8 @@ impl MetaEvmProvider for EvmProvider {
     ///
     /// # Gas Pricing Strategy
     ///
-    /// - **EIP-1559 networks**: Uses automatic gas pricing via the provider's fillers.
+    /// - **EIP-1559 networks**: Uses automatic gas pricing via the provider's fillers, unless the
+    ///   [`EvmPolicy`] sets a gas price ceiling: the fees are then estimated and checked here.
     /// - **Legacy networks**: Fetches the current gas price using `get_gas_price()` and sets it explicitly.
     ///
     /// # Timeout Configuration
@@ -317,9 +508,14 @@ impl MetaEvmProvider for EvmProvider {
     ///
     /// # Errors
     ///
+    /// Returns [`FacilitatorLocalError::NotBroadcast`], before anything is broadcast, if:
+    /// - Gas price fetching fails (on legacy networks, or with a gas price ceiling)
+    /// - The gas price exceeds the ceiling of the [`EvmPolicy`]
+    /// - The transaction can not be filled, e.g. gas estimation finds the signer short of funds
+    /// - The node refuses the transaction for lack of funds
+    ///
     /// Returns [`FacilitatorLocalError::ContractCall`] if:
-    /// - Gas price fetching fails (on legacy networks)
-    /// - Transaction sending fails
+    /// - Transaction sending fails otherwise, as the transaction may have reached the node
     /// - Receipt retrieval fails or times out
     async fn send_transaction(
         &self,
@@ -330,40 +526,77 @@ impl MetaEvmProvider for EvmProvider {
             .with_to(tx.to)
             .with_from(from_address)
             .with_input(tx.calldata);
+        let policy = self.policy();
         if !self.eip1559 {
             let provider = &self.inner;
             let gas: u128 = provider
                 .get_gas_price()
                 .instrument(tracing::info_span!("get_gas_price"))
                 .await
-                .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+                .map_err(|e| FacilitatorLocalError::NotBroadcast(format!("{e:?}")))?;
+            policy.assert_gas_price(gas)?;
             txr.set_gas_price(gas);
+        } else if policy.max_gas_price.is_some() {
+            // Estimated here rather than in the filler, to check them against the ceiling
+            let fees = self
+                .inner
+                .estimate_eip1559_fees()
+                .instrument(tracing::info_span!("estimate_eip1559_fees"))
+                .await
+                .map_err(|e| FacilitatorLocalError::NotBroadcast(format!("{e:?}")))?;
+            policy.assert_gas_price(fees.max_fee_per_gas)?;
+            txr.set_max_fee_per_gas(fees.max_fee_per_gas);
+            txr.set_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
         }
 
+        // Filled and signed ahead of sending, to record its nonce with the pending settlement
+        let filled = match self.inner.fill(txr).await {
+            Ok(filled) => filled.try_into_envelope().map_err(|e| format!("{e:?}")),
+            Err(e) => Err(format!("{e:?}")),
+        };
+        let envelope = match filled {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                // The nonce may be allocated already - reset nonce to force requery
+                self.nonce_manager.reset_nonce(from_address).await;
+                return Err(FacilitatorLocalError::NotBroadcast(e));
+            }
+        };
+        let nonce = envelope.nonce();
+
         // Send transaction with error handling for nonce reset
-        let pending_tx = match self.inner.send_transaction(txr).await {
+        let pending_tx = match self.inner.send_tx_envelope(envelope).await {
             Ok(pending) => pending,
             Err(e) => {
                 // Transaction submission failed - reset nonce to force requery
                 self.nonce_manager.reset_nonce(from_address).await;
-                return Err(FacilitatorLocalError::ContractCall(format!("{e:?}")));
+                let unaffordable = e
+                    .as_error_resp()
+                    .is_some_and(|resp| resp.message.contains("insufficient funds"));
+                let message = format!("{e:?}");
+                return Err(if unaffordable {
+                    FacilitatorLocalError::NotBroadcast(message)
+                } else {
+                    FacilitatorLocalError::ContractCall(message)
+                });
             }
         };
-
-        // Get receipt with timeout and error handling for nonce reset
-        // Default timeout of 30 seconds is reasonable for most EVM chains
-        let timeout = std::time::Duration::from_secs(
-            std::env::var("TX_RECEIPT_TIMEOUT_SECS")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(30),
+        let tx_hash = TransactionHash::Evm(pending_tx.tx_hash().0);
+        pending_settlements::broadcast(
+            self.chain.network,
+            tx_hash.clone(),
+            from_address.into(),
+            Some(nonce),
         );
 
+        // Get receipt with timeout and error handling for nonce reset
         let watcher = pending_tx
             .with_required_confirmations(tx.confirmations)
-            .with_timeout(Some(timeout));
-
-        match watcher.get_receipt().await {
+            .with_timeout(Some(tx_receipt_timeout()));
+        let receipt = watcher.get_receipt().await;
+        // The settlement is answered either way; only a shutdown cutting it short leaves it pending
+        pending_settlements::settled(&tx_hash);
+        match receipt {
             Ok(receipt) => Ok(receipt),
             Err(e) => {
                 // Receipt fetch failed (timeout or other error) - reset nonce to force requery
@@ -374,6 +607,59 @@ impl MetaEvmProvider for EvmProvider {
     }
 }
 
+/// How long to wait for a transaction receipt.
+///
+/// Default timeout of 30 seconds is reasonable for most EVM chains;
+/// override via `TX_RECEIPT_TIMEOUT_SECS` environment variable.
+fn tx_receipt_timeout() -> std::time::Duration {
+    std::time::Duration::from_secs(
+        std::env::var("TX_RECEIPT_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(30),
+    )
+}
+
+impl EvmProvider {
+    /// Seeds the nonce of `signer` after a restart.
+    ///
+    /// The next transaction follows both the pending transaction count reported by the node, and
+    /// `in_flight`, the nonces of transactions of `signer` known to be still pending, which a node
+    /// behind the others may not report yet.
+    pub async fn reseed_nonce(
+        &self,
+        signer: Address,
+        in_flight: impl IntoIterator<Item = u64>,
+    ) -> Result<u64, FacilitatorLocalError> {
+        let pending_count = self
+            .inner
+            .get_transaction_count(signer)
+            .pending()
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        let next_nonce = in_flight
+            .into_iter()
+            .map(|nonce| nonce + 1)
+            .fold(pending_count, u64::max);
+        self.nonce_manager.seed_nonce(signer, next_nonce).await;
+        Ok(next_nonce)
+    }
+
+    /// Whether `address` is one of the signers of this provider, whose nonces it manages.
+    pub fn is_signer(&self, address: Address) -> bool {
+        self.signer_addresses.contains(&address)
+    }
+
+    /// Number of transactions of `signer` in the latest block: the nonce of the next one to land.
+    pub async fn mined_nonce(&self, signer: Address) -> Result<u64, FacilitatorLocalError> {
+        self.inner
+            .get_transaction_count(signer)
+            .latest()
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+    }
+}
+
 impl NetworkProviderOps for EvmProvider {
     /// Address of the default signer used by this provider (for tx sending).
     fn signer_address(&self) -> MixedAddress {
@@ -386,11 +672,80 @@ impl NetworkProviderOps for EvmProvider {
     }
 }
 
+impl SignerBalanceQuery for EvmProvider {
+    async fn signer_balances(
+        &self,
+    ) -> Result<Vec<(MixedAddress, TokenAmount)>, FacilitatorLocalError> {
+        let mut balances = Vec::with_capacity(self.signer_addresses.len());
+        for address in self.signer_addresses.iter() {
+            let balance = self
+                .inner
+                .get_balance(*address)
+                .await
+                .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+            balances.push(((*address).into(), balance.into()));
+        }
+        Ok(balances)
+    }
+}
+
+impl FacilitatorFeeQuery for EvmProvider {
+    fn fee_due(&self, request: &VerifyRequest) -> Option<TokenAmount> {
+        // Buyer-signed native transfers pay their own gas, and are not charged a fee
+        if let ExactPaymentPayload::EvmNative(_) = request.payment_payload.payload {
+            return None;
+        }
+        let requirements = &request.payment_requirements;
+        let fee_due = self
+            .fee()?
+            .amount(&requirements.asset, requirements.max_amount_required);
+        (!fee_due.0.is_zero()).then_some(fee_due)
+    }
+}
+
+impl PaymentAmountQuery for EvmProvider {
+    /// The authorized `value` of an ERC-3009 payment, with the decimals of its token: known for
+    /// USDC, read from the token contract and cached otherwise. The `value` of a native transfer,
+    /// in wei.
+    async fn payment_amount(&self, request: &VerifyRequest) -> Option<PaymentAmount> {
+        match &request.payment_payload.payload {
+            ExactPaymentPayload::Evm(payload) => {
+                let asset: Address = request.payment_requirements.asset.clone().try_into().ok()?;
+                let usdc = USDCDeployment::by_network(self.network());
+                let decimals = if usdc.address() == asset.into() {
+                    Some(usdc.decimals)
+                } else {
+                    let token = USDC::new(asset, &self.inner);
+                    self.metadata_cache
+                        .token_decimals(asset, || async { token.decimals().call().await })
+                        .await
+                        .inspect_err(
+                            |e| tracing::debug!(%asset, error = %e, "Token decimals lookup failed"),
+                        )
+                        .ok()
+                };
+                Some(PaymentAmount {
+                    amount: payload.authorization.value,
+                    decimals,
+                })
+            }
+            ExactPaymentPayload::EvmNative(payload) => {
+                let envelope =
+                    TxEnvelope::decode_2718(&mut payload.raw_transaction.as_ref()).ok()?;
+                Some(PaymentAmount {
+                    amount: envelope.value().into(),
+                    decimals: Some(self.network().native_decimals()),
+                })
+            }
+            ExactPaymentPayload::Solana(_) => None,
+        }
+    }
+}
+
 impl FromEnvByNetworkBuild for EvmProvider {
     async fn from_env(network: Network) -> Result<Option<Self>, Box<dyn std::error::Error>> {
-        let env_var = from_env::rpc_env_name_from_network(network);
-        let rpc_url = match std::env::var(env_var).ok() {
-            Some(rpc_url) => rpc_url,
+        let rpc_endpoints = match from_env::rpc_endpoints_from_env(network)? {
+            Some(rpc_endpoints) => rpc_endpoints,
             None => {
                 tracing::warn!(network=%network, "no RPC URL configured, skipping");
                 return Ok(None);
@@ -403,7 +758,15 @@ impl FromEnvByNetworkBuild for EvmProvider {
             Network::Solana => false,
             Network::SolanaDevnet => false,
         };
-        let provider = EvmProvider::try_new(wallet, &rpc_url, is_eip1559, network).await?;
+        let confirmations = from_env::evm_confirmations_from_env(network)?;
+        let policy = EvmPolicy::from_lookup(network, |name| std::env::var(name).ok())?;
+        let mut provider = EvmProvider::try_new(wallet, &rpc_endpoints, is_eip1559, network)
+            .await?
+            .with_confirmations(confirmations)
+            .with_policy(policy);
+        if let Some(fee) = from_env::facilitator_fee_from_env(network, provider.signer_address())? {
+            provider = provider.with_fee(fee);
+        }
         Ok(Some(provider))
     }
 }
@@ -417,92 +780,95 @@ where
 
     /// Verify x402 payment intent by simulating signature validity and ERC-3009 transfer.
     ///
-    /// For EIP-6492 signatures, perform a multicall: first the validator’s
-    /// `isValidSigWithSideEffects` (which *may* deploy the counterfactual wallet in sim),
-    /// then the token’s `transferWithAuthorization`. Both run within a single `eth_call`
-    /// so the state is shared during simulation.
+    /// The payer's balance and, for EIP-6492 signatures, the validator's
+    /// `isValidSigWithSideEffects` and the wallet's code are read in one JSON-RPC batch. The
+    /// settlement transaction is then simulated in an `eth_call` from a facilitator signer, built
+    /// exactly as [`Facilitator::settle`] sends it: the payment transfer, preceded by the wallet
+    /// deployment if it is counterfactual and followed by the fee transfer if one is due.
     ///
     /// # Errors
     /// - [`FacilitatorLocalError::NetworkMismatch`], [`FacilitatorLocalError::SchemeMismatch`], [`FacilitatorLocalError::ReceiverMismatch`] if inputs are inconsistent.
     /// - [`FacilitatorLocalError::InvalidTiming`] if outside `validAfter/validBefore`.
+    /// - [`FacilitatorLocalError::InvalidFee`] if the fee authorization is missing or wrong.
     /// - [`FacilitatorLocalError::InsufficientFunds`] / `FacilitatorLocalError::InsufficientValue` on balance/value checks.
     /// - [`FacilitatorLocalError::ContractCall`] if on-chain calls revert.
     async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
         let payload = &request.payment_payload;
         let requirements = &request.payment_requirements;
-        let (contract, payment, eip712_domain) =
-            assert_valid_payment(self.inner(), self.chain(), payload, requirements).await?;
-
+        if let ExactPaymentPayload::EvmNative(native_payload) = &payload.payload {
+            let payment = assert_valid_native_payment(
+                self.inner(),
+                self.chain(),
+                payload,
+                native_payload,
+                requirements,
+            )
+            .await?;
+            simulate_native_payment(self.inner(), &payment).await?;
+            return Ok(VerifyResponse::valid(payment.from.into()));
+        }
+        let (contract, payment, fee_payment, eip712_domain) = assert_valid_payment(
+            self.inner(),
+            self.chain(),
+            self.metadata_cache(),
+            self.fee(),
+            payload,
+            requirements,
+        )
+        .await?;
+
+        let amount_required = requirements.max_amount_required.0.saturating_add(
+            fee_payment
+                .as_ref()
+                .map_or(U256::ZERO, |fee_payment| fee_payment.value.0),
+        );
         let signed_message = SignedMessage::extract(&payment, &eip712_domain)?;
         let payer = signed_message.address;
-        let hash = signed_message.hash;
-        match signed_message.signature {
-            StructuredSignature::EIP6492 {
-                factory: _,
-                factory_calldata: _,
-                inner,
-                original,
-            } => {
-                // Prepare the call to validate EIP-6492 signature
-                let validator6492 = Validator6492::new(VALIDATOR_ADDRESS, self.inner());
-                let is_valid_signature_call =
-                    validator6492.isValidSigWithSideEffects(payer, hash, original);
-                // Prepare the call to simulate transfer the funds
-                let transfer_call = transferWithAuthorization_0(&contract, &payment, inner).await?;
-                // Execute both calls in a single transaction simulation to accommodate for possible smart wallet creation
-                let (is_valid_signature_result, transfer_result) = self
-                    .inner()
-                    .multicall()
-                    .add(is_valid_signature_call)
-                    .add(transfer_call.tx)
-                    .aggregate3()
-                    .instrument(tracing::info_span!("call_transferWithAuthorization_0",
-                            from = %transfer_call.from,
-                            to = %transfer_call.to,
-                            value = %transfer_call.value,
-                            valid_after = %transfer_call.valid_after,
-                            valid_before = %transfer_call.valid_before,
-                            nonce = %transfer_call.nonce,
-                            signature = %transfer_call.signature,
-                            token_contract = %transfer_call.contract_address,
-                            otel.kind = "client",
-                    ))
-                    .await
-                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-                let is_valid_signature_result = is_valid_signature_result
-                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-                if !is_valid_signature_result {
-                    return Err(FacilitatorLocalError::InvalidSignature(
-                        payer.into(),
-                        "Incorrect signature".to_string(),
-                    ));
-                }
-                transfer_result.map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
-            }
-            StructuredSignature::EIP1271(signature) => {
-                // It is EOA or EIP-1271 signature, which we can pass to the transfer simulation
-                let transfer_call =
-                    transferWithAuthorization_0(&contract, &payment, signature).await?;
-                transfer_call
-                    .tx
-                    .call()
-                    .into_future()
-                    .instrument(tracing::info_span!("call_transferWithAuthorization_0",
-                            from = %transfer_call.from,
-                            to = %transfer_call.to,
-                            value = %transfer_call.value,
-                            valid_after = %transfer_call.valid_after,
-                            valid_before = %transfer_call.valid_before,
-                            nonce = %transfer_call.nonce,
-                            signature = %transfer_call.signature,
-                            token_contract = %transfer_call.contract_address,
-                            otel.kind = "client",
-                    ))
-                    .await
-                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-            }
+        let payer_state = read_payer_state(
+            self.inner(),
+            self.metadata_cache(),
+            &contract,
+            &signed_message,
+            true,
+        )
+        .await?;
+        assert_balance_covers(&payment.from, payer_state.balance, amount_required)?;
+        if payer_state.is_valid_signature == Some(false) {
+            return Err(FacilitatorLocalError::InvalidSignature(
+                payer.into(),
+                "Incorrect signature".to_string(),
+            ));
         }
 
+        let (calls, transfer_call, sig_kind) = settlement_calls(
+            &contract,
+            &payment,
+            fee_payment.as_ref(),
+            &eip712_domain,
+            signed_message.signature,
+            payer_state.is_deployed,
+        )
+        .await?;
+        let transaction = batch_transaction(calls, self.confirmations());
+        self.inner()
+            .call(simulation_request(self.simulation_sender(), &transaction))
+            .into_future()
+            .instrument(tracing::info_span!("call_transferWithAuthorization_0",
+                    from = %transfer_call.from,
+                    to = %transfer_call.to,
+                    value = %transfer_call.value,
+                    valid_after = %transfer_call.valid_after,
+                    valid_before = %transfer_call.valid_before,
+                    nonce = %transfer_call.nonce,
+                    signature = %transfer_call.signature,
+                    token_contract = %transfer_call.contract_address,
+                    sig_kind = sig_kind,
+                    fee = fee_payment.is_some(),
+                    otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+
         Ok(VerifyResponse::valid(payer.into()))
     }
 
@@ -518,6 +884,9 @@ where
     /// If the wallet is already deployed (or the signature is plain EIP-1271/EOA),
     /// we submit a single `transferWithAuthorization` transaction.
     ///
+    /// A facilitator fee, if due, is appended to the same Multicall3 batch, so the payment
+    /// never settles without it.
+    ///
     /// # Returns
     /// A [`SettleResponse`] containing success flag and transaction hash.
     ///
@@ -527,102 +896,93 @@ where
     async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
         let payload = &request.payment_payload;
         let requirements = &request.payment_requirements;
-        let (contract, payment, eip712_domain) =
-            assert_valid_payment(self.inner(), self.chain(), payload, requirements).await?;
-
-        let signed_message = SignedMessage::extract(&payment, &eip712_domain)?;
-        let payer = signed_message.address;
-        let transaction_receipt_fut = match signed_message.signature {
-            StructuredSignature::EIP6492 {
-                factory,
-                factory_calldata,
-                inner,
-                original: _,
-            } => {
-                let is_contract_deployed = is_contract_deployed(self.inner(), &payer).await?;
-                let transfer_call = transferWithAuthorization_0(&contract, &payment, inner).await?;
-                if is_contract_deployed {
-                    // transferWithAuthorization with inner signature
-                    self.send_transaction(MetaTransaction {
-                        to: transfer_call.tx.target(),
-                        calldata: transfer_call.tx.calldata().clone(),
-                        confirmations: 1,
-                    })
-                    .instrument(
-                        tracing::info_span!("call_transferWithAuthorization_0",
-                            from = %transfer_call.from,
-                            to = %transfer_call.to,
-                            value = %transfer_call.value,
-                            valid_after = %transfer_call.valid_after,
-                            valid_before = %transfer_call.valid_before,
-                            nonce = %transfer_call.nonce,
-                            signature = %transfer_call.signature,
-                            token_contract = %transfer_call.contract_address,
-                            sig_kind="EIP6492.deployed",
-                            otel.kind = "client",
-                        ),
-                    )
-                } else {
-                    // deploy the smart wallet, and transferWithAuthorization with inner signature
-                    let deployment_call = IMulticall3::Call3 {
-                        allowFailure: true,
-                        target: factory,
-                        callData: factory_calldata,
-                    };
-                    let transfer_with_authorization_call = IMulticall3::Call3 {
-                        allowFailure: false,
-                        target: transfer_call.tx.target(),
-                        callData: transfer_call.tx.calldata().clone(),
-                    };
-                    let aggregate_call = IMulticall3::aggregate3Call {
-                        calls: vec![deployment_call, transfer_with_authorization_call],
-                    };
-                    self.send_transaction(MetaTransaction {
-                        to: MULTICALL3_ADDRESS,
-                        calldata: aggregate_call.abi_encode().into(),
-                        confirmations: 1,
-                    })
-                    .instrument(
-                        tracing::info_span!("call_transferWithAuthorization_0",
-                            from = %transfer_call.from,
-                            to = %transfer_call.to,
-                            value = %transfer_call.value,
-                            valid_after = %transfer_call.valid_after,
-                            valid_before = %transfer_call.valid_before,
-                            nonce = %transfer_call.nonce,
-                            signature = %transfer_call.signature,
-                            token_contract = %transfer_call.contract_address,
-                            sig_kind="EIP6492.counterfactual",
-                            otel.kind = "client",
-                        ),
-                    )
-                }
+        if let ExactPaymentPayload::EvmNative(native_payload) = &payload.payload {
+            let payment = assert_valid_native_payment(
+                self.inner(),
+                self.chain(),
+                payload,
+                native_payload,
+                requirements,
+            )
+            .await?;
+            let receipt = relay_native_payment(
+                self.inner(),
+                self.chain().network,
+                &payment,
+                self.confirmations(),
+            )
+            .await?;
+            let success = receipt.status();
+            if success {
+                tracing::event!(Level::INFO,
+                    status = "ok",
+                    tx = %receipt.transaction_hash,
+                    "native transfer succeeded"
+                );
+            } else {
+                tracing::event!(Level::WARN,
+                    status = "failed",
+                    tx = %receipt.transaction_hash,
+                    "native transfer failed"
+                );
             }
-            StructuredSignature::EIP1271(eip1271_signature) => {
-                let transfer_call =
-                    transferWithAuthorization_0(&contract, &payment, eip1271_signature).await?;
-                // transferWithAuthorization with eip1271 signature
-                self.send_transaction(MetaTransaction {
-                    to: transfer_call.tx.target(),
-                    calldata: transfer_call.tx.calldata().clone(),
-                    confirmations: 1,
-                })
-                .instrument(
-                    tracing::info_span!("call_transferWithAuthorization_0",
-                        from = %transfer_call.from,
-                        to = %transfer_call.to,
-                        value = %transfer_call.value,
-                        valid_after = %transfer_call.valid_after,
-                        valid_before = %transfer_call.valid_before,
-                        nonce = %transfer_call.nonce,
-                        signature = %transfer_call.signature,
-                        token_contract = %transfer_call.contract_address,
-                        sig_kind="EIP1271",
-                        otel.kind = "client",
-                    ),
-                )
-            }
-        };
+            return Ok(SettleResponse {
+                success,
+                error_reason: (!success).then_some(FacilitatorErrorReason::TransactionFailed),
+                payer: payment.from.into(),
+                transaction: Some(TransactionHash::Evm(receipt.transaction_hash.0)),
+                network: payload.network,
+            });
+        }
+        let (contract, payment, fee_payment, eip712_domain) = assert_valid_payment(
+            self.inner(),
+            self.chain(),
+            self.metadata_cache(),
+            self.fee(),
+            payload,
+            requirements,
+        )
+        .await?;
+        let amount_required = requirements.max_amount_required.0.saturating_add(
+            fee_payment
+                .as_ref()
+                .map_or(U256::ZERO, |fee_payment| fee_payment.value.0),
+        );
+        let signed_message = SignedMessage::extract(&payment, &eip712_domain)?;
+        let payer_state = read_payer_state(
+            self.inner(),
+            self.metadata_cache(),
+            &contract,
+            &signed_message,
+            false,
+        )
+        .await?;
+        assert_balance_covers(&payment.from, payer_state.balance, amount_required)?;
+
+        let (calls, transfer_call, sig_kind) = settlement_calls(
+            &contract,
+            &payment,
+            fee_payment.as_ref(),
+            &eip712_domain,
+            signed_message.signature,
+            payer_state.is_deployed,
+        )
+        .await?;
+        let transaction_receipt_fut = self
+            .send_transaction(batch_transaction(calls, self.confirmations()))
+            .instrument(tracing::info_span!("call_transferWithAuthorization_0",
+                from = %transfer_call.from,
+                to = %transfer_call.to,
+                value = %transfer_call.value,
+                valid_after = %transfer_call.valid_after,
+                valid_before = %transfer_call.valid_before,
+                nonce = %transfer_call.nonce,
+                signature = %transfer_call.signature,
+                token_contract = %transfer_call.contract_address,
+                sig_kind = sig_kind,
+                fee = fee_payment.is_some(),
+                otel.kind = "client",
+            ));
         let receipt = transaction_receipt_fut.await?;
         let success = receipt.status();
         if success {
@@ -661,13 +1021,17 @@ where
             network: self.chain().network().to_string(),
             x402_version: X402Version::V1,
             scheme: Scheme::Exact,
-            extra: None,
+            extra: self.fee().map(|fee| SupportedPaymentKindExtra {
+                fee_payer: None,
+                recommended_compute_unit_price: None,
+                fee: Some(fee.clone()),
+            }),
         }];
         Ok(SupportedPaymentKindsResponse { kinds })
     }
 }
 
-impl crate::chain::TransactionStatusQuery for EvmProvider {
+impl TransactionStatusQuery for EvmProvider {
     #[instrument(skip_all, err, fields(tx_hash = %tx_hash))]
     async fn get_transaction_status(
         &self,
@@ -703,15 +1067,14 @@ impl crate::chain::TransactionStatusQuery for EvmProvider {
                     .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
 
                 let block_number = receipt.block_number;
-                let current_block_u64: u64 = current_block.into();
                 let confirmations = block_number
-                    .map(|bn| current_block_u64.saturating_sub(bn))
+                    .map(|bn| current_block.saturating_sub(bn))
                     .unwrap_or(0);
 
                 Ok(TransactionStatusResponse {
                     transaction_hash: tx_hash.clone(),
                     status,
-                    network,
+                    network: Some(network),
                     block_number,
                     confirmations: Some(confirmations),
                     error: if status == TransactionStatus::Failed {
@@ -729,7 +1092,7 @@ impl crate::chain::TransactionStatusQuery for EvmProvider {
                         Ok(TransactionStatusResponse {
                             transaction_hash: tx_hash.clone(),
                             status: TransactionStatus::Pending,
-                            network,
+                            network: Some(network),
                             block_number: None,
                             confirmations: None,
                             error: None,
@@ -740,7 +1103,7 @@ impl crate::chain::TransactionStatusQuery for EvmProvider {
                         Ok(TransactionStatusResponse {
                             transaction_hash: tx_hash.clone(),
                             status: TransactionStatus::NotFound,
-                            network,
+                            network: Some(network),
                             block_number: None,
                             confirmations: None,
                             error: Some("Transaction not found".to_string()),
@@ -754,6 +1117,85 @@ impl crate::chain::TransactionStatusQuery for EvmProvider {
     }
 }
 
+impl FinalityQuery for EvmProvider {
+    /// Compares the block of the transaction receipt with the canonical and finalized blocks.
+    ///
+    /// The transaction was reorganized out only when the canonical block at the height of its
+    /// receipt has another hash. A missing receipt is not conclusive, since the node may lag
+    /// behind or the transaction may land again, so the finality is checked again later.
+    /// A reverted transaction is still included in the chain, and is judged by its block alone.
+    #[instrument(skip_all, err, fields(tx_hash = %tx_hash))]
+    async fn get_finality(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> Result<Finality, FacilitatorLocalError> {
+        let evm_hash = match tx_hash {
+            TransactionHash::Evm(hash) => alloy::primitives::TxHash::from(*hash),
+            TransactionHash::Solana(_) => {
+                return Err(FacilitatorLocalError::DecodingError(
+                    "Transaction hash is for Solana, but provider is EVM".to_string(),
+                ));
+            }
+        };
+        let receipt = self
+            .inner
+            .get_transaction_receipt(evm_hash)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        let Some((block_number, block_hash)) =
+            receipt.and_then(|receipt| receipt.block_number.zip(receipt.block_hash))
+        else {
+            return Ok(Finality::Unfinalized);
+        };
+        let canonical_block = self
+            .inner
+            .get_block_by_number(BlockNumberOrTag::Number(block_number))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        match canonical_block {
+            Some(block) if block.header.hash != block_hash => return Ok(Finality::Reorged),
+            Some(_) => {}
+            None => return Ok(Finality::Unfinalized),
+        }
+        let finalized_block = self
+            .inner
+            .get_block_by_number(BlockNumberOrTag::Finalized)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        match finalized_block {
+            Some(block) if block_number <= block.header.number => Ok(Finality::Finalized),
+            _ => Ok(Finality::Unfinalized),
+        }
+    }
+}
+
+impl SettlementCostQuery for EvmProvider {
+    /// Gas used by the transaction, at its effective gas price.
+    #[instrument(skip_all, err, fields(tx_hash = %tx_hash))]
+    async fn get_settlement_cost(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> Result<TokenAmount, FacilitatorLocalError> {
+        let evm_hash = match tx_hash {
+            TransactionHash::Evm(hash) => alloy::primitives::TxHash::from(*hash),
+            TransactionHash::Solana(_) => {
+                return Err(FacilitatorLocalError::DecodingError(
+                    "Transaction hash is for Solana, but provider is EVM".to_string(),
+                ));
+            }
+        };
+        let receipt = self
+            .inner
+            .get_transaction_receipt(evm_hash)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?
+            .ok_or_else(|| {
+                FacilitatorLocalError::ContractCall("Transaction receipt not found".to_string())
+            })?;
+        Ok(TokenAmount::from(receipt.effective_gas_price) * receipt.gas_used)
+    }
+}
+
 /// A prepared call to `transferWithAuthorization` (ERC-3009) including all derived fields.
 ///
 /// This struct wraps the assembled call builder, making it reusable across verification
@@ -810,36 +1252,15 @@ fn assert_time(
     Ok(())
 }
 
-/// Checks if the payer has enough on-chain token balance to meet the `maxAmountRequired`.
-///
-/// Performs an `ERC20.balanceOf()` call using the USDC contract instance.
+/// Compares an already fetched token balance with the maximum required amount.
 ///
 /// # Errors
 /// Returns [`FacilitatorLocalError::InsufficientFunds`] if the balance is too low.
-/// Returns [`FacilitatorLocalError::ContractCall`] if the balance query fails.
-#[instrument(skip_all, err, fields(
-    sender = %sender,
-    max_required = %max_amount_required,
-    token_contract = %usdc_contract.address()
-))]
-async fn assert_enough_balance<P: Provider>(
-    usdc_contract: &USDC::USDCInstance<P>,
+fn assert_balance_covers(
     sender: &EvmAddress,
+    balance: U256,
     max_amount_required: U256,
 ) -> Result<(), FacilitatorLocalError> {
-    let balance = usdc_contract
-        .balanceOf(sender.0)
-        .call()
-        .into_future()
-        .instrument(tracing::info_span!(
-            "fetch_token_balance",
-            token_contract = %usdc_contract.address(),
-            sender = %sender,
-            otel.kind = "client"
-        ))
-        .await
-        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-
     if balance < max_amount_required {
         Err(FacilitatorLocalError::InsufficientFunds((*sender).into()))
     } else {
@@ -869,47 +1290,24 @@ fn assert_enough_value(
     }
 }
 
-/// Check whether contract code is present at `address`.
-///
-/// Uses `eth_getCode` against this provider. This is useful after a counterfactual
-/// deployment to confirm visibility on the sending RPC before submitting a
-/// follow-up transaction.
-///
-/// # Errors
-/// Return [`FacilitatorLocalError::ContractCall`] if the RPC call fails.
-async fn is_contract_deployed<P: Provider>(
-    provider: P,
-    address: &Address,
-) -> Result<bool, FacilitatorLocalError> {
-    let bytes = provider
-        .get_code_at(*address)
-        .into_future()
-        .instrument(tracing::info_span!("get_code_at",
-            address = %address,
-            otel.kind = "client",
-        ))
-        .await
-        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-    Ok(!bytes.is_empty())
-}
-
 /// Constructs the correct EIP-712 domain for signature verification.
 ///
 /// Resolves the `name` and `version` based on:
+/// - The `extra.name`/`extra.version` of the requirements (if present),
 /// - Static metadata from [`USDCDeployment`] (if available),
-/// - Or by calling `version()` on the token contract if not matched statically.
+/// - Or by calling `name()`/`version()` on the token contract, cached in `metadata_cache`.
 #[instrument(skip_all, err, fields(
-    network = %payload.network,
+    network = %chain.network,
     asset = %asset_address
 ))]
-async fn assert_domain<P: Provider>(
+pub(crate) async fn assert_domain<P: Provider>(
     chain: &EvmChain,
     token_contract: &USDC::USDCInstance<P>,
-    payload: &PaymentPayload,
     asset_address: &Address,
     requirements: &PaymentRequirements,
+    metadata_cache: &EvmMetadataCache,
 ) -> Result<Eip712Domain, FacilitatorLocalError> {
-    let usdc = USDCDeployment::by_network(payload.network);
+    let usdc = USDCDeployment::by_network(chain.network);
     let name = requirements
         .extra
         .as_ref()
@@ -918,16 +1316,19 @@ async fn assert_domain<P: Provider>(
     let name = if let Some(name) = name {
         name
     } else {
-        token_contract
-            .name()
-            .call()
-            .into_future()
-            .instrument(tracing::info_span!(
-                "fetch_eip712_name",
-                otel.kind = "client",
-            ))
-            .await
-            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?
+        metadata_cache
+            .token_name(*asset_address, || async {
+                token_contract
+                    .name()
+                    .call()
+                    .into_future()
+                    .instrument(tracing::info_span!(
+                        "fetch_eip712_name",
+                        otel.kind = "client",
+                    ))
+                    .await
+            })
+            .await?
     };
     let chain_id = chain.chain_id;
     let version = requirements
@@ -945,16 +1346,19 @@ async fn assert_domain<P: Provider>(
     let version = if let Some(version) = version {
         version
     } else {
-        token_contract
-            .version()
-            .call()
-            .into_future()
-            .instrument(tracing::info_span!(
-                "fetch_eip712_version",
-                otel.kind = "client",
-            ))
-            .await
-            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?
+        metadata_cache
+            .token_version(*asset_address, || async {
+                token_contract
+                    .version()
+                    .call()
+                    .into_future()
+                    .instrument(tracing::info_span!(
+                        "fetch_eip712_version",
+                        otel.kind = "client",
+                    ))
+                    .await
+            })
+            .await?
     };
     let domain = eip712_domain! {
         name: name,
@@ -965,28 +1369,436 @@ async fn assert_domain<P: Provider>(
     Ok(domain)
 }
 
-/// Runs all preconditions needed for a successful payment:
-/// - Valid scheme, network, and receiver.
-/// - Valid time window (validAfter/validBefore).
-/// - Correct EIP-712 domain construction.
-/// - Sufficient on-chain balance.
-/// - Sufficient value in payload.
-#[instrument(skip_all, err)]
-async fn assert_valid_payment<P: Provider>(
-    provider: P,
-    chain: &EvmChain,
-    payload: &PaymentPayload,
-    requirements: &PaymentRequirements,
-) -> Result<(USDC::USDCInstance<P>, ExactEvmPayment, Eip712Domain), FacilitatorLocalError> {
-    let payment_payload = match &payload.payload {
-        ExactPaymentPayload::Evm(payload) => payload,
-        ExactPaymentPayload::Solana(_) => {
-            return Err(FacilitatorLocalError::UnsupportedNetwork(None));
+/// Maximum number of entries of each map of an [`EvmMetadataCache`].
+const METADATA_CACHE_CAPACITY: usize = 1024;
+
+/// Time-bounded map of at most [`METADATA_CACHE_CAPACITY`] entries.
+#[derive(Debug)]
+struct BoundedCache<K: Eq + std::hash::Hash, V> {
+    entries: DashMap<K, (V, Instant)>,
+    ttl: Duration,
+}
+
+impl<K: Eq + std::hash::Hash + Clone, V: Clone> BoundedCache<K, V> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            entries: DashMap::new(),
+            ttl,
         }
-    };
-    let payer = payment_payload.authorization.from;
-    if payload.network != chain.network {
-        return Err(FacilitatorLocalError::NetworkMismatch(
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        self.entries
+            .get(key)
+            .filter(|entry| entry.1.elapsed() <= self.ttl)
+            .map(|entry| entry.0.clone())
+    }
+
+    /// Inserts `value`. When full, expired entries are dropped first, then the oldest one.
+    fn insert(&self, key: K, value: V) {
+        if self.entries.len() >= METADATA_CACHE_CAPACITY && !self.entries.contains_key(&key) {
+            self.entries
+                .retain(|_, (_, fetched_at)| fetched_at.elapsed() <= self.ttl);
+            if self.entries.len() >= METADATA_CACHE_CAPACITY {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|entry| entry.1)
+                    .map(|entry| entry.key().clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+        self.entries.insert(key, (value, Instant::now()));
+    }
+}
+
+/// Time-bounded cache of on-chain metadata that would otherwise cost RPC calls on every payment.
+/// Each [`EvmProvider`] has its own, so entries are per chain.
+///
+/// - EIP-712 `name()` and `version()` of tokens, by token address. Overrides from the
+///   requirements' `extra` are never cached: they are applied by [`assert_domain`] on top.
+/// - Presence of contract code at payer addresses. Only deployed contracts are cached: a
+///   counterfactual wallet without code may be deployed at any time, while deployed code stays.
+///
+/// Addresses come from client-supplied payments, so each map holds at most
+/// [`METADATA_CACHE_CAPACITY`] entries. Lookups are counted in the
+/// `x402.evm.metadata_cache.lookups` metric.
+#[derive(Clone, Debug)]
+pub struct EvmMetadataCache {
+    names: Arc<BoundedCache<Address, String>>,
+    versions: Arc<BoundedCache<Address, String>>,
+    decimals: Arc<BoundedCache<Address, u8>>,
+    deployed: Arc<BoundedCache<Address, ()>>,
+}
+
+impl Default for EvmMetadataCache {
+    fn default() -> Self {
+        Self::new(METADATA_CACHE_TTL)
+    }
+}
+
+impl EvmMetadataCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            names: Arc::new(BoundedCache::new(ttl)),
+            versions: Arc::new(BoundedCache::new(ttl)),
+            decimals: Arc::new(BoundedCache::new(ttl)),
+            deployed: Arc::new(BoundedCache::new(ttl)),
+        }
+    }
+
+    fn record_lookup(cache: &'static str, hit: bool) {
+        METADATA_CACHE_LOOKUPS.add(
+            1,
+            &[
+                KeyValue::new("cache", cache),
+                KeyValue::new("result", if hit { "hit" } else { "miss" }),
+            ],
+        );
+    }
+
+    /// Returns the cached value of `key`, or the one `fetch` returns, caching it.
+    async fn get_or_fetch<V, F, Fut, E>(
+        cache: &BoundedCache<Address, V>,
+        name: &'static str,
+        key: Address,
+        fetch: F,
+    ) -> Result<V, FacilitatorLocalError>
+    where
+        V: Clone,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+        E: std::fmt::Debug,
+    {
+        let cached = cache.get(&key);
+        Self::record_lookup(name, cached.is_some());
+        if let Some(value) = cached {
+            return Ok(value);
+        }
+        let value = fetch()
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        cache.insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// Returns the EIP-712 `name()` of the token at `asset`, calling `fetch` on a miss.
+    pub async fn token_name<F, Fut, E>(
+        &self,
+        asset: Address,
+        fetch: F,
+    ) -> Result<String, FacilitatorLocalError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String, E>>,
+        E: std::fmt::Debug,
+    {
+        Self::get_or_fetch(&self.names, "eip712_name", asset, fetch).await
+    }
+
+    /// Returns the EIP-712 `version()` of the token at `asset`, calling `fetch` on a miss.
+    pub async fn token_version<F, Fut, E>(
+        &self,
+        asset: Address,
+        fetch: F,
+    ) -> Result<String, FacilitatorLocalError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String, E>>,
+        E: std::fmt::Debug,
+    {
+        Self::get_or_fetch(&self.versions, "eip712_version", asset, fetch).await
+    }
+
+    /// Returns the `decimals()` of the token at `asset`, calling `fetch` on a miss.
+    pub async fn token_decimals<F, Fut, E>(
+        &self,
+        asset: Address,
+        fetch: F,
+    ) -> Result<u8, FacilitatorLocalError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<u8, E>>,
+        E: std::fmt::Debug,
+    {
+        Self::get_or_fetch(&self.decimals, "decimals", asset, fetch).await
+    }
+
+    /// Returns whether contract code is known to be deployed at `address`.
+    pub fn is_known_deployed(&self, address: &Address) -> bool {
+        let cached = self.deployed.get(address).is_some();
+        Self::record_lookup("code_presence", cached);
+        cached
+    }
+
+    /// Records that contract code is deployed at `address`.
+    pub fn record_deployed(&self, address: Address) {
+        self.deployed.insert(address, ());
+    }
+}
+
+/// Calls settling `payment`, signed with `signature`, and its fee authorization, if any.
+///
+/// For EIP-6492 signatures, the transfer uses the inner signature, and a wallet that is not
+/// deployed yet is first deployed by its factory, best-effort, so deployment and transfer are
+/// atomic. The fee transfer follows the payment, so that both succeed or fail together. An
+/// EIP-6492 fee signature keeps its deployment too: it leads the batch, best-effort, unless the
+/// payment signature already deploys the wallet.
+///
+/// Returns the calls, the payment's transfer call and the kind of signature, for tracing.
+async fn settlement_calls<'a, P: Provider>(
+    contract: &'a USDC::USDCInstance<P>,
+    payment: &ExactEvmPayment,
+    fee_payment: Option<&ExactEvmPayment>,
+    eip712_domain: &Eip712Domain,
+    signature: StructuredSignature,
+    is_deployed: bool,
+) -> Result<
+    (
+        Vec<IMulticall3::Call3>,
+        TransferWithAuthorization0Call<&'a P>,
+        &'static str,
+    ),
+    FacilitatorLocalError,
+> {
+    let payment_deploys = matches!(signature, StructuredSignature::EIP6492 { .. });
+    let (mut calls, transfer_call, sig_kind) = match signature {
+        StructuredSignature::EIP6492 {
+            factory,
+            factory_calldata,
+            inner,
+            original: _,
+        } => {
+            let transfer_call = transferWithAuthorization_0(contract, payment, inner).await?;
+            if is_deployed {
+                (Vec::new(), transfer_call, "EIP6492.deployed")
+            } else {
+                let deployment_call = IMulticall3::Call3 {
+                    allowFailure: true,
+                    target: factory,
+                    callData: factory_calldata,
+                };
+                (
+                    vec![deployment_call],
+                    transfer_call,
+                    "EIP6492.counterfactual",
+                )
+            }
+        }
+        StructuredSignature::EIP1271(signature) => {
+            let transfer_call = transferWithAuthorization_0(contract, payment, signature).await?;
+            (Vec::new(), transfer_call, "EIP1271")
+        }
+    };
+    calls.push(IMulticall3::Call3 {
+        allowFailure: false,
+        target: transfer_call.tx.target(),
+        callData: transfer_call.tx.calldata().clone(),
+    });
+    if let Some(fee_payment) = fee_payment {
+        let fee_signature = match SignedMessage::extract(fee_payment, eip712_domain)?.signature {
+            StructuredSignature::EIP6492 {
+                factory,
+                factory_calldata,
+                inner,
+                original: _,
+            } => {
+                if !payment_deploys {
+                    calls.insert(
+                        0,
+                        IMulticall3::Call3 {
+                            allowFailure: true,
+                            target: factory,
+                            callData: factory_calldata,
+                        },
+                    );
+                }
+                inner
+            }
+            StructuredSignature::EIP1271(signature) => signature,
+        };
+        let fee_call = transferWithAuthorization_0(contract, fee_payment, fee_signature).await?;
+        calls.push(IMulticall3::Call3 {
+            allowFailure: false,
+            target: fee_call.tx.target(),
+            callData: fee_call.tx.calldata().clone(),
+        });
+    }
+    Ok((calls, transfer_call, sig_kind))
+}
+
+/// State of a payer, read before its payment is simulated or settled.
+struct PayerState {
+    /// Token balance of the payer.
+    balance: U256,
+    /// Whether the payer's wallet is deployed. Only read for EIP-6492 signatures, `true` otherwise.
+    is_deployed: bool,
+    /// Outcome of the EIP-6492 validator, when asked for.
+    is_valid_signature: Option<bool>,
+}
+
+/// Reads the state of the payer of `signed_message` in a single JSON-RPC batch: its token balance and,
+/// for EIP-6492 signatures, whether its wallet is deployed, unless known from `metadata_cache`.
+///
+/// With `validate_signature`, the EIP-6492 validator's `isValidSigWithSideEffects` runs in the
+/// same batch, in its own `eth_call`, as it *may* deploy the counterfactual wallet.
+#[instrument(skip_all, err, fields(payer = %signed_message.address))]
+async fn read_payer_state<P: Provider>(
+    provider: &P,
+    metadata_cache: &EvmMetadataCache,
+    contract: &USDC::USDCInstance<&P>,
+    signed_message: &SignedMessage,
+    validate_signature: bool,
+) -> Result<PayerState, FacilitatorLocalError> {
+    let contract_call = |e: alloy::transports::TransportError| {
+        FacilitatorLocalError::ContractCall(format!("{e:?}"))
+    };
+    let payer = signed_message.address;
+    let latest = BlockId::latest();
+    let mut batch = BatchRequest::new(provider.client());
+    let balance_request = TransactionRequest::default()
+        .with_to(*contract.address())
+        .with_input(contract.balanceOf(payer).calldata().clone());
+    let balance = batch
+        .add_call::<_, Bytes>("eth_call", &(balance_request, latest))
+        .map_err(contract_call)?;
+    let (code, is_valid_signature) = match &signed_message.signature {
+        StructuredSignature::EIP6492 { original, .. } => {
+            let code = if metadata_cache.is_known_deployed(&payer) {
+                None
+            } else {
+                Some(
+                    batch
+                        .add_call::<_, Bytes>("eth_getCode", &(payer, latest))
+                        .map_err(contract_call)?,
+                )
+            };
+            let is_valid_signature = if validate_signature {
+                let validator6492 = Validator6492::new(VALIDATOR_ADDRESS, provider);
+                let is_valid_signature_request = TransactionRequest::default()
+                    .with_to(VALIDATOR_ADDRESS)
+                    .with_input(
+                        validator6492
+                            .isValidSigWithSideEffects(payer, signed_message.hash, original.clone())
+                            .calldata()
+                            .clone(),
+                    );
+                Some(
+                    batch
+                        .add_call::<_, Bytes>("eth_call", &(is_valid_signature_request, latest))
+                        .map_err(contract_call)?,
+                )
+            } else {
+                None
+            };
+            (code, is_valid_signature)
+        }
+        StructuredSignature::EIP1271(_) => (None, None),
+    };
+    batch
+        .send()
+        .instrument(tracing::info_span!("fetch_payer_state",
+            payer = %payer,
+            token_contract = %contract.address(),
+            otel.kind = "client"
+        ))
+        .await
+        .map_err(contract_call)?;
+
+    let balance = USDC::balanceOfCall::abi_decode_returns(&balance.await.map_err(contract_call)?)
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    let is_deployed = match code {
+        Some(code) => {
+            let is_deployed = !code.await.map_err(contract_call)?.is_empty();
+            if is_deployed {
+                metadata_cache.record_deployed(payer);
+            }
+            is_deployed
+        }
+        None => true,
+    };
+    let is_valid_signature = match is_valid_signature {
+        Some(is_valid_signature) => Some(
+            Validator6492::isValidSigWithSideEffectsCall::abi_decode_returns(
+                &is_valid_signature.await.map_err(contract_call)?,
+            )
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?,
+        ),
+        None => None,
+    };
+    Ok(PayerState {
+        balance,
+        is_deployed,
+        is_valid_signature,
+    })
+}
+
+/// The `eth_call` simulating `tx` exactly as [`MetaEvmProvider::send_transaction`] sends it from
+/// `sender`: called directly on the target for a single call, through Multicall3 for a batch.
+fn simulation_request(sender: Address, tx: &MetaTransaction) -> TransactionRequest {
+    TransactionRequest::default()
+        .with_from(sender)
+        .with_to(tx.to)
+        .with_input(tx.calldata.clone())
+}
+
+/// Builds the transaction submitting `calls`: a single call is sent directly to its target,
+/// several are batched atomically through Multicall3 `aggregate3`.
+fn batch_transaction(mut calls: Vec<IMulticall3::Call3>, confirmations: u64) -> MetaTransaction {
+    if calls.len() == 1 {
+        let call = calls.remove(0);
+        MetaTransaction {
+            to: call.target,
+            calldata: call.callData,
+            confirmations,
+        }
+    } else {
+        let aggregate_call = IMulticall3::aggregate3Call { calls };
+        MetaTransaction {
+            to: MULTICALL3_ADDRESS,
+            calldata: aggregate_call.abi_encode().into(),
+            confirmations,
+        }
+    }
+}
+
+/// Runs all preconditions needed for a successful payment, except the on-chain balance:
+/// - Valid scheme, network, and receiver.
+/// - Valid time window (validAfter/validBefore).
+/// - Correct EIP-712 domain construction.
+/// - Sufficient value in payload.
+/// - A separate authorization paying the facilitator fee, if one is due.
+///
+/// The balance is checked by the caller, which reads it in the same JSON-RPC batch as the other
+/// payer state, see [`read_payer_state`].
+#[instrument(skip_all, err)]
+async fn assert_valid_payment<P: Provider>(
+    provider: P,
+    chain: &EvmChain,
+    metadata_cache: &EvmMetadataCache,
+    fee: Option<&FacilitatorFee>,
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<
+    (
+        USDC::USDCInstance<P>,
+        ExactEvmPayment,
+        Option<ExactEvmPayment>,
+        Eip712Domain,
+    ),
+    FacilitatorLocalError,
+> {
+    let payment_payload = match &payload.payload {
+        ExactPaymentPayload::Evm(payload) => payload,
+        ExactPaymentPayload::Solana(_) | ExactPaymentPayload::EvmNative(_) => {
+            return Err(FacilitatorLocalError::UnsupportedNetwork(None));
+        }
+    };
+    let payer = payment_payload.authorization.from;
+    if payload.network != chain.network {
+        return Err(FacilitatorLocalError::NetworkMismatch(
             Some(payer.into()),
             chain.network,
             payload.network,
@@ -1029,15 +1841,10 @@ async fn assert_valid_payment<P: Provider>(
         .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
     let contract = USDC::new(asset_address, provider);
 
-    let domain = assert_domain(chain, &contract, payload, &asset_address, requirements).await?;
+    let domain =
+        assert_domain(chain, &contract, &asset_address, requirements, metadata_cache).await?;
 
     let amount_required = requirements.max_amount_required.0;
-    assert_enough_balance(
-        &contract,
-        &payment_payload.authorization.from,
-        amount_required,
-    )
-    .await?;
     let value: U256 = payment_payload.authorization.value.into();
     assert_enough_value(&payer, &value, &amount_required)?;
 
@@ -1051,8 +1858,280 @@ async fn assert_valid_payment<P: Provider>(
         nonce: payment_payload.authorization.nonce,
         signature: payment_payload.signature.clone(),
     };
+    let fee_payment = assert_valid_fee(chain, fee, payment_payload, requirements)?;
 
-    Ok((contract, payment, domain))
+    Ok((contract, payment, fee_payment, domain))
+}
+
+/// Checks the authorization paying the facilitator fee, if a nonzero fee is due:
+/// same payer, fee recipient as receiver, exact fee amount, a nonce distinct from the
+/// payment, and a valid time window.
+///
+/// # Errors
+/// Returns [`FacilitatorLocalError::InvalidFee`] if the fee authorization is missing or wrong.
+fn assert_valid_fee(
+    chain: &EvmChain,
+    fee: Option<&FacilitatorFee>,
+    payment_payload: &ExactEvmPayload,
+    requirements: &PaymentRequirements,
+) -> Result<Option<ExactEvmPayment>, FacilitatorLocalError> {
+    let payer = payment_payload.authorization.from;
+    let Some(fee) = fee else {
+        return Ok(None);
+    };
+    let fee_due = fee.amount(&requirements.asset, requirements.max_amount_required);
+    if fee_due.0.is_zero() {
+        return Ok(None);
+    }
+    let fee_recipient: EvmAddress = fee
+        .recipient
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    let fee_payload = payment_payload.fee.as_ref().ok_or_else(|| {
+        FacilitatorLocalError::InvalidFee(
+            payer.into(),
+            format!("missing fee authorization of {fee_due}"),
+        )
+    })?;
+    let authorization = &fee_payload.authorization;
+    if authorization.from != payer {
+        return Err(FacilitatorLocalError::InvalidFee(
+            payer.into(),
+            format!("fee payer {} is not the payer", authorization.from),
+        ));
+    }
+    if authorization.to != fee_recipient {
+        return Err(FacilitatorLocalError::InvalidFee(
+            payer.into(),
+            format!(
+                "fee recipient {} does not match {fee_recipient}",
+                authorization.to
+            ),
+        ));
+    }
+    if authorization.value != fee_due {
+        return Err(FacilitatorLocalError::InvalidFee(
+            payer.into(),
+            format!("fee {} does not match {fee_due}", authorization.value),
+        ));
+    }
+    if authorization.nonce == payment_payload.authorization.nonce {
+        return Err(FacilitatorLocalError::InvalidFee(
+            payer.into(),
+            "fee nonce reuses the payment nonce".to_string(),
+        ));
+    }
+    assert_time(
+        payer.into(),
+        authorization.valid_after,
+        authorization.valid_before,
+    )?;
+    Ok(Some(ExactEvmPayment {
+        chain: *chain,
+        from: authorization.from,
+        to: authorization.to,
+        value: authorization.value,
+        valid_after: authorization.valid_after,
+        valid_before: authorization.valid_before,
+        nonce: authorization.nonce,
+        signature: fee_payload.signature.clone(),
+    }))
+}
+
+/// Runs all preconditions for a native coin payment:
+/// - Decodable legacy, EIP-2930 or EIP-1559 transaction with a recoverable signer.
+/// - Valid scheme and network, and a chain id matching this chain (no pre-EIP-155 replays).
+/// - A plain value transfer (no calldata) to `payTo` of at least `maxAmountRequired`.
+/// - The payer's next pending nonce, and enough balance for value plus maximum gas cost.
+#[instrument(skip_all, err)]
+async fn assert_valid_native_payment<P: Provider>(
+    provider: P,
+    chain: &EvmChain,
+    payload: &PaymentPayload,
+    native_payload: &ExactEvmNativePayload,
+    requirements: &PaymentRequirements,
+) -> Result<NativeEvmPayment, FacilitatorLocalError> {
+    let raw_transaction = native_payload.raw_transaction.clone();
+    let envelope = TxEnvelope::decode_2718(&mut raw_transaction.as_ref())
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+    if !matches!(
+        envelope,
+        TxEnvelope::Legacy(_) | TxEnvelope::Eip2930(_) | TxEnvelope::Eip1559(_)
+    ) {
+        return Err(FacilitatorLocalError::DecodingError(
+            "invalid_exact_evm_native_payload_transaction_type".to_string(),
+        ));
+    }
+    let from = envelope
+        .recover_signer()
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+    let payer: EvmAddress = from.into();
+    if payload.network != chain.network {
+        return Err(FacilitatorLocalError::NetworkMismatch(
+            Some(payer.into()),
+            chain.network,
+            payload.network,
+        ));
+    }
+    if requirements.network != chain.network {
+        return Err(FacilitatorLocalError::NetworkMismatch(
+            Some(payer.into()),
+            chain.network,
+            requirements.network,
+        ));
+    }
+    if payload.scheme != requirements.scheme {
+        return Err(FacilitatorLocalError::SchemeMismatch(
+            Some(payer.into()),
+            requirements.scheme,
+            payload.scheme,
+        ));
+    }
+    let asset_address: Address = requirements
+        .asset
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    if asset_address != NATIVE_COIN_ASSET {
+        return Err(FacilitatorLocalError::DecodingError(
+            "invalid_exact_evm_native_payload_asset".to_string(),
+        ));
+    }
+    if envelope.chain_id() != Some(chain.chain_id) {
+        return Err(FacilitatorLocalError::DecodingError(
+            "invalid_exact_evm_native_payload_chain_id".to_string(),
+        ));
+    }
+    let requirements_to: EvmAddress = requirements
+        .pay_to
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    let to = match envelope.kind() {
+        TxKind::Call(to) if to == requirements_to.0 => to,
+        TxKind::Call(to) => {
+            return Err(FacilitatorLocalError::ReceiverMismatch(
+                payer.into(),
+                to.to_string(),
+                requirements_to.to_string(),
+            ));
+        }
+        TxKind::Create => {
+            return Err(FacilitatorLocalError::ReceiverMismatch(
+                payer.into(),
+                "contract creation".to_string(),
+                requirements_to.to_string(),
+            ));
+        }
+    };
+    if !envelope.input().is_empty() {
+        return Err(FacilitatorLocalError::DecodingError(
+            "invalid_exact_evm_native_payload_calldata".to_string(),
+        ));
+    }
+    let value = envelope.value();
+    assert_enough_value(&payer, &value, &requirements.max_amount_required.0)?;
+
+    let pending_nonce = provider
+        .get_transaction_count(from)
+        .pending()
+        .into_future()
+        .instrument(tracing::info_span!("get_transaction_count",
+            address = %from,
+            otel.kind = "client",
+        ))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if envelope.nonce() != pending_nonce {
+        return Err(FacilitatorLocalError::DecodingError(
+            "invalid_exact_evm_native_payload_nonce".to_string(),
+        ));
+    }
+    let balance = provider
+        .get_balance(from)
+        .into_future()
+        .instrument(tracing::info_span!("get_balance",
+            address = %from,
+            otel.kind = "client",
+        ))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    let max_gas_cost =
+        U256::from(envelope.gas_limit()).saturating_mul(U256::from(envelope.max_fee_per_gas()));
+    if balance < value.saturating_add(max_gas_cost) {
+        return Err(FacilitatorLocalError::InsufficientFunds(payer.into()));
+    }
+
+    Ok(NativeEvmPayment {
+        from,
+        to,
+        value,
+        tx_hash: *envelope.tx_hash(),
+        nonce: envelope.nonce(),
+        raw_transaction,
+    })
+}
+
+/// Simulates a native coin transfer with `eth_call`, catching recipients that reject value.
+async fn simulate_native_payment<P: Provider>(
+    provider: P,
+    payment: &NativeEvmPayment,
+) -> Result<(), FacilitatorLocalError> {
+    let tx = TransactionRequest::default()
+        .with_from(payment.from)
+        .with_to(payment.to)
+        .with_value(payment.value);
+    provider
+        .call(tx)
+        .into_future()
+        .instrument(tracing::info_span!("call_native_transfer",
+            from = %payment.from,
+            to = %payment.to,
+            value = %payment.value,
+            otel.kind = "client",
+        ))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    Ok(())
+}
+
+/// Relays the buyer-signed native coin transfer and waits for its receipt after `confirmations`.
+///
+/// The relayed transfer is recorded as a pending settlement of `network` until it has a receipt,
+/// signed by the payer with its own nonce.
+async fn relay_native_payment<P: Provider>(
+    provider: P,
+    network: Network,
+    payment: &NativeEvmPayment,
+    confirmations: u64,
+) -> Result<TransactionReceipt, FacilitatorLocalError> {
+    let pending_tx = provider
+        .send_raw_transaction(&payment.raw_transaction)
+        .instrument(tracing::info_span!("send_raw_transaction",
+            from = %payment.from,
+            to = %payment.to,
+            value = %payment.value,
+            tx = %payment.tx_hash,
+            otel.kind = "client",
+        ))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    let tx_hash = TransactionHash::Evm(payment.tx_hash.0);
+    pending_settlements::broadcast(
+        network,
+        tx_hash.clone(),
+        payment.from.into(),
+        Some(payment.nonce),
+    );
+    let receipt = pending_tx
+        .with_required_confirmations(confirmations)
+        .with_timeout(Some(tx_receipt_timeout()))
+        .get_receipt()
+        .await;
+    // The settlement is answered either way; only a shutdown cutting it short leaves it pending
+    pending_settlements::settled(&tx_hash);
+    receipt.map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
 }
 
 /// Constructs a full `transferWithAuthorization` call for a verified payment payload.
@@ -1319,12 +2398,27 @@ impl PendingNonceManager {
             tracing::debug!(%address, "reset nonce cache, will requery on next use");
         }
     }
+
+    /// Sets the nonce the next transaction of `address` uses.
+    pub async fn seed_nonce(&self, address: Address, next_nonce: u64) {
+        let nonce_lock = {
+            let rm = self
+                .nonces
+                .entry(address)
+                .or_insert_with(|| Arc::new(Mutex::new(u64::MAX)));
+            Arc::clone(rm.value())
+        };
+        // The cache holds the last nonce used; the NONE sentinel for 0 requeries instead
+        *nonce_lock.lock().await = next_nonce.checked_sub(1).unwrap_or(u64::MAX);
+        tracing::debug!(%address, next_nonce, "seeded nonce cache");
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use alloy::primitives::address;
+    use alloy::sol_types::SolValue;
 
     #[tokio::test]
     async fn test_reset_nonce_clears_cache() {
@@ -1387,6 +2481,118 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_seed_nonce_sets_next_allocation() {
+        let manager = PendingNonceManager::default();
+        let test_address = address!("0000000000000000000000000000000000000003");
+
+        manager.seed_nonce(test_address, 7).await;
+        {
+            let nonce_lock = manager.nonces.get(&test_address).unwrap();
+            assert_eq!(*nonce_lock.lock().await, 6);
+        }
+
+        // A signer without any transaction yet requeries
+        manager.seed_nonce(test_address, 0).await;
+        let nonce_lock = manager.nonces.get(&test_address).unwrap();
+        assert_eq!(*nonce_lock.lock().await, u64::MAX);
+    }
+
+    #[tokio::test]
+    async fn test_reseed_nonce_follows_in_flight_transactions() {
+        let asserter = alloy::transports::mock::Asserter::new();
+        let provider = EvmProvider::mocked(Network::MonadTestnet, asserter.clone());
+        let signer = address!("0000000000000000000000000000000000000007");
+
+        // The node reports 5 transactions, but nonce 7 is known to be in flight
+        asserter.push_success(&alloy::primitives::U64::from(5));
+        assert_eq!(provider.reseed_nonce(signer, [6, 7]).await.unwrap(), 8);
+        let nonce = provider
+            .nonce_manager
+            .get_next_nonce(&provider.inner, signer)
+            .await
+            .unwrap();
+        assert_eq!(nonce, 8);
+
+        asserter.push_success(&alloy::primitives::U64::from(5));
+        assert_eq!(provider.reseed_nonce(signer, []).await.unwrap(), 5);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    /// A receipt of a successful or reverted transaction included at `number` in block `hash`.
+    fn mined_receipt(number: u64, hash: B256, status: bool) -> serde_json::Value {
+        serde_json::json!({
+            "type": "0x2",
+            "status": if status { "0x1" } else { "0x0" },
+            "cumulativeGasUsed": "0x5208",
+            "logs": [],
+            "logsBloom": alloy::primitives::Bloom::ZERO,
+            "transactionHash": B256::repeat_byte(0xaa),
+            "transactionIndex": "0x0",
+            "blockHash": hash,
+            "blockNumber": alloy::primitives::U64::from(number),
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x1",
+            "from": address!("0000000000000000000000000000000000000001"),
+            "to": address!("0000000000000000000000000000000000000002"),
+            "contractAddress": null,
+        })
+    }
+
+    fn block(number: u64, hash: B256) -> alloy::rpc::types::Block {
+        let mut block = alloy::rpc::types::Block::<alloy::rpc::types::Transaction>::default();
+        block.header.hash = hash;
+        block.header.inner.number = number;
+        block
+    }
+
+    #[tokio::test]
+    async fn test_finality_follows_canonical_and_finalized_blocks() {
+        let asserter = alloy::transports::mock::Asserter::new();
+        let provider = EvmProvider::mocked(Network::MonadTestnet, asserter.clone());
+        let tx_hash = TransactionHash::Evm([0xaa; 32]);
+        let included = B256::repeat_byte(0x01);
+
+        // Included, ahead of the finalized block
+        asserter.push_success(&mined_receipt(10, included, true));
+        asserter.push_success(&block(10, included));
+        asserter.push_success(&block(9, B256::repeat_byte(0x09)));
+        let finality = provider.get_finality(&tx_hash).await.unwrap();
+        assert_eq!(finality, Finality::Unfinalized);
+
+        // Finalized, even though the transaction reverted
+        asserter.push_success(&mined_receipt(10, included, false));
+        asserter.push_success(&block(10, included));
+        asserter.push_success(&block(12, B256::repeat_byte(0x0c)));
+        let finality = provider.get_finality(&tx_hash).await.unwrap();
+        assert_eq!(finality, Finality::Finalized);
+
+        // The canonical block at the receipt height is another one
+        asserter.push_success(&mined_receipt(10, included, true));
+        asserter.push_success(&block(10, B256::repeat_byte(0x02)));
+        let finality = provider.get_finality(&tx_hash).await.unwrap();
+        assert_eq!(finality, Finality::Reorged);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finality_is_checked_again_without_a_receipt() {
+        let asserter = alloy::transports::mock::Asserter::new();
+        let provider = EvmProvider::mocked(Network::MonadTestnet, asserter.clone());
+        let tx_hash = TransactionHash::Evm([0xaa; 32]);
+
+        asserter.push_success(&None::<()>);
+        let finality = provider.get_finality(&tx_hash).await.unwrap();
+        assert_eq!(finality, Finality::Unfinalized);
+
+        // The node has not caught up with the block of the receipt yet
+        asserter.push_success(&mined_receipt(10, B256::repeat_byte(0x01), true));
+        asserter.push_success(&None::<()>);
+        let finality = provider.get_finality(&tx_hash).await.unwrap();
+        assert_eq!(finality, Finality::Unfinalized);
+        assert!(asserter.read_q().is_empty());
+    }
+
     #[tokio::test]
     async fn test_reset_nonce_on_nonexistent_address() {
         let manager = PendingNonceManager::default();
@@ -1468,4 +2674,586 @@ mod tests {
             assert_eq!(*nonce_lock.lock().await, u64::MAX);
         }
     }
+
+    #[tokio::test]
+    async fn test_metadata_cache_caches_on_chain_lookups_only() {
+        let cache = EvmMetadataCache::new(Duration::from_secs(60));
+        let chain = EvmChain::try_from(Network::MonadTestnet).unwrap();
+        let asset = address!("0000000000000000000000000000000000000003");
+        let requirements = |extra: serde_json::Value| -> PaymentRequirements {
+            serde_json::from_value(serde_json::json!({
+                "scheme": "exact",
+                "network": "monad-testnet",
+                "maxAmountRequired": "1000",
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "text/plain",
+                "payTo": "0x1111111111111111111111111111111111111111",
+                "maxTimeoutSeconds": 60,
+                "asset": asset.to_string(),
+                "extra": extra,
+            }))
+            .unwrap()
+        };
+        // Answers `version()` once, any further RPC call fails. The name is known for the network.
+        let asserter = alloy::transports::mock::Asserter::new();
+        asserter.push_success(&Bytes::from("2".abi_encode()));
+        let provider = ProviderBuilder::default().connect_mocked_client(asserter.clone());
+        let token = USDC::new(asset, &provider);
+        let plain = requirements(serde_json::json!({}));
+
+        let domain = assert_domain(&chain, &token, &asset, &plain, &cache)
+            .await
+            .unwrap();
+        assert_eq!(domain.version.unwrap(), "2");
+        assert!(asserter.read_q().is_empty());
+
+        // Overrides apply on top of the cached lookups, and are never cached themselves
+        let overridden = requirements(serde_json::json!({ "version": "3" }));
+        let domain = assert_domain(&chain, &token, &asset, &overridden, &cache)
+            .await
+            .unwrap();
+        assert_eq!(domain.version.unwrap(), "3");
+        let domain = assert_domain(&chain, &token, &asset, &plain, &cache)
+            .await
+            .unwrap();
+        assert_eq!(domain.version.unwrap(), "2");
+        assert_eq!(cache.versions.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_bounded_cache_evicts_oldest_entry_when_full() {
+        let cache = BoundedCache::new(Duration::from_secs(60));
+        cache.insert(0, ());
+        std::thread::sleep(Duration::from_millis(2));
+        for i in 1..METADATA_CACHE_CAPACITY {
+            cache.insert(i, ());
+        }
+        cache.insert(METADATA_CACHE_CAPACITY, ());
+        assert_eq!(cache.entries.len(), METADATA_CACHE_CAPACITY);
+        assert!(cache.get(&0).is_none());
+        assert!(cache.get(&METADATA_CACHE_CAPACITY).is_some());
+    }
+
+    const PAY_TO: Address = address!("1111111111111111111111111111111111111111");
+
+    /// EIP-6492 signature of a counterfactual wallet, deployed by `factory`.
+    fn eip6492_signature(factory: Address) -> StructuredSignature {
+        StructuredSignature::EIP6492 {
+            factory,
+            factory_calldata: Bytes::from([0xde, 0xad]),
+            inner: Bytes::from([0x01; 65]),
+            original: Bytes::from([0x02; 65]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drained_signer_fails_before_broadcast() {
+        let asserter = alloy::transports::mock::Asserter::new();
+        let provider = EvmProvider::mocked(Network::MonadTestnet, asserter.clone());
+        // Whichever call filling the transaction comes first: gas estimation, fees or nonce
+        for _ in 0..4 {
+            asserter.push_failure_msg("insufficient funds for gas * price + value");
+        }
+
+        let result = provider
+            .send_transaction(MetaTransaction {
+                to: PAY_TO,
+                calldata: Bytes::new(),
+                confirmations: 1,
+            })
+            .await;
+        assert!(
+            matches!(
+                result,
+                Err(FacilitatorLocalError::NotBroadcast(ref e)) if e.contains("insufficient funds")
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_evm_policy_caps_gas_price() {
+        let lookup = |value: &'static str| {
+            move |name: &str| (name == "X402_EVM_MAX_GAS_PRICE_MONAD").then(|| value.to_string())
+        };
+        let policy = EvmPolicy::from_lookup(Network::Monad, lookup("100")).unwrap();
+        assert_eq!(policy.max_gas_price, Some(100));
+        assert!(policy.assert_gas_price(100).is_ok());
+        assert!(policy.assert_gas_price(101).is_err());
+        assert!(EvmPolicy::default().assert_gas_price(u128::MAX).is_ok());
+        let error = EvmPolicy::from_lookup(Network::Monad, lookup("100 gwei")).unwrap_err();
+        assert!(
+            error.to_string().contains("X402_EVM_MAX_GAS_PRICE_MONAD"),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_payer_state_batches_payer_reads() {
+        let cache = EvmMetadataCache::new(Duration::from_secs(60));
+        let token = address!("0000000000000000000000000000000000000003");
+        let payer = address!("0000000000000000000000000000000000000004");
+        let signed_message = SignedMessage {
+            address: payer,
+            hash: FixedBytes([7; 32]),
+            signature: eip6492_signature(address!("0000000000000000000000000000000000000005")),
+        };
+        // Balance, code and signature validity, answered in batch order
+        let asserter = alloy::transports::mock::Asserter::new();
+        asserter.push_success(&Bytes::from(U256::from(1_000).abi_encode()));
+        asserter.push_success(&Bytes::new());
+        asserter.push_success(&Bytes::from(true.abi_encode()));
+        let provider = ProviderBuilder::default().connect_mocked_client(asserter.clone());
+        let contract = USDC::new(token, &provider);
+
+        let state = read_payer_state(&provider, &cache, &contract, &signed_message, true)
+            .await
+            .unwrap();
+        assert_eq!(state.balance, U256::from(1_000));
+        assert!(!state.is_deployed);
+        assert_eq!(state.is_valid_signature, Some(true));
+        assert!(asserter.read_q().is_empty());
+
+        // Deployed code is cached, and no longer read
+        asserter.push_success(&Bytes::from(U256::from(900).abi_encode()));
+        asserter.push_success(&Bytes::from([0x60, 0x80]));
+        let state = read_payer_state(&provider, &cache, &contract, &signed_message, false)
+            .await
+            .unwrap();
+        assert!(state.is_deployed);
+        assert_eq!(state.is_valid_signature, None);
+        asserter.push_success(&Bytes::from(U256::from(800).abi_encode()));
+        let state = read_payer_state(&provider, &cache, &contract, &signed_message, false)
+            .await
+            .unwrap();
+        assert_eq!(state.balance, U256::from(800));
+        assert!(state.is_deployed);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_simulation_sends_settlement_transaction_from_signer() {
+        let token = address!("0000000000000000000000000000000000000003");
+        let payer = address!("0000000000000000000000000000000000000004");
+        let factory = address!("0000000000000000000000000000000000000005");
+        let sender = address!("0000000000000000000000000000000000000006");
+        let chain = EvmChain::try_from(Network::MonadTestnet).unwrap();
+        let provider = ProviderBuilder::default()
+            .connect_mocked_client(alloy::transports::mock::Asserter::new());
+        let contract = USDC::new(token, &provider);
+        let payment = ExactEvmPayment {
+            chain,
+            from: payer.into(),
+            to: PAY_TO.into(),
+            value: TokenAmount::from(1_000u64),
+            valid_after: UnixTimestamp(0),
+            valid_before: UnixTimestamp(u64::MAX),
+            nonce: HexEncodedNonce([9; 32]),
+            signature: EvmSignature(vec![0x01; 65]),
+        };
+        let domain = eip712_domain! { name: "USDC", version: "2", };
+
+        // A single transfer is called on the token itself, as `msg.sender` matters to it
+        let signature = StructuredSignature::EIP1271(Bytes::from([0x01; 65]));
+        let (calls, transfer_call, sig_kind) =
+            settlement_calls(&contract, &payment, None, &domain, signature, true)
+                .await
+                .unwrap();
+        assert_eq!(sig_kind, "EIP1271");
+        let transaction = batch_transaction(calls, 1);
+        let request = simulation_request(sender, &transaction);
+        assert_eq!(request.from, Some(sender));
+        assert_eq!(request.to, Some(TxKind::Call(token)));
+        assert_eq!(request.input.input(), Some(transfer_call.tx.calldata()));
+
+        // A counterfactual wallet is deployed in the same Multicall3 batch as settlement does
+        let (calls, _, sig_kind) = settlement_calls(
+            &contract,
+            &payment,
+            None,
+            &domain,
+            eip6492_signature(factory),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(sig_kind, "EIP6492.counterfactual");
+        assert_eq!(calls[0].target, factory);
+        assert!(calls[0].allowFailure);
+        assert_eq!(calls[1].target, token);
+        assert!(!calls[1].allowFailure);
+        let request = simulation_request(sender, &batch_transaction(calls, 1));
+        assert_eq!(request.from, Some(sender));
+        assert_eq!(request.to, Some(TxKind::Call(MULTICALL3_ADDRESS)));
+    }
+
+    fn native_requirements(asset: Address, max_amount_required: u64) -> PaymentRequirements {
+        serde_json::from_value(serde_json::json!({
+            "scheme": "exact",
+            "network": "monad-testnet",
+            "maxAmountRequired": max_amount_required.to_string(),
+            "resource": "https://example.com/paid",
+            "description": "",
+            "mimeType": "text/plain",
+            "payTo": PAY_TO.to_string(),
+            "maxTimeoutSeconds": 60,
+            "asset": asset.to_string(),
+        }))
+        .unwrap()
+    }
+
+    /// Signs a native transfer of `value` wei, returning its payload and signer.
+    fn native_payload(
+        chain_id: u64,
+        to: Address,
+        value: u64,
+        input: Bytes,
+    ) -> (PaymentPayload, ExactEvmNativePayload, Address) {
+        use alloy::consensus::{SignableTransaction, TxEip1559};
+        use alloy::eips::eip2718::Encodable2718;
+        use alloy::signers::SignerSync;
+        use alloy::signers::local::PrivateKeySigner;
+
+        let signer = PrivateKeySigner::random();
+        let tx = TxEip1559 {
+            chain_id,
+            nonce: 7,
+            gas_limit: 21_000,
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: 1,
+            to: TxKind::Call(to),
+            value: U256::from(value),
+            access_list: Default::default(),
+            input,
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        let envelope = TxEnvelope::from(tx.into_signed(signature));
+        let native_payload = ExactEvmNativePayload {
+            raw_transaction: envelope.encoded_2718().into(),
+        };
+        let payload = PaymentPayload {
+            x402_version: X402Version::V1,
+            scheme: Scheme::Exact,
+            network: Network::MonadTestnet,
+            payload: ExactPaymentPayload::EvmNative(native_payload.clone()),
+        };
+        (payload, native_payload, signer.address())
+    }
+
+    /// Provider answering the pending nonce and balance lookups of the payer, in that order.
+    fn payer_state_provider(nonce: u64, balance: u64) -> impl Provider {
+        let asserter = alloy::transports::mock::Asserter::new();
+        asserter.push_success(&alloy::primitives::U64::from(nonce));
+        asserter.push_success(&U256::from(balance));
+        ProviderBuilder::default().connect_mocked_client(asserter)
+    }
+
+    #[tokio::test]
+    async fn test_native_payment_accepts_signed_transfer() {
+        let chain = EvmChain::try_from(Network::MonadTestnet).unwrap();
+        let requirements = native_requirements(NATIVE_COIN_ASSET, 1_000);
+        // Overpaying is accepted, as for token payments
+        let (payload, native_payload, payer) = native_payload(10143, PAY_TO, 1_500, Bytes::new());
+
+        let payment = assert_valid_native_payment(
+            payer_state_provider(7, 1_500 + 21_000 * 100),
+            &chain,
+            &payload,
+            &native_payload,
+            &requirements,
+        )
+        .await
+        .unwrap();
+        assert_eq!(payment.from, payer);
+        assert_eq!(payment.to, PAY_TO);
+        assert_eq!(payment.value, U256::from(1_500));
+        assert_eq!(payment.raw_transaction, native_payload.raw_transaction);
+    }
+
+    /// An authorization of `value` from `from` to `to`, valid at any time.
+    fn authorization(
+        from: Address,
+        to: Address,
+        value: u64,
+        nonce: u8,
+    ) -> crate::types::ExactEvmPayloadAuthorization {
+        crate::types::ExactEvmPayloadAuthorization {
+            from: from.into(),
+            to: to.into(),
+            value: TokenAmount::from(value),
+            valid_after: UnixTimestamp(0),
+            valid_before: UnixTimestamp(u64::MAX),
+            nonce: HexEncodedNonce([nonce; 32]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_supported_advertises_native_payments_as_exempt_from_the_fee() {
+        let token: MixedAddress = address!("0000000000000000000000000000000000000003").into();
+        let native: MixedAddress = NATIVE_COIN_ASSET.into();
+        let fee = FacilitatorFee {
+            recipient: address!("0000000000000000000000000000000000000007").into(),
+            bps: Some(100),
+            flat: Default::default(),
+            exempt: Vec::new(),
+        };
+        let provider = EvmProvider::mocked(
+            Network::MonadTestnet,
+            alloy::transports::mock::Asserter::new(),
+        )
+        .with_fee(fee);
+
+        let supported = provider.supported().await.unwrap();
+        let extra = supported.kinds[0].extra.clone().unwrap();
+        let fee = extra.fee.unwrap();
+        assert_eq!(fee.exempt, vec![native.clone()]);
+        let amount = TokenAmount::from(10_000u64);
+        assert_eq!(fee.amount(&native, amount), TokenAmount::from(0u64));
+        assert_eq!(fee.amount(&token, amount), TokenAmount::from(100u64));
+    }
+
+    #[test]
+    fn test_assert_valid_fee_checks_the_fee_authorization() {
+        let chain = EvmChain::try_from(Network::MonadTestnet).unwrap();
+        let payer = address!("0000000000000000000000000000000000000004");
+        let recipient = address!("0000000000000000000000000000000000000007");
+        let token = address!("0000000000000000000000000000000000000003");
+        let requirements = native_requirements(token, 10_000);
+        let fee = FacilitatorFee {
+            recipient: recipient.into(),
+            bps: Some(100),
+            flat: Default::default(),
+            exempt: Vec::new(),
+        };
+        let payload = |fee: Option<crate::types::ExactEvmPayloadAuthorization>| ExactEvmPayload {
+            signature: EvmSignature(vec![0x01; 65]),
+            authorization: authorization(payer, PAY_TO, 10_000, 1),
+            fee: fee.map(|authorization| {
+                Box::new(crate::types::ExactEvmFeePayload {
+                    signature: EvmSignature(vec![0x02; 65]),
+                    authorization,
+                })
+            }),
+        };
+
+        let accepted = payload(Some(authorization(payer, recipient, 100, 2)));
+        let fee_payment = assert_valid_fee(&chain, Some(&fee), &accepted, &requirements)
+            .unwrap()
+            .expect("a fee is due");
+        assert_eq!(fee_payment.to, EvmAddress(recipient));
+        assert_eq!(fee_payment.value, TokenAmount::from(100u64));
+        assert_eq!(fee_payment.signature.0, vec![0x02; 65]);
+        // Without a fee configured, the fee authorization is not looked at
+        assert!(
+            assert_valid_fee(&chain, None, &payload(None), &requirements)
+                .unwrap()
+                .is_none()
+        );
+
+        let other = address!("0000000000000000000000000000000000000008");
+        for rejected in [
+            payload(None),
+            payload(Some(authorization(other, recipient, 100, 2))),
+            payload(Some(authorization(payer, other, 100, 2))),
+            payload(Some(authorization(payer, recipient, 99, 2))),
+            payload(Some(authorization(payer, recipient, 100, 1))),
+        ] {
+            let error = assert_valid_fee(&chain, Some(&fee), &rejected, &requirements)
+                .err()
+                .expect("fee must be rejected");
+            assert!(
+                matches!(error, FacilitatorLocalError::InvalidFee(ref address, _) if *address == payer.into()),
+                "{error}"
+            );
+        }
+    }
+
+    /// An EIP-6492 signature deploying a wallet with `factory`, as buyers send it.
+    fn wrapped_eip6492_signature(factory: Address) -> EvmSignature {
+        let mut signature = Sig6492 {
+            factory,
+            factoryCalldata: Bytes::from([0xde, 0xad]),
+            innerSig: Bytes::from([0x02; 65]),
+        }
+        .abi_encode_params();
+        signature.extend_from_slice(&EIP6492_MAGIC_SUFFIX);
+        EvmSignature(signature)
+    }
+
+    #[tokio::test]
+    async fn test_fee_settles_in_the_payment_batch() {
+        let token = address!("0000000000000000000000000000000000000003");
+        let payer = address!("0000000000000000000000000000000000000004");
+        let factory = address!("0000000000000000000000000000000000000005");
+        let recipient = address!("0000000000000000000000000000000000000007");
+        let chain = EvmChain::try_from(Network::MonadTestnet).unwrap();
+        let provider = ProviderBuilder::default()
+            .connect_mocked_client(alloy::transports::mock::Asserter::new());
+        let contract = USDC::new(token, &provider);
+        let domain = eip712_domain! { name: "USDC", version: "2", };
+        let payment =
+            |to: Address, value: u64, nonce: u8, signature: EvmSignature| ExactEvmPayment {
+                chain,
+                from: payer.into(),
+                to: to.into(),
+                value: TokenAmount::from(value),
+                valid_after: UnixTimestamp(0),
+                valid_before: UnixTimestamp(u64::MAX),
+                nonce: HexEncodedNonce([nonce; 32]),
+                signature,
+            };
+        let transfer = payment(PAY_TO, 1_000, 1, EvmSignature(vec![0x01; 65]));
+
+        // Payment then fee, in one Multicall3 transaction, neither allowed to fail
+        let fee = payment(recipient, 10, 2, EvmSignature(vec![0x02; 65]));
+        let signature = StructuredSignature::EIP1271(Bytes::from([0x01; 65]));
+        let (calls, transfer_call, _) =
+            settlement_calls(&contract, &transfer, Some(&fee), &domain, signature, true)
+                .await
+                .unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].callData, *transfer_call.tx.calldata());
+        assert!(
+            calls
+                .iter()
+                .all(|call| call.target == token && !call.allowFailure)
+        );
+        let transaction = batch_transaction(calls, 1);
+        assert_eq!(transaction.to, MULTICALL3_ADDRESS);
+
+        // A fee signed by a counterfactual wallet keeps its deployment, ahead of the payment
+        let fee = payment(recipient, 10, 2, wrapped_eip6492_signature(factory));
+        let signature = StructuredSignature::EIP1271(Bytes::from([0x01; 65]));
+        let (calls, _, _) =
+            settlement_calls(&contract, &transfer, Some(&fee), &domain, signature, true)
+                .await
+                .unwrap();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0].target, factory);
+        assert!(calls[0].allowFailure);
+        assert_eq!(calls[0].callData, Bytes::from([0xde, 0xad]));
+        assert_eq!(calls[2].target, token);
+
+        // The wallet is deployed once, by the payment signature
+        let (calls, _, sig_kind) = settlement_calls(
+            &contract,
+            &transfer,
+            Some(&fee),
+            &domain,
+            eip6492_signature(factory),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(sig_kind, "EIP6492.counterfactual");
+        let deployments = calls.iter().filter(|call| call.target == factory).count();
+        assert_eq!((calls.len(), deployments), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_payment_amount_reads_the_authorized_value_and_token_decimals() {
+        let asserter = alloy::transports::mock::Asserter::new();
+        let provider = EvmProvider::mocked(Network::MonadTestnet, asserter.clone());
+        let token = address!("0000000000000000000000000000000000000003");
+        let payer = address!("0000000000000000000000000000000000000001");
+        let authorization = authorization(payer, PAY_TO, 700, 0);
+        let request = VerifyRequest {
+            x402_version: X402Version::V1,
+            payment_payload: PaymentPayload {
+                x402_version: X402Version::V1,
+                scheme: Scheme::Exact,
+                network: Network::MonadTestnet,
+                payload: ExactPaymentPayload::Evm(ExactEvmPayload {
+                    signature: EvmSignature(vec![0; 65]),
+                    authorization,
+                    fee: None,
+                }),
+            },
+            payment_requirements: native_requirements(token, 1_000),
+        };
+
+        // The decimals of a token other than USDC are read once from the token
+        asserter.push_success(&Bytes::from(U256::from(8).to_be_bytes_vec()));
+        for _ in 0..2 {
+            let paid = provider.payment_amount(&request).await;
+            assert_eq!(
+                paid,
+                Some(PaymentAmount {
+                    amount: TokenAmount::from(700u64),
+                    decimals: Some(8),
+                })
+            );
+        }
+        assert!(asserter.read_q().is_empty());
+
+        let (payload, _, _) = native_payload(10143, PAY_TO, 1_500, Bytes::new());
+        let request = VerifyRequest {
+            payment_payload: payload,
+            ..request
+        };
+        let paid = provider.payment_amount(&request).await;
+        assert_eq!(
+            paid,
+            Some(PaymentAmount {
+                amount: TokenAmount::from(1_500u64),
+                decimals: Some(18),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_native_payment_rejects_invalid_transfers() {
+        let chain = EvmChain::try_from(Network::MonadTestnet).unwrap();
+        let native = native_requirements(NATIVE_COIN_ASSET, 1_000);
+        let other = address!("2222222222222222222222222222222222222222");
+        let funded = 1_000 + 21_000 * 100;
+        let cases = [
+            (native_payload(10143, PAY_TO, 999, Bytes::new()), &native, 7, funded),
+            (native_payload(10143, other, 1_000, Bytes::new()), &native, 7, funded),
+            (native_payload(143, PAY_TO, 1_000, Bytes::new()), &native, 7, funded),
+            (native_payload(10143, PAY_TO, 1_000, Bytes::from([1u8])), &native, 7, funded),
+            (native_payload(10143, PAY_TO, 1_000, Bytes::new()), &native, 8, funded),
+            (native_payload(10143, PAY_TO, 1_000, Bytes::new()), &native, 7, funded - 1),
+        ];
+        let mut errors = Vec::new();
+        for ((payload, native_payload, _), requirements, nonce, balance) in cases {
+            let result = assert_valid_native_payment(
+                payer_state_provider(nonce, balance),
+                &chain,
+                &payload,
+                &native_payload,
+                requirements,
+            )
+            .await;
+            errors.push(result.err().expect("payment must be rejected"));
+        }
+        assert!(matches!(errors[0], FacilitatorLocalError::InsufficientValue(_)));
+        assert!(matches!(errors[1], FacilitatorLocalError::ReceiverMismatch(..)));
+        assert!(
+            matches!(&errors[2], FacilitatorLocalError::DecodingError(e) if e.ends_with("chain_id"))
+        );
+        assert!(
+            matches!(&errors[3], FacilitatorLocalError::DecodingError(e) if e.ends_with("calldata"))
+        );
+        assert!(
+            matches!(&errors[4], FacilitatorLocalError::DecodingError(e) if e.ends_with("nonce"))
+        );
+        assert!(matches!(errors[5], FacilitatorLocalError::InsufficientFunds(_)));
+
+        let token_asset = address!("3333333333333333333333333333333333333333");
+        let token = native_requirements(token_asset, 1_000);
+        let (payload, native_payload, _) = native_payload(10143, PAY_TO, 1_000, Bytes::new());
+        let result = assert_valid_native_payment(
+            payer_state_provider(7, funded),
+            &chain,
+            &payload,
+            &native_payload,
+            &token,
+        )
+        .await;
+        assert!(
+            matches!(result, Err(FacilitatorLocalError::DecodingError(e)) if e.ends_with("asset"))
+        );
+    }
 }
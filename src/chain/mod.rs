@@ -6,11 +6,12 @@ use crate::chain::solana::SolanaProvider;
 use crate::facilitator::Facilitator;
 use crate::network::{Network, NetworkFamily};
 use crate::types::{
-    MixedAddress, Scheme, SettleRequest, SettleResponse, SupportedPaymentKindsResponse,
-    TransactionHash, TransactionStatusResponse, VerifyRequest, VerifyResponse,
+    Finality, MixedAddress, Scheme, SettleRequest, SettleResponse, SupportedPaymentKindsResponse,
+    TokenAmount, TransactionHash, TransactionStatusResponse, VerifyRequest, VerifyResponse,
 };
 
 pub mod evm;
+pub mod rpc_pool;
 pub mod solana;
 
 pub enum NetworkProvider {
@@ -55,6 +56,57 @@ pub trait TransactionStatusQuery {
     ) -> impl Future<Output = Result<TransactionStatusResponse, FacilitatorLocalError>> + Send;
 }
 
+/// Trait for checking whether a settled transaction is final, or was reorganized out.
+pub trait FinalityQuery {
+    /// The finality of a transaction that was included in the chain.
+    fn get_finality(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> impl Future<Output = Result<Finality, FacilitatorLocalError>> + Send;
+}
+
+/// Trait for querying what a settlement transaction cost the facilitator.
+pub trait SettlementCostQuery {
+    /// Native coin the facilitator spent on a transaction, in its smallest unit (wei, lamports).
+    fn get_settlement_cost(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> impl Future<Output = Result<TokenAmount, FacilitatorLocalError>> + Send;
+}
+
+/// Trait for querying the native coin balances of the facilitator signers.
+pub trait SignerBalanceQuery {
+    /// Balance of every signer, in the smallest unit of the native coin (wei, lamports).
+    fn signer_balances(
+        &self,
+    ) -> impl Future<Output = Result<Vec<(MixedAddress, TokenAmount)>, FacilitatorLocalError>> + Send;
+}
+
+/// Trait for the service fee a provider charges on payments, see
+/// [`FacilitatorFee`](crate::types::FacilitatorFee).
+pub trait FacilitatorFeeQuery {
+    /// Fee the payment of `request` must pay the facilitator, `None` if it pays none.
+    fn fee_due(&self, request: &VerifyRequest) -> Option<TokenAmount>;
+}
+
+/// What a payment transfers to its recipient, as decoded from its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaymentAmount {
+    /// In the smallest unit of the asset.
+    pub amount: TokenAmount,
+    /// Decimals of the asset, if they could be resolved.
+    pub decimals: Option<u8>,
+}
+
+/// Trait for decoding the amount a payment transfers, which may be below the maximum required.
+pub trait PaymentAmountQuery {
+    /// Amount the payment of `request` transfers, `None` if its payload can not be decoded.
+    fn payment_amount(
+        &self,
+        request: &VerifyRequest,
+    ) -> impl Future<Output = Option<PaymentAmount>> + Send;
+}
+
 impl NetworkProviderOps for NetworkProvider {
     fn signer_address(&self) -> MixedAddress {
         match self {
@@ -108,6 +160,59 @@ impl TransactionStatusQuery for NetworkProvider {
     }
 }
 
+impl FinalityQuery for NetworkProvider {
+    async fn get_finality(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> Result<Finality, FacilitatorLocalError> {
+        match self {
+            NetworkProvider::Evm(provider) => provider.get_finality(tx_hash).await,
+            NetworkProvider::Solana(provider) => provider.get_finality(tx_hash).await,
+        }
+    }
+}
+
+impl SettlementCostQuery for NetworkProvider {
+    async fn get_settlement_cost(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> Result<TokenAmount, FacilitatorLocalError> {
+        match self {
+            NetworkProvider::Evm(provider) => provider.get_settlement_cost(tx_hash).await,
+            NetworkProvider::Solana(provider) => provider.get_settlement_cost(tx_hash).await,
+        }
+    }
+}
+
+impl FacilitatorFeeQuery for NetworkProvider {
+    fn fee_due(&self, request: &VerifyRequest) -> Option<TokenAmount> {
+        match self {
+            NetworkProvider::Evm(provider) => provider.fee_due(request),
+            NetworkProvider::Solana(provider) => provider.fee_due(request),
+        }
+    }
+}
+
+impl PaymentAmountQuery for NetworkProvider {
+    async fn payment_amount(&self, request: &VerifyRequest) -> Option<PaymentAmount> {
+        match self {
+            NetworkProvider::Evm(provider) => provider.payment_amount(request).await,
+            NetworkProvider::Solana(provider) => provider.payment_amount(request).await,
+        }
+    }
+}
+
+impl SignerBalanceQuery for NetworkProvider {
+    async fn signer_balances(
+        &self,
+    ) -> Result<Vec<(MixedAddress, TokenAmount)>, FacilitatorLocalError> {
+        match self {
+            NetworkProvider::Evm(provider) => provider.signer_balances().await,
+            NetworkProvider::Solana(provider) => provider.signer_balances().await,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum FacilitatorLocalError {
     /// The network is not supported by this facilitator.
@@ -134,6 +239,10 @@ pub enum FacilitatorLocalError {
     /// Low-level contract interaction failure (e.g. call failed, method not found).
     #[error("Invalid contract call: {0}")]
     ContractCall(String),
+    /// The settlement transaction was not broadcast: it could not be prepared, or the node
+    /// refused it, e.g. because the signer can not pay for gas or gas exceeds the price ceiling.
+    #[error("Settlement transaction not broadcast: {0}")]
+    NotBroadcast(String),
     /// EIP-712 signature is invalid or mismatched.
     #[error("Invalid signature: {1}")]
     InvalidSignature(MixedAddress, String),
@@ -143,7 +252,16 @@ pub enum FacilitatorLocalError {
     /// The payload's `value` is not enough to meet the requirements.
     #[error("Insufficient value")]
     InsufficientValue(MixedAddress),
+    /// The payload does not pay the facilitator fee as required.
+    #[error("Invalid facilitator fee: {1}")]
+    InvalidFee(MixedAddress, String),
     /// The payload decoding failed.
     #[error("Decoding error: {0}")]
     DecodingError(String),
+    /// A transaction hash looked up on every network was found on several of them.
+    #[error("Transaction found on several networks")]
+    AmbiguousTransaction(Vec<Network>),
+    /// The facilitator is shutting down, and does not accept new settlements.
+    #[error("Shutting down")]
+    ShuttingDown,
 }
@@ -0,0 +1,644 @@
+//! Weighted pools of RPC endpoints with health-aware failover.
+//!
+//! A network may be served by several RPC endpoints, configured as a comma-separated list in its
+//! `RPC_URL_*` variable, each URL optionally followed by `|<weight>` (default `1`):
+//!
+//! ```text
+//! RPC_URL_MONAD=https://rpc-a.example|3,https://rpc-b.example
+//! ```
+//!
+//! Read calls go to a single endpoint, picked by smooth weighted round-robin with each weight
+//! scaled by the endpoint's health: moving averages of its latency and of its transport error
+//! rate. A call failing at the transport level (connection error, timeout, HTTP error status) is
+//! retried on the other endpoints, healthiest first. JSON-RPC errors are answers, not failures,
+//! and are returned as is.
+//!
+//! Transaction submissions are broadcast to the [`BROADCAST_FANOUT`] healthiest endpoints at once
+//! for faster inclusion; the first successful answer is returned, and the other submissions are
+//! left to complete in the background.
+//!
+//! Identical reads issued concurrently, like the same payer balance requested by several
+//! verifications at once, are coalesced by a [`Coalescer`]: only the first one reaches an
+//! endpoint, and the others share its answer.
+//!
+//! [`EvmRpcTransport`] plugs a pool into Alloy as a transport, and [`SolanaRpcSender`] into the
+//! Solana [`RpcClient`](solana_client::nonblocking::rpc_client::RpcClient) as a sender.
+
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::http::Http;
+use alloy::transports::{BoxTransport, RpcError, Transport, TransportError, TransportFut};
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tower::Service;
+use url::Url;
+
+/// Number of endpoints a transaction submission is broadcast to.
+pub const BROADCAST_FANOUT: usize = 3;
+/// Weight of the latest sample in the latency and error rate moving averages.
+const HEALTH_SMOOTHING: f64 = 0.2;
+/// Latency differences below this do not affect endpoint selection.
+const LATENCY_TOLERANCE: Duration = Duration::from_millis(50);
+/// Lowest health factor, so that a failing endpoint still gets the odd probe and can recover.
+const MIN_HEALTH: f64 = 0.05;
+
+/// JSON-RPC methods broadcast to several EVM endpoints.
+const EVM_WRITE_METHODS: &[&str] = &[
+    "eth_sendRawTransaction",
+    "eth_sendRawTransactionSync",
+    "eth_sendTransaction",
+];
+
+/// An RPC endpoint and its share of read traffic relative to the other endpoints of the network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RpcEndpoint {
+    pub url: String,
+    pub weight: u32,
+}
+
+impl RpcEndpoint {
+    /// An endpoint with weight `1`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            weight: 1,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+impl Display for RpcEndpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}|{}", self.url, self.weight)
+    }
+}
+
+impl FromStr for RpcEndpoint {
+    type Err = RpcPoolError;
+
+    /// Parses `<url>` or `<url>|<weight>`, with a positive weight.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (url, weight) = match s.rsplit_once('|') {
+            Some((url, weight)) => {
+                let weight = weight
+                    .trim()
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|weight| *weight > 0)
+                    .ok_or_else(|| RpcPoolError::InvalidWeight(s.to_string()))?;
+                (url.trim(), weight)
+            }
+            None => (s.trim(), 1),
+        };
+        if url.is_empty() {
+            return Err(RpcPoolError::InvalidUrl(s.to_string()));
+        }
+        Ok(RpcEndpoint::new(url).with_weight(weight))
+    }
+}
+
+/// Parses a comma-separated list of [`RpcEndpoint`]s, skipping empty entries.
+pub fn parse_rpc_endpoints(value: &str) -> Result<Vec<RpcEndpoint>, RpcPoolError> {
+    let endpoints = value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(RpcEndpoint::from_str)
+        .collect::<Result<Vec<_>, _>>()?;
+    if endpoints.is_empty() {
+        return Err(RpcPoolError::Empty);
+    }
+    Ok(endpoints)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RpcPoolError {
+    #[error("No RPC endpoint configured")]
+    Empty,
+    #[error("Invalid RPC URL: {0}")]
+    InvalidUrl(String),
+    #[error("Invalid RPC endpoint weight, expected a positive integer: {0}")]
+    InvalidWeight(String),
+}
+
+/// Health and round-robin state of one endpoint.
+#[derive(Debug, Default)]
+struct EndpointState {
+    /// Moving average of call latency; `None` until the first call.
+    latency: Option<Duration>,
+    /// Moving average of the transport error rate, in `[0, 1]`.
+    error_rate: f64,
+    /// Smooth weighted round-robin counter.
+    current_weight: f64,
+}
+
+/// Endpoints of one network, with their health.
+pub struct RpcPool<T> {
+    endpoints: Vec<(RpcEndpoint, T)>,
+    state: Mutex<Vec<EndpointState>>,
+}
+
+impl<T> RpcPool<T> {
+    /// Builds a pool, connecting each endpoint with `connect`.
+    pub fn try_new<E>(
+        endpoints: &[RpcEndpoint],
+        connect: impl Fn(&RpcEndpoint) -> Result<T, E>,
+    ) -> Result<Self, E>
+    where
+        E: From<RpcPoolError>,
+    {
+        if endpoints.is_empty() {
+            return Err(RpcPoolError::Empty.into());
+        }
+        let endpoints = endpoints
+            .iter()
+            .map(|endpoint| Ok((endpoint.clone(), connect(endpoint)?)))
+            .collect::<Result<Vec<_>, E>>()?;
+        let state = endpoints.iter().map(|_| EndpointState::default()).collect();
+        Ok(Self {
+            endpoints,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn endpoints(&self) -> impl Iterator<Item = &RpcEndpoint> {
+        self.endpoints.iter().map(|(endpoint, _)| endpoint)
+    }
+
+    /// Configured weight scaled by health: penalised by the error rate, and by latency relative
+    /// to the fastest endpoint beyond [`LATENCY_TOLERANCE`].
+    fn effective_weights(&self, state: &[EndpointState]) -> Vec<f64> {
+        let fastest = state.iter().filter_map(|s| s.latency).min();
+        self.endpoints
+            .iter()
+            .zip(state)
+            .map(|((endpoint, _), state)| {
+                let speed = match (fastest, state.latency) {
+                    (Some(fastest), Some(latency)) => {
+                        (fastest + LATENCY_TOLERANCE).as_secs_f64()
+                            / (latency + LATENCY_TOLERANCE).as_secs_f64()
+                    }
+                    _ => 1.0,
+                };
+                let health = ((1.0 - state.error_rate) * speed).max(MIN_HEALTH);
+                f64::from(endpoint.weight) * health
+            })
+            .collect()
+    }
+
+    /// Endpoint indices by decreasing effective weight.
+    fn healthiest(&self, weights: &[f64]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.endpoints.len()).collect();
+        order.sort_by(|a, b| weights[*b].total_cmp(&weights[*a]));
+        order
+    }
+
+    /// Endpoints to try for a read: the smooth weighted round-robin pick, then the others by health.
+    fn read_order(&self) -> Vec<usize> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let weights = self.effective_weights(&state);
+        let total: f64 = weights.iter().sum();
+        for (state, weight) in state.iter_mut().zip(&weights) {
+            state.current_weight += weight;
+        }
+        let picked = (0..state.len())
+            .max_by(|a, b| {
+                state[*a]
+                    .current_weight
+                    .total_cmp(&state[*b].current_weight)
+            })
+            .unwrap_or_default();
+        state[picked].current_weight -= total;
+        let mut order = vec![picked];
+        order.extend(
+            self.healthiest(&weights)
+                .into_iter()
+                .filter(|i| *i != picked),
+        );
+        order
+    }
+
+    /// Endpoints a write is broadcast to.
+    fn broadcast_targets(&self) -> Vec<usize> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let weights = self.effective_weights(&state);
+        let mut targets = self.healthiest(&weights);
+        targets.truncate(BROADCAST_FANOUT);
+        targets
+    }
+
+    fn record(&self, index: usize, latency: Duration, failed: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = &mut state[index];
+        state.latency = Some(match state.latency {
+            Some(average) => {
+                average.mul_f64(1.0 - HEALTH_SMOOTHING) + latency.mul_f64(HEALTH_SMOOTHING)
+            }
+            None => latency,
+        });
+        let sample = if failed { 1.0 } else { 0.0 };
+        state.error_rate = state.error_rate * (1.0 - HEALTH_SMOOTHING) + sample * HEALTH_SMOOTHING;
+    }
+
+    /// Runs a read call on one endpoint, failing over to the others while `is_transport_error`.
+    pub async fn read<R, E, Fut>(
+        &self,
+        call: impl Fn(&T) -> Fut,
+        is_transport_error: impl Fn(&E) -> bool,
+    ) -> Result<R, E>
+    where
+        Fut: Future<Output = Result<R, E>>,
+        E: Display,
+    {
+        let mut last_error = None;
+        for index in self.read_order() {
+            let (endpoint, transport) = &self.endpoints[index];
+            let started = Instant::now();
+            let result = call(transport).await;
+            let failed = result.as_ref().is_err_and(&is_transport_error);
+            self.record(index, started.elapsed(), failed);
+            match result {
+                Err(error) if failed => {
+                    tracing::warn!(rpc = %endpoint.url, error = %error, "RPC endpoint failed, trying next");
+                    last_error = Some(error);
+                }
+                result => return result,
+            }
+        }
+        Err(last_error.expect("pool has at least one endpoint"))
+    }
+
+    /// Runs a write call on the healthiest endpoints at once, returning the first success, or
+    /// else the first JSON-RPC error, or else the last transport error.
+    pub async fn broadcast<R, E, Fut>(
+        &self,
+        call: impl Fn(&T) -> Fut,
+        is_transport_error: impl Fn(&E) -> bool,
+    ) -> Result<R, E>
+    where
+        Fut: Future<Output = Result<R, E>> + Send + 'static,
+        R: Send + 'static,
+        E: Display + Send + 'static,
+    {
+        let mut calls = JoinSet::new();
+        for index in self.broadcast_targets() {
+            let request = call(&self.endpoints[index].1);
+            calls.spawn(async move {
+                let started = Instant::now();
+                (index, request.await, started.elapsed())
+            });
+        }
+        let mut rejection = None;
+        let mut last_error = None;
+        while let Some(joined) = calls.join_next().await {
+            let Ok((index, result, latency)) = joined else {
+                continue;
+            };
+            let failed = result.as_ref().is_err_and(&is_transport_error);
+            self.record(index, latency, failed);
+            match result {
+                Ok(response) => {
+                    // Let the other submissions reach their endpoints.
+                    calls.detach_all();
+                    return Ok(response);
+                }
+                Err(error) if failed => {
+                    let url = &self.endpoints[index].0.url;
+                    tracing::warn!(rpc = %url, error = %error, "RPC endpoint failed to broadcast");
+                    last_error = Some(error);
+                }
+                Err(error) => {
+                    rejection.get_or_insert(error);
+                }
+            }
+        }
+        Err(rejection
+            .or(last_error)
+            .expect("broadcast to at least one endpoint"))
+    }
+}
+
+/// Shares the successful result of an in-flight call with identical calls made while it runs.
+///
+/// If the first call fails, each waiting call is made on its own instead, so errors are never
+/// shared.
+pub struct Coalescer<K, V> {
+    in_flight: DashMap<K, watch::Receiver<Option<V>>>,
+}
+
+/// Removes the key of a leading call once it completes, or is dropped.
+struct InFlight<'a, K: Eq + Hash, V> {
+    in_flight: &'a DashMap<K, watch::Receiver<Option<V>>>,
+    key: K,
+}
+
+impl<K: Eq + Hash, V> Drop for InFlight<'_, K, V> {
+    fn drop(&mut self) {
+        self.in_flight.remove(&self.key);
+    }
+}
+
+impl<K: Eq + Hash, V> Default for Coalescer<K, V> {
+    fn default() -> Self {
+        Self {
+            in_flight: DashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Coalescer<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `call` unless an identical call, keyed by `key`, is already in flight.
+    pub async fn run<E, Fut>(&self, key: K, call: impl FnOnce() -> Fut) -> Result<V, E>
+    where
+        Fut: Future<Output = Result<V, E>>,
+    {
+        let sender = match self.in_flight.entry(key.clone()) {
+            Entry::Occupied(entry) => {
+                let mut receiver = entry.get().clone();
+                drop(entry);
+                if let Ok(value) = receiver.wait_for(Option::is_some).await {
+                    if let Some(value) = &*value {
+                        return Ok(value.clone());
+                    }
+                }
+                return call().await;
+            }
+            Entry::Vacant(entry) => {
+                let (sender, receiver) = watch::channel(None);
+                entry.insert(receiver);
+                sender
+            }
+        };
+        let _in_flight = InFlight {
+            in_flight: &self.in_flight,
+            key,
+        };
+        let result = call().await;
+        if let Ok(value) = &result {
+            let _ = sender.send(Some(value.clone()));
+        }
+        result
+    }
+}
+
+/// Alloy transport over a pool of HTTP endpoints.
+#[derive(Clone)]
+pub struct EvmRpcTransport {
+    pool: Arc<RpcPool<BoxTransport>>,
+    coalescer: Arc<Coalescer<(String, String), ResponsePacket>>,
+}
+
+impl EvmRpcTransport {
+    pub fn try_new(endpoints: &[RpcEndpoint]) -> Result<Self, RpcPoolError> {
+        let pool = RpcPool::try_new(endpoints, |endpoint| {
+            let url = Url::parse(&endpoint.url)
+                .map_err(|_| RpcPoolError::InvalidUrl(endpoint.url.clone()))?;
+            Ok::<_, RpcPoolError>(Http::new(url).boxed())
+        })?;
+        Ok(Self {
+            pool: Arc::new(pool),
+            coalescer: Arc::new(Coalescer::new()),
+        })
+    }
+}
+
+fn is_evm_write(request: &RequestPacket) -> bool {
+    match request {
+        RequestPacket::Single(request) => EVM_WRITE_METHODS.contains(&request.method()),
+        RequestPacket::Batch(requests) => requests
+            .iter()
+            .any(|request| EVM_WRITE_METHODS.contains(&request.method())),
+    }
+}
+
+/// Methods whose answers depend on state kept by the node for the caller, never coalesced.
+const EVM_STATEFUL_METHOD_PREFIXES: &[&str] = &[
+    "eth_newFilter",
+    "eth_newBlockFilter",
+    "eth_newPendingTransactionFilter",
+    "eth_getFilter",
+    "eth_uninstallFilter",
+    "eth_subscribe",
+    "eth_unsubscribe",
+];
+
+/// Key under which a single read request is coalesced: its method and serialized params.
+fn evm_coalescing_key(request: &RequestPacket) -> Option<(String, String)> {
+    let RequestPacket::Single(request) = request else {
+        return None;
+    };
+    let method = request.method();
+    if EVM_WRITE_METHODS.contains(&method)
+        || EVM_STATEFUL_METHOD_PREFIXES
+            .iter()
+            .any(|prefix| method.starts_with(prefix))
+    {
+        return None;
+    }
+    let params = request.params().map(|params| params.get()).unwrap_or("");
+    Some((method.to_string(), params.to_string()))
+}
+
+fn is_evm_transport_error(error: &TransportError) -> bool {
+    matches!(error, RpcError::Transport(_))
+}
+
+impl Service<RequestPacket> for EvmRpcTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let pool = self.pool.clone();
+        let coalescer = self.coalescer.clone();
+        Box::pin(async move {
+            let call = |transport: &BoxTransport| transport.clone().call(request.clone());
+            if is_evm_write(&request) {
+                return pool.broadcast(call, is_evm_transport_error).await;
+            }
+            let Some(key) = evm_coalescing_key(&request) else {
+                return pool.read(call, is_evm_transport_error).await;
+            };
+            let response = coalescer
+                .run(key, || pool.read(call, is_evm_transport_error))
+                .await?;
+            // A shared answer carries the id of the request that fetched it.
+            match (response, &request) {
+                (ResponsePacket::Single(mut response), RequestPacket::Single(request)) => {
+                    response.id = request.id().clone();
+                    Ok(ResponsePacket::Single(response))
+                }
+                (response, _) => Ok(response),
+            }
+        })
+    }
+}
+
+/// Solana RPC sender over a pool of HTTP endpoints.
+pub struct SolanaRpcSender {
+    pool: RpcPool<Arc<HttpSender>>,
+    coalescer: Coalescer<(String, String), serde_json::Value>,
+}
+
+impl SolanaRpcSender {
+    pub fn try_new(endpoints: &[RpcEndpoint]) -> Result<Self, RpcPoolError> {
+        let pool = RpcPool::try_new(endpoints, |endpoint| {
+            Ok::<_, RpcPoolError>(Arc::new(HttpSender::new(endpoint.url.clone())))
+        })?;
+        Ok(Self {
+            pool,
+            coalescer: Coalescer::new(),
+        })
+    }
+}
+
+fn is_solana_transport_error(error: &solana_client::client_error::ClientError) -> bool {
+    matches!(
+        error.kind(),
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_)
+    )
+}
+
+#[async_trait::async_trait]
+impl RpcSender for SolanaRpcSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        let call = |sender: &Arc<HttpSender>| {
+            let sender = sender.clone();
+            let params = params.clone();
+            async move { sender.send(request, params).await }
+        };
+        match request {
+            RpcRequest::SendTransaction => {
+                self.pool.broadcast(call, is_solana_transport_error).await
+            }
+            RpcRequest::RequestAirdrop => self.pool.read(call, is_solana_transport_error).await,
+            _ => {
+                let key = (request.to_string(), params.to_string());
+                self.coalescer
+                    .run(key, || self.pool.read(call, is_solana_transport_error))
+                    .await
+            }
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        let mut stats = RpcTransportStats::default();
+        for (_, sender) in &self.pool.endpoints {
+            let endpoint_stats = sender.get_transport_stats();
+            stats.request_count += endpoint_stats.request_count;
+            stats.elapsed_time += endpoint_stats.elapsed_time;
+            stats.rate_limited_time += endpoint_stats.rate_limited_time;
+        }
+        stats
+    }
+
+    fn url(&self) -> String {
+        self.pool
+            .endpoints()
+            .map(|endpoint| endpoint.url.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_parse_rpc_endpoints() {
+        let endpoints = parse_rpc_endpoints("https://a.example|3, https://b.example,").unwrap();
+        assert_eq!(
+            endpoints,
+            vec![
+                RpcEndpoint::new("https://a.example").with_weight(3),
+                RpcEndpoint::new("https://b.example"),
+            ]
+        );
+        assert!(parse_rpc_endpoints("https://a.example|0").is_err());
+        assert!(parse_rpc_endpoints(" , ").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_fails_over_and_deprioritises_failing_endpoint() {
+        let endpoints = [
+            RpcEndpoint::new("down").with_weight(10),
+            RpcEndpoint::new("up"),
+        ];
+        let pool = RpcPool::try_new(&endpoints, |endpoint| {
+            Ok::<_, RpcPoolError>((endpoint.url.clone(), AtomicUsize::new(0)))
+        })
+        .unwrap();
+        let call = |(url, calls): &(String, AtomicUsize)| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            let result = if url == "down" {
+                Err("connection refused".to_string())
+            } else {
+                Ok(url.clone())
+            };
+            async move { result }
+        };
+
+        for _ in 0..20 {
+            let result = pool.read(call, |_| true).await;
+            assert_eq!(result.unwrap(), "up");
+        }
+        // Each failure scales the weight of "down" by 0.8, so it soon stops being picked first.
+        let down_calls = pool.endpoints[0].1.1.load(Ordering::SeqCst);
+        assert!(down_calls < 20, "down endpoint tried {down_calls} times");
+        assert_eq!(pool.broadcast_targets(), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_coalescer_shares_in_flight_result() {
+        let coalescer = Coalescer::<&str, u64>::new();
+        let calls = AtomicUsize::new(0);
+        let (release, released) = watch::channel(false);
+        let (calls, released) = (&calls, &released);
+        let call = move || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            released.clone().wait_for(|released| *released).await.ok();
+            Ok::<_, ()>(42)
+        };
+
+        let leader = coalescer.run("balance", call);
+        let follower = coalescer.run("balance", call);
+        let other = coalescer.run("nonce", call);
+        let (leader, follower, other, _) = tokio::join!(leader, follower, other, async {
+            tokio::task::yield_now().await;
+            release.send(true).unwrap();
+        });
+        assert_eq!((leader, follower, other), (Ok(42), Ok(42), Ok(42)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(coalescer.in_flight.is_empty());
+    }
+}
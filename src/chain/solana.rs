@@ -1,32 +1,70 @@
+use dashmap::DashMap;
+use serde::Deserialize;
+use solana_address_lookup_table_interface::state::AddressLookupTable;
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig};
+use solana_client::rpc_client::RpcClientConfig;
+use solana_client::rpc_config::{
+    RpcSendTransactionConfig, RpcSimulateTransactionConfig, RpcTransactionConfig,
+};
+use solana_client::rpc_request::RpcRequest;
 use solana_commitment_config::CommitmentConfig;
+use solana_nonce::state::{Data as NonceData, State as NonceState};
+use solana_nonce::versions::Versions as NonceVersions;
+use solana_sdk::account::Account;
 use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::v0::MessageAddressTableLookup;
 use solana_sdk::pubkey;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, Signature};
 use solana_sdk::signer::Signer;
-use solana_sdk::transaction::VersionedTransaction;
-use std::fmt::{Debug, Formatter};
-use std::sync::Arc;
-use std::time::Duration;
+use solana_sdk::transaction::{TransactionError, VersionedTransaction};
+use solana_system_interface::instruction::SystemInstruction;
+use spl_token_2022::extension::pausable::PausableConfig;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use tracing_core::Level;
 
+use crate::chain::rpc_pool::{RpcEndpoint, SolanaRpcSender};
 use crate::chain::{
-    FacilitatorLocalError, FromEnvByNetworkBuild, NetworkProviderOps, TransactionStatusQuery,
+    FacilitatorFeeQuery, FacilitatorLocalError, FinalityQuery, FromEnvByNetworkBuild,
+    NetworkProviderOps, PaymentAmount, PaymentAmountQuery, SettlementCostQuery, SignerBalanceQuery,
+    TransactionStatusQuery,
 };
 use crate::facilitator::Facilitator;
 use crate::from_env;
-use crate::network::Network;
+use crate::network::{Network, USDCDeployment};
+use crate::pending_settlements;
 use crate::types::{
-    Base64Bytes, ExactPaymentPayload, FacilitatorErrorReason, MixedAddress, PaymentRequirements,
-    SettleRequest, SettleResponse, SupportedPaymentKind, SupportedPaymentKindExtra,
-    SupportedPaymentKindsResponse, TokenAmount, TransactionHash, TransactionStatus,
-    TransactionStatusResponse, VerifyRequest, VerifyResponse,
+    Base64Bytes, ExactPaymentPayload, FacilitatorErrorReason, FacilitatorFee, Finality,
+    MixedAddress, PaymentRequirements, PriorityFeeResponse, SettleRequest, SettleResponse,
+    SupportedPaymentKind, SupportedPaymentKindExtra, SupportedPaymentKindsResponse, TokenAmount,
+    TransactionHash, TransactionStatus, TransactionStatusResponse, VerifyDetails, VerifyRequest,
+    VerifyResponse,
 };
 use crate::types::{Scheme, X402Version};
 
-const ATA_PROGRAM_PUBKEY: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+/// Associated Token Account program id.
+pub const ATA_PROGRAM_PUBKEY: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Value of [`PaymentRequirements::asset`] designating native SOL (amounts in lamports).
+///
+/// This is the System Program id, which can never be a token mint.
+pub const NATIVE_SOL_ASSET: Pubkey = solana_system_interface::program::ID;
+
+/// How long resolved address lookup table contents are reused before being refetched.
+const ADDRESS_LOOKUP_TABLE_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// How often a settlement transaction is rebroadcast while waiting for confirmation.
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a recommended compute unit price is reused before `getRecentPrioritizationFees` is
+/// queried again for the same accounts.
+const PRIORITY_FEE_CACHE_TTL: Duration = Duration::from_secs(10);
 
 #[derive(Clone, Debug)]
 pub struct SolanaChain {
@@ -87,8 +125,11 @@ pub struct SolanaProvider {
     keypair: Arc<Keypair>,
     chain: SolanaChain,
     rpc_client: Arc<RpcClient>,
-    max_compute_unit_limit: u32,
-    max_compute_unit_price: u64,
+    policy: Arc<RwLock<Arc<SolanaPolicy>>>,
+    lookup_tables: AddressLookupTableCache,
+    priority_fees: PriorityFeeCache,
+    advertised_price: Arc<Mutex<AdvertisedPrice>>,
+    fee: Option<FacilitatorFee>,
 }
 
 impl Debug for SolanaProvider {
@@ -102,71 +143,222 @@ impl Debug for SolanaProvider {
 }
 
 impl SolanaProvider {
-    fn max_compute_unit_limit_from_env(network: Network) -> u32 {
-        let suffix = match network {
-            Network::Solana => "SOLANA",
-            Network::SolanaDevnet => "SOLANA_DEVNET",
-            _ => return 200_000, // fallback (shouldn't be used)
-        };
-
-        let limit_var = format!("X402_SOLANA_MAX_COMPUTE_UNIT_LIMIT_{}", suffix);
-        std::env::var(&limit_var)
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(match network {
-                Network::Solana => 400_000,
-                Network::SolanaDevnet => 200_000,
-                _ => 200_000,
-            })
-    }
-
-    fn max_compute_unit_price_from_env(network: Network) -> u64 {
-        let suffix = match network {
-            Network::Solana => "SOLANA",
-            Network::SolanaDevnet => "SOLANA_DEVNET",
-            _ => return 100_000, // fallback (shouldn't be used)
-        };
-
-        let price_var = format!("X402_SOLANA_MAX_COMPUTE_UNIT_PRICE_{}", suffix);
-        std::env::var(&price_var)
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(match network {
-                Network::Solana => 1_000_000,
-                Network::SolanaDevnet => 100_000,
-                _ => 100_000,
-            })
-    }
-
+    /// RPC calls are spread over `rpc_endpoints` with failover, see [`SolanaRpcSender`].
     pub fn try_new(
         keypair: Keypair,
-        rpc_url: String,
+        rpc_endpoints: &[RpcEndpoint],
         network: Network,
         max_compute_unit_limit: u32,
         max_compute_unit_price: u64,
-    ) -> Result<Self, FacilitatorLocalError> {
+        mint_extension_policy: MintExtensionPolicy,
+        priority_fee_policy: PriorityFeePolicy,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let chain = SolanaChain::try_from(network)?;
+        let policy = SolanaPolicy {
+            max_compute_unit_limit,
+            max_compute_unit_price,
+            mint_extension_policy,
+            priority_fee_policy,
+            settle_commitment: CommitmentConfig::confirmed(),
+        };
         {
             let signer_addresses = vec![keypair.pubkey()];
             tracing::info!(
                 network = %network,
-                rpc = rpc_url,
+                rpc = ?rpc_endpoints,
                 signers = ?signer_addresses,
-                max_compute_unit_limit,
-                max_compute_unit_price,
+                policy = ?policy,
                 "Initialized Solana provider"
             );
         }
-        let rpc_client = RpcClient::new(rpc_url);
+        let sender = SolanaRpcSender::try_new(rpc_endpoints)
+            .map_err(|e| format!("Failed to connect to {network}: {e}"))?;
+        let rpc_client = RpcClient::new_sender(
+            sender,
+            RpcClientConfig::with_commitment(CommitmentConfig::default()),
+        );
         Ok(Self {
             keypair: Arc::new(keypair),
             chain,
             rpc_client: Arc::new(rpc_client),
-            max_compute_unit_limit,
-            max_compute_unit_price,
+            policy: Arc::new(RwLock::new(Arc::new(policy))),
+            lookup_tables: AddressLookupTableCache::new(ADDRESS_LOOKUP_TABLE_CACHE_TTL),
+            priority_fees: PriorityFeeCache::new(PRIORITY_FEE_CACHE_TTL),
+            advertised_price: Arc::new(Mutex::new(AdvertisedPrice::default())),
+            fee: None,
+        })
+    }
+
+    /// Requires payments to carry a last instruction paying `fee` to its recipient.
+    pub fn with_fee(mut self, fee: FacilitatorFee) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Replaces the RPC client, to check payments against canned RPC responses.
+    #[cfg(test)]
+    pub(crate) fn with_rpc_client(mut self, rpc_client: RpcClient) -> Self {
+        self.rpc_client = Arc::new(rpc_client);
+        self
+    }
+
+    /// Nonzero facilitator fee due on a payment meeting `requirements`.
+    fn fee_amount(&self, requirements: &PaymentRequirements) -> Option<TokenAmount> {
+        let fee_due = self
+            .fee
+            .as_ref()?
+            .amount(&requirements.asset, requirements.max_amount_required);
+        (fee_due != TokenAmount::from(0u64)).then_some(fee_due)
+    }
+
+    /// Waits for settlement transactions to reach `commitment`, `confirmed` by default.
+    pub fn with_settle_commitment(self, commitment: CommitmentConfig) -> Self {
+        let policy = SolanaPolicy {
+            settle_commitment: commitment,
+            ..SolanaPolicy::clone(&self.policy())
+        };
+        *self.policy.write().expect("policy lock poisoned") = Arc::new(policy);
+        self
+    }
+
+    /// The policy currently in force. Callers keep the snapshot for the whole check they run.
+    pub fn policy(&self) -> Arc<SolanaPolicy> {
+        self.policy.read().expect("policy lock poisoned").clone()
+    }
+
+    /// Swaps in a new policy, as on configuration reload. Payments verified from now on are
+    /// checked against it; cached fee recommendations made under the old one are dropped.
+    pub fn set_policy(&self, policy: SolanaPolicy) {
+        tracing::info!(network = %self.network(), policy = ?policy, "Updated Solana policy");
+        *self.policy.write().expect("policy lock poisoned") = Arc::new(policy);
+        self.priority_fees.clear();
+        self.advertised_price
+            .lock()
+            .expect("advertised price lock poisoned")
+            .price = None;
+    }
+
+    /// Recommends a compute unit price (in micro-lamports) for a transaction writing `accounts`.
+    ///
+    /// The price is the configured percentile of `getRecentPrioritizationFees` for these accounts,
+    /// raised to the configured floor and capped at `max_compute_unit_price`, so that a payment
+    /// built with it always passes [`Self::verify_compute_price_instruction`].
+    pub async fn recommended_compute_unit_price(
+        &self,
+        accounts: &[Pubkey],
+    ) -> Result<u64, FacilitatorLocalError> {
+        if let Some(price) = self.priority_fees.get(accounts) {
+            return Ok(price);
+        }
+        let fees = self
+            .rpc_client
+            .get_recent_prioritization_fees(accounts)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+        let fees: Vec<u64> = fees.iter().map(|fee| fee.prioritization_fee).collect();
+        let policy = self.policy();
+        let price = policy
+            .priority_fee_policy
+            .recommend(&fees)
+            .min(policy.max_compute_unit_price);
+        self.priority_fees.insert(accounts.to_vec(), price);
+        Ok(price)
+    }
+
+    /// Recommends a priority fee for paying `asset` (USDC by default) to `pay_to`.
+    ///
+    /// When `pay_to` is given, its associated token accounts are included in the fee estimate.
+    pub async fn priority_fee(
+        &self,
+        asset: Option<MixedAddress>,
+        pay_to: Option<MixedAddress>,
+    ) -> Result<PriorityFeeResponse, FacilitatorLocalError> {
+        let usdc = USDCDeployment::by_network(self.network());
+        let asset = asset.unwrap_or_else(|| usdc.asset.address.clone());
+        let mint: SolanaAddress = asset.try_into()?;
+        let pay_to = pay_to
+            .map(SolanaAddress::try_from)
+            .transpose()?
+            .map(Pubkey::from);
+        let accounts = priority_fee_accounts(&mint.pubkey, pay_to.as_ref());
+        let compute_unit_price = self.recommended_compute_unit_price(&accounts).await?;
+        Ok(PriorityFeeResponse {
+            network: self.network(),
+            compute_unit_price,
+            max_compute_unit_price: self.policy().max_compute_unit_price,
         })
     }
 
+    /// Compute unit price advertised by `/supported` for paying the network's USDC.
+    ///
+    /// The last recommendation is served even once stale, while a single background task
+    /// refreshes it, so listing supported kinds only waits on RPC for the very first one.
+    async fn advertised_compute_unit_price(&self) -> Option<u64> {
+        let (price, refresh) = {
+            let mut advertised = self
+                .advertised_price
+                .lock()
+                .expect("advertised price lock poisoned");
+            let stale = advertised
+                .price
+                .is_none_or(|(_, fetched_at)| fetched_at.elapsed() > PRIORITY_FEE_CACHE_TTL);
+            let refresh = stale && !advertised.refreshing;
+            advertised.refreshing |= refresh;
+            (advertised.price.map(|(price, _)| price), refresh)
+        };
+        if !refresh {
+            return price;
+        }
+        let provider = self.clone();
+        let refreshed =
+            tokio::spawn(async move { provider.refresh_advertised_compute_unit_price().await });
+        match price {
+            Some(price) => Some(price),
+            None => refreshed.await.ok().flatten(),
+        }
+    }
+
+    async fn refresh_advertised_compute_unit_price(&self) -> Option<u64> {
+        let price = match self.priority_fee(None, None).await {
+            Ok(priority_fee) => Some(priority_fee.compute_unit_price),
+            Err(e) => {
+                tracing::warn!(
+                    network = %self.network(),
+                    error = %e,
+                    "priority fee estimation failed"
+                );
+                None
+            }
+        };
+        let mut advertised = self
+            .advertised_price
+            .lock()
+            .expect("advertised price lock poisoned");
+        advertised.refreshing = false;
+        if let Some(price) = price {
+            advertised.price = Some((price, Instant::now()));
+        }
+        price
+    }
+
+    /// Resolves every account key the transaction message can reference.
+    ///
+    /// For legacy messages and v0 messages without lookups this is just the static account keys.
+    /// For v0 messages using address lookup tables, the tables are loaded over RPC (through
+    /// [`AddressLookupTableCache`]) and the loaded addresses are appended in runtime order.
+    pub async fn resolve_account_keys(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> Result<Vec<Pubkey>, FacilitatorLocalError> {
+        let static_keys = transaction.message.static_account_keys();
+        let lookups = match transaction.message.address_table_lookups() {
+            Some(lookups) if !lookups.is_empty() => lookups,
+            _ => return Ok(static_keys.to_vec()),
+        };
+        let tables = self.lookup_tables.load(&self.rpc_client, lookups).await?;
+        loaded_account_keys(static_keys, lookups, &tables)
+    }
+
     pub fn verify_compute_limit_instruction(
         &self,
         transaction: &VersionedTransaction,
@@ -226,7 +418,7 @@ impl SolanaProvider {
         let mut buf = [0u8; 8];
         buf.copy_from_slice(&data[1..]);
         let microlamports = u64::from_le_bytes(buf);
-        if microlamports > self.max_compute_unit_price {
+        if microlamports > self.policy().max_compute_unit_price {
             return Err(FacilitatorLocalError::DecodingError(
                 "compute unit price exceeds facilitator maximum".to_string(),
             ));
@@ -236,11 +428,10 @@ impl SolanaProvider {
 
     pub fn verify_create_ata_instruction(
         &self,
-        transaction: &VersionedTransaction,
+        tx: &TransactionInt,
         index: usize,
         requirements: &PaymentRequirements,
     ) -> Result<(), FacilitatorLocalError> {
-        let tx = TransactionInt::new(transaction.clone());
         let instruction = tx.instruction(index)?;
         instruction.assert_not_empty()?;
 
@@ -300,96 +491,137 @@ impl SolanaProvider {
         Ok(())
     }
 
-    // this expects the destination ATA to already exist
-    pub async fn verify_transfer_instruction(
+    /// Verifies a native SOL `SystemProgram::Transfer` of at least `max_amount_required` lamports
+    /// to `pay_to`.
+    pub fn verify_system_transfer_instruction(
         &self,
-        transaction: &VersionedTransaction,
+        tx: &TransactionInt,
         instruction_index: usize,
         requirements: &PaymentRequirements,
-        has_dest_ata: bool,
-    ) -> Result<TransferCheckedInstruction, FacilitatorLocalError> {
-        let tx = TransactionInt::new(transaction.clone());
-        let instruction = tx.instruction(instruction_index)?;
-        instruction.assert_not_empty()?;
-        let program_id = instruction.program_id();
-        let transfer_checked_instruction = if spl_token::ID.eq(&program_id) {
-            let token_instruction =
-                spl_token::instruction::TokenInstruction::unpack(instruction.data_slice())
-                    .map_err(|_| {
-                        FacilitatorLocalError::DecodingError(
-                            "invalid_exact_svm_payload_transaction_instructions".to_string(),
-                        )
-                    })?;
-            let (amount, decimals) = match token_instruction {
-                spl_token::instruction::TokenInstruction::TransferChecked { amount, decimals } => {
-                    (amount, decimals)
-                }
-                _ => {
-                    return Err(FacilitatorLocalError::DecodingError(
-                        "invalid_exact_svm_payload_transaction_instructions".to_string(),
-                    ));
-                }
-            };
-            // Source = 0
-            let source = instruction.account(0)?;
-            // Mint = 1
-            let mint = instruction.account(1)?;
-            // Destination = 2
-            let destination = instruction.account(2)?;
-            // Authority = 3
-            let authority = instruction.account(3)?;
-            TransferCheckedInstruction {
-                amount,
-                decimals,
-                source,
-                mint,
-                destination,
-                authority,
-                token_program: spl_token::ID,
-                data: instruction.data(),
-            }
-        } else if spl_token_2022::ID.eq(&program_id) {
-            let token_instruction =
-                spl_token_2022::instruction::TokenInstruction::unpack(instruction.data_slice())
-                    .map_err(|_| {
-                        FacilitatorLocalError::DecodingError(
-                            "invalid_exact_svm_payload_transaction_instructions".to_string(),
-                        )
-                    })?;
-            let (amount, decimals) = match token_instruction {
-                spl_token_2022::instruction::TokenInstruction::TransferChecked {
-                    amount,
-                    decimals,
-                } => (amount, decimals),
-                _ => {
-                    return Err(FacilitatorLocalError::DecodingError(
-                        "invalid_exact_svm_payload_transaction_instructions".to_string(),
+    ) -> Result<SystemTransferInstruction, FacilitatorLocalError> {
+        let transfer = decode_system_transfer(tx, instruction_index)?;
+
+        // Verify that the fee payer is not transferring funds
+        if transfer.from == self.keypair.pubkey() {
+            return Err(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_fee_payer_transferring_funds".to_string(),
+            ));
+        }
+        let pay_to: SolanaAddress = requirements.pay_to.clone().try_into()?;
+        if transfer.to != pay_to.pubkey {
+            return Err(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_transfer_to_incorrect_recipient".to_string(),
+            ));
+        }
+        assert_amount_received(TokenAmount::from(transfer.lamports), requirements)?;
+        Ok(transfer)
+    }
+
+    /// Verifies the last instruction of a payment, paying `fee_due` to the fee recipient from
+    /// `payer`: a system transfer for native SOL, otherwise a `TransferChecked` of the same mint
+    /// and token program as `payment` to the ATA of the recipient.
+    ///
+    /// Returns the ATA of the recipient for a token fee, which must exist: it is not created by
+    /// the payment.
+    ///
+    /// # Errors
+    /// Returns [`FacilitatorLocalError::InvalidFee`] if the fee is paid by another account, to
+    /// another one, in another asset or amount.
+    pub fn verify_fee_instruction(
+        &self,
+        tx: &TransactionInt,
+        instruction_index: usize,
+        payer: Pubkey,
+        payment: Option<&TransferCheckedInstruction>,
+        fee_due: TokenAmount,
+    ) -> Result<Option<Pubkey>, FacilitatorLocalError> {
+        let invalid_fee =
+            |reason: &str| FacilitatorLocalError::InvalidFee(payer.into(), reason.to_string());
+        let fee = self
+            .fee
+            .as_ref()
+            .ok_or(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_fee_not_configured".to_string(),
+            ))?;
+        let recipient: SolanaAddress = fee.recipient.clone().try_into()?;
+        let (amount, from, to, expected_to) = match payment {
+            None => {
+                let transfer = decode_system_transfer(tx, instruction_index)?;
+                (
+                    transfer.lamports,
+                    transfer.from,
+                    transfer.to,
+                    recipient.pubkey,
+                )
+            }
+            Some(payment) => {
+                let transfer = decode_transfer_checked(tx, instruction_index)?;
+                if transfer.token_program != payment.token_program || transfer.mint != payment.mint
+                {
+                    return Err(invalid_fee(
+                        "invalid_exact_svm_payload_transaction_fee_incorrect_asset",
                     ));
                 }
-            };
-            // Source = 0
-            let source = instruction.account(0)?;
-            // Mint = 1
-            let mint = instruction.account(1)?;
-            // Destination = 2
-            let destination = instruction.account(2)?;
-            // Authority = 3
-            let authority = instruction.account(3)?;
-            TransferCheckedInstruction {
-                amount,
-                decimals,
-                source,
-                mint,
-                destination,
-                authority,
-                token_program: spl_token_2022::ID,
-                data: instruction.data(),
+                let ata = associated_token_address(
+                    &recipient.pubkey,
+                    &transfer.token_program,
+                    &transfer.mint,
+                );
+                (
+                    transfer.amount,
+                    transfer.authority,
+                    transfer.destination,
+                    ata,
+                )
             }
-        } else {
-            return Err(FacilitatorLocalError::DecodingError(
-                "invalid_exact_svm_payload_transaction_not_a_transfer_instruction".to_string(),
-            ));
         };
+        if from != payer {
+            return Err(invalid_fee(
+                "invalid_exact_svm_payload_transaction_fee_incorrect_payer",
+            ));
+        }
+        if to != expected_to {
+            return Err(invalid_fee(
+                "invalid_exact_svm_payload_transaction_fee_incorrect_recipient",
+            ));
+        }
+        if TokenAmount::from(amount) != fee_due {
+            return Err(invalid_fee(
+                "invalid_exact_svm_payload_transaction_fee_amount_mismatch",
+            ));
+        }
+        Ok(payment.is_some().then_some(expected_to))
+    }
+
+    /// Verifies a transfer instruction and the accounts it touches, fetched over RPC.
+    ///
+    /// `has_dest_ata` tells whether the transaction creates the destination ATA itself;
+    /// otherwise the destination ATA must already exist.
+    pub async fn verify_transfer_instruction(
+        &self,
+        tx: &TransactionInt,
+        instruction_index: usize,
+        requirements: &PaymentRequirements,
+        has_dest_ata: bool,
+    ) -> Result<(TransferCheckedInstruction, Option<VerifyDetails>), FacilitatorLocalError> {
+        let transfer = self.decode_transfer_instruction(tx, instruction_index, requirements)?;
+        let accounts = self.fetch_transfer_accounts(&transfer, None).await?;
+        let details = self
+            .verify_transfer_accounts(&transfer, &accounts, requirements, has_dest_ata)
+            .await?;
+        Ok((transfer, details))
+    }
+
+    /// Decodes a `TransferChecked` instruction and checks it against the requirements, without
+    /// any RPC call: the authority is not the fee payer, and the destination is the ATA of
+    /// `pay_to`.
+    pub fn decode_transfer_instruction(
+        &self,
+        tx: &TransactionInt,
+        instruction_index: usize,
+        requirements: &PaymentRequirements,
+    ) -> Result<TransferCheckedInstruction, FacilitatorLocalError> {
+        let transfer_checked_instruction = decode_transfer_checked(tx, instruction_index)?;
 
         // Verify that the fee payer is not transferring funds (not the authority)
         let fee_payer_pubkey = self.keypair.pubkey();
@@ -402,25 +634,44 @@ impl SolanaProvider {
         let asset_address: SolanaAddress = requirements.asset.clone().try_into()?;
         let pay_to_address: SolanaAddress = requirements.pay_to.clone().try_into()?;
         let token_program = transfer_checked_instruction.token_program;
-        // findAssociatedTokenPda
-        let (ata, _) = Pubkey::find_program_address(
-            &[
-                pay_to_address.pubkey.as_ref(),
-                token_program.as_ref(),
-                asset_address.pubkey.as_ref(),
-            ],
-            &ATA_PROGRAM_PUBKEY,
+        let ata = associated_token_address(
+            &pay_to_address.pubkey,
+            &token_program,
+            &asset_address.pubkey,
         );
         if transfer_checked_instruction.destination != ata {
             return Err(FacilitatorLocalError::DecodingError(
                 "invalid_exact_svm_payload_transaction_transfer_to_incorrect_ata".to_string(),
             ));
         }
-        let accounts = self
-            .rpc_client
-            .get_multiple_accounts(&[transfer_checked_instruction.source, ata])
+        Ok(transfer_checked_instruction)
+    }
+
+    /// Fetches the [`TransferCheckedInstruction::accounts`] of a transfer, followed by the
+    /// `fee_account` a token fee is paid to, if any.
+    async fn fetch_transfer_accounts(
+        &self,
+        transfer: &TransferCheckedInstruction,
+        fee_account: Option<Pubkey>,
+    ) -> Result<Vec<Option<Account>>, FacilitatorLocalError> {
+        let mut accounts = transfer.accounts().to_vec();
+        accounts.extend(fee_account);
+        self.rpc_client
+            .get_multiple_accounts(&accounts)
             .await
-            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))
+    }
+
+    /// Checks the source, destination and mint accounts of a transfer, in the order of
+    /// [`TransferCheckedInstruction::accounts`], and computes the amount received by `pay_to`.
+    async fn verify_transfer_accounts(
+        &self,
+        transfer_checked_instruction: &TransferCheckedInstruction,
+        accounts: &[Option<Account>],
+        requirements: &PaymentRequirements,
+        has_dest_ata: bool,
+    ) -> Result<Option<VerifyDetails>, FacilitatorLocalError> {
+        let token_program = transfer_checked_instruction.token_program;
         let is_sender_missing = accounts.first().cloned().is_none_or(|a| a.is_none());
         if is_sender_missing {
             return Err(FacilitatorLocalError::DecodingError(
@@ -433,14 +684,162 @@ impl SolanaProvider {
                 "invalid_exact_svm_payload_transaction_receiver_ata_not_found".to_string(),
             ));
         }
-        let instruction_amount: TokenAmount = transfer_checked_instruction.amount.into();
-        let requirements_amount: TokenAmount = requirements.max_amount_required;
-        if instruction_amount != requirements_amount {
+        let mint_account = accounts.get(2).cloned().flatten();
+        let mint_account = mint_account.ok_or(FacilitatorLocalError::DecodingError(
+            "invalid_exact_svm_payload_transaction_mint_not_found".to_string(),
+        ))?;
+        if mint_account.owner != token_program {
             return Err(FacilitatorLocalError::DecodingError(
-                "invalid_exact_svm_payload_transaction_amount_mismatch".to_string(),
+                "invalid_exact_svm_payload_transaction_mint_not_owned_by_token_program".to_string(),
             ));
         }
-        Ok(transfer_checked_instruction)
+        let details = if token_program == spl_token_2022::ID {
+            self.verify_token_2022_mint(transfer_checked_instruction, &mint_account.data)
+                .await?
+        } else {
+            None
+        };
+
+        // With a transfer fee the recipient is credited less than the transferred amount,
+        // so the requirement is matched against what actually reaches `pay_to`.
+        let received = details.as_ref().map_or(
+            TokenAmount::from(transfer_checked_instruction.amount),
+            |details| details.net_amount,
+        );
+        assert_amount_received(received, requirements)?;
+        Ok(details)
+    }
+
+    /// Applies the [`MintExtensionPolicy`] to a Token-2022 mint and computes the net amount.
+    ///
+    /// Returns `None` for mints without any policy-relevant extensions.
+    async fn verify_token_2022_mint(
+        &self,
+        transfer: &TransferCheckedInstruction,
+        mint_data: &[u8],
+    ) -> Result<Option<VerifyDetails>, FacilitatorLocalError> {
+        let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data).map_err(
+            |_| {
+                FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_mint_invalid_data".to_string(),
+                )
+            },
+        )?;
+        let extensions: Vec<MintExtension> = mint
+            .get_extension_types()
+            .map_err(|_| {
+                FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_mint_invalid_data".to_string(),
+                )
+            })?
+            .into_iter()
+            .filter_map(MintExtension::from_extension_type)
+            .collect();
+        if extensions.is_empty() {
+            return Ok(None);
+        }
+        let policy = self.policy();
+        for extension in extensions.iter() {
+            if !policy.mint_extension_policy.is_allowed(*extension) {
+                return Err(FacilitatorLocalError::DecodingError(format!(
+                    "invalid_exact_svm_payload_transaction_mint_extension_not_allowed_{}",
+                    extension.as_str().replace('-', "_")
+                )));
+            }
+        }
+        if let Ok(pausable) = mint.get_extension::<PausableConfig>() {
+            if bool::from(pausable.paused) {
+                return Err(FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_mint_paused".to_string(),
+                ));
+            }
+        }
+        let transfer_fee = match mint.get_extension::<TransferFeeConfig>() {
+            Ok(transfer_fee_config) => {
+                let epoch = self
+                    .rpc_client
+                    .get_epoch_info()
+                    .await
+                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?
+                    .epoch;
+                let fee = transfer_fee_config
+                    .calculate_epoch_fee(epoch, transfer.amount)
+                    .ok_or(FacilitatorLocalError::DecodingError(
+                        "invalid_exact_svm_payload_transaction_transfer_fee_overflow".to_string(),
+                    ))?;
+                Some(fee)
+            }
+            Err(_) => None,
+        };
+        let net_amount = transfer.amount.saturating_sub(transfer_fee.unwrap_or(0));
+        Ok(Some(VerifyDetails {
+            amount: transfer.amount.into(),
+            net_amount: net_amount.into(),
+            transfer_fee: transfer_fee.map(TokenAmount::from),
+            token_extensions: extensions.iter().map(|e| e.as_str().to_string()).collect(),
+        }))
+    }
+
+    /// Checks the nonce account of a durable nonce transaction over RPC: it must be an
+    /// initialized nonce account whose stored nonce is the transaction's blockhash, and whose
+    /// authority is the one advancing it.
+    async fn verify_durable_nonce(
+        &self,
+        tx: &TransactionInt,
+        advance_nonce: &AdvanceNonceInstruction,
+    ) -> Result<(), FacilitatorLocalError> {
+        let nonce = fetch_durable_nonce(
+            &self.rpc_client,
+            &advance_nonce.nonce_account,
+            CommitmentConfig::confirmed(),
+        )
+        .await?
+        .ok_or(FacilitatorLocalError::DecodingError(
+            "invalid_exact_svm_payload_transaction_nonce_account_invalid".to_string(),
+        ))?;
+        if nonce.blockhash() != *tx.inner.message.recent_blockhash() {
+            return Err(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_nonce_mismatch".to_string(),
+            ));
+        }
+        if nonce.authority != advance_nonce.nonce_authority {
+            return Err(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_nonce_authority_mismatch".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the settlement transaction of `request` can still land, if it is a durable nonce
+    /// transaction: it does not expire with its blockhash, but once its nonce account is advanced.
+    ///
+    /// Returns `None` for a transaction bound to a recent blockhash.
+    pub async fn durable_nonce_unexpired(
+        &self,
+        request: &SettleRequest,
+    ) -> Result<Option<bool>, FacilitatorLocalError> {
+        let ExactPaymentPayload::Solana(payload) = &request.payment_payload.payload else {
+            return Ok(None);
+        };
+        let bytes = Base64Bytes::from(payload.transaction.as_bytes())
+            .decode()
+            .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+        let transaction = bincode::deserialize::<VersionedTransaction>(bytes.as_slice())
+            .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+        let account_keys = self.resolve_account_keys(&transaction).await?;
+        let tx = TransactionInt::new(transaction).with_account_keys(account_keys);
+        let Some(advance_nonce) = tx.advance_nonce_instruction()? else {
+            return Ok(None);
+        };
+        let unexpired = tx
+            .is_unexpired(
+                &self.rpc_client,
+                Some(&advance_nonce.nonce_account),
+                CommitmentConfig::confirmed(),
+                None,
+            )
+            .await?;
+        Ok(Some(unexpired))
     }
 
     async fn verify_transfer(
@@ -452,7 +851,7 @@ impl SolanaProvider {
 
         // Assert valid payment START
         let payment_payload = match &payload.payload {
-            ExactPaymentPayload::Evm(..) => {
+            ExactPaymentPayload::Evm(..) | ExactPaymentPayload::EvmNative(..) => {
                 return Err(FacilitatorLocalError::UnsupportedNetwork(None));
             }
             ExactPaymentPayload::Solana(payload) => payload,
@@ -485,46 +884,85 @@ impl SolanaProvider {
         let transaction = bincode::deserialize::<VersionedTransaction>(bytes.as_slice())
             .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
 
+        // resolve accounts loaded through address lookup tables before any introspection
+        let account_keys = self.resolve_account_keys(&transaction).await?;
+        let tx = TransactionInt::new(transaction.clone()).with_account_keys(account_keys);
+
         // perform transaction introspection to validate the transaction structure and details
-        let instructions = transaction.message.instructions();
-        let compute_units = self.verify_compute_limit_instruction(&transaction, 0)?;
-        if compute_units > self.max_compute_unit_limit {
+        // a leading AdvanceNonceAccount makes this a durable nonce transaction, which is not
+        // bound to a recent blockhash; the remaining instructions are shifted by one
+        let offset = match tx.advance_nonce_instruction()? {
+            Some(advance_nonce) => {
+                self.verify_durable_nonce(&tx, &advance_nonce).await?;
+                1
+            }
+            None => 0,
+        };
+        let instructions = &transaction.message.instructions()[offset..];
+        let compute_units = self.verify_compute_limit_instruction(&transaction, offset)?;
+        if compute_units > self.policy().max_compute_unit_limit {
             return Err(FacilitatorLocalError::DecodingError(
                 "compute unit limit exceeds facilitator maximum".to_string(),
             ));
         }
         tracing::debug!(compute_units = compute_units, "Verified compute unit limit");
-        self.verify_compute_price_instruction(&transaction, 1)?;
-        let transfer_instruction = if instructions.len() == 3 {
+        self.verify_compute_price_instruction(&transaction, offset + 1)?;
+        let asset: SolanaAddress = requirements.asset.clone().try_into()?;
+        // a facilitator fee, if due, is paid by an extra last instruction
+        let fee_due = self.fee_amount(requirements);
+        let payment_len = instructions.len() - usize::from(fee_due.is_some());
+        // token transfers are decoded here, and their accounts checked along the simulation
+        let (payer, token_transfer) = if asset.pubkey == NATIVE_SOL_ASSET && payment_len == 3 {
+            // native SOL is paid with a plain system transfer, no token accounts involved
+            let transfer =
+                self.verify_system_transfer_instruction(&tx, offset + 2, requirements)?;
+            (transfer.from, None)
+        } else if asset.pubkey == NATIVE_SOL_ASSET {
+            return Err(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_instructions_count".to_string(),
+            ));
+        } else if payment_len == 3 {
             // verify that the transfer instruction is valid
             // this expects the destination ATA to already exist
-            self.verify_transfer_instruction(&transaction, 2, requirements, false)
-                .await?
-        } else if instructions.len() == 4 {
+            let transfer = self.decode_transfer_instruction(&tx, offset + 2, requirements)?;
+            (transfer.authority, Some((transfer, false)))
+        } else if payment_len == 4 {
             // verify that the transfer instruction is valid
             // this expects the destination ATA to be created in the same transaction
-            self.verify_create_ata_instruction(&transaction, 2, requirements)?;
-            self.verify_transfer_instruction(&transaction, 3, requirements, true)
-                .await?
+            self.verify_create_ata_instruction(&tx, offset + 2, requirements)?;
+            let transfer = self.decode_transfer_instruction(&tx, offset + 3, requirements)?;
+            (transfer.authority, Some((transfer, true)))
         } else {
             return Err(FacilitatorLocalError::DecodingError(
                 "invalid_exact_svm_payload_transaction_instructions_count".to_string(),
             ));
         };
 
+        let (native_fee_index, fee_account) = match fee_due {
+            Some(fee_due) => {
+                let fee_index = offset + payment_len;
+                let payment = token_transfer.as_ref().map(|(transfer, _)| transfer);
+                let fee_account =
+                    self.verify_fee_instruction(&tx, fee_index, payer, payment, fee_due)?;
+                (payment.is_none().then_some(fee_index), fee_account)
+            }
+            None => (None, None),
+        };
+
         // Rule 2: Fee payer safety check
         // Verify that the fee payer is not included in any instruction's accounts
-        // This single check covers all cases: authority, source, or any other role
+        // This single check covers all cases: authority, source, or any other role,
+        // including accounts loaded through address lookup tables.
+        // The only exception is the recipient of a native SOL fee, which may be the fee payer.
         let fee_payer_pubkey = self.keypair.pubkey();
-        for instruction in transaction.message.instructions().iter() {
-            for account_idx in instruction.accounts.iter() {
-                let account = transaction
-                    .message
-                    .static_account_keys()
-                    .get(*account_idx as usize)
-                    .ok_or(FacilitatorLocalError::DecodingError(
-                        "invalid_account_index".to_string(),
-                    ))?;
+        for (index, instruction) in transaction.message.instructions().iter().enumerate() {
+            for (position, account_idx) in instruction.accounts.iter().enumerate() {
+                if native_fee_index == Some(index) && position == 1 {
+                    continue;
+                }
+                let account = tx.account_keys().get(*account_idx as usize).ok_or(
+                    FacilitatorLocalError::DecodingError("invalid_account_index".to_string()),
+                )?;
 
                 if *account == fee_payer_pubkey {
                     return Err(FacilitatorLocalError::DecodingError(
@@ -534,7 +972,7 @@ impl SolanaProvider {
             }
         }
 
-        let tx = TransactionInt::new(transaction.clone()).sign(&self.keypair)?;
+        let tx = tx.sign(&self.keypair)?;
         let cfg = RpcSimulateTransactionConfig {
             sig_verify: false,
             replace_recent_blockhash: false,
@@ -544,18 +982,52 @@ impl SolanaProvider {
             inner_instructions: false,
             min_context_slot: None,
         };
-        let sim = self
-            .rpc_client
-            .simulate_transaction_with_config(&tx.inner, cfg)
-            .await
-            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+        // the token accounts are checked as they are before the transaction, fetched along
+        // with the simulation: the simulated states already include the transfer
+        let accounts = async {
+            match &token_transfer {
+                Some((transfer, _)) => self
+                    .fetch_transfer_accounts(transfer, fee_account)
+                    .await
+                    .map(Some),
+                None => Ok(None),
+            }
+        };
+        let (sim, accounts) = tokio::join!(
+            self.rpc_client
+                .simulate_transaction_with_config(&tx.inner, cfg),
+            accounts
+        );
+        let sim = sim.map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+        let accounts = accounts?;
+        // a token fee is paid to the ATA of the fee recipient, which the payment does not create
+        let fee_account_missing = accounts
+            .as_ref()
+            .is_some_and(|accounts| accounts.get(3).is_some_and(Option::is_none));
+        if fee_account_missing {
+            return Err(FacilitatorLocalError::InvalidFee(
+                payer.into(),
+                "invalid_exact_svm_payload_transaction_fee_recipient_ata_not_found".to_string(),
+            ));
+        }
+        let details = match (token_transfer, accounts) {
+            (Some((transfer, has_dest_ata)), Some(accounts)) => {
+                self.verify_transfer_accounts(&transfer, &accounts, requirements, has_dest_ata)
+                    .await?
+            }
+            _ => None,
+        };
         if sim.value.err.is_some() {
             return Err(FacilitatorLocalError::DecodingError(
                 "invalid_exact_svm_payload_transaction_simulation_failed".to_string(),
             ));
         }
-        let payer: SolanaAddress = transfer_instruction.authority.into();
-        Ok(VerifyTransferResult { payer, transaction })
+        let payer: SolanaAddress = payer.into();
+        Ok(VerifyTransferResult {
+            payer,
+            transaction,
+            details,
+        })
     }
 
     pub fn fee_payer(&self) -> MixedAddress {
@@ -566,24 +1038,28 @@ impl SolanaProvider {
 
 impl FromEnvByNetworkBuild for SolanaProvider {
     async fn from_env(network: Network) -> Result<Option<Self>, Box<dyn std::error::Error>> {
-        let env_var = from_env::rpc_env_name_from_network(network);
-        let rpc_url = match std::env::var(env_var).ok() {
-            Some(rpc_url) => rpc_url,
+        let rpc_endpoints = match from_env::rpc_endpoints_from_env(network)? {
+            Some(rpc_endpoints) => rpc_endpoints,
             None => {
                 tracing::warn!(network=%network, "no RPC URL configured, skipping");
                 return Ok(None);
             }
         };
         let keypair = from_env::SignerType::from_env()?.make_solana_wallet()?;
-        let max_compute_unit_limit = Self::max_compute_unit_limit_from_env(network);
-        let max_compute_unit_price = Self::max_compute_unit_price_from_env(network);
-        let provider = SolanaProvider::try_new(
+        let policy = SolanaPolicy::from_lookup(network, |name| std::env::var(name).ok())?;
+        let mut provider = SolanaProvider::try_new(
             keypair,
-            rpc_url,
+            &rpc_endpoints,
             network,
-            max_compute_unit_limit,
-            max_compute_unit_price,
-        )?;
+            policy.max_compute_unit_limit,
+            policy.max_compute_unit_price,
+            policy.mint_extension_policy,
+            policy.priority_fee_policy,
+        )?
+        .with_settle_commitment(policy.settle_commitment);
+        if let Some(fee) = from_env::facilitator_fee_from_env(network, provider.fee_payer())? {
+            provider = provider.with_fee(fee);
+        }
         Ok(Some(provider))
     }
 }
@@ -591,6 +1067,7 @@ impl FromEnvByNetworkBuild for SolanaProvider {
 pub struct VerifyTransferResult {
     pub payer: SolanaAddress,
     pub transaction: VersionedTransaction,
+    pub details: Option<VerifyDetails>,
 }
 
 #[derive(Debug)]
@@ -605,62 +1082,466 @@ pub struct TransferCheckedInstruction {
     pub data: Vec<u8>,
 }
 
-impl NetworkProviderOps for SolanaProvider {
-    fn signer_address(&self) -> MixedAddress {
-        self.fee_payer()
-    }
-
-    fn network(&self) -> Network {
-        self.chain.network
+impl TransferCheckedInstruction {
+    /// Accounts checked during verification: source, destination and mint.
+    pub fn accounts(&self) -> [Pubkey; 3] {
+        [self.source, self.destination, self.mint]
     }
 }
 
-impl Facilitator for SolanaProvider {
-    type Error = FacilitatorLocalError;
-
-    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
-        let verification = self.verify_transfer(request).await?;
-        Ok(VerifyResponse::valid(verification.payer.into()))
-    }
+/// Accounts of a leading `SystemProgram::AdvanceNonceAccount` instruction.
+#[derive(Debug)]
+pub struct AdvanceNonceInstruction {
+    pub nonce_account: Pubkey,
+    pub nonce_authority: Pubkey,
+}
 
-    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
-        let verification = self.verify_transfer(request).await?;
-        let tx = TransactionInt::new(verification.transaction).sign(&self.keypair)?;
-        // Verify if fully signed
-        if !tx.is_fully_signed() {
-            tracing::event!(Level::WARN, status = "failed", "undersigned transaction");
-            return Ok(SettleResponse {
-                success: false,
-                error_reason: Some(FacilitatorErrorReason::UnexpectedSettleError),
-                payer: verification.payer.into(),
-                transaction: None,
-                network: self.network(),
-            });
-        }
-        let tx_sig = tx
-            .send_and_confirm(&self.rpc_client, CommitmentConfig::confirmed())
-            .await?;
-        let settle_response = SettleResponse {
-            success: true,
-            error_reason: None,
-            payer: verification.payer.into(),
-            transaction: Some(TransactionHash::Solana(*tx_sig.as_array())),
-            network: self.network(),
-        };
-        Ok(settle_response)
+/// Checks that `received`, the amount credited to `pay_to`, covers `max_amount_required`.
+///
+/// As on EVM, paying more than required is accepted, whether or not the mint takes a transfer fee.
+fn assert_amount_received(
+    received: TokenAmount,
+    requirements: &PaymentRequirements,
+) -> Result<(), FacilitatorLocalError> {
+    if received < requirements.max_amount_required {
+        return Err(FacilitatorLocalError::DecodingError(
+            "invalid_exact_svm_payload_transaction_amount_mismatch".to_string(),
+        ));
     }
+    Ok(())
+}
 
-    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
-        let kinds = vec![SupportedPaymentKind {
-            network: self.network().to_string(),
-            scheme: Scheme::Exact,
-            x402_version: X402Version::V1,
-            extra: Some(SupportedPaymentKindExtra {
-                fee_payer: self.signer_address(),
-            }),
-        }];
-        Ok(SupportedPaymentKindsResponse { kinds })
-    }
+/// Fetches the state of a durable nonce account.
+///
+/// Returns `None` if the account does not exist, is not owned by the System Program, or is not
+/// an initialized (current version) nonce account.
+async fn fetch_durable_nonce(
+    rpc_client: &RpcClient,
+    nonce_account: &Pubkey,
+    commitment_config: CommitmentConfig,
+) -> Result<Option<NonceData>, FacilitatorLocalError> {
+    let account = rpc_client
+        .get_account_with_commitment(nonce_account, commitment_config)
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?
+        .value;
+    let account = match account {
+        Some(account) if account.owner == solana_system_interface::program::ID => account,
+        _ => return Ok(None),
+    };
+    let nonce = match bincode::deserialize::<NonceVersions>(&account.data) {
+        Ok(NonceVersions::Current(state)) => match *state {
+            NonceState::Initialized(data) => Some(data),
+            NonceState::Uninitialized => None,
+        },
+        Ok(NonceVersions::Legacy(_)) | Err(_) => None,
+    };
+    Ok(nonce)
+}
+
+/// A decoded native SOL `SystemProgram::Transfer` instruction.
+#[derive(Debug)]
+pub struct SystemTransferInstruction {
+    pub lamports: u64,
+    pub from: Pubkey,
+    pub to: Pubkey,
+}
+
+/// Decodes a `TransferChecked` instruction of the SPL Token or Token-2022 program.
+fn decode_transfer_checked(
+    tx: &TransactionInt,
+    instruction_index: usize,
+) -> Result<TransferCheckedInstruction, FacilitatorLocalError> {
+    let instruction = tx.instruction(instruction_index)?;
+    instruction.assert_not_empty()?;
+    let program_id = instruction.program_id();
+    let transfer_checked_instruction = if spl_token::ID.eq(&program_id) {
+        let token_instruction = spl_token::instruction::TokenInstruction::unpack(
+            instruction.data_slice(),
+        )
+        .map_err(|_| {
+            FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_instructions".to_string(),
+            )
+        })?;
+        let (amount, decimals) = match token_instruction {
+            spl_token::instruction::TokenInstruction::TransferChecked { amount, decimals } => {
+                (amount, decimals)
+            }
+            _ => {
+                return Err(FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_instructions".to_string(),
+                ));
+            }
+        };
+        // Source = 0
+        let source = instruction.account(0)?;
+        // Mint = 1
+        let mint = instruction.account(1)?;
+        // Destination = 2
+        let destination = instruction.account(2)?;
+        // Authority = 3
+        let authority = instruction.account(3)?;
+        TransferCheckedInstruction {
+            amount,
+            decimals,
+            source,
+            mint,
+            destination,
+            authority,
+            token_program: spl_token::ID,
+            data: instruction.data(),
+        }
+    } else if spl_token_2022::ID.eq(&program_id) {
+        let token_instruction =
+            spl_token_2022::instruction::TokenInstruction::unpack(instruction.data_slice())
+                .map_err(|_| {
+                    FacilitatorLocalError::DecodingError(
+                        "invalid_exact_svm_payload_transaction_instructions".to_string(),
+                    )
+                })?;
+        let (amount, decimals) = match token_instruction {
+            spl_token_2022::instruction::TokenInstruction::TransferChecked { amount, decimals } => {
+                (amount, decimals)
+            }
+            _ => {
+                return Err(FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_instructions".to_string(),
+                ));
+            }
+        };
+        // Source = 0
+        let source = instruction.account(0)?;
+        // Mint = 1
+        let mint = instruction.account(1)?;
+        // Destination = 2
+        let destination = instruction.account(2)?;
+        // Authority = 3
+        let authority = instruction.account(3)?;
+        TransferCheckedInstruction {
+            amount,
+            decimals,
+            source,
+            mint,
+            destination,
+            authority,
+            token_program: spl_token_2022::ID,
+            data: instruction.data(),
+        }
+    } else {
+        return Err(FacilitatorLocalError::DecodingError(
+            "invalid_exact_svm_payload_transaction_not_a_transfer_instruction".to_string(),
+        ));
+    };
+    Ok(transfer_checked_instruction)
+}
+
+/// Decodes a native SOL `SystemProgram::Transfer` instruction.
+fn decode_system_transfer(
+    tx: &TransactionInt,
+    instruction_index: usize,
+) -> Result<SystemTransferInstruction, FacilitatorLocalError> {
+    let instruction = tx.instruction(instruction_index)?;
+    instruction.assert_not_empty()?;
+    if instruction.program_id() != solana_system_interface::program::ID {
+        return Err(FacilitatorLocalError::DecodingError(
+            "invalid_exact_svm_payload_transaction_not_a_transfer_instruction".to_string(),
+        ));
+    }
+    let system_instruction = bincode::deserialize::<SystemInstruction>(instruction.data_slice())
+        .map_err(|_| {
+            FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_instructions".to_string(),
+            )
+        })?;
+    let lamports = match system_instruction {
+        SystemInstruction::Transfer { lamports } => lamports,
+        _ => {
+            return Err(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_not_a_transfer_instruction".to_string(),
+            ));
+        }
+    };
+    // Funding account = 0
+    let from = instruction.account(0)?;
+    // Recipient account = 1
+    let to = instruction.account(1)?;
+    Ok(SystemTransferInstruction { lamports, from, to })
+}
+
+/// Final state of a settlement transaction submitted by [`TransactionInt::send_and_confirm`].
+#[derive(Debug)]
+pub enum SendOutcome {
+    /// Landed and reached the requested commitment.
+    Confirmed(Signature),
+    /// Landed, but execution failed.
+    Failed(Signature, TransactionError),
+    /// Never landed: the blockhash expired first.
+    Expired(Signature),
+}
+
+/// Checks whether `signature` has landed, and if so whether it succeeded at `commitment_config`.
+async fn signature_outcome(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+    commitment_config: CommitmentConfig,
+) -> Result<Option<SendOutcome>, FacilitatorLocalError> {
+    let statuses = rpc_client
+        .get_signature_statuses(&[*signature])
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+    let outcome = match statuses.value.into_iter().next().flatten() {
+        Some(status) => match status.err {
+            Some(err) => Some(SendOutcome::Failed(*signature, err)),
+            None if status.satisfies_commitment(commitment_config) => {
+                Some(SendOutcome::Confirmed(*signature))
+            }
+            None => None,
+        },
+        None => None,
+    };
+    Ok(outcome)
+}
+
+impl NetworkProviderOps for SolanaProvider {
+    fn signer_address(&self) -> MixedAddress {
+        self.fee_payer()
+    }
+
+    fn network(&self) -> Network {
+        self.chain.network
+    }
+}
+
+impl FinalityQuery for SolanaProvider {
+    /// Looks up the signature status, through the ledger history once out of the recent cache.
+    ///
+    /// A missing status means the slot of the transaction was skipped by a fork switch. A failed
+    /// transaction is still included in the ledger, and is judged by its commitment alone.
+    async fn get_finality(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> Result<Finality, FacilitatorLocalError> {
+        let signature = match tx_hash {
+            TransactionHash::Solana(sig) => Signature::from(*sig),
+            TransactionHash::Evm(_) => {
+                return Err(FacilitatorLocalError::DecodingError(
+                    "Transaction hash is for EVM, but provider is Solana".to_string(),
+                ));
+            }
+        };
+        let statuses = self
+            .rpc_client
+            .get_signature_statuses_with_history(&[signature])
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+        let finality = match statuses.value.into_iter().next().flatten() {
+            Some(status) if status.satisfies_commitment(CommitmentConfig::finalized()) => {
+                Finality::Finalized
+            }
+            Some(_) => Finality::Unfinalized,
+            None => Finality::Reorged,
+        };
+        Ok(finality)
+    }
+}
+
+impl SettlementCostQuery for SolanaProvider {
+    /// Lamports the fee payer lost to the transaction: its fee, and the rent of the accounts it
+    /// funded, like the associated token account of a recipient.
+    async fn get_settlement_cost(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> Result<TokenAmount, FacilitatorLocalError> {
+        let signature = match tx_hash {
+            TransactionHash::Solana(sig) => Signature::from(*sig),
+            TransactionHash::Evm(_) => {
+                return Err(FacilitatorLocalError::DecodingError(
+                    "Transaction hash is for EVM, but provider is Solana".to_string(),
+                ));
+            }
+        };
+        let config = RpcTransactionConfig {
+            encoding: None,
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        let transaction = self
+            .rpc_client
+            .get_transaction_with_config(&signature, config)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+        let meta = transaction.transaction.meta.ok_or_else(|| {
+            FacilitatorLocalError::ContractCall("Transaction has no status metadata".to_string())
+        })?;
+        // The fee payer is the first account of the transaction
+        let spent = match (meta.pre_balances.first(), meta.post_balances.first()) {
+            (Some(pre), Some(post)) => pre.saturating_sub(*post),
+            _ => meta.fee,
+        };
+        Ok(TokenAmount::from(spent))
+    }
+}
+
+impl SignerBalanceQuery for SolanaProvider {
+    async fn signer_balances(
+        &self,
+    ) -> Result<Vec<(MixedAddress, TokenAmount)>, FacilitatorLocalError> {
+        let pubkey = self.keypair.pubkey();
+        let lamports = self
+            .rpc_client
+            .get_balance(&pubkey)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+        Ok(vec![(MixedAddress::Solana(pubkey), lamports.into())])
+    }
+}
+
+impl FacilitatorFeeQuery for SolanaProvider {
+    fn fee_due(&self, request: &VerifyRequest) -> Option<TokenAmount> {
+        self.fee_amount(&request.payment_requirements)
+    }
+}
+
+impl PaymentAmountQuery for SolanaProvider {
+    /// The amount of the first `TransferChecked` of the transaction, with the decimals of its
+    /// mint, or of its first system transfer for native SOL. The payment precedes the fee, and
+    /// only instruction data is read, so lookup tables need not be resolved.
+    async fn payment_amount(&self, request: &VerifyRequest) -> Option<PaymentAmount> {
+        let ExactPaymentPayload::Solana(payload) = &request.payment_payload.payload else {
+            return None;
+        };
+        let bytes = Base64Bytes::from(payload.transaction.as_bytes())
+            .decode()
+            .ok()?;
+        let transaction = bincode::deserialize::<VersionedTransaction>(bytes.as_slice()).ok()?;
+        let account_keys = transaction.message.static_account_keys();
+        transaction
+            .message
+            .instructions()
+            .iter()
+            .find_map(|instruction| {
+                let program_id = account_keys.get(usize::from(instruction.program_id_index))?;
+                if *program_id == spl_token::ID || *program_id == spl_token_2022::ID {
+                    match spl_token_2022::instruction::TokenInstruction::unpack(&instruction.data)
+                        .ok()?
+                    {
+                        spl_token_2022::instruction::TokenInstruction::TransferChecked {
+                            amount,
+                            decimals,
+                        } => Some(PaymentAmount {
+                            amount: TokenAmount::from(amount),
+                            decimals: Some(decimals),
+                        }),
+                        _ => None,
+                    }
+                } else if *program_id == solana_system_interface::program::ID {
+                    match bincode::deserialize::<SystemInstruction>(&instruction.data).ok()? {
+                        SystemInstruction::Transfer { lamports } => Some(PaymentAmount {
+                            amount: TokenAmount::from(lamports),
+                            decimals: Some(self.network().native_decimals()),
+                        }),
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+impl Facilitator for SolanaProvider {
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let verification = self.verify_transfer(request).await?;
+        let response = match verification.details {
+            Some(details) => VerifyResponse::valid_with_details(verification.payer.into(), details),
+            None => VerifyResponse::valid(verification.payer.into()),
+        };
+        Ok(response)
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let verification = self.verify_transfer(request).await?;
+        let tx = TransactionInt::new(verification.transaction).sign(&self.keypair)?;
+        // Verify if fully signed
+        if !tx.is_fully_signed() {
+            tracing::event!(Level::WARN, status = "failed", "undersigned transaction");
+            return Ok(SettleResponse {
+                success: false,
+                error_reason: Some(FacilitatorErrorReason::UnexpectedSettleError),
+                payer: verification.payer.into(),
+                transaction: None,
+                network: self.network(),
+            });
+        }
+        // Recorded before sending: the transaction may land even if sending reports an error
+        let signature = TransactionHash::Solana(*tx.signature().as_array());
+        pending_settlements::broadcast(self.network(), signature.clone(), self.fee_payer(), None);
+        let outcome = tx
+            .send_and_confirm(&self.rpc_client, self.policy().settle_commitment)
+            .await;
+        // The settlement is answered either way; only a shutdown cutting it short leaves it pending
+        pending_settlements::settled(&signature);
+        let outcome = outcome?;
+        let settle_response = match outcome {
+            SendOutcome::Confirmed(tx_sig) => SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: verification.payer.into(),
+                transaction: Some(TransactionHash::Solana(*tx_sig.as_array())),
+                network: self.network(),
+            },
+            SendOutcome::Failed(tx_sig, err) => {
+                tracing::event!(
+                    Level::WARN,
+                    status = "failed",
+                    signature = %tx_sig,
+                    error = %err,
+                    "settlement transaction failed"
+                );
+                SettleResponse {
+                    success: false,
+                    error_reason: Some(FacilitatorErrorReason::TransactionFailed),
+                    payer: verification.payer.into(),
+                    transaction: Some(TransactionHash::Solana(*tx_sig.as_array())),
+                    network: self.network(),
+                }
+            }
+            SendOutcome::Expired(tx_sig) => {
+                tracing::event!(
+                    Level::WARN,
+                    status = "expired",
+                    signature = %tx_sig,
+                    "blockhash expired before settlement transaction landed"
+                );
+                SettleResponse {
+                    success: false,
+                    error_reason: Some(FacilitatorErrorReason::BlockhashExpired),
+                    payer: verification.payer.into(),
+                    transaction: None,
+                    network: self.network(),
+                }
+            }
+        };
+        Ok(settle_response)
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        let recommended_compute_unit_price = self.advertised_compute_unit_price().await;
+        let kinds = vec![SupportedPaymentKind {
+            network: self.network().to_string(),
+            scheme: Scheme::Exact,
+            x402_version: X402Version::V1,
+            extra: Some(SupportedPaymentKindExtra {
+                fee_payer: Some(self.signer_address()),
+                recommended_compute_unit_price,
+                fee: self.fee.clone(),
+            }),
+        }];
+        Ok(SupportedPaymentKindsResponse { kinds })
+    }
 }
 
 impl TransactionStatusQuery for SolanaProvider {
@@ -682,14 +1563,14 @@ impl TransactionStatusQuery for SolanaProvider {
 
         let network = self.network();
 
-        // Query transaction status using get_signature_statuses
+        // Query transaction status, through the ledger history once out of the recent cache
         let statuses = self
             .rpc_client
-            .get_signature_statuses(&[solana_sig])
+            .get_signature_statuses_with_history(&[solana_sig])
             .await
             .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
 
-        let status_opt = statuses.value.get(0).and_then(|s| s.as_ref());
+        let status_opt = statuses.value.first().and_then(|s| s.as_ref());
 
         match status_opt {
             Some(status) => {
@@ -698,11 +1579,21 @@ impl TransactionStatusQuery for SolanaProvider {
                     Ok(TransactionStatusResponse {
                         transaction_hash: tx_hash.clone(),
                         status: TransactionStatus::Failed,
-                        network,
-                        block_number: Some(status.slot as u64),
+                        network: Some(network),
+                        block_number: Some(status.slot),
                         confirmations: None, // Solana doesn't use confirmations the same way
                         error: Some(format!("{err:?}")),
                     })
+                } else if !status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                    // Processed, but not yet voted on by the cluster
+                    Ok(TransactionStatusResponse {
+                        transaction_hash: tx_hash.clone(),
+                        status: TransactionStatus::Pending,
+                        network: Some(network),
+                        block_number: Some(status.slot),
+                        confirmations: None,
+                        error: None,
+                    })
                 } else {
                     // Transaction confirmed
                     // Get current slot to calculate confirmations
@@ -717,50 +1608,202 @@ impl TransactionStatusQuery for SolanaProvider {
                     Ok(TransactionStatusResponse {
                         transaction_hash: tx_hash.clone(),
                         status: TransactionStatus::Confirmed,
-                        network,
-                        block_number: Some(status.slot as u64),
+                        network: Some(network),
+                        block_number: Some(status.slot),
                         confirmations: Some(confirmations as u64),
                         error: None,
                     })
                 }
             }
             None => {
-                // Transaction not found - could be pending or doesn't exist
-                // Try to get transaction to see if it exists but isn't finalized
-                let tx_config = solana_client::rpc_config::RpcTransactionConfig {
+                // No status: look the transaction up in the ledger before calling it not found.
+                // The RPC answers `null` for an unknown transaction; any error is the node's.
+                let tx_config = RpcTransactionConfig {
                     encoding: None, // Use default encoding
-                    commitment: Some(CommitmentConfig::processed()),
+                    commitment: Some(CommitmentConfig::confirmed()),
                     max_supported_transaction_version: Some(0),
                 };
-                match self.rpc_client.get_transaction_with_config(&solana_sig, tx_config).await {
-                    Ok(_) => {
-                        // Transaction exists but not finalized
-                        Ok(TransactionStatusResponse {
-                            transaction_hash: tx_hash.clone(),
-                            status: TransactionStatus::Pending,
-                            network,
-                            block_number: None,
-                            confirmations: None,
-                            error: None,
-                        })
-                    }
-                    Err(_) => {
-                        // Transaction not found
-                        Ok(TransactionStatusResponse {
+                let transaction: Option<LedgerTransaction> = self
+                    .rpc_client
+                    .send(
+                        RpcRequest::GetTransaction,
+                        serde_json::json!([solana_sig.to_string(), tx_config]),
+                    )
+                    .await
+                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+                let response = match transaction {
+                    Some(transaction) => {
+                        let err = transaction.meta.and_then(|meta| meta.err);
+                        TransactionStatusResponse {
                             transaction_hash: tx_hash.clone(),
-                            status: TransactionStatus::NotFound,
-                            network,
-                            block_number: None,
+                            status: match err {
+                                Some(_) => TransactionStatus::Failed,
+                                None => TransactionStatus::Confirmed,
+                            },
+                            network: Some(network),
+                            block_number: Some(transaction.slot),
                             confirmations: None,
-                            error: Some("Transaction not found".to_string()),
-                        })
+                            error: err.map(|err| format!("{err:?}")),
+                        }
                     }
-                }
+                    None => TransactionStatusResponse {
+                        transaction_hash: tx_hash.clone(),
+                        status: TransactionStatus::NotFound,
+                        network: Some(network),
+                        block_number: None,
+                        confirmations: None,
+                        error: Some("Transaction not found".to_string()),
+                    },
+                };
+                Ok(response)
             }
         }
     }
 }
 
+/// The parts of a `getTransaction` result read by the transaction status lookup.
+#[derive(Deserialize)]
+struct LedgerTransaction {
+    slot: u64,
+    meta: Option<LedgerTransactionMeta>,
+}
+
+#[derive(Deserialize)]
+struct LedgerTransactionMeta {
+    err: Option<TransactionError>,
+}
+
+/// Token-2022 mint extensions that change transfer semantics and are subject to facilitator policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MintExtension {
+    /// The token program withholds a fee from every transfer.
+    TransferFee,
+    /// Every transfer invokes an additional program chosen by the mint authority.
+    TransferHook,
+    /// A delegate can move or burn tokens out of any account, including the seller's.
+    PermanentDelegate,
+    /// The mint authority can pause all transfers.
+    Pausable,
+    /// Tokens can not be transferred at all.
+    NonTransferable,
+    /// Balances and transfers can be encrypted.
+    ConfidentialTransfer,
+}
+
+impl MintExtension {
+    pub fn variants() -> &'static [MintExtension] {
+        &[
+            MintExtension::TransferFee,
+            MintExtension::TransferHook,
+            MintExtension::PermanentDelegate,
+            MintExtension::Pausable,
+            MintExtension::NonTransferable,
+            MintExtension::ConfidentialTransfer,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MintExtension::TransferFee => "transfer-fee",
+            MintExtension::TransferHook => "transfer-hook",
+            MintExtension::PermanentDelegate => "permanent-delegate",
+            MintExtension::Pausable => "pausable",
+            MintExtension::NonTransferable => "non-transferable",
+            MintExtension::ConfidentialTransfer => "confidential-transfer",
+        }
+    }
+
+    /// Maps a mint-level [`ExtensionType`] to the policy-relevant [`MintExtension`], if any.
+    pub fn from_extension_type(extension_type: ExtensionType) -> Option<Self> {
+        match extension_type {
+            ExtensionType::TransferFeeConfig => Some(MintExtension::TransferFee),
+            ExtensionType::TransferHook => Some(MintExtension::TransferHook),
+            ExtensionType::PermanentDelegate => Some(MintExtension::PermanentDelegate),
+            ExtensionType::Pausable => Some(MintExtension::Pausable),
+            ExtensionType::NonTransferable => Some(MintExtension::NonTransferable),
+            ExtensionType::ConfidentialTransferMint => Some(MintExtension::ConfidentialTransfer),
+            _ => None,
+        }
+    }
+}
+
+impl Display for MintExtension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for MintExtension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MintExtension::variants()
+            .iter()
+            .find(|extension| extension.as_str() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown mint extension {s}"))
+    }
+}
+
+/// Which Token-2022 [`MintExtension`]s the facilitator accepts on a payment mint.
+///
+/// Parsed from a comma-separated list such as `transfer-fee,pausable`; `none` allows nothing.
+/// By default only [`MintExtension::TransferFee`] is allowed, since the facilitator accounts
+/// for the withheld fee when matching the payment amount.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintExtensionPolicy {
+    allowed: HashSet<MintExtension>,
+}
+
+impl Default for MintExtensionPolicy {
+    fn default() -> Self {
+        Self {
+            allowed: HashSet::from([MintExtension::TransferFee]),
+        }
+    }
+}
+
+impl MintExtensionPolicy {
+    pub fn is_allowed(&self, extension: MintExtension) -> bool {
+        self.allowed.contains(&extension)
+    }
+}
+
+impl FromStr for MintExtensionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || s == "none" {
+            return Ok(Self {
+                allowed: HashSet::new(),
+            });
+        }
+        let allowed = s
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(MintExtension::from_str)
+            .collect::<Result<HashSet<_>, _>>()?;
+        Ok(Self { allowed })
+    }
+}
+
+impl Display for MintExtensionPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let allowed: Vec<&str> = MintExtension::variants()
+            .iter()
+            .filter(|extension| self.is_allowed(**extension))
+            .map(MintExtension::as_str)
+            .collect();
+        if allowed.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", allowed.join(","))
+        }
+    }
+}
+
 pub struct InstructionInt {
     instruction: CompiledInstruction,
     account_keys: Vec<Pubkey>,
@@ -815,12 +1858,35 @@ impl InstructionInt {
 
 pub struct TransactionInt {
     inner: VersionedTransaction,
+    account_keys: Vec<Pubkey>,
 }
 
 impl TransactionInt {
     pub fn new(transaction: VersionedTransaction) -> Self {
-        Self { inner: transaction }
+        let account_keys = transaction.message.static_account_keys().to_vec();
+        Self {
+            inner: transaction,
+            account_keys,
+        }
+    }
+
+    /// Replaces the static account keys with a fully resolved list,
+    /// see [`SolanaProvider::resolve_account_keys`].
+    pub fn with_account_keys(self, account_keys: Vec<Pubkey>) -> Self {
+        Self {
+            inner: self.inner,
+            account_keys,
+        }
+    }
+
+    pub fn account_keys(&self) -> &[Pubkey] {
+        self.account_keys.as_slice()
+    }
+
+    pub fn into_inner(self) -> VersionedTransaction {
+        self.inner
     }
+
     pub fn instruction(&self, index: usize) -> Result<InstructionInt, FacilitatorLocalError> {
         let instruction = self
             .inner
@@ -831,7 +1897,7 @@ impl TransactionInt {
             .ok_or(FacilitatorLocalError::DecodingError(
                 "invalid_exact_svm_payload_transaction_instructions".to_string(),
             ))?;
-        let account_keys = self.inner.message.static_account_keys().to_vec();
+        let account_keys = self.account_keys.clone();
 
         Ok(InstructionInt {
             instruction,
@@ -875,7 +1941,15 @@ impl TransactionInt {
         }
         // tx.signatures.push(signature);
         tx.signatures[pos] = signature;
-        Ok(Self { inner: tx })
+        Ok(Self {
+            inner: tx,
+            account_keys: self.account_keys,
+        })
+    }
+
+    /// The fee payer signature, which identifies the transaction.
+    pub fn signature(&self) -> Signature {
+        self.inner.signatures.first().cloned().unwrap_or_default()
     }
 
     pub async fn send(&self, rpc_client: &RpcClient) -> Result<Signature, FacilitatorLocalError> {
@@ -891,21 +1965,128 @@ impl TransactionInt {
             .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))
     }
 
-    pub async fn send_and_confirm(
+    /// Returns the leading `AdvanceNonceAccount` instruction, if this is a durable nonce
+    /// transaction.
+    pub fn advance_nonce_instruction(
+        &self,
+    ) -> Result<Option<AdvanceNonceInstruction>, FacilitatorLocalError> {
+        if self.inner.message.instructions().is_empty() {
+            return Ok(None);
+        }
+        let instruction = self.instruction(0)?;
+        if instruction.program_id() != solana_system_interface::program::ID {
+            return Ok(None);
+        }
+        match bincode::deserialize::<SystemInstruction>(instruction.data_slice()) {
+            Ok(SystemInstruction::AdvanceNonceAccount) => {}
+            _ => return Ok(None),
+        }
+        // Nonce account = 0, RecentBlockhashes sysvar = 1, Nonce authority = 2
+        Ok(Some(AdvanceNonceInstruction {
+            nonce_account: instruction.account(0)?,
+            nonce_authority: instruction.account(2)?,
+        }))
+    }
+
+    /// Whether the transaction can still land.
+    ///
+    /// A durable nonce transaction stays valid until its nonce account is advanced. Otherwise
+    /// the recent blockhash must still be valid, and the block height must not exceed
+    /// `last_valid_block_height` when given.
+    async fn is_unexpired(
         &self,
         rpc_client: &RpcClient,
+        durable_nonce: Option<&Pubkey>,
         commitment_config: CommitmentConfig,
-    ) -> Result<Signature, FacilitatorLocalError> {
-        let tx_sig = self.send(rpc_client).await?;
-        loop {
-            let confirmed = rpc_client
-                .confirm_transaction_with_commitment(&tx_sig, commitment_config)
+        last_valid_block_height: Option<u64>,
+    ) -> Result<bool, FacilitatorLocalError> {
+        let blockhash = self.inner.message.recent_blockhash();
+        if let Some(nonce_account) = durable_nonce {
+            let nonce = fetch_durable_nonce(rpc_client, nonce_account, commitment_config).await?;
+            return Ok(nonce.is_some_and(|nonce| nonce.blockhash() == *blockhash));
+        }
+        if let Some(last_valid_block_height) = last_valid_block_height {
+            let block_height = rpc_client
+                .get_block_height_with_commitment(commitment_config)
                 .await
                 .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
-            if confirmed.value {
-                return Ok(tx_sig);
+            if block_height > last_valid_block_height {
+                return Ok(false);
+            }
+        }
+        rpc_client
+            .is_blockhash_valid(blockhash, commitment_config)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))
+    }
+
+    /// Sends the transaction and waits until it reaches `commitment_config` or expires.
+    ///
+    /// Fails fast with [`SendOutcome::Expired`] if the transaction can no longer land before the
+    /// first send. While waiting, the same signed transaction is rebroadcast every
+    /// [`REBROADCAST_INTERVAL`], since RPC nodes may drop it under load. Expiry is detected
+    /// through `isBlockhashValid`, bounded by the last valid block height of the latest blockhash
+    /// at send time, which the client's blockhash cannot outlive. Durable nonce transactions
+    /// expire only once their nonce account is advanced.
+    pub async fn send_and_confirm(
+        &self,
+        rpc_client: &RpcClient,
+        commitment_config: CommitmentConfig,
+    ) -> Result<SendOutcome, FacilitatorLocalError> {
+        let tx_sig = self.signature();
+        let durable_nonce = self
+            .advance_nonce_instruction()?
+            .map(|advance_nonce| advance_nonce.nonce_account);
+        let is_unexpired = self
+            .is_unexpired(
+                rpc_client,
+                durable_nonce.as_ref(),
+                CommitmentConfig::processed(),
+                None,
+            )
+            .await?;
+        if !is_unexpired {
+            return Ok(SendOutcome::Expired(tx_sig));
+        }
+        let last_valid_block_height = match durable_nonce {
+            Some(_) => None,
+            None => {
+                let (_, last_valid_block_height) = rpc_client
+                    .get_latest_blockhash_with_commitment(commitment_config)
+                    .await
+                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+                Some(last_valid_block_height)
             }
+        };
+
+        let tx_sig = self.send(rpc_client).await?;
+        let mut last_broadcast = Instant::now();
+        loop {
             tokio::time::sleep(Duration::from_millis(500)).await;
+            if let Some(outcome) = signature_outcome(rpc_client, &tx_sig, commitment_config).await?
+            {
+                return Ok(outcome);
+            }
+            if last_broadcast.elapsed() < REBROADCAST_INTERVAL {
+                continue;
+            }
+            let is_unexpired = self
+                .is_unexpired(
+                    rpc_client,
+                    durable_nonce.as_ref(),
+                    commitment_config,
+                    last_valid_block_height,
+                )
+                .await?;
+            if !is_unexpired {
+                // The transaction may have landed between the last status check and expiry.
+                let outcome = signature_outcome(rpc_client, &tx_sig, commitment_config).await?;
+                return Ok(outcome.unwrap_or(SendOutcome::Expired(tx_sig)));
+            }
+            if let Err(e) = self.send(rpc_client).await {
+                tracing::debug!(signature = %tx_sig, error = %e, "rebroadcast failed");
+            }
+            last_broadcast = Instant::now();
         }
     }
 
@@ -919,3 +2100,1239 @@ impl TransactionInt {
         Ok(string)
     }
 }
+
+/// Time-bounded cache of address lookup table contents, keyed by table address.
+///
+/// Lookup tables are append-only while active, so a cached entry stays valid for every index it
+/// covers. An entry is refetched once it expires, or when a transaction references an index past
+/// its end, which means the table has been extended since it was cached.
+#[derive(Clone, Debug)]
+pub struct AddressLookupTableCache {
+    tables: Arc<DashMap<Pubkey, CachedLookupTable>>,
+    ttl: Duration,
+}
+
+#[derive(Clone, Debug)]
+struct CachedLookupTable {
+    addresses: Arc<Vec<Pubkey>>,
+    fetched_at: Instant,
+}
+
+impl AddressLookupTableCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            tables: Arc::new(DashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Returns the cached addresses of `table` if fresh and holding at least `min_len` entries.
+    fn get(&self, table: &Pubkey, min_len: usize) -> Option<Arc<Vec<Pubkey>>> {
+        let entry = self.tables.get(table)?;
+        if entry.fetched_at.elapsed() > self.ttl || entry.addresses.len() < min_len {
+            return None;
+        }
+        Some(Arc::clone(&entry.addresses))
+    }
+
+    fn insert(&self, table: Pubkey, addresses: Arc<Vec<Pubkey>>) {
+        self.tables.insert(
+            table,
+            CachedLookupTable {
+                addresses,
+                fetched_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Loads the tables referenced by `lookups`, in the same order.
+    ///
+    /// Cache misses are fetched with a single `getMultipleAccounts` call.
+    pub async fn load(
+        &self,
+        rpc_client: &RpcClient,
+        lookups: &[MessageAddressTableLookup],
+    ) -> Result<Vec<Arc<Vec<Pubkey>>>, FacilitatorLocalError> {
+        let mut tables: Vec<Option<Arc<Vec<Pubkey>>>> = lookups
+            .iter()
+            .map(|lookup| {
+                let min_len = lookup
+                    .writable_indexes
+                    .iter()
+                    .chain(lookup.readonly_indexes.iter())
+                    .max()
+                    .map_or(0, |index| *index as usize + 1);
+                self.get(&lookup.account_key, min_len)
+            })
+            .collect();
+        let missing: Vec<Pubkey> = lookups
+            .iter()
+            .zip(tables.iter())
+            .filter(|(_, table)| table.is_none())
+            .map(|(lookup, _)| lookup.account_key)
+            .collect();
+        if !missing.is_empty() {
+            let accounts = rpc_client
+                .get_multiple_accounts(&missing)
+                .await
+                .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+            for (key, account) in missing.iter().zip(accounts) {
+                let account = account.ok_or(FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_address_lookup_table_not_found"
+                        .to_string(),
+                ))?;
+                if account.owner != solana_address_lookup_table_interface::program::ID {
+                    return Err(FacilitatorLocalError::DecodingError(
+                        "invalid_exact_svm_payload_transaction_address_lookup_table_invalid_owner"
+                            .to_string(),
+                    ));
+                }
+                let table = AddressLookupTable::deserialize(&account.data).map_err(|_| {
+                    FacilitatorLocalError::DecodingError(
+                        "invalid_exact_svm_payload_transaction_address_lookup_table_invalid_data"
+                            .to_string(),
+                    )
+                })?;
+                let addresses = Arc::new(table.addresses.to_vec());
+                self.insert(*key, Arc::clone(&addresses));
+                for (lookup, slot) in lookups.iter().zip(tables.iter_mut()) {
+                    if lookup.account_key == *key && slot.is_none() {
+                        *slot = Some(Arc::clone(&addresses));
+                    }
+                }
+            }
+        }
+        tables
+            .into_iter()
+            .map(|table| {
+                table.ok_or(FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_address_lookup_table_not_found"
+                        .to_string(),
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Assembles the account keys of a v0 message the same way the runtime does: static keys,
+/// then writable addresses from every lookup, then readonly addresses from every lookup.
+fn loaded_account_keys(
+    static_keys: &[Pubkey],
+    lookups: &[MessageAddressTableLookup],
+    tables: &[Arc<Vec<Pubkey>>],
+) -> Result<Vec<Pubkey>, FacilitatorLocalError> {
+    let lookup_address = |table: &Arc<Vec<Pubkey>>, index: &u8| {
+        table
+            .get(*index as usize)
+            .cloned()
+            .ok_or(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_address_lookup_table_index".to_string(),
+            ))
+    };
+    let mut writable = Vec::new();
+    let mut readonly = Vec::new();
+    for (lookup, table) in lookups.iter().zip(tables.iter()) {
+        for index in lookup.writable_indexes.iter() {
+            writable.push(lookup_address(table, index)?);
+        }
+        for index in lookup.readonly_indexes.iter() {
+            readonly.push(lookup_address(table, index)?);
+        }
+    }
+    let mut account_keys = static_keys.to_vec();
+    account_keys.extend(writable);
+    account_keys.extend(readonly);
+    Ok(account_keys)
+}
+
+/// Limits a [`SolanaProvider`] enforces on payment transactions. Reloadable at runtime, see
+/// [`SolanaProvider::set_policy`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolanaPolicy {
+    /// Maximum compute unit limit a payment transaction may request.
+    pub max_compute_unit_limit: u32,
+    /// Maximum compute unit price, in micro-lamports, a payment transaction may pay.
+    pub max_compute_unit_price: u64,
+    pub mint_extension_policy: MintExtensionPolicy,
+    pub priority_fee_policy: PriorityFeePolicy,
+    /// Commitment a settlement transaction waits for before it is reported.
+    pub settle_commitment: CommitmentConfig,
+}
+
+impl SolanaPolicy {
+    /// Reads the policy of `network` from its `X402_SOLANA_*_{SOLANA|SOLANA_DEVNET}` variables,
+    /// as returned by `lookup`, falling back to per-network defaults.
+    pub fn from_lookup(
+        network: Network,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let suffix = match network {
+            Network::Solana => "SOLANA",
+            Network::SolanaDevnet => "SOLANA_DEVNET",
+            _ => return Err(format!("{network} is not a Solana network").into()),
+        };
+        let var = |name: &str| lookup(&format!("X402_SOLANA_{name}_{suffix}"));
+        let mainnet = network == Network::Solana;
+
+        let max_compute_unit_limit = match var("MAX_COMPUTE_UNIT_LIMIT") {
+            Some(value) => value
+                .parse()
+                .map_err(|e| format!("env X402_SOLANA_MAX_COMPUTE_UNIT_LIMIT_{suffix}: {e}"))?,
+            None if mainnet => 400_000,
+            None => 200_000,
+        };
+        let max_compute_unit_price = match var("MAX_COMPUTE_UNIT_PRICE") {
+            Some(value) => value
+                .parse()
+                .map_err(|e| format!("env X402_SOLANA_MAX_COMPUTE_UNIT_PRICE_{suffix}: {e}"))?,
+            None if mainnet => 1_000_000,
+            None => 100_000,
+        };
+        let mint_extension_policy = match var("ALLOWED_MINT_EXTENSIONS") {
+            Some(value) => MintExtensionPolicy::from_str(&value)
+                .map_err(|e| format!("env X402_SOLANA_ALLOWED_MINT_EXTENSIONS_{suffix}: {e}"))?,
+            None => MintExtensionPolicy::default(),
+        };
+        let percentile = match var("PRIORITY_FEE_PERCENTILE") {
+            Some(value) => value
+                .parse::<u8>()
+                .ok()
+                .filter(|percentile| *percentile <= 100)
+                .ok_or_else(|| {
+                    format!("env X402_SOLANA_PRIORITY_FEE_PERCENTILE_{suffix}: {value} is not a percentile")
+                })?,
+            None => 75,
+        };
+        let floor = match var("PRIORITY_FEE_FLOOR") {
+            Some(value) => value
+                .parse()
+                .map_err(|e| format!("env X402_SOLANA_PRIORITY_FEE_FLOOR_{suffix}: {e}"))?,
+            None if mainnet => 1_000,
+            None => 0,
+        };
+        let settle_commitment = match var("COMMITMENT") {
+            Some(value) => CommitmentConfig::from_str(&value)
+                .map_err(|e| format!("env X402_SOLANA_COMMITMENT_{suffix}: {e}"))?,
+            None => CommitmentConfig::confirmed(),
+        };
+        Ok(Self {
+            max_compute_unit_limit,
+            max_compute_unit_price,
+            mint_extension_policy,
+            priority_fee_policy: PriorityFeePolicy { percentile, floor },
+            settle_commitment,
+        })
+    }
+}
+
+/// How a recommended compute unit price is derived from recent prioritization fees.
+///
+/// Configured per network via `X402_SOLANA_PRIORITY_FEE_PERCENTILE_{SOLANA|SOLANA_DEVNET}`
+/// and `X402_SOLANA_PRIORITY_FEE_FLOOR_{SOLANA|SOLANA_DEVNET}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriorityFeePolicy {
+    /// Percentile (0–100) of recent per-slot prioritization fees to recommend.
+    pub percentile: u8,
+    /// Minimum recommended compute unit price, in micro-lamports.
+    pub floor: u64,
+}
+
+impl Default for PriorityFeePolicy {
+    fn default() -> Self {
+        Self {
+            percentile: 75,
+            floor: 0,
+        }
+    }
+}
+
+impl PriorityFeePolicy {
+    /// Picks the configured percentile of `fees` (nearest rank), never going below the floor.
+    pub fn recommend(&self, fees: &[u64]) -> u64 {
+        if fees.is_empty() {
+            return self.floor;
+        }
+        let mut fees = fees.to_vec();
+        fees.sort_unstable();
+        let percentile = self.percentile.min(100) as usize;
+        let rank = (percentile * fees.len()).div_ceil(100).max(1);
+        fees[rank - 1].max(self.floor)
+    }
+}
+
+/// Short-lived cache of recommended compute unit prices, keyed by the queried accounts.
+#[derive(Clone, Debug)]
+pub struct PriorityFeeCache {
+    prices: Arc<DashMap<Vec<Pubkey>, (u64, Instant)>>,
+    ttl: Duration,
+}
+
+impl PriorityFeeCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            prices: Arc::new(DashMap::new()),
+            ttl,
+        }
+    }
+
+    pub fn clear(&self) {
+        self.prices.clear();
+    }
+
+    fn get(&self, accounts: &[Pubkey]) -> Option<u64> {
+        let entry = self.prices.get(accounts)?;
+        let (price, fetched_at) = *entry;
+        (fetched_at.elapsed() <= self.ttl).then_some(price)
+    }
+
+    fn insert(&self, accounts: Vec<Pubkey>, price: u64) {
+        // Keys come from client-supplied `payTo` addresses, so drop stale entries as we go.
+        self.prices
+            .retain(|_, (_, fetched_at)| fetched_at.elapsed() <= self.ttl);
+        self.prices.insert(accounts, (price, Instant::now()));
+    }
+}
+
+/// Last compute unit price advertised by `/supported`, and whether a refresh is under way.
+#[derive(Debug, Default)]
+struct AdvertisedPrice {
+    price: Option<(u64, Instant)>,
+    refreshing: bool,
+}
+
+/// Associated token account of `owner` for `mint` under `token_program` (findAssociatedTokenPda).
+pub fn associated_token_address(owner: &Pubkey, token_program: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let (ata, _) = Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ATA_PROGRAM_PUBKEY,
+    );
+    ata
+}
+
+/// Accounts a payment of `mint` to `pay_to` writes: the mint itself plus the recipient's
+/// associated token account under both token programs, since the mint owner is not known here.
+/// A native SOL payment only writes the recipient itself.
+fn priority_fee_accounts(mint: &Pubkey, pay_to: Option<&Pubkey>) -> Vec<Pubkey> {
+    if *mint == NATIVE_SOL_ASSET {
+        return pay_to.into_iter().cloned().collect();
+    }
+    let mut accounts = vec![*mint];
+    if let Some(pay_to) = pay_to {
+        for token_program in [spl_token::ID, spl_token_2022::ID] {
+            accounts.push(associated_token_address(pay_to, &token_program, mint));
+        }
+    }
+    accounts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_fixtures::transfer_fee_mint;
+    use crate::types::{ExactSolanaPayload, PaymentPayload};
+    use solana_rpc_client::mock_sender::MocksMap;
+
+    #[test]
+    fn test_loaded_account_keys_orders_writable_before_readonly() {
+        let static_keys = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let table_a = Arc::new(vec![Pubkey::new_unique(), Pubkey::new_unique()]);
+        let table_b = Arc::new(vec![Pubkey::new_unique(), Pubkey::new_unique()]);
+        let lookups = vec![
+            MessageAddressTableLookup {
+                account_key: Pubkey::new_unique(),
+                writable_indexes: vec![1],
+                readonly_indexes: vec![0],
+            },
+            MessageAddressTableLookup {
+                account_key: Pubkey::new_unique(),
+                writable_indexes: vec![0],
+                readonly_indexes: vec![1],
+            },
+        ];
+
+        let tables = [table_a.clone(), table_b.clone()];
+        let keys = loaded_account_keys(&static_keys, &lookups, &tables).expect("indexes in range");
+
+        assert_eq!(
+            keys,
+            vec![
+                static_keys[0],
+                static_keys[1],
+                table_a[1],
+                table_b[0],
+                table_a[0],
+                table_b[1],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_loaded_account_keys_rejects_out_of_range_index() {
+        let table = Arc::new(vec![Pubkey::new_unique()]);
+        let lookups = vec![MessageAddressTableLookup {
+            account_key: Pubkey::new_unique(),
+            writable_indexes: vec![],
+            readonly_indexes: vec![3],
+        }];
+
+        assert!(loaded_account_keys(&[], &lookups, &[table]).is_err());
+    }
+
+    fn native_sol_requirements(pay_to: Pubkey, lamports: u64) -> PaymentRequirements {
+        PaymentRequirements {
+            scheme: Scheme::Exact,
+            network: Network::SolanaDevnet,
+            max_amount_required: TokenAmount::from(lamports),
+            resource: "https://example.com/api".parse().unwrap(),
+            description: String::new(),
+            mime_type: "application/json".to_string(),
+            output_schema: None,
+            pay_to: MixedAddress::Solana(pay_to),
+            max_timeout_seconds: 60,
+            asset: MixedAddress::Solana(NATIVE_SOL_ASSET),
+            extra: None,
+        }
+    }
+
+    fn system_transfer_tx(
+        fee_payer: &Pubkey,
+        from: &Pubkey,
+        to: &Pubkey,
+        lamports: u64,
+    ) -> TransactionInt {
+        use solana_sdk::instruction::{AccountMeta, Instruction};
+        use solana_sdk::message::{Message, VersionedMessage};
+
+        let instruction = Instruction::new_with_bincode(
+            solana_system_interface::program::ID,
+            &SystemInstruction::Transfer { lamports },
+            vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+        );
+        let message = Message::new(&[instruction], Some(fee_payer));
+        TransactionInt::new(VersionedTransaction {
+            signatures: vec![],
+            message: VersionedMessage::Legacy(message),
+        })
+    }
+
+    fn test_provider() -> SolanaProvider {
+        SolanaProvider::try_new(
+            Keypair::new(),
+            &[RpcEndpoint::new("http://localhost:8899")],
+            Network::SolanaDevnet,
+            200_000,
+            100_000,
+            MintExtensionPolicy::default(),
+            PriorityFeePolicy::default(),
+        )
+        .unwrap()
+        .with_rpc_client(RpcClient::new_mock("succeeds".to_string()))
+    }
+
+    #[test]
+    fn test_verify_system_transfer_instruction() {
+        let provider = test_provider();
+        let fee_payer = provider.keypair.pubkey();
+        let buyer = Pubkey::new_unique();
+        let seller = Pubkey::new_unique();
+        let requirements = native_sol_requirements(seller, 5_000);
+
+        let tx = system_transfer_tx(&fee_payer, &buyer, &seller, 5_000);
+        let transfer = provider
+            .verify_system_transfer_instruction(&tx, 0, &requirements)
+            .expect("valid native SOL transfer");
+        assert_eq!(transfer.from, buyer);
+        assert_eq!(transfer.lamports, 5_000);
+
+        let tx = system_transfer_tx(&fee_payer, &buyer, &seller, 4_999);
+        assert!(
+            provider
+                .verify_system_transfer_instruction(&tx, 0, &requirements)
+                .is_err()
+        );
+
+        let tx = system_transfer_tx(&fee_payer, &buyer, &seller, 6_000);
+        assert!(
+            provider
+                .verify_system_transfer_instruction(&tx, 0, &requirements)
+                .is_ok()
+        );
+
+        let tx = system_transfer_tx(&fee_payer, &buyer, &Pubkey::new_unique(), 5_000);
+        assert!(
+            provider
+                .verify_system_transfer_instruction(&tx, 0, &requirements)
+                .is_err()
+        );
+
+        let tx = system_transfer_tx(&fee_payer, &fee_payer, &seller, 5_000);
+        assert!(
+            provider
+                .verify_system_transfer_instruction(&tx, 0, &requirements)
+                .is_err()
+        );
+    }
+
+    fn token_requirements(mint: Pubkey, pay_to: Pubkey, amount: u64) -> PaymentRequirements {
+        PaymentRequirements {
+            asset: MixedAddress::Solana(mint),
+            ..native_sol_requirements(pay_to, amount)
+        }
+    }
+
+    fn transfer_checked(
+        mint: Pubkey,
+        pay_to: Pubkey,
+        token_program: Pubkey,
+        amount: u64,
+    ) -> TransferCheckedInstruction {
+        TransferCheckedInstruction {
+            amount,
+            decimals: 6,
+            source: Pubkey::new_unique(),
+            mint,
+            destination: associated_token_address(&pay_to, &token_program, &mint),
+            authority: Pubkey::new_unique(),
+            token_program,
+            data: vec![],
+        }
+    }
+
+    /// Source, destination and mint accounts of a transfer, with the given mint data.
+    fn transfer_accounts(token_program: Pubkey, mint_data: Vec<u8>) -> Vec<Option<Account>> {
+        let account = |data: Vec<u8>| {
+            Some(Account {
+                lamports: 1_000_000,
+                data,
+                owner: token_program,
+                executable: false,
+                rent_epoch: 0,
+            })
+        };
+        vec![account(vec![]), account(vec![]), account(mint_data)]
+    }
+
+    #[tokio::test]
+    async fn test_verify_transfer_accounts_accepts_overpayment_without_transfer_fee() {
+        let provider = test_provider();
+        let (mint, seller) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let requirements = token_requirements(mint, seller, 10_000);
+        let accounts = transfer_accounts(spl_token::ID, vec![]);
+
+        for (amount, is_valid) in [(9_999, false), (10_000, true), (10_001, true)] {
+            let transfer = transfer_checked(mint, seller, spl_token::ID, amount);
+            let result = provider
+                .verify_transfer_accounts(&transfer, &accounts, &requirements, false)
+                .await;
+            assert_eq!(result.is_ok(), is_valid, "amount {amount}");
+            if let Ok(details) = result {
+                assert_eq!(details, None);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_transfer_accounts_matches_net_amount_with_transfer_fee() {
+        let provider = test_provider();
+        let (mint, seller) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let requirements = token_requirements(mint, seller, 10_000);
+        // 1% transfer fee, rounded up
+        let accounts = transfer_accounts(spl_token_2022::ID, transfer_fee_mint(100, u64::MAX));
+
+        let transfer = transfer_checked(mint, seller, spl_token_2022::ID, 10_000);
+        assert!(
+            provider
+                .verify_transfer_accounts(&transfer, &accounts, &requirements, false)
+                .await
+                .is_err()
+        );
+
+        let transfer = transfer_checked(mint, seller, spl_token_2022::ID, 10_102);
+        let details = provider
+            .verify_transfer_accounts(&transfer, &accounts, &requirements, false)
+            .await
+            .unwrap()
+            .expect("details of a transfer-fee mint");
+        assert_eq!(details.amount, TokenAmount::from(10_102u64));
+        assert_eq!(details.transfer_fee, Some(TokenAmount::from(102u64)));
+        assert_eq!(details.net_amount, TokenAmount::from(10_000u64));
+
+        let transfer = transfer_checked(mint, seller, spl_token_2022::ID, 20_000);
+        let details = provider
+            .verify_transfer_accounts(&transfer, &accounts, &requirements, false)
+            .await
+            .unwrap()
+            .expect("details of a transfer-fee mint");
+        assert_eq!(details.net_amount, TokenAmount::from(19_800u64));
+    }
+
+    /// A `TransferChecked` of `amount` of `mint` from the ATA of `authority` to the one of `to`.
+    fn token_transfer(
+        authority: &Pubkey,
+        mint: &Pubkey,
+        to: &Pubkey,
+        amount: u64,
+    ) -> solana_sdk::instruction::Instruction {
+        spl_token::instruction::transfer_checked(
+            &spl_token::ID,
+            &associated_token_address(authority, &spl_token::ID, mint),
+            mint,
+            &associated_token_address(to, &spl_token::ID, mint),
+            authority,
+            &[],
+            amount,
+            6,
+        )
+        .unwrap()
+    }
+
+    /// A `SystemProgram::Transfer` of `lamports` from `from` to `to`.
+    fn sol_transfer(
+        from: &Pubkey,
+        to: &Pubkey,
+        lamports: u64,
+    ) -> solana_sdk::instruction::Instruction {
+        use solana_sdk::instruction::{AccountMeta, Instruction};
+
+        Instruction::new_with_bincode(
+            solana_system_interface::program::ID,
+            &SystemInstruction::Transfer { lamports },
+            vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+        )
+    }
+
+    /// A request paying `requirements` with `instructions`, after the compute budget ones, with
+    /// `fee_payer` paying fees.
+    fn payment_request(
+        fee_payer: &Pubkey,
+        instructions: &[solana_sdk::instruction::Instruction],
+        requirements: PaymentRequirements,
+    ) -> VerifyRequest {
+        use solana_sdk::compute_budget::ComputeBudgetInstruction;
+        use solana_sdk::message::{Message, VersionedMessage};
+
+        let mut all = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1),
+        ];
+        all.extend_from_slice(instructions);
+        let transaction = VersionedTransaction {
+            signatures: vec![],
+            message: VersionedMessage::Legacy(Message::new(&all, Some(fee_payer))),
+        };
+        let transaction = Base64Bytes::encode(bincode::serialize(&transaction).unwrap());
+        VerifyRequest {
+            x402_version: X402Version::V1,
+            payment_payload: PaymentPayload {
+                x402_version: X402Version::V1,
+                scheme: Scheme::Exact,
+                network: Network::SolanaDevnet,
+                payload: ExactPaymentPayload::Solana(ExactSolanaPayload {
+                    transaction: String::from_utf8(transaction.0.into_owned()).unwrap(),
+                }),
+            },
+            payment_requirements: requirements,
+        }
+    }
+
+    /// A transaction paying `amount` of `mint` to the ATA of `pay_to`, with `fee_payer` paying fees.
+    fn token_payment_request(
+        fee_payer: &Pubkey,
+        buyer: &Pubkey,
+        mint: &Pubkey,
+        pay_to: &Pubkey,
+        amount: u64,
+    ) -> VerifyRequest {
+        payment_request(
+            fee_payer,
+            &[token_transfer(buyer, mint, pay_to, amount)],
+            token_requirements(*mint, *pay_to, amount),
+        )
+    }
+
+    /// The transaction of a Solana `request`, as verification introspects it.
+    fn request_transaction(request: &VerifyRequest) -> TransactionInt {
+        let ExactPaymentPayload::Solana(payload) = &request.payment_payload.payload else {
+            panic!("expected a Solana payload");
+        };
+        let bytes = Base64Bytes::from(payload.transaction.as_bytes())
+            .decode()
+            .unwrap();
+        TransactionInt::new(bincode::deserialize(bytes.as_slice()).unwrap())
+    }
+
+    fn fee_to(recipient: Pubkey) -> FacilitatorFee {
+        FacilitatorFee {
+            recipient: MixedAddress::Solana(recipient),
+            bps: Some(100),
+            flat: Default::default(),
+            exempt: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_verify_fee_instruction() {
+        let (buyer, mint, seller, recipient) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let provider = test_provider().with_fee(fee_to(recipient));
+        let fee_payer = provider.keypair.pubkey();
+        let fee_due = TokenAmount::from(100u64);
+        let requirements = token_requirements(mint, seller, 10_000);
+        let fee_instruction = |fee: solana_sdk::instruction::Instruction| {
+            let payment = token_transfer(&buyer, &mint, &seller, 10_000);
+            let tx = request_transaction(&payment_request(
+                &fee_payer,
+                &[payment, fee],
+                requirements.clone(),
+            ));
+            let payment = decode_transfer_checked(&tx, 2).unwrap();
+            provider.verify_fee_instruction(&tx, 3, buyer, Some(&payment), fee_due)
+        };
+
+        let fee_account = fee_instruction(token_transfer(&buyer, &mint, &recipient, 100)).unwrap();
+        assert_eq!(
+            fee_account,
+            Some(associated_token_address(&recipient, &spl_token::ID, &mint))
+        );
+        let other = Pubkey::new_unique();
+        for rejected in [
+            token_transfer(&buyer, &mint, &recipient, 99),
+            token_transfer(&buyer, &mint, &other, 100),
+            token_transfer(&buyer, &other, &recipient, 100),
+            token_transfer(&other, &mint, &recipient, 100),
+        ] {
+            let error = fee_instruction(rejected).expect_err("fee must be rejected");
+            assert!(
+                matches!(error, FacilitatorLocalError::InvalidFee(ref payer, _) if *payer == MixedAddress::Solana(buyer)),
+                "{error}"
+            );
+        }
+
+        // Native SOL pays its fee with a system transfer to the recipient itself
+        let requirements = native_sol_requirements(seller, 10_000);
+        let native_fee = |fee: solana_sdk::instruction::Instruction| {
+            let payment = sol_transfer(&buyer, &seller, 10_000);
+            let tx = request_transaction(&payment_request(
+                &fee_payer,
+                &[payment, fee],
+                requirements.clone(),
+            ));
+            provider.verify_fee_instruction(&tx, 3, buyer, None, fee_due)
+        };
+        assert_eq!(
+            native_fee(sol_transfer(&buyer, &recipient, 100)).unwrap(),
+            None
+        );
+        assert!(matches!(
+            native_fee(sol_transfer(&buyer, &recipient, 101)),
+            Err(FacilitatorLocalError::InvalidFee(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_native_sol_fee_may_be_paid_to_the_fee_payer() {
+        let provider = test_provider();
+        let fee_payer = provider.keypair.pubkey();
+        let provider = provider.with_fee(fee_to(fee_payer));
+        let (buyer, seller) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let request = payment_request(
+            &fee_payer,
+            &[
+                sol_transfer(&buyer, &seller, 10_000),
+                sol_transfer(&buyer, &fee_payer, 100),
+            ],
+            native_sol_requirements(seller, 10_000),
+        );
+        let result = provider.verify_transfer(&request).await.unwrap();
+        assert_eq!(result.payer.pubkey, buyer);
+
+        // The exemption covers the fee recipient only, not the payment
+        let request = payment_request(
+            &fee_payer,
+            &[
+                sol_transfer(&buyer, &fee_payer, 10_000),
+                sol_transfer(&buyer, &fee_payer, 100),
+            ],
+            native_sol_requirements(fee_payer, 10_000),
+        );
+        let result = provider.verify_transfer(&request).await;
+        assert!(matches!(
+            result,
+            Err(FacilitatorLocalError::DecodingError(e))
+                if e == "invalid_exact_svm_payload_transaction_fee_payer_included_in_instruction_accounts"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_token_fee_requires_the_recipient_token_account() {
+        let (buyer, mint, seller, recipient) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let provider = test_provider().with_fee(fee_to(recipient));
+        let fee_payer = provider.keypair.pubkey();
+        let request = payment_request(
+            &fee_payer,
+            &[
+                token_transfer(&buyer, &mint, &seller, 10_000),
+                token_transfer(&buyer, &mint, &recipient, 100),
+            ],
+            token_requirements(mint, seller, 10_000),
+        );
+        let account = serde_json::json!({
+            "lamports": 1_000_000,
+            "data": ["", "base64"],
+            "owner": spl_token::ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": 0,
+        });
+        let mocks = |fee_account: serde_json::Value| {
+            RpcClient::new_mock_with_mocks(
+                "succeeds".to_string(),
+                [(
+                    RpcRequest::GetMultipleAccounts,
+                    serde_json::json!({
+                        "context": {"slot": 1},
+                        "value": [account, account, account, fee_account],
+                    }),
+                )]
+                .into(),
+            )
+        };
+
+        let provider = provider.with_rpc_client(mocks(serde_json::Value::Null));
+        let result = provider.verify_transfer(&request).await;
+        assert!(matches!(
+            result,
+            Err(FacilitatorLocalError::InvalidFee(_, e))
+                if e == "invalid_exact_svm_payload_transaction_fee_recipient_ata_not_found"
+        ));
+
+        let provider = provider.with_rpc_client(mocks(account.clone()));
+        provider.verify_transfer(&request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_transfer_checks_accounts_before_the_transaction() {
+        let provider = test_provider();
+        let fee_payer = provider.keypair.pubkey();
+        let (buyer, mint, seller) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let request = token_payment_request(&fee_payer, &buyer, &mint, &seller, 1_000);
+        let account = serde_json::json!({
+            "lamports": 1_000_000,
+            "data": ["", "base64"],
+            "owner": spl_token::ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": 0,
+        });
+        // The simulated states include the accounts as the transfer leaves them
+        let simulation = serde_json::json!({
+            "context": {"slot": 1},
+            "value": {"err": null, "accounts": [account, account, account]},
+        });
+        let mocks = |destination: serde_json::Value| {
+            RpcClient::new_mock_with_mocks(
+                "succeeds".to_string(),
+                [
+                    (RpcRequest::SimulateTransaction, simulation.clone()),
+                    (
+                        RpcRequest::GetMultipleAccounts,
+                        serde_json::json!({
+                            "context": {"slot": 1},
+                            "value": [account, destination, account],
+                        }),
+                    ),
+                ]
+                .into(),
+            )
+        };
+
+        let provider = provider.with_rpc_client(mocks(serde_json::Value::Null));
+        let result = provider.verify_transfer(&request).await;
+        assert!(matches!(
+            result,
+            Err(FacilitatorLocalError::DecodingError(e))
+                if e == "invalid_exact_svm_payload_transaction_receiver_ata_not_found"
+        ));
+
+        let provider = provider.with_rpc_client(mocks(account.clone()));
+        let result = provider.verify_transfer(&request).await.unwrap();
+        assert_eq!(result.payer.pubkey, buyer);
+    }
+
+    #[tokio::test]
+    async fn test_payment_amount_decodes_the_transfer() {
+        let provider = test_provider();
+        let fee_payer = provider.keypair.pubkey();
+        let (buyer, mint, seller) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut request = token_payment_request(&fee_payer, &buyer, &mint, &seller, 1_000);
+        // The transfer may pay less than the maximum the requirements accept
+        request.payment_requirements.max_amount_required = TokenAmount::from(5_000u64);
+        let paid = provider.payment_amount(&request).await;
+        assert_eq!(
+            paid,
+            Some(PaymentAmount {
+                amount: TokenAmount::from(1_000u64),
+                decimals: Some(6),
+            })
+        );
+
+        request.payment_payload.payload = ExactPaymentPayload::Solana(ExactSolanaPayload {
+            transaction: "not a transaction".to_string(),
+        });
+        assert_eq!(provider.payment_amount(&request).await, None);
+    }
+
+    /// A signed legacy transaction, as `sendTransaction` on the mock RPC client expects.
+    fn signed_transfer_tx() -> TransactionInt {
+        let payer = Pubkey::new_unique();
+        let mut tx = system_transfer_tx(&payer, &payer, &Pubkey::new_unique(), 1);
+        tx.inner.signatures = vec![Signature::new_unique()];
+        tx
+    }
+
+    fn signature_statuses(status: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({"context": {"slot": 1}, "value": [status]})
+    }
+
+    #[tokio::test]
+    async fn test_transaction_status() {
+        let tx_hash = TransactionHash::Solana(*Signature::new_unique().as_array());
+        let status = async |rpc_client: RpcClient| {
+            test_provider()
+                .with_rpc_client(rpc_client)
+                .get_transaction_status(&tx_hash)
+                .await
+        };
+
+        let response = status(RpcClient::new_mock("succeeds".to_string())).await;
+        assert_eq!(response.unwrap().status, TransactionStatus::Confirmed);
+        let response = status(RpcClient::new_mock("instruction_error".to_string())).await;
+        assert_eq!(response.unwrap().status, TransactionStatus::Failed);
+        let processed = signature_statuses(serde_json::json!({
+            "slot": 1,
+            "confirmations": 0,
+            "err": null,
+            "status": {"Ok": null},
+            "confirmationStatus": "processed",
+        }));
+        let rpc_client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            [(RpcRequest::GetSignatureStatuses, processed)].into(),
+        );
+        assert_eq!(
+            status(rpc_client).await.unwrap().status,
+            TransactionStatus::Pending
+        );
+
+        // Without a status, the transaction is looked up in the ledger
+        let response = status(RpcClient::new_mock("sig_not_found".to_string())).await;
+        let response = response.unwrap();
+        assert_eq!(response.status, TransactionStatus::Confirmed);
+        assert_eq!(response.block_number, Some(2));
+        let rpc_client = RpcClient::new_mock_with_mocks(
+            "sig_not_found".to_string(),
+            [(RpcRequest::GetTransaction, serde_json::Value::Null)].into(),
+        );
+        let response = status(rpc_client).await.unwrap();
+        assert_eq!(response.status, TransactionStatus::NotFound);
+
+        // An RPC failure is an error, not a missing transaction
+        let response = status(RpcClient::new_mock("fails".to_string())).await;
+        assert!(matches!(
+            response,
+            Err(FacilitatorLocalError::ContractCall(_))
+        ));
+        let rpc_client = RpcClient::new_mock_with_mocks(
+            "sig_not_found".to_string(),
+            [(RpcRequest::GetTransaction, serde_json::json!("unavailable"))].into(),
+        );
+        let response = status(rpc_client).await;
+        assert!(matches!(
+            response,
+            Err(FacilitatorLocalError::ContractCall(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_signature_outcome() {
+        let signature = Signature::new_unique();
+        let confirmed = CommitmentConfig::confirmed();
+
+        let rpc_client = RpcClient::new_mock("succeeds".to_string());
+        let outcome = signature_outcome(&rpc_client, &signature, confirmed).await;
+        assert!(matches!(outcome, Ok(Some(SendOutcome::Confirmed(s))) if s == signature));
+
+        let rpc_client = RpcClient::new_mock("instruction_error".to_string());
+        let outcome = signature_outcome(&rpc_client, &signature, confirmed).await;
+        assert!(matches!(outcome, Ok(Some(SendOutcome::Failed(s, _))) if s == signature));
+
+        let rpc_client = RpcClient::new_mock("sig_not_found".to_string());
+        let outcome = signature_outcome(&rpc_client, &signature, confirmed).await;
+        assert!(matches!(outcome, Ok(None)));
+
+        // Landed, but not yet at the requested commitment
+        let processed = signature_statuses(serde_json::json!({
+            "slot": 1,
+            "confirmations": 0,
+            "err": null,
+            "status": {"Ok": null},
+            "confirmationStatus": "processed",
+        }));
+        let rpc_client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            [(RpcRequest::GetSignatureStatuses, processed)].into(),
+        );
+        let outcome = signature_outcome(&rpc_client, &signature, confirmed).await;
+        assert!(matches!(outcome, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_send_and_confirm_fails_fast_on_expired_blockhash() {
+        let tx = signed_transfer_tx();
+        let blockhash_invalid = serde_json::json!({"context": {"slot": 1}, "value": false});
+        let rpc_client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            [(RpcRequest::IsBlockhashValid, blockhash_invalid)].into(),
+        );
+
+        let outcome = tx
+            .send_and_confirm(&rpc_client, CommitmentConfig::confirmed())
+            .await;
+        assert!(matches!(outcome, Ok(SendOutcome::Expired(s)) if s == tx.signature()));
+    }
+
+    #[tokio::test]
+    async fn test_send_and_confirm_returns_first_landed_status() {
+        let tx = signed_transfer_tx();
+        let blockhash_valid = serde_json::json!({"context": {"slot": 1}, "value": true});
+        let rpc_client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            [(RpcRequest::IsBlockhashValid, blockhash_valid)].into(),
+        );
+
+        let outcome = tx
+            .send_and_confirm(&rpc_client, CommitmentConfig::confirmed())
+            .await;
+        assert!(matches!(outcome, Ok(SendOutcome::Confirmed(s)) if s == tx.signature()));
+    }
+
+    #[tokio::test]
+    async fn test_send_and_confirm_expires_past_last_valid_block_height() {
+        let tx = signed_transfer_tx();
+        let mut mocks = MocksMap::default();
+        mocks.insert(
+            RpcRequest::IsBlockhashValid,
+            serde_json::json!({"context": {"slot": 1}, "value": true}),
+        );
+        // The mock's latest blockhash is valid up to block height 1234
+        mocks.insert(RpcRequest::GetBlockHeight, serde_json::json!(1235));
+        for _ in 0..20 {
+            mocks.insert(
+                RpcRequest::GetSignatureStatuses,
+                signature_statuses(serde_json::Value::Null),
+            );
+        }
+        let rpc_client = RpcClient::new_mock_with_mocks_map("succeeds", mocks);
+
+        let outcome = tx
+            .send_and_confirm(&rpc_client, CommitmentConfig::confirmed())
+            .await;
+        assert!(matches!(outcome, Ok(SendOutcome::Expired(s)) if s == tx.signature()));
+    }
+
+    #[test]
+    fn test_advance_nonce_instruction_detection() {
+        use solana_sdk::instruction::{AccountMeta, Instruction};
+        use solana_sdk::message::{Message, VersionedMessage};
+
+        let fee_payer = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let nonce_authority = Pubkey::new_unique();
+        let advance_nonce = Instruction::new_with_bincode(
+            solana_system_interface::program::ID,
+            &SystemInstruction::AdvanceNonceAccount,
+            vec![
+                AccountMeta::new(nonce_account, false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(nonce_authority, true),
+            ],
+        );
+        let message = Message::new(&[advance_nonce], Some(&fee_payer));
+        let tx = TransactionInt::new(VersionedTransaction {
+            signatures: vec![],
+            message: VersionedMessage::Legacy(message),
+        });
+        let detected = tx
+            .advance_nonce_instruction()
+            .unwrap()
+            .expect("durable nonce transaction");
+        assert_eq!(detected.nonce_account, nonce_account);
+        assert_eq!(detected.nonce_authority, nonce_authority);
+
+        let tx = system_transfer_tx(&fee_payer, &Pubkey::new_unique(), &nonce_account, 1);
+        assert!(tx.advance_nonce_instruction().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_priority_fee_policy_recommend() {
+        let policy = PriorityFeePolicy {
+            percentile: 75,
+            floor: 0,
+        };
+        assert_eq!(policy.recommend(&[]), 0);
+        assert_eq!(policy.recommend(&[40, 10, 30, 20]), 30);
+        assert_eq!(policy.recommend(&[0, 0, 0, 500]), 0);
+
+        let median = PriorityFeePolicy {
+            percentile: 50,
+            floor: 0,
+        };
+        assert_eq!(median.recommend(&[5, 1, 3]), 3);
+
+        let floored = PriorityFeePolicy {
+            percentile: 100,
+            floor: 1_000,
+        };
+        assert_eq!(floored.recommend(&[]), 1_000);
+        assert_eq!(floored.recommend(&[10, 20]), 1_000);
+        assert_eq!(floored.recommend(&[10, 5_000]), 5_000);
+
+        let lowest = PriorityFeePolicy {
+            percentile: 0,
+            floor: 0,
+        };
+        assert_eq!(lowest.recommend(&[7, 3, 9]), 3);
+    }
+
+    #[test]
+    fn test_try_new_rejects_missing_rpc_endpoints() {
+        let error = SolanaProvider::try_new(
+            Keypair::new(),
+            &[],
+            Network::SolanaDevnet,
+            200_000,
+            100_000,
+            MintExtensionPolicy::default(),
+            PriorityFeePolicy::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to connect to solana-devnet: No RPC endpoint configured"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_supported_serves_cached_compute_unit_price() {
+        let fees = serde_json::json!([
+            {"slot": 1, "prioritizationFee": 100},
+            {"slot": 2, "prioritizationFee": 200},
+            {"slot": 3, "prioritizationFee": 300},
+            {"slot": 4, "prioritizationFee": 400},
+        ]);
+        let mut provider = test_provider().with_rpc_client(RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            [(RpcRequest::GetRecentPrioritizationFees, fees)].into(),
+        ));
+        let advertised = |supported: SupportedPaymentKindsResponse| {
+            supported.kinds[0]
+                .extra
+                .as_ref()
+                .and_then(|extra| extra.recommended_compute_unit_price)
+        };
+
+        assert_eq!(advertised(provider.supported().await.unwrap()), Some(300));
+
+        // Served without RPC while fresh, and while stale until a refresh succeeds
+        provider.rpc_client = Arc::new(RpcClient::new_mock("fails".to_string()));
+        assert_eq!(advertised(provider.supported().await.unwrap()), Some(300));
+        provider.advertised_price.lock().unwrap().price =
+            Some((300, Instant::now() - 2 * PRIORITY_FEE_CACHE_TTL));
+        assert_eq!(advertised(provider.supported().await.unwrap()), Some(300));
+
+        // A new policy invalidates the advertised price
+        provider.set_policy(SolanaPolicy::clone(&provider.policy()));
+        while provider.advertised_price.lock().unwrap().refreshing {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(advertised(provider.supported().await.unwrap()), None);
+    }
+
+    #[test]
+    fn test_solana_policy_rejects_invalid_settings() {
+        let lookup = |name: &'static str, value: &'static str| {
+            move |key: &str| (key == name).then(|| value.to_string())
+        };
+
+        let policy = SolanaPolicy::from_lookup(
+            Network::Solana,
+            lookup("X402_SOLANA_PRIORITY_FEE_PERCENTILE_SOLANA", "90"),
+        )
+        .unwrap();
+        assert_eq!(policy.priority_fee_policy.percentile, 90);
+        assert_eq!(policy.priority_fee_policy.floor, 1_000);
+        let policy = SolanaPolicy::from_lookup(
+            Network::SolanaDevnet,
+            lookup("X402_SOLANA_MAX_COMPUTE_UNIT_LIMIT_SOLANA_DEVNET", "300000"),
+        )
+        .unwrap();
+        assert_eq!(policy.max_compute_unit_limit, 300_000);
+        assert_eq!(policy.max_compute_unit_price, 100_000);
+
+        for (name, value) in [
+            ("X402_SOLANA_PRIORITY_FEE_PERCENTILE_SOLANA", "101"),
+            ("X402_SOLANA_PRIORITY_FEE_PERCENTILE_SOLANA", "high"),
+            ("X402_SOLANA_PRIORITY_FEE_FLOOR_SOLANA", "-1"),
+            ("X402_SOLANA_MAX_COMPUTE_UNIT_LIMIT_SOLANA", "400k"),
+            ("X402_SOLANA_MAX_COMPUTE_UNIT_PRICE_SOLANA", "-5"),
+        ] {
+            let error = SolanaPolicy::from_lookup(Network::Solana, lookup(name, value))
+                .expect_err(value)
+                .to_string();
+            assert!(error.contains(name), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_mint_extension_policy_parsing() {
+        let policy = MintExtensionPolicy::from_str("transfer-fee, transfer-hook").unwrap();
+        assert!(policy.is_allowed(MintExtension::TransferFee));
+        assert!(policy.is_allowed(MintExtension::TransferHook));
+        assert!(!policy.is_allowed(MintExtension::PermanentDelegate));
+        assert_eq!(policy.to_string(), "transfer-fee,transfer-hook");
+
+        let none = MintExtensionPolicy::from_str("none").unwrap();
+        assert!(!none.is_allowed(MintExtension::TransferFee));
+
+        assert!(MintExtensionPolicy::from_str("transfer-fees").is_err());
+        assert!(MintExtensionPolicy::default().is_allowed(MintExtension::TransferFee));
+    }
+
+    #[test]
+    fn test_lookup_table_cache_misses_on_short_entry() {
+        let cache = AddressLookupTableCache::new(Duration::from_secs(60));
+        let table = Pubkey::new_unique();
+        cache.insert(table, Arc::new(vec![Pubkey::new_unique()]));
+
+        assert!(cache.get(&table, 1).is_some());
+        assert!(cache.get(&table, 2).is_none());
+    }
+}
@@ -0,0 +1,445 @@
+//! Command-line interface of the facilitator binary.
+//!
+//! Besides `serve`, the default, operator commands run against the providers configured for the
+//! server, and print their results as JSON:
+//! - `verify <payload.json> --requirements <requirements.json>` – run [`Facilitator::verify`] on a
+//!   payment payload, without settling
+//! - `decode-payment <base64>` – decode an `X-PAYMENT` header, including a Solana transaction
+//! - `signers` – signer addresses and native balances per network
+//! - `tx-status <hash> [--network <network>]` – status of a settlement transaction
+//! - `supported` – supported payment kinds
+//! - `config check` – the resolved configuration, secrets redacted
+
+use alloy::hex;
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+use solana_sdk::message::v0::MessageAddressTableLookup;
+use solana_sdk::transaction::VersionedTransaction;
+use std::path::{Path, PathBuf};
+
+use crate::chain::SignerBalanceQuery;
+use crate::facilitator::Facilitator;
+use crate::facilitator_local::FacilitatorLocal;
+use crate::network::Network;
+use crate::provider_cache::ProviderCache;
+use crate::types::{
+    Base64Bytes, ExactPaymentPayload, MixedAddress, PaymentPayload, PaymentRequirements,
+    TokenAmount, TransactionHash, VerifyRequest,
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML or YAML configuration file. Environment variables override its values.
+    #[arg(long, global = true, env = "X402_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the HTTP server (default).
+    Serve,
+    /// Decode a base64 `X-PAYMENT` header into its payment payload.
+    DecodePayment { payment: String },
+    #[command(flatten)]
+    Provider(ProviderCommand),
+    /// Inspect the configuration.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+/// Commands run against the configured providers, see [`run`].
+#[derive(Subcommand)]
+pub enum ProviderCommand {
+    /// Verify a payment offline: the file holds a payment payload, as JSON.
+    Verify {
+        payload: PathBuf,
+        /// File holding the payment requirements to verify the payload against, as JSON.
+        #[arg(long)]
+        requirements: PathBuf,
+    },
+    /// List signer addresses and their native balances per network.
+    Signers,
+    /// Query the status of a settlement transaction by its hash.
+    TxStatus {
+        hash: String,
+        /// Network to look the transaction up on, instead of all networks.
+        #[arg(long)]
+        network: Option<Network>,
+    },
+    /// List the supported payment kinds.
+    Supported,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Validate the configuration and print it resolved, with secrets redacted.
+    Check,
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Signer of a network, as listed by the `signers` command.
+#[derive(Serialize)]
+struct SignerEntry {
+    network: String,
+    address: MixedAddress,
+    /// In the smallest unit of the native coin: wei or lamports.
+    balance: TokenAmount,
+}
+
+/// Runs a command against the configured providers.
+pub async fn run(command: ProviderCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let provider_cache = ProviderCache::from_env().await?;
+    match command {
+        ProviderCommand::Verify {
+            payload,
+            requirements,
+        } => {
+            let request = verify_request(&payload, &requirements)?;
+            let facilitator = FacilitatorLocal::new(provider_cache);
+            print_json(&facilitator.verify(&request).await?)
+        }
+        ProviderCommand::Signers => {
+            let mut signers = Vec::new();
+            for (network, provider) in &provider_cache {
+                for (address, balance) in provider.signer_balances().await? {
+                    signers.push(SignerEntry {
+                        network: network.to_string(),
+                        address,
+                        balance,
+                    });
+                }
+            }
+            signers.sort_by(|a, b| a.network.cmp(&b.network));
+            print_json(&signers)
+        }
+        ProviderCommand::TxStatus { hash, network } => {
+            let hash: TransactionHash = serde_json::from_value(serde_json::Value::String(hash))
+                .map_err(|_| "expected an EVM (0x-prefixed hex) or Solana (base58) hash")?;
+            let facilitator = FacilitatorLocal::new(provider_cache);
+            let status = match network {
+                Some(network) => {
+                    facilitator
+                        .get_network_transaction_status(network, &hash)
+                        .await?
+                }
+                None => facilitator.get_transaction_status(&hash).await?,
+            };
+            print_json(&status)
+        }
+        ProviderCommand::Supported => {
+            let facilitator = FacilitatorLocal::new(provider_cache);
+            print_json(&facilitator.supported().await?)
+        }
+    }
+}
+
+/// Reads the verify request of a payment payload and the requirements it pays, as JSON files.
+fn verify_request(
+    payload: &Path,
+    requirements: &Path,
+) -> Result<VerifyRequest, Box<dyn std::error::Error>> {
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(|e| format!("Can not read {}: {e}", path.display()))
+    };
+    let payment_payload: PaymentPayload = serde_json::from_slice(&read(payload)?)
+        .map_err(|e| format!("Invalid payment payload {}: {e}", payload.display()))?;
+    let payment_requirements: PaymentRequirements = serde_json::from_slice(&read(requirements)?)
+        .map_err(|e| {
+            format!(
+                "Invalid payment requirements {}: {e}",
+                requirements.display()
+            )
+        })?;
+    Ok(VerifyRequest {
+        x402_version: payment_payload.x402_version,
+        payment_payload,
+        payment_requirements,
+    })
+}
+
+/// Prints the payment payload encoded in `payment`, with its Solana transaction decoded.
+pub fn decode_payment(payment: &str) -> Result<(), Box<dyn std::error::Error>> {
+    print_json(&describe_payment(payment)?)
+}
+
+/// The payment payload encoded in `payment`, with its Solana transaction decoded as
+/// `decodedTransaction`.
+fn describe_payment(payment: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let payload = PaymentPayload::try_from(Base64Bytes::from(payment.trim().as_bytes()))?;
+    let mut output = serde_json::to_value(&payload)?;
+    if let ExactPaymentPayload::Solana(solana) = &payload.payload {
+        let bytes = Base64Bytes::from(solana.transaction.as_bytes()).decode()?;
+        let transaction = bincode::deserialize::<VersionedTransaction>(&bytes)?;
+        output["decodedTransaction"] = describe_transaction(&transaction);
+    }
+    Ok(output)
+}
+
+/// A readable view of a Solana transaction: keys in base58, instruction data in hex.
+///
+/// Instruction accounts are resolved to their keys. Those loaded from an address lookup table,
+/// which takes an RPC to resolve, are shown as `<table>[<index>]`.
+fn describe_transaction(transaction: &VersionedTransaction) -> serde_json::Value {
+    let message = &transaction.message;
+    let account_keys = message.static_account_keys();
+    let lookup_tables = message.address_table_lookups().unwrap_or_default();
+    // Loaded addresses follow the static keys: the writable ones of every table, then the
+    // read-only ones
+    let loaded = |indexes: fn(&MessageAddressTableLookup) -> &Vec<u8>| {
+        lookup_tables.iter().flat_map(move |lookup| {
+            indexes(lookup)
+                .iter()
+                .map(|index| format!("{}[{index}]", lookup.account_key))
+        })
+    };
+    let loaded_keys =
+        loaded(|lookup| &lookup.writable_indexes).chain(loaded(|lookup| &lookup.readonly_indexes));
+    let keys: Vec<String> = account_keys
+        .iter()
+        .map(ToString::to_string)
+        .chain(loaded_keys)
+        .collect();
+    let key = |index: u8| keys.get(index as usize).cloned();
+    let instructions: Vec<_> = message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            json!({
+                "programId": key(instruction.program_id_index),
+                "accounts": instruction.accounts.iter().map(|index| key(*index)).collect::<Vec<_>>(),
+                "data": hex::encode(&instruction.data),
+            })
+        })
+        .collect();
+    let lookups: Vec<_> = lookup_tables
+        .iter()
+        .map(|lookup| {
+            json!({
+                "accountKey": lookup.account_key.to_string(),
+                "writableIndexes": lookup.writable_indexes,
+                "readonlyIndexes": lookup.readonly_indexes,
+            })
+        })
+        .collect();
+    json!({
+        "signatures": transaction
+            .signatures
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        "recentBlockhash": message.recent_blockhash().to_string(),
+        "accountKeys": account_keys.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        "instructions": instructions,
+        "addressTableLookups": lookups,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_fixtures::settle_request;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::message::{Message, MessageHeader, VersionedMessage, v0};
+    use solana_sdk::pubkey::Pubkey;
+    use solana_system_interface::instruction::SystemInstruction;
+
+    fn encode_payment(payload: serde_json::Value) -> String {
+        let bytes = Base64Bytes::encode(serde_json::to_vec(&payload).unwrap());
+        String::from_utf8(bytes.as_ref().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_cli_parses_operator_commands() {
+        let cli = Cli::try_parse_from([
+            "x402-facilitator",
+            "verify",
+            "payload.json",
+            "--requirements",
+            "requirements.json",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Provider(ProviderCommand::Verify { payload, requirements }))
+                if payload == Path::new("payload.json") && requirements == Path::new("requirements.json")
+        ));
+        assert!(Cli::try_parse_from(["x402-facilitator", "verify", "payload.json"]).is_err());
+
+        let cli = Cli::try_parse_from([
+            "x402-facilitator",
+            "tx-status",
+            "0x01",
+            "--network",
+            "monad",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Provider(ProviderCommand::TxStatus {
+                network: Some(Network::Monad),
+                ..
+            }))
+        ));
+        let cli = Cli::try_parse_from(["x402-facilitator", "decode-payment", "e30="]).unwrap();
+        assert!(matches!(cli.command, Some(Command::DecodePayment { .. })));
+        let cli = Cli::try_parse_from(["x402-facilitator"]).unwrap();
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn test_verify_request_reads_payload_and_requirements() {
+        let dir = std::env::temp_dir();
+        let payload = dir.join(format!("x402-cli-payload-{}.json", std::process::id()));
+        let requirements = dir.join(format!("x402-cli-requirements-{}.json", std::process::id()));
+        let request = settle_request(1_000);
+        let payment_payload = serde_json::to_value(&request.payment_payload).unwrap();
+        let payment_requirements = serde_json::to_value(&request.payment_requirements).unwrap();
+        std::fs::write(&payload, payment_payload.to_string()).unwrap();
+        std::fs::write(&requirements, payment_requirements.to_string()).unwrap();
+
+        let request = verify_request(&payload, &requirements).unwrap();
+        assert_eq!(request.payment_payload.network, Network::SolanaDevnet);
+        assert_eq!(
+            request.payment_requirements.max_amount_required,
+            TokenAmount::from(1_000u64)
+        );
+
+        // A full verify request is not a payment payload
+        let verify_request_body = json!({
+            "x402Version": 1,
+            "paymentPayload": payment_payload,
+            "paymentRequirements": payment_requirements,
+        });
+        std::fs::write(&payload, verify_request_body.to_string()).unwrap();
+        let error = verify_request(&payload, &requirements).unwrap_err();
+        assert!(
+            error.to_string().starts_with("Invalid payment payload"),
+            "{error}"
+        );
+        std::fs::remove_file(&payload).unwrap();
+        std::fs::remove_file(&requirements).unwrap();
+    }
+
+    #[test]
+    fn test_describe_payment_decodes_solana_transaction() {
+        let fee_payer = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let seller = Pubkey::new_unique();
+        let instruction = Instruction::new_with_bincode(
+            solana_system_interface::program::ID,
+            &SystemInstruction::Transfer { lamports: 5_000 },
+            vec![
+                AccountMeta::new(buyer, true),
+                AccountMeta::new(seller, false),
+            ],
+        );
+        let message = Message::new(&[instruction], Some(&fee_payer));
+        let transaction = VersionedTransaction {
+            signatures: vec![Default::default(); 2],
+            message: VersionedMessage::Legacy(message),
+        };
+        let transaction = Base64Bytes::encode(bincode::serialize(&transaction).unwrap());
+        let payment = encode_payment(json!({
+            "x402Version": 1,
+            "scheme": "exact",
+            "network": "solana-devnet",
+            "payload": {"transaction": String::from_utf8(transaction.as_ref().to_vec()).unwrap()}
+        }));
+
+        let output = describe_payment(&payment).unwrap();
+        let instruction = &output["decodedTransaction"]["instructions"][0];
+        assert_eq!(
+            instruction["programId"],
+            solana_system_interface::program::ID.to_string()
+        );
+        assert_eq!(
+            instruction["accounts"],
+            json!([buyer.to_string(), seller.to_string()])
+        );
+        // Transfer discriminant, then 5_000 lamports, little endian
+        assert_eq!(instruction["data"], "020000008813000000000000");
+        assert_eq!(output["network"], "solana-devnet");
+
+        assert!(describe_payment("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_describe_payment_leaves_evm_payload_as_is() {
+        let payload = json!({
+            "x402Version": 1,
+            "scheme": "exact",
+            "network": "monad-testnet",
+            "payload": {
+                "signature": format!("0x{}", "11".repeat(65)),
+                "authorization": {
+                    "from": "0x0000000000000000000000000000000000000004",
+                    "to": "0x1111111111111111111111111111111111111111",
+                    "value": "1000",
+                    "validAfter": "0",
+                    "validBefore": "4102444800",
+                    "nonce": format!("0x{}", "09".repeat(32))
+                }
+            }
+        });
+        let output = describe_payment(&encode_payment(payload.clone())).unwrap();
+        assert_eq!(output["payload"]["authorization"]["value"], "1000");
+        assert!(output.get("decodedTransaction").is_none());
+    }
+
+    #[test]
+    fn test_describe_transaction_names_lookup_table_accounts() {
+        let fee_payer = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let table = Pubkey::new_unique();
+        let message = v0::Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: vec![fee_payer, program],
+            recent_blockhash: Hash::new_unique(),
+            instructions: vec![solana_sdk::instruction::CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![0, 2, 3, 4],
+                data: vec![0xab],
+            }],
+            address_table_lookups: vec![MessageAddressTableLookup {
+                account_key: table,
+                writable_indexes: vec![7],
+                readonly_indexes: vec![1, 5],
+            }],
+        };
+        let transaction = VersionedTransaction {
+            signatures: vec![Default::default()],
+            message: VersionedMessage::V0(message),
+        };
+
+        let output = describe_transaction(&transaction);
+        let instruction = &output["instructions"][0];
+        assert_eq!(instruction["programId"], program.to_string());
+        assert_eq!(
+            instruction["accounts"],
+            json!([
+                fee_payer.to_string(),
+                format!("{table}[7]"),
+                format!("{table}[1]"),
+                format!("{table}[5]"),
+            ])
+        );
+        assert_eq!(instruction["data"], "ab");
+        assert_eq!(
+            output["addressTableLookups"][0]["writableIndexes"],
+            json!([7])
+        );
+    }
+}
@@ -0,0 +1,982 @@
+//! Typed configuration file for the facilitator.
+//!
+//! Every setting the facilitator reads from environment variables can also be set in a TOML or
+//! YAML file, passed with `--config`:
+//!
+//! ```toml
+//! [server]
+//! port = 8080
+//!
+//! [signer]
+//! type = "private-key"
+//! evm_private_key = "0x..."
+//!
+//! [networks.monad-testnet]
+//! rpc = "https://rpc-a.example|3,https://rpc-b.example"
+//! fee_bps = 25
+//!
+//! [networks.solana]
+//! rpc = "https://api.mainnet-beta.solana.com"
+//! max_compute_unit_price = 1000000
+//!
+//! [limits]
+//! settle_per_minute = 30
+//! ```
+//!
+//! Unknown keys are rejected, and every value is validated, whether it comes from the file or from
+//! the environment. Environment variables take precedence over the file. Once resolved, the
+//! configuration is exported back to the environment variables the rest of the crate reads, see
+//! [`Config::export_env`].
+//!
+//! The rate limits and the network policies (Solana compute unit caps, allowed mint extensions,
+//! priority fees and settle commitment, EVM gas price ceilings) can be reloaded at runtime with a
+//! [`ConfigReloader`]; other settings are applied at startup only.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use solana_commitment_config::CommitmentConfig;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use crate::admin_auth::ENV_ADMIN_API_TOKEN;
+use crate::chain::rpc_pool::parse_rpc_endpoints;
+use crate::chain::solana::MintExtensionPolicy;
+use crate::facilitator_local::FacilitatorLocal;
+use crate::from_env::{
+    ENV_EVM_PRIVATE_KEY, ENV_SIGNER_TYPE, ENV_SOLANA_PRIVATE_KEY, SignerType,
+    evm_confirmations_env_name, evm_max_gas_price_env_name, fee_env_name,
+    rpc_env_name_from_network,
+};
+use crate::network::{Network, NetworkFamily};
+use crate::pending_settlements::{
+    ENV_PENDING_SETTLEMENTS_PATH, ENV_SETTLE_DRAIN_TIMEOUT_SECS, ENV_SETTLEMENT_WEBHOOK_URL,
+};
+use crate::provider_cache::ProviderCache;
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::settlement_ledger::ENV_SETTLEMENT_LEDGER_PATH;
+use crate::types::{FacilitatorFee, MixedAddress};
+
+/// Placeholder printed instead of secret values.
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Can not read config file {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("Config file {0} must have a .toml, .yaml or .yml extension")]
+    UnsupportedFormat(PathBuf),
+    #[error("Invalid config file {0}: {1}")]
+    Parse(PathBuf, String),
+    #[error("Invalid value of env {0}: {1}")]
+    Env(String, String),
+    #[error("Invalid config value {0}: {1}")]
+    Invalid(String, String),
+}
+
+/// Format of a configuration file, derived from its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// A secret value, like a private key, never printed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{REDACTED}")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+/// HTTP server settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// `HOST`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<IpAddr>,
+    /// `PORT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// `ADMIN_API_TOKEN`, required by the operator routes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<Secret>,
+}
+
+/// Signer credentials, shared by all networks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignerConfig {
+    /// `SIGNER_TYPE`
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub signer_type: Option<SignerType>,
+    /// `EVM_PRIVATE_KEY`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evm_private_key: Option<Secret>,
+    /// `SOLANA_PRIVATE_KEY`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solana_private_key: Option<Secret>,
+}
+
+/// Settings of a single network. A network is served only if `rpc` is set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// `RPC_URL_*`: comma-separated endpoints, each optionally followed by `|<weight>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpc: Option<String>,
+    /// `X402_SOLANA_MAX_COMPUTE_UNIT_LIMIT_*`, Solana only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_compute_unit_limit: Option<u32>,
+    /// `X402_SOLANA_MAX_COMPUTE_UNIT_PRICE_*`, Solana only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_compute_unit_price: Option<u64>,
+    /// `X402_SOLANA_ALLOWED_MINT_EXTENSIONS_*`, Solana only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_mint_extensions: Option<String>,
+    /// `X402_SOLANA_PRIORITY_FEE_PERCENTILE_*`, Solana only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority_fee_percentile: Option<u8>,
+    /// `X402_SOLANA_PRIORITY_FEE_FLOOR_*`, Solana only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority_fee_floor: Option<u64>,
+    /// `X402_SOLANA_COMMITMENT_*`, Solana only: `processed`, `confirmed` or `finalized`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+    /// `X402_EVM_CONFIRMATIONS_*`, EVM only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<u64>,
+    /// `X402_EVM_MAX_GAS_PRICE_*`, EVM only: highest gas price, in wei, settlements pay.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_gas_price: Option<u64>,
+    /// `X402_FEE_BPS_*`: facilitator fee in basis points of the payment amount.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_bps: Option<u16>,
+    /// `X402_FEE_FLAT_*`: comma-separated flat facilitator fees, as `<asset>=<amount>` or `<amount>`
+    /// for any asset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_flat: Option<String>,
+    /// `X402_FEE_RECIPIENT_*`: receives the facilitator fees, the signer by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_recipient: Option<MixedAddress>,
+}
+
+/// Per-network settings, keyed by network name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworksConfig {
+    #[serde(rename = "monad", skip_serializing_if = "is_default")]
+    pub monad: NetworkConfig,
+    #[serde(rename = "monad-testnet", skip_serializing_if = "is_default")]
+    pub monad_testnet: NetworkConfig,
+    #[serde(rename = "solana", skip_serializing_if = "is_default")]
+    pub solana: NetworkConfig,
+    #[serde(rename = "solana-devnet", skip_serializing_if = "is_default")]
+    pub solana_devnet: NetworkConfig,
+}
+
+impl NetworksConfig {
+    pub fn by_network(&self, network: Network) -> &NetworkConfig {
+        match network {
+            Network::Monad => &self.monad,
+            Network::MonadTestnet => &self.monad_testnet,
+            Network::Solana => &self.solana,
+            Network::SolanaDevnet => &self.solana_devnet,
+        }
+    }
+
+    fn iter_mut(&mut self) -> [(Network, &mut NetworkConfig); 4] {
+        [
+            (Network::Monad, &mut self.monad),
+            (Network::MonadTestnet, &mut self.monad_testnet),
+            (Network::Solana, &mut self.solana),
+            (Network::SolanaDevnet, &mut self.solana_devnet),
+        ]
+    }
+}
+
+/// Rate limits and timeouts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// `RATE_LIMIT_VERIFY_PER_MINUTE`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify_per_minute: Option<u32>,
+    /// `RATE_LIMIT_SETTLE_PER_MINUTE`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settle_per_minute: Option<u32>,
+    /// `RATE_LIMIT_TRANSACTION_STATUS_PER_MINUTE`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_status_per_minute: Option<u32>,
+    /// `RATE_LIMIT_GENERAL_PER_MINUTE`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub general_per_minute: Option<u32>,
+    /// `TX_RECEIPT_TIMEOUT_SECS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_receipt_timeout_secs: Option<u64>,
+}
+
+/// Settlements in flight at shutdown, their reconciliation at the next start, and their history.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SettlementsConfig {
+    /// `SETTLE_DRAIN_TIMEOUT_SECS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drain_timeout_secs: Option<u64>,
+    /// `PENDING_SETTLEMENTS_PATH`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_path: Option<String>,
+    /// `SETTLEMENT_WEBHOOK_URL`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// `SETTLEMENT_LEDGER_PATH`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ledger_path: Option<String>,
+}
+
+/// OpenTelemetry export settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
+    /// `OTEL_EXPORTER_OTLP_HEADERS`, may carry API keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub otlp_headers: Option<Secret>,
+    /// `OTEL_EXPORTER_OTLP_PROTOCOL`: `http/protobuf` or `grpc`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub otlp_protocol: Option<String>,
+    /// `OTEL_SERVICE_NAME`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    /// `OTEL_SERVICE_VERSION`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_version: Option<String>,
+    /// `OTEL_SERVICE_DEPLOYMENT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_deployment: Option<String>,
+}
+
+/// The whole facilitator configuration.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "is_default")]
+    pub server: ServerConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub signer: SignerConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub networks: NetworksConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub limits: LimitsConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub settlements: SettlementsConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub telemetry: TelemetryConfig,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// A configuration value backed by an environment variable.
+trait Setting {
+    fn set(&mut self, value: &str) -> Result<(), String>;
+    fn get(&self) -> Option<String>;
+}
+
+impl<T> Setting for Option<T>
+where
+    T: FromStr + Display,
+    T::Err: Display,
+{
+    fn set(&mut self, value: &str) -> Result<(), String> {
+        *self = Some(T::from_str(value.trim()).map_err(|e| e.to_string())?);
+        Ok(())
+    }
+
+    fn get(&self) -> Option<String> {
+        self.as_ref().map(T::to_string)
+    }
+}
+
+impl Setting for Option<Secret> {
+    fn set(&mut self, value: &str) -> Result<(), String> {
+        *self = Some(Secret(value.to_string()));
+        Ok(())
+    }
+
+    fn get(&self) -> Option<String> {
+        self.as_ref().map(|secret| secret.expose().to_string())
+    }
+}
+
+/// Loads the configuration, then reloads it on demand against the environment as it was when
+/// the loader was created.
+///
+/// [`Config::export_env`] writes the resolved values to the environment, so reloading against
+/// the live environment would let them shadow later edits of the file.
+#[derive(Clone)]
+pub struct ConfigLoader {
+    path: Option<PathBuf>,
+    env: HashMap<String, String>,
+}
+
+impl ConfigLoader {
+    /// Captures the environment variables backing the configuration.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let env = Config::default()
+            .settings()
+            .into_iter()
+            .filter_map(|(name, _)| std::env::var(&name).ok().map(|value| (name, value)))
+            .collect();
+        Self { path, env }
+    }
+
+    /// Reads the configuration file, if any, overlays the captured environment variables on it,
+    /// and validates the result.
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        let mut config = match &self.path {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        config.overlay(|name| self.env.get(name).cloned())?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Applies reloaded configurations to a running facilitator: the network policies of its
+/// providers, see [`ProviderCache::apply_policies`], and the limits of its [`RateLimiter`].
+///
+/// Reloads are requested with SIGHUP, or on `POST /config/reload`.
+pub struct ConfigReloader {
+    loader: ConfigLoader,
+    facilitator: Arc<FacilitatorLocal<ProviderCache>>,
+    rate_limiter: Arc<RateLimiter>,
+    config: Mutex<Config>,
+}
+
+impl ConfigReloader {
+    /// Creates a reloader of a facilitator started with `config`, as loaded by `loader`.
+    pub fn new(
+        loader: ConfigLoader,
+        config: Config,
+        facilitator: Arc<FacilitatorLocal<ProviderCache>>,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Self {
+        Self {
+            loader,
+            facilitator,
+            rate_limiter,
+            config: Mutex::new(config),
+        }
+    }
+
+    /// Loads the configuration again and applies its reloadable part. An invalid configuration
+    /// is rejected, and the current one kept.
+    ///
+    /// Returns whether the new configuration also changes settings applied on restart only.
+    pub fn reload(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.apply(self.loader.load()?)
+    }
+
+    fn apply(&self, reloaded: Config) -> Result<bool, Box<dyn std::error::Error>> {
+        // Held throughout, so that concurrent reloads apply one configuration each
+        let mut config = self.config.lock().expect("config lock poisoned");
+        let env = reloaded.env_vars();
+        let rate_limits = RateLimitConfig::from_lookup(|name| env.get(name).cloned())?;
+        self.facilitator.provider_map().apply_policies(&reloaded)?;
+        if self.rate_limiter.config() != rate_limits {
+            self.rate_limiter.set_config(rate_limits);
+        }
+        let requires_restart = config.requires_restart(&reloaded);
+        *config = reloaded;
+        Ok(requires_restart)
+    }
+}
+
+impl Config {
+    /// Parses a configuration file, without environment overrides.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let format = ConfigFormat::from_path(path)
+            .ok_or_else(|| ConfigError::UnsupportedFormat(path.to_path_buf()))?;
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+        Self::parse(&contents, format).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    pub fn parse(contents: &str, format: ConfigFormat) -> Result<Self, String> {
+        match format {
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Every setting, along with the environment variable that backs it.
+    fn settings(&mut self) -> Vec<(String, &mut dyn Setting)> {
+        let common: [(&str, &mut dyn Setting); 21] = [
+            ("HOST", &mut self.server.host),
+            ("PORT", &mut self.server.port),
+            (ENV_ADMIN_API_TOKEN, &mut self.server.admin_token),
+            (ENV_SIGNER_TYPE, &mut self.signer.signer_type),
+            (ENV_EVM_PRIVATE_KEY, &mut self.signer.evm_private_key),
+            (ENV_SOLANA_PRIVATE_KEY, &mut self.signer.solana_private_key),
+            (
+                "RATE_LIMIT_VERIFY_PER_MINUTE",
+                &mut self.limits.verify_per_minute,
+            ),
+            (
+                "RATE_LIMIT_SETTLE_PER_MINUTE",
+                &mut self.limits.settle_per_minute,
+            ),
+            (
+                "RATE_LIMIT_TRANSACTION_STATUS_PER_MINUTE",
+                &mut self.limits.transaction_status_per_minute,
+            ),
+            (
+                "RATE_LIMIT_GENERAL_PER_MINUTE",
+                &mut self.limits.general_per_minute,
+            ),
+            (
+                "TX_RECEIPT_TIMEOUT_SECS",
+                &mut self.limits.tx_receipt_timeout_secs,
+            ),
+            (
+                ENV_SETTLE_DRAIN_TIMEOUT_SECS,
+                &mut self.settlements.drain_timeout_secs,
+            ),
+            (
+                ENV_PENDING_SETTLEMENTS_PATH,
+                &mut self.settlements.pending_path,
+            ),
+            (
+                ENV_SETTLEMENT_WEBHOOK_URL,
+                &mut self.settlements.webhook_url,
+            ),
+            (
+                ENV_SETTLEMENT_LEDGER_PATH,
+                &mut self.settlements.ledger_path,
+            ),
+            (
+                "OTEL_EXPORTER_OTLP_ENDPOINT",
+                &mut self.telemetry.otlp_endpoint,
+            ),
+            (
+                "OTEL_EXPORTER_OTLP_HEADERS",
+                &mut self.telemetry.otlp_headers,
+            ),
+            (
+                "OTEL_EXPORTER_OTLP_PROTOCOL",
+                &mut self.telemetry.otlp_protocol,
+            ),
+            ("OTEL_SERVICE_NAME", &mut self.telemetry.service_name),
+            ("OTEL_SERVICE_VERSION", &mut self.telemetry.service_version),
+            (
+                "OTEL_SERVICE_DEPLOYMENT",
+                &mut self.telemetry.service_deployment,
+            ),
+        ];
+        let mut settings: Vec<(String, &mut dyn Setting)> = common
+            .into_iter()
+            .map(|(name, setting)| (name.to_string(), setting))
+            .collect();
+        for (network, config) in self.networks.iter_mut() {
+            settings.push((
+                rpc_env_name_from_network(network).to_string(),
+                &mut config.rpc,
+            ));
+            let fee: [(&str, &mut dyn Setting); 3] = [
+                ("BPS", &mut config.fee_bps),
+                ("FLAT", &mut config.fee_flat),
+                ("RECIPIENT", &mut config.fee_recipient),
+            ];
+            settings.extend(
+                fee.into_iter()
+                    .map(|(name, setting)| (fee_env_name(name, network), setting)),
+            );
+            if let NetworkFamily::Evm = NetworkFamily::from(network) {
+                settings.push((
+                    evm_confirmations_env_name(network),
+                    &mut config.confirmations,
+                ));
+                settings.push((
+                    evm_max_gas_price_env_name(network),
+                    &mut config.max_gas_price,
+                ));
+            }
+            if let NetworkFamily::Solana = NetworkFamily::from(network) {
+                let suffix = env_suffix(network);
+                let solana: [(&str, &mut dyn Setting); 6] = [
+                    ("MAX_COMPUTE_UNIT_LIMIT", &mut config.max_compute_unit_limit),
+                    ("MAX_COMPUTE_UNIT_PRICE", &mut config.max_compute_unit_price),
+                    (
+                        "ALLOWED_MINT_EXTENSIONS",
+                        &mut config.allowed_mint_extensions,
+                    ),
+                    (
+                        "PRIORITY_FEE_PERCENTILE",
+                        &mut config.priority_fee_percentile,
+                    ),
+                    ("PRIORITY_FEE_FLOOR", &mut config.priority_fee_floor),
+                    ("COMMITMENT", &mut config.commitment),
+                ];
+                settings.extend(
+                    solana
+                        .into_iter()
+                        .map(|(name, setting)| (format!("X402_SOLANA_{name}_{suffix}"), setting)),
+                );
+            }
+        }
+        settings
+    }
+
+    /// Overrides settings with the variables found by `lookup`, usually the environment.
+    pub fn overlay(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        for (name, setting) in self.settings() {
+            if let Some(value) = lookup(&name) {
+                setting
+                    .set(&value)
+                    .map_err(|e| ConfigError::Env(name.clone(), e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks values that are typed as strings, and settings that do not apply to a network.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for network in Network::variants() {
+            let config = self.networks.by_network(*network);
+            let key = |field: &str| format!("networks.{network}.{field}");
+            if let Some(rpc) = &config.rpc {
+                parse_rpc_endpoints(rpc)
+                    .map_err(|e| ConfigError::Invalid(key("rpc"), e.to_string()))?;
+            }
+            if let Some(extensions) = &config.allowed_mint_extensions {
+                MintExtensionPolicy::from_str(extensions)
+                    .map_err(|e| ConfigError::Invalid(key("allowed_mint_extensions"), e))?;
+            }
+            if let Some(percentile) = config.priority_fee_percentile {
+                if percentile > 100 {
+                    return Err(ConfigError::Invalid(
+                        key("priority_fee_percentile"),
+                        format!("{percentile} is not a percentile"),
+                    ));
+                }
+            }
+            if let Some(commitment) = &config.commitment {
+                CommitmentConfig::from_str(commitment)
+                    .map_err(|e| ConfigError::Invalid(key("commitment"), e.to_string()))?;
+            }
+            if let Some(bps) = config.fee_bps {
+                if bps > 10_000 {
+                    return Err(ConfigError::Invalid(
+                        key("fee_bps"),
+                        format!("{bps} basis points is more than the whole payment"),
+                    ));
+                }
+            }
+            if let Some(flat) = &config.fee_flat {
+                FacilitatorFee::parse_flat(flat)
+                    .map_err(|e| ConfigError::Invalid(key("fee_flat"), e))?;
+            }
+            if let Some(recipient) = &config.fee_recipient {
+                if config.fee_bps.is_none() && config.fee_flat.is_none() {
+                    return Err(ConfigError::Invalid(
+                        key("fee_recipient"),
+                        "no `fee_bps` or `fee_flat` is set".to_string(),
+                    ));
+                }
+                let is_matching = match NetworkFamily::from(*network) {
+                    NetworkFamily::Evm => matches!(recipient, MixedAddress::Evm(_)),
+                    NetworkFamily::Solana => matches!(recipient, MixedAddress::Solana(_)),
+                };
+                if !is_matching {
+                    return Err(ConfigError::Invalid(
+                        key("fee_recipient"),
+                        format!("{recipient} is not an address on {network}"),
+                    ));
+                }
+            }
+            match NetworkFamily::from(*network) {
+                NetworkFamily::Evm => {
+                    let evm_only = NetworkConfig {
+                        rpc: config.rpc.clone(),
+                        confirmations: config.confirmations,
+                        max_gas_price: config.max_gas_price,
+                        fee_bps: config.fee_bps,
+                        fee_flat: config.fee_flat.clone(),
+                        fee_recipient: config.fee_recipient.clone(),
+                        ..NetworkConfig::default()
+                    };
+                    if *config != evm_only {
+                        return Err(ConfigError::Invalid(
+                            format!("networks.{network}"),
+                            "only `rpc`, `confirmations`, `max_gas_price` and the `fee_*` settings apply to EVM networks"
+                                .to_string(),
+                        ));
+                    }
+                }
+                NetworkFamily::Solana => {
+                    if config.confirmations.is_some() {
+                        return Err(ConfigError::Invalid(
+                            key("confirmations"),
+                            "applies to EVM networks, use `commitment` on Solana".to_string(),
+                        ));
+                    }
+                    if config.max_gas_price.is_some() {
+                        return Err(ConfigError::Invalid(
+                            key("max_gas_price"),
+                            "applies to EVM networks, use `max_compute_unit_price` on Solana"
+                                .to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(webhook_url) = &self.settlements.webhook_url {
+            url::Url::parse(webhook_url).map_err(|e| {
+                ConfigError::Invalid("settlements.webhook_url".to_string(), e.to_string())
+            })?;
+        }
+        if let Some(protocol) = &self.telemetry.otlp_protocol {
+            if !matches!(protocol.as_str(), "http/protobuf" | "http" | "grpc") {
+                return Err(ConfigError::Invalid(
+                    "telemetry.otlp_protocol".to_string(),
+                    format!("unknown protocol {protocol}, expected http/protobuf or grpc"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The environment variable of every configured setting, with its value.
+    pub fn env_vars(&self) -> HashMap<String, String> {
+        self.clone()
+            .settings()
+            .into_iter()
+            .filter_map(|(name, setting)| setting.get().map(|value| (name, value)))
+            .collect()
+    }
+
+    /// Sets the environment variable of every configured setting.
+    ///
+    /// The crate reads its settings from the environment, so this must run before any other
+    /// thread is started: setting environment variables is not thread-safe.
+    pub fn export_env(&self) {
+        for (name, value) in self.env_vars() {
+            // SAFETY: called at startup, before the runtime spawns any thread.
+            unsafe { std::env::set_var(name, value) };
+        }
+    }
+
+    /// Whether `other` differs from this configuration in settings that are only applied at
+    /// startup, that is anything but the rate limits and the network policies: the Solana
+    /// compute unit caps, mint extensions, priority fees and commitment, and the EVM gas price
+    /// ceilings.
+    pub fn requires_restart(&self, other: &Config) -> bool {
+        fn startup_settings(config: &Config) -> Config {
+            let mut config = config.clone();
+            config.limits = LimitsConfig {
+                tx_receipt_timeout_secs: config.limits.tx_receipt_timeout_secs,
+                ..LimitsConfig::default()
+            };
+            for (_, network) in config.networks.iter_mut() {
+                *network = NetworkConfig {
+                    rpc: network.rpc.take(),
+                    confirmations: network.confirmations,
+                    fee_bps: network.fee_bps,
+                    fee_flat: network.fee_flat.take(),
+                    fee_recipient: network.fee_recipient.take(),
+                    ..NetworkConfig::default()
+                };
+            }
+            config
+        }
+        startup_settings(self) != startup_settings(other)
+    }
+
+    /// The configuration as TOML, with secrets redacted.
+    pub fn to_redacted_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
+    }
+}
+
+/// Suffix of the per-network environment variables, like `SOLANA_DEVNET`.
+fn env_suffix(network: Network) -> String {
+    network.to_string().replace('-', "_").to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TokenAmount;
+
+    #[test]
+    fn test_config_rejects_unknown_keys() {
+        let error =
+            Config::parse("[limits]\nsettle_per_minut = 10\n", ConfigFormat::Toml).unwrap_err();
+        assert!(
+            error.contains("unknown field `settle_per_minut`"),
+            "{error}"
+        );
+        let error = Config::parse(
+            "networks: {solana: {rcp: 'https://a.example'}}\n",
+            ConfigFormat::Yaml,
+        )
+        .unwrap_err();
+        assert!(error.contains("unknown field `rcp`"), "{error}");
+        let error = Config::parse("networks: {base: {}}\n", ConfigFormat::Yaml).unwrap_err();
+        assert!(error.contains("unknown field `base`"), "{error}");
+    }
+
+    #[test]
+    fn test_yaml_config_parses_like_toml() {
+        let yaml = concat!(
+            "server: {port: 8080}\n",
+            "signer: {type: private-key, solana_private_key: abc}\n",
+            "networks:\n",
+            "  solana: {rpc: 'https://a.example|3,https://b.example', max_compute_unit_price: 10}\n",
+            "  monad-testnet: {confirmations: 2}\n",
+            "limits: {settle_per_minute: 30}\n",
+        );
+        let toml = concat!(
+            "[server]\nport = 8080\n",
+            "[signer]\ntype = \"private-key\"\nsolana_private_key = \"abc\"\n",
+            "[networks.solana]\n",
+            "rpc = \"https://a.example|3,https://b.example\"\nmax_compute_unit_price = 10\n",
+            "[networks.monad-testnet]\nconfirmations = 2\n",
+            "[limits]\nsettle_per_minute = 30\n",
+        );
+        let config = Config::parse(yaml, ConfigFormat::Yaml).unwrap();
+        assert_eq!(config, Config::parse(toml, ConfigFormat::Toml).unwrap());
+        config.validate().unwrap();
+        assert_eq!(config.server.port, Some(8080));
+        assert_eq!(config.signer.signer_type, Some(SignerType::PrivateKey));
+        assert_eq!(config.networks.solana.max_compute_unit_price, Some(10));
+        assert_eq!(config.networks.monad_testnet.confirmations, Some(2));
+
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("x402.yml")),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("x402.yaml")),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(ConfigFormat::from_path(Path::new("x402.json")), None);
+    }
+
+    #[test]
+    fn test_env_overrides_file_values() {
+        let toml =
+            "[networks.solana]\nmax_compute_unit_price = 10\n[limits]\nsettle_per_minute = 30\n";
+        let mut config = Config::parse(toml, ConfigFormat::Toml).unwrap();
+        config
+            .overlay(|name| {
+                (name == "X402_SOLANA_MAX_COMPUTE_UNIT_PRICE_SOLANA").then(|| "20".to_string())
+            })
+            .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.networks.solana.max_compute_unit_price, Some(20));
+        assert_eq!(config.limits.settle_per_minute, Some(30));
+
+        let error = config
+            .overlay(|name| (name == "RATE_LIMIT_SETTLE_PER_MINUTE").then(|| "ten".to_string()))
+            .unwrap_err();
+        assert!(
+            matches!(error, ConfigError::Env(name, _) if name == "RATE_LIMIT_SETTLE_PER_MINUTE")
+        );
+    }
+
+    #[test]
+    fn test_env_vars_lists_configured_settings_only() {
+        let toml = concat!(
+            "[signer]\nevm_private_key = \"0xabc\"\n",
+            "[networks.monad-testnet]\nconfirmations = 3\n",
+            "[telemetry]\nservice_deployment = \"config-test\"\n",
+        );
+        let config = Config::parse(toml, ConfigFormat::Toml).unwrap();
+        // Secrets are exported as they are, only printing redacts them
+        let expected: HashMap<String, String> = [
+            ("EVM_PRIVATE_KEY", "0xabc"),
+            ("X402_EVM_CONFIRMATIONS_MONAD_TESTNET", "3"),
+            ("OTEL_SERVICE_DEPLOYMENT", "config-test"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        assert_eq!(config.env_vars(), expected);
+    }
+
+    #[test]
+    fn test_config_check_redacts_secrets() {
+        let toml = concat!(
+            "[server]\nadmin_token = \"admin-secret\"\n",
+            "[signer]\ntype = \"private-key\"\n",
+            "evm_private_key = \"0xevm-secret\"\nsolana_private_key = \"solana-secret\"\n",
+            "[telemetry]\notlp_headers = \"x-api-key=otlp-secret\"\nservice_name = \"x402\"\n",
+        );
+        let config = Config::parse(toml, ConfigFormat::Toml).unwrap();
+        let printed = config.to_redacted_toml().unwrap();
+        for secret in [
+            "admin-secret",
+            "0xevm-secret",
+            "solana-secret",
+            "otlp-secret",
+        ] {
+            assert!(!printed.contains(secret), "{printed}");
+            assert!(!format!("{config:?}").contains(secret));
+        }
+        for line in [
+            "admin_token = \"<redacted>\"",
+            "evm_private_key = \"<redacted>\"",
+            "solana_private_key = \"<redacted>\"",
+            "otlp_headers = \"<redacted>\"",
+            "service_name = \"x402\"",
+        ] {
+            assert!(printed.contains(line), "{printed}");
+        }
+    }
+
+    #[test]
+    fn test_fee_settings_validate_and_apply_per_asset() {
+        let toml = concat!(
+            "[networks.solana-devnet]\n",
+            "fee_bps = 25\n",
+            "fee_flat = \"4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU=1000, 5\"\n",
+        );
+        let mut config = Config::parse(toml, ConfigFormat::Toml).unwrap();
+        config.validate().unwrap();
+
+        let flat = config.networks.solana_devnet.fee_flat.as_deref().unwrap();
+        let fee = FacilitatorFee {
+            recipient: MixedAddress::from_str("11111111111111111111111111111111").unwrap(),
+            bps: config.networks.solana_devnet.fee_bps,
+            flat: FacilitatorFee::parse_flat(flat).unwrap(),
+            exempt: Vec::new(),
+        };
+        let usdc = MixedAddress::from_str("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU").unwrap();
+        let other = MixedAddress::from_str("So11111111111111111111111111111111111111112").unwrap();
+        // 25 bps of 1_000_001 is 2_500.0025, rounded up
+        let amount = TokenAmount::from(1_000_001u64);
+        assert_eq!(fee.amount(&usdc, amount), TokenAmount::from(3_501u64));
+        assert_eq!(fee.amount(&other, amount), TokenAmount::from(2_506u64));
+        assert!(FacilitatorFee::parse_flat("5, 6").is_err());
+
+        config
+            .overlay(|name| {
+                (name == "X402_FEE_RECIPIENT_SOLANA_DEVNET")
+                    .then(|| "0x1111111111111111111111111111111111111111".to_string())
+            })
+            .unwrap();
+        let error = config.validate().unwrap_err();
+        assert!(
+            matches!(&error, ConfigError::Invalid(key, _) if key == "networks.solana-devnet.fee_recipient"),
+            "{error}"
+        );
+
+        config
+            .overlay(|name| (name == "X402_FEE_BPS_MONAD").then(|| "10001".to_string()))
+            .unwrap();
+        config.networks.solana_devnet.fee_recipient = None;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_requires_restart_ignores_reloadable_settings() {
+        let config = Config::parse(
+            "[networks.monad]\nrpc = \"https://a.example\"\n",
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        let mut reloaded = config.clone();
+        reloaded.limits.settle_per_minute = Some(5);
+        reloaded.networks.monad.max_gas_price = Some(100);
+        reloaded.networks.solana.max_compute_unit_price = Some(5_000);
+        reloaded.networks.solana.commitment = Some("finalized".to_string());
+        assert!(!config.requires_restart(&reloaded));
+
+        for change in [
+            |config: &mut Config| config.networks.monad.rpc = Some("https://b.example".to_string()),
+            |config: &mut Config| config.networks.monad.confirmations = Some(3),
+            |config: &mut Config| config.limits.tx_receipt_timeout_secs = Some(10),
+            |config: &mut Config| config.server.port = Some(9090),
+        ] {
+            let mut reloaded = reloaded.clone();
+            change(&mut reloaded);
+            assert!(config.requires_restart(&reloaded), "{reloaded:?}");
+        }
+    }
+
+    #[test]
+    fn test_config_reloader_applies_rate_limits() {
+        let rate_limiter = Arc::new(RateLimiter::new(None));
+        let reloader = ConfigReloader::new(
+            ConfigLoader::new(None),
+            Config::default(),
+            Arc::new(FacilitatorLocal::new(ProviderCache::from_iter([]))),
+            rate_limiter.clone(),
+        );
+        let mut config = Config::default();
+        config.limits.verify_per_minute = Some(10);
+        assert!(!reloader.apply(config.clone()).unwrap());
+        let limits = rate_limiter.config().unwrap();
+        assert_eq!(limits.verify_per_minute, 10);
+        assert_eq!(limits.settle_per_minute, 30);
+
+        // Startup settings are kept, and reported
+        config.server.port = Some(9090);
+        assert!(reloader.apply(config).unwrap());
+        let config = Config {
+            server: ServerConfig {
+                port: Some(9090),
+                ..ServerConfig::default()
+            },
+            limits: LimitsConfig {
+                verify_per_minute: Some(0),
+                settle_per_minute: Some(0),
+                transaction_status_per_minute: Some(0),
+                general_per_minute: Some(0),
+                tx_receipt_timeout_secs: None,
+            },
+            ..Config::default()
+        };
+        assert!(!reloader.apply(config).unwrap());
+        assert_eq!(rate_limiter.config(), None);
+    }
+}
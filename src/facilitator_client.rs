@@ -0,0 +1,402 @@
+//! Remote [`Facilitator`] reached over HTTP.
+//!
+//! [`FacilitatorClient`] calls the `/verify`, `/settle` and `/supported` endpoints of a hosted
+//! facilitator (such as one running this crate's binary), so seller code written against
+//! [`Facilitator`] can use a remote facilitator in place of a [`crate::facilitator_local::FacilitatorLocal`].
+//!
+//! ```ignore
+//! let facilitator = FacilitatorClient::try_new("https://facilitator.example.com".parse()?)?
+//!     .with_bearer_token("secret")?
+//!     .with_timeout(Duration::from_secs(10));
+//! let app = Router::new()
+//!     .route("/weather", get(weather))
+//!     .layer(X402Layer::new(facilitator).with_requirements(requirements));
+//! ```
+
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use url::Url;
+
+use crate::facilitator::Facilitator;
+use crate::types::{
+    ErrorResponse, FacilitatorErrorReason, SettleRequest, SettleResponse,
+    SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Default timeout for a single HTTP request to the facilitator.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default number of retries after the first attempt.
+const DEFAULT_MAX_RETRIES: u32 = 2;
+/// Delay before the first retry; doubled on each following retry.
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+/// Default time a `/supported` response is served from cache.
+const DEFAULT_SUPPORTED_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Errors returned by [`FacilitatorClient`].
+#[derive(Debug, thiserror::Error)]
+pub enum FacilitatorClientError {
+    #[error("Failed to build HTTP client: {0}")]
+    Client(#[source] reqwest::Error),
+    #[error("Invalid facilitator URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("Invalid header value: {0}")]
+    InvalidHeader(String),
+    #[error("Request to {url} failed: {source}")]
+    Http {
+        url: Url,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// The facilitator rejected the payment without naming a payer.
+    #[error("Payment rejected by facilitator: {0}")]
+    Rejected(FacilitatorErrorReason),
+    #[error("Facilitator responded with {status}: {error}")]
+    Status { status: StatusCode, error: String },
+    #[error("Failed to decode facilitator response: {0}")]
+    Decoding(String),
+}
+
+impl FacilitatorClientError {
+    /// Maps the error to the [`FacilitatorErrorReason`] a local facilitator would report.
+    pub fn reason(&self) -> FacilitatorErrorReason {
+        match self {
+            FacilitatorClientError::Rejected(reason) => reason.clone(),
+            FacilitatorClientError::Status { status, error } if status.is_client_error() => {
+                FacilitatorErrorReason::FreeForm(error.clone())
+            }
+            _ => FacilitatorErrorReason::UnexpectedSettleError,
+        }
+    }
+}
+
+impl IntoResponse for FacilitatorClientError {
+    fn into_response(self) -> Response {
+        match self {
+            FacilitatorClientError::Rejected(reason) => {
+                (StatusCode::OK, Json(VerifyResponse::invalid(None, reason))).into_response()
+            }
+            FacilitatorClientError::Status { status, error } => {
+                (status, Json(ErrorResponse { error })).into_response()
+            }
+            error => (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse {
+                    error: error.to_string(),
+                }),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// A [`Facilitator`] that forwards requests to a remote x402 facilitator over HTTP.
+///
+/// Requests that fail with a connection error, a timeout or a `5xx` status are retried with
+/// exponential backoff. `/settle` is retried only when the request did not reach the facilitator
+/// (a connection error or `503 Service Unavailable`), so a settlement is never submitted twice.
+///
+/// The `/supported` response is cached for [`Self::with_supported_cache_ttl`].
+#[derive(Clone, Debug)]
+pub struct FacilitatorClient {
+    base_url: Url,
+    http: Client,
+    headers: HeaderMap,
+    timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+    supported_cache_ttl: Duration,
+    supported: Arc<RwLock<Option<(Instant, SupportedPaymentKindsResponse)>>>,
+}
+
+impl FacilitatorClient {
+    /// Creates a client for the facilitator at `base_url`, e.g. `https://facilitator.example.com/`.
+    ///
+    /// Endpoints are resolved relative to `base_url`, so a path prefix like `/x402/` is preserved.
+    pub fn try_new(mut base_url: Url) -> Result<Self, FacilitatorClientError> {
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        let http = Client::builder()
+            .build()
+            .map_err(FacilitatorClientError::Client)?;
+        Ok(Self {
+            base_url,
+            http,
+            headers: HeaderMap::new(),
+            timeout: DEFAULT_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            supported_cache_ttl: DEFAULT_SUPPORTED_CACHE_TTL,
+            supported: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    /// The base URL endpoints are resolved against.
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Sets the timeout for each HTTP request, including each retry.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the number of retries after a failed attempt. `0` disables retries.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry. Each following retry waits twice as long.
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Sets how long a `/supported` response is cached. [`Duration::ZERO`] disables caching.
+    pub fn with_supported_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.supported_cache_ttl = ttl;
+        self
+    }
+
+    /// Adds a header sent with every request, e.g. an API key.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request.
+    pub fn with_bearer_token(self, token: &str) -> Result<Self, FacilitatorClientError> {
+        let mut value = HeaderValue::try_from(format!("Bearer {token}"))
+            .map_err(|e| FacilitatorClientError::InvalidHeader(e.to_string()))?;
+        value.set_sensitive(true);
+        Ok(self.with_header(AUTHORIZATION, value))
+    }
+
+    /// Sends a request to `path` and returns the response status and body.
+    ///
+    /// `idempotent` requests are retried on any `5xx` status or timeout; others are retried only
+    /// when the facilitator did not receive them.
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&VerifyRequest>,
+        idempotent: bool,
+    ) -> Result<(StatusCode, Vec<u8>), FacilitatorClientError> {
+        let url = self.base_url.join(path)?;
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .http
+                .request(method.clone(), url.clone())
+                .headers(self.headers.clone())
+                .timeout(self.timeout);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let result = request.send().await;
+            let retryable = match &result {
+                Ok(response) if idempotent => response.status().is_server_error(),
+                Ok(response) => response.status() == StatusCode::SERVICE_UNAVAILABLE,
+                Err(e) if idempotent => e.is_connect() || e.is_timeout(),
+                Err(e) => e.is_connect(),
+            };
+            if retryable && attempt < self.max_retries {
+                let backoff = self.retry_backoff * 2u32.saturating_pow(attempt);
+                attempt += 1;
+                tracing::debug!(%url, attempt, ?backoff, "Retrying facilitator request");
+                tokio::time::sleep(backoff).await;
+                continue;
+            }
+            let response = result.map_err(|source| FacilitatorClientError::Http {
+                url: url.clone(),
+                source,
+            })?;
+            let status = response.status();
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|source| FacilitatorClientError::Http { url, source })?;
+            return Ok((status, bytes.to_vec()));
+        }
+    }
+}
+
+impl Facilitator for FacilitatorClient {
+    type Error = FacilitatorClientError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let (status, body) = self
+            .request(Method::POST, "verify", Some(request), true)
+            .await?;
+        // Rejected payments come back as `isValid: false`, whatever the status code.
+        match serde_json::from_slice::<VerifyResponse>(&body) {
+            Ok(response) => Ok(response),
+            Err(e) => Err(status_error(status, &body, e)),
+        }
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let (status, body) = self
+            .request(Method::POST, "settle", Some(request), false)
+            .await?;
+        if let Ok(response) = serde_json::from_slice::<SettleResponse>(&body) {
+            return Ok(response);
+        }
+        // A facilitator that fails to re-verify the payment answers `/settle` with a verify response.
+        match serde_json::from_slice::<VerifyResponse>(&body) {
+            Ok(VerifyResponse::Invalid {
+                reason,
+                payer: Some(payer),
+            }) => Ok(SettleResponse {
+                success: false,
+                error_reason: Some(reason),
+                payer,
+                transaction: None,
+                network: request.network(),
+            }),
+            Ok(VerifyResponse::Invalid {
+                reason,
+                payer: None,
+            }) => Err(FacilitatorClientError::Rejected(reason)),
+            Ok(VerifyResponse::Valid { .. }) => Err(FacilitatorClientError::Decoding(
+                "unexpected verify response to /settle".to_string(),
+            )),
+            Err(e) => Err(status_error(status, &body, e)),
+        }
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        if let Some((fetched_at, supported)) = self.supported.read().await.as_ref() {
+            if fetched_at.elapsed() < self.supported_cache_ttl {
+                return Ok(supported.clone());
+            }
+        }
+        let (status, body) = self.request(Method::GET, "supported", None, true).await?;
+        if !status.is_success() {
+            return Err(status_error(status, &body, "unexpected status"));
+        }
+        let supported: SupportedPaymentKindsResponse = decode(&body)?;
+        if !self.supported_cache_ttl.is_zero() {
+            *self.supported.write().await = Some((Instant::now(), supported.clone()));
+        }
+        Ok(supported)
+    }
+}
+
+fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, FacilitatorClientError> {
+    serde_json::from_slice(body).map_err(|e| FacilitatorClientError::Decoding(e.to_string()))
+}
+
+/// Builds an error from a response that is not a protocol payload, preferring the
+/// facilitator's [`ErrorResponse`] message over the raw body.
+fn status_error(
+    status: StatusCode,
+    body: &[u8],
+    cause: impl std::fmt::Display,
+) -> FacilitatorClientError {
+    if status.is_success() {
+        return FacilitatorClientError::Decoding(cause.to_string());
+    }
+    let error = match serde_json::from_slice::<ErrorResponse>(body) {
+        Ok(response) => response.error,
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    };
+    FacilitatorClientError::Status { status, error }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::FacilitatorLocalError;
+    use crate::handlers;
+    use crate::network::Network;
+    use crate::types::MixedAddress;
+    use crate::types::test_fixtures::settle_request;
+    use axum::Router;
+    use axum::routing::get;
+    use solana_sdk::pubkey::Pubkey;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone)]
+    struct InsufficientFundsFacilitator;
+
+    impl Facilitator for InsufficientFundsFacilitator {
+        type Error = FacilitatorLocalError;
+
+        async fn verify(&self, _request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+            Err(FacilitatorLocalError::InsufficientFunds(payer()))
+        }
+
+        async fn settle(&self, _request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+            Err(FacilitatorLocalError::InsufficientFunds(payer()))
+        }
+
+        async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+            Ok(SupportedPaymentKindsResponse { kinds: vec![] })
+        }
+    }
+
+    fn payer() -> MixedAddress {
+        MixedAddress::Solana(Pubkey::default())
+    }
+
+    async fn serve(router: Router) -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{address}/prefix").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_settle_maps_verification_failure() {
+        let router = Router::new().nest(
+            "/prefix",
+            handlers::routes().with_state(InsufficientFundsFacilitator),
+        );
+        let client = FacilitatorClient::try_new(serve(router).await).unwrap();
+
+        let response = client.settle(&settle_request(1_000)).await.unwrap();
+        assert!(!response.success);
+        assert!(matches!(
+            response.error_reason,
+            Some(FacilitatorErrorReason::InsufficientFunds)
+        ));
+        assert_eq!(response.network, Network::SolanaDevnet);
+    }
+
+    #[tokio::test]
+    async fn test_supported_is_retried_and_cached() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let router = Router::new().route(
+            "/prefix/supported",
+            get(move || {
+                let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+                async move {
+                    if first {
+                        Err(StatusCode::BAD_GATEWAY)
+                    } else {
+                        Ok(axum::Json(serde_json::json!({ "kinds": [] })))
+                    }
+                }
+            }),
+        );
+        let client = FacilitatorClient::try_new(serve(router).await)
+            .unwrap()
+            .with_retry_backoff(Duration::from_millis(1));
+
+        client.supported().await.unwrap();
+        client.supported().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -11,12 +11,19 @@
 
 use tracing::instrument;
 
-use crate::chain::{FacilitatorLocalError, TransactionStatusQuery};
+use crate::chain::{
+    FacilitatorFeeQuery, FacilitatorLocalError, NetworkProvider, NetworkProviderOps,
+    PaymentAmountQuery, TransactionStatusQuery,
+};
 use crate::facilitator::Facilitator;
+use crate::network::{Network, NetworkFamily};
+use crate::pending_settlements::SettlementTracker;
 use crate::provider_cache::ProviderMap;
+use crate::settlement_ledger::SettlementLedger;
 use crate::types::{
-    SettleRequest, SettleResponse, SupportedPaymentKindsResponse, TransactionHash,
-    TransactionStatusResponse, VerifyRequest, VerifyResponse,
+    Finality, MixedAddress, PriorityFeeResponse, SettleRequest, SettleResponse,
+    SupportedPaymentKindsResponse, TransactionHash, TransactionStatus, TransactionStatusResponse,
+    VerifyRequest, VerifyResponse,
 };
 
 /// A concrete [`Facilitator`] implementation that verifies and settles x402 payments
@@ -24,8 +31,13 @@ use crate::types::{
 ///
 /// This type is generic over the [`ProviderMap`] implementation used to access EVM providers,
 /// which enables testing or customization beyond the default [`ProviderCache`].
+///
+/// Settlements run through a [`SettlementTracker`], which a shutdown drains. Successful ones are
+/// recorded in a [`SettlementLedger`], with the amount and the facilitator fee they paid.
 pub struct FacilitatorLocal<A> {
     provider_map: A,
+    settlements: SettlementTracker,
+    ledger: SettlementLedger,
 }
 
 impl<A> FacilitatorLocal<A> {
@@ -33,14 +45,40 @@ impl<A> FacilitatorLocal<A> {
     ///
     /// The provider cache is used to resolve the appropriate EVM provider for each payment's target network.
     pub fn new(provider_map: A) -> Self {
-        FacilitatorLocal { provider_map }
+        FacilitatorLocal {
+            provider_map,
+            settlements: SettlementTracker::new(),
+            ledger: SettlementLedger::new(),
+        }
+    }
+
+    /// Runs settlements through `settlements`, to share it, or to journal pending settlements.
+    pub fn with_settlements(mut self, settlements: SettlementTracker) -> Self {
+        self.settlements = settlements;
+        self
+    }
+
+    /// Records settlements in `ledger`, to share it.
+    pub fn with_ledger(mut self, ledger: SettlementLedger) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    /// The providers this facilitator dispatches to, by network.
+    pub fn provider_map(&self) -> &A {
+        &self.provider_map
+    }
+
+    /// The settlement history of this facilitator.
+    pub fn ledger(&self) -> &SettlementLedger {
+        &self.ledger
     }
 }
 
 impl<A, E> Facilitator for FacilitatorLocal<A>
 where
     A: ProviderMap + Sync,
-    A::Value: Facilitator<Error = E>,
+    A::Value: Facilitator<Error = E> + FacilitatorFeeQuery + PaymentAmountQuery + Sync,
     E: Send,
     FacilitatorLocalError: From<E>,
 {
@@ -83,7 +121,8 @@ where
     /// # Errors
     ///
     /// Returns [`FacilitatorLocalError`] if validation or contract call fails. Transaction receipt is included
-    /// in the response on success or failure.
+    /// in the response on success or failure. Returns [`FacilitatorLocalError::ShuttingDown`] once
+    /// the settlements are draining.
     #[instrument(skip_all, err, fields(network = %request.payment_payload.network))]
     async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
         let network = request.network();
@@ -91,7 +130,21 @@ where
             .provider_map
             .by_network(network)
             .ok_or(FacilitatorLocalError::UnsupportedNetwork(None))?;
-        let settle_response = provider.settle(request).await?;
+        let settle_response = self
+            .settlements
+            .track(request, provider.settle(request))
+            .await
+            .ok_or(FacilitatorLocalError::ShuttingDown)??;
+        // Only a successful settlement has transferred the fee and the payment
+        let (fee, amount) = if settle_response.success {
+            (
+                provider.fee_due(request),
+                provider.payment_amount(request).await,
+            )
+        } else {
+            (None, None)
+        };
+        self.ledger.record(request, &settle_response, fee, amount);
         Ok(settle_response)
     }
 
@@ -109,49 +162,118 @@ where
 impl<A> FacilitatorLocal<A>
 where
     A: ProviderMap + Sync,
-    A::Value: TransactionStatusQuery + Sync,
+    A::Value: TransactionStatusQuery + NetworkProviderOps + Sync,
 {
-    /// Query the status of a transaction by its hash.
+    /// Query the status of a transaction by its hash, on any configured network.
+    ///
+    /// The network cannot be determined from the hash alone, so all providers of the hash's
+    /// network family are queried concurrently. A transaction found on none of them is
+    /// [`TransactionStatus::NotFound`], without a network; if some provider failed to answer,
+    /// its error is returned instead, as the transaction may be on its network. A transaction
+    /// found on several networks fails with [`FacilitatorLocalError::AmbiguousTransaction`].
     ///
-    /// This method tries all configured providers until one successfully returns the transaction status.
-    /// This is necessary because we cannot determine the network from the transaction hash alone.
+    /// A settlement of this facilitator reorganized out of the chain is reported as
+    /// [`TransactionStatus::Reorged`].
     #[instrument(skip_all, err, fields(tx_hash = %tx_hash))]
     pub async fn get_transaction_status(
         &self,
         tx_hash: &TransactionHash,
     ) -> Result<TransactionStatusResponse, FacilitatorLocalError> {
-        // Try each provider until one succeeds
-        for provider in self.provider_map.values() {
-            match provider.get_transaction_status(tx_hash).await {
-                Ok(status) if status.status != crate::types::TransactionStatus::NotFound => {
-                    return Ok(status);
-                }
-                Ok(_) => {
-                    // Transaction not found on this network, try next
-                    continue;
-                }
+        if let Some(reorged) = self.reorged_status(tx_hash) {
+            return Ok(reorged);
+        }
+
+        let family = NetworkFamily::from(tx_hash);
+        let lookups = self
+            .provider_map
+            .values()
+            .filter(|provider| NetworkFamily::from(provider.network()) == family)
+            .map(|provider| provider.get_transaction_status(tx_hash));
+        let mut found = Vec::new();
+        let mut error = None;
+        for result in futures::future::join_all(lookups).await {
+            match result {
+                Ok(status) if status.status != TransactionStatus::NotFound => found.push(status),
+                Ok(_) => {}
                 Err(e) => {
-                    // If it's a decoding error (wrong network type), continue
-                    // Otherwise, return the error
-                    if matches!(
-                        e,
-                        FacilitatorLocalError::DecodingError(_) | FacilitatorLocalError::ContractCall(_)
-                    ) {
-                        continue;
-                    }
-                    return Err(e);
+                    error.get_or_insert(e);
                 }
             }
         }
-
-        // If we get here, transaction wasn't found on any network
+        if found.len() > 1 {
+            let networks = found.iter().filter_map(|status| status.network).collect();
+            return Err(FacilitatorLocalError::AmbiguousTransaction(networks));
+        }
+        if let Some(status) = found.pop() {
+            return Ok(status);
+        }
+        if let Some(error) = error {
+            return Err(error);
+        }
         Ok(TransactionStatusResponse {
             transaction_hash: tx_hash.clone(),
-            status: crate::types::TransactionStatus::NotFound,
-            network: crate::network::Network::MonadTestnet, // Default, will be ignored
+            status: TransactionStatus::NotFound,
+            network: None,
             block_number: None,
             confirmations: None,
             error: Some("Transaction not found on any configured network".to_string()),
         })
     }
+
+    /// Query the status of a transaction by its hash, on `network` only.
+    ///
+    /// Fails with [`FacilitatorLocalError::UnsupportedNetwork`] if the network is not configured,
+    /// and with [`FacilitatorLocalError::DecodingError`] if the hash is not of its family.
+    #[instrument(skip_all, err, fields(network = %network, tx_hash = %tx_hash))]
+    pub async fn get_network_transaction_status(
+        &self,
+        network: Network,
+        tx_hash: &TransactionHash,
+    ) -> Result<TransactionStatusResponse, FacilitatorLocalError> {
+        let provider = self
+            .provider_map
+            .by_network(network)
+            .ok_or(FacilitatorLocalError::UnsupportedNetwork(None))?;
+        match self.reorged_status(tx_hash) {
+            Some(reorged) if reorged.network == Some(network) => Ok(reorged),
+            _ => provider.get_transaction_status(tx_hash).await,
+        }
+    }
+
+    /// [`TransactionStatus::Reorged`] if `tx_hash` settled a payment since reorganized out.
+    fn reorged_status(&self, tx_hash: &TransactionHash) -> Option<TransactionStatusResponse> {
+        let record = self.ledger.by_transaction(tx_hash)?;
+        (record.finality == Some(Finality::Reorged)).then(|| TransactionStatusResponse {
+            transaction_hash: tx_hash.clone(),
+            status: TransactionStatus::Reorged,
+            network: Some(record.response.network),
+            block_number: None,
+            confirmations: None,
+            error: Some("Settlement was reorganized out of the chain".to_string()),
+        })
+    }
+}
+
+impl<A> FacilitatorLocal<A>
+where
+    A: ProviderMap<Value = NetworkProvider>,
+{
+    /// Recommend a Solana compute unit price for paying `asset` to `pay_to` on `network`.
+    ///
+    /// Fails with [`FacilitatorLocalError::UnsupportedNetwork`] if the network is not configured
+    /// or is not a Solana network.
+    #[instrument(skip_all, err, fields(network = %network))]
+    pub async fn priority_fee(
+        &self,
+        network: Network,
+        asset: Option<MixedAddress>,
+        pay_to: Option<MixedAddress>,
+    ) -> Result<PriorityFeeResponse, FacilitatorLocalError> {
+        match self.provider_map.by_network(network) {
+            Some(NetworkProvider::Solana(provider)) => provider.priority_fee(asset, pay_to).await,
+            Some(NetworkProvider::Evm(_)) | None => {
+                Err(FacilitatorLocalError::UnsupportedNetwork(None))
+            }
+        }
+    }
 }
@@ -0,0 +1,580 @@
+//! Composite [`Facilitator`] delegating to an ordered list of upstream facilitators.
+//!
+//! [`FacilitatorRouter`] tries the upstreams serving the payment's network and scheme in order,
+//! moving on to the next one when an upstream is unavailable: unreachable, failing RPC calls,
+//! or not configured for the network (see [`FailoverError`]). A rejected payment is returned as is.
+//! A settlement only moves on when the upstream failed before broadcasting any transaction.
+//!
+//! Upstreams that fail repeatedly are considered unhealthy for a while and are only tried after
+//! the healthy ones. Upstreams marked [`Upstream::verify_only`] never settle, e.g. a remote
+//! facilitator used to double-check payments, or a local one whose signer has no funds.
+//!
+//! ```ignore
+//! let router = FacilitatorRouter::new()
+//!     .with_upstream(Upstream::new("local", local_facilitator))
+//!     .with_upstream(Upstream::new("backup", FacilitatorClient::try_new(backup_url)?));
+//! ```
+
+use axum::Json;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::facilitator_client::FacilitatorClientError;
+use crate::network::Network;
+use crate::types::{
+    FacilitatorErrorReason, Scheme, SettleRequest, SettleResponse, SupportedPaymentKind,
+    SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Consecutive failures after which an upstream is considered unhealthy.
+const UNHEALTHY_AFTER_FAILURES: u32 = 3;
+/// How long an unhealthy upstream is tried only after the healthy ones.
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Classifies facilitator errors for failover.
+pub trait FailoverError {
+    /// `true` if the facilitator could not handle the request, so another one may;
+    /// `false` if it handled the request and rejected the payment.
+    fn should_failover(&self) -> bool;
+
+    /// `true` if a settlement failed before the facilitator broadcast any transaction, so another
+    /// one may settle the payment without risking settling it twice.
+    fn should_failover_settle(&self) -> bool;
+}
+
+impl FailoverError for FacilitatorLocalError {
+    fn should_failover(&self) -> bool {
+        matches!(
+            self,
+            FacilitatorLocalError::UnsupportedNetwork(_)
+                | FacilitatorLocalError::ContractCall(_)
+                | FacilitatorLocalError::NotBroadcast(_)
+                | FacilitatorLocalError::ClockError(_)
+                | FacilitatorLocalError::ShuttingDown
+        )
+    }
+
+    /// A failed RPC call may be the broadcast itself, or a receipt lookup after it; a transaction
+    /// refused before broadcast, e.g. by a drained signer, is not.
+    fn should_failover_settle(&self) -> bool {
+        matches!(
+            self,
+            FacilitatorLocalError::UnsupportedNetwork(_)
+                | FacilitatorLocalError::NotBroadcast(_)
+                | FacilitatorLocalError::ClockError(_)
+                | FacilitatorLocalError::ShuttingDown
+        )
+    }
+}
+
+impl FailoverError for FacilitatorClientError {
+    fn should_failover(&self) -> bool {
+        match self {
+            FacilitatorClientError::Rejected(_) => false,
+            FacilitatorClientError::Status { status, .. } => status.is_server_error(),
+            _ => true,
+        }
+    }
+
+    /// Only errors raised before the request reached the upstream, or a `503` refusing it, are
+    /// known to happen before broadcast. Other server errors and timeouts may follow one.
+    fn should_failover_settle(&self) -> bool {
+        match self {
+            FacilitatorClientError::Client(_)
+            | FacilitatorClientError::Url(_)
+            | FacilitatorClientError::InvalidHeader(_) => true,
+            FacilitatorClientError::Http { source, .. } => source.is_connect(),
+            FacilitatorClientError::Status { status, .. } => {
+                *status == StatusCode::SERVICE_UNAVAILABLE
+            }
+            FacilitatorClientError::Rejected(_) | FacilitatorClientError::Decoding(_) => false,
+        }
+    }
+}
+
+/// Errors returned by [`FacilitatorRouter`].
+#[derive(Debug, thiserror::Error)]
+pub enum FacilitatorRouterError {
+    #[error("No upstream facilitator for {0} on {1}")]
+    NoRoute(Scheme, Network),
+    /// The last upstream tried failed; `response` is how that upstream would have answered.
+    #[error("Upstream facilitator {upstream} failed: {message}")]
+    Upstream {
+        upstream: String,
+        message: String,
+        response: Response,
+    },
+}
+
+impl IntoResponse for FacilitatorRouterError {
+    fn into_response(self) -> Response {
+        match self {
+            FacilitatorRouterError::NoRoute(..) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    None,
+                    FacilitatorErrorReason::InvalidNetwork,
+                )),
+            )
+                .into_response(),
+            FacilitatorRouterError::Upstream { response, .. } => response,
+        }
+    }
+}
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Error of an upstream facilitator, with its HTTP rendering kept for the router's response.
+struct UpstreamError {
+    failover: bool,
+    message: String,
+    response: Box<Response>,
+}
+
+impl UpstreamError {
+    fn new<E: FailoverError + IntoResponse + std::fmt::Display>(error: E) -> Self {
+        let failover = error.should_failover();
+        Self::with_failover(error, failover)
+    }
+
+    fn settle<E: FailoverError + IntoResponse + std::fmt::Display>(error: E) -> Self {
+        let failover = error.should_failover_settle();
+        Self::with_failover(error, failover)
+    }
+
+    fn with_failover<E: IntoResponse + std::fmt::Display>(error: E, failover: bool) -> Self {
+        Self {
+            failover,
+            message: error.to_string(),
+            response: Box::new(error.into_response()),
+        }
+    }
+}
+
+/// Object-safe view of a [`Facilitator`], so upstreams of different types can share a list.
+trait DynFacilitator: Send + Sync {
+    fn verify<'a>(
+        &'a self,
+        request: &'a VerifyRequest,
+    ) -> BoxFuture<'a, Result<VerifyResponse, UpstreamError>>;
+
+    fn settle<'a>(
+        &'a self,
+        request: &'a SettleRequest,
+    ) -> BoxFuture<'a, Result<SettleResponse, UpstreamError>>;
+
+    fn supported(&self) -> BoxFuture<'_, Result<SupportedPaymentKindsResponse, UpstreamError>>;
+}
+
+impl<F> DynFacilitator for F
+where
+    F: Facilitator + Send + Sync,
+    F::Error: FailoverError + IntoResponse,
+{
+    fn verify<'a>(
+        &'a self,
+        request: &'a VerifyRequest,
+    ) -> BoxFuture<'a, Result<VerifyResponse, UpstreamError>> {
+        Box::pin(async move {
+            Facilitator::verify(self, request)
+                .await
+                .map_err(UpstreamError::new)
+        })
+    }
+
+    fn settle<'a>(
+        &'a self,
+        request: &'a SettleRequest,
+    ) -> BoxFuture<'a, Result<SettleResponse, UpstreamError>> {
+        Box::pin(async move {
+            Facilitator::settle(self, request)
+                .await
+                .map_err(UpstreamError::settle)
+        })
+    }
+
+    fn supported(&self) -> BoxFuture<'_, Result<SupportedPaymentKindsResponse, UpstreamError>> {
+        Box::pin(async move {
+            Facilitator::supported(self)
+                .await
+                .map_err(UpstreamError::new)
+        })
+    }
+}
+
+/// Failure tracking of one upstream.
+#[derive(Default)]
+struct Health {
+    consecutive_failures: AtomicU32,
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl Health {
+    fn is_healthy(&self) -> bool {
+        let unhealthy_until = self
+            .unhealthy_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        unhealthy_until.is_none_or(|until| Instant::now() >= until)
+    }
+
+    fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        *self
+            .unhealthy_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= UNHEALTHY_AFTER_FAILURES {
+            *self
+                .unhealthy_until
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = Some(Instant::now() + UNHEALTHY_COOLDOWN);
+        }
+    }
+}
+
+/// An upstream facilitator of a [`FacilitatorRouter`].
+pub struct Upstream {
+    name: String,
+    facilitator: Box<dyn DynFacilitator>,
+    networks: Vec<Network>,
+    schemes: Vec<Scheme>,
+    verify_only: bool,
+    health: Health,
+}
+
+impl Upstream {
+    /// Wraps `facilitator`, serving every network and scheme it supports.
+    /// `name` identifies the upstream in logs and errors.
+    pub fn new<F>(name: impl Into<String>, facilitator: F) -> Self
+    where
+        F: Facilitator + Send + Sync + 'static,
+        F::Error: FailoverError + IntoResponse,
+    {
+        Self {
+            name: name.into(),
+            facilitator: Box::new(facilitator),
+            networks: Vec::new(),
+            schemes: Vec::new(),
+            verify_only: false,
+            health: Health::default(),
+        }
+    }
+
+    /// Restricts the upstream to `network`. May be called several times to serve several networks.
+    pub fn for_network(mut self, network: Network) -> Self {
+        self.networks.push(network);
+        self
+    }
+
+    /// Restricts the upstream to `scheme`. May be called several times to serve several schemes.
+    pub fn for_scheme(mut self, scheme: Scheme) -> Self {
+        self.schemes.push(scheme);
+        self
+    }
+
+    /// Uses the upstream for verification only; settlements go to the other upstreams.
+    pub fn verify_only(mut self) -> Self {
+        self.verify_only = true;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn serves(&self, network: Network, scheme: Scheme) -> bool {
+        (self.networks.is_empty() || self.networks.contains(&network))
+            && (self.schemes.is_empty() || self.schemes.contains(&scheme))
+    }
+
+    fn serves_kind(&self, kind: &SupportedPaymentKind) -> bool {
+        (self.networks.is_empty() || self.networks.iter().any(|n| n.to_string() == kind.network))
+            && (self.schemes.is_empty() || self.schemes.contains(&kind.scheme))
+    }
+
+    /// Records the outcome of a call; returns the error if another upstream should be tried.
+    fn record<T>(
+        &self,
+        result: Result<T, UpstreamError>,
+    ) -> Result<Result<T, UpstreamError>, UpstreamError> {
+        match result {
+            Err(error) if error.failover => {
+                self.health.record_failure();
+                tracing::warn!(upstream = %self.name, error = %error.message, "Facilitator upstream unavailable");
+                Err(error)
+            }
+            result => {
+                self.health.record_success();
+                Ok(result)
+            }
+        }
+    }
+
+    fn router_error(&self, error: UpstreamError) -> FacilitatorRouterError {
+        FacilitatorRouterError::Upstream {
+            upstream: self.name.clone(),
+            message: error.message,
+            response: *error.response,
+        }
+    }
+}
+
+/// A [`Facilitator`] that delegates to an ordered list of [`Upstream`] facilitators.
+#[derive(Default)]
+pub struct FacilitatorRouter {
+    upstreams: Vec<Upstream>,
+}
+
+impl FacilitatorRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an upstream; earlier upstreams are preferred.
+    pub fn with_upstream(mut self, upstream: Upstream) -> Self {
+        self.upstreams.push(upstream);
+        self
+    }
+
+    /// Upstreams serving `network` and `scheme`, healthy ones first, each group in order.
+    fn candidates(&self, network: Network, scheme: Scheme, settle: bool) -> Vec<&Upstream> {
+        let (mut healthy, unhealthy): (Vec<&Upstream>, Vec<&Upstream>) = self
+            .upstreams
+            .iter()
+            .filter(|upstream| upstream.serves(network, scheme))
+            .filter(|upstream| !(settle && upstream.verify_only))
+            .partition(|upstream| upstream.health.is_healthy());
+        healthy.extend(unhealthy);
+        healthy
+    }
+}
+
+impl Facilitator for FacilitatorRouter {
+    type Error = FacilitatorRouterError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let network = request.network();
+        let scheme = request.payment_payload.scheme;
+        let mut last_error = FacilitatorRouterError::NoRoute(scheme, network);
+        for upstream in self.candidates(network, scheme, false) {
+            match upstream.record(upstream.facilitator.verify(request).await) {
+                Ok(result) => return result.map_err(|e| upstream.router_error(e)),
+                Err(error) => last_error = upstream.router_error(error),
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Settles through the first available upstream that is not verify-only.
+    ///
+    /// Moves on to the next upstream only if the settlement failed before any transaction was
+    /// broadcast (see [`FailoverError::should_failover_settle`]). Any other failure is returned
+    /// as is: the transaction may still land, and a second upstream could settle the payment again
+    /// with another nonce or blockhash.
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let network = request.network();
+        let scheme = request.payment_payload.scheme;
+        let mut last_error = FacilitatorRouterError::NoRoute(scheme, network);
+        for upstream in self.candidates(network, scheme, true) {
+            match upstream.record(upstream.facilitator.settle(request).await) {
+                Ok(result) => return result.map_err(|e| upstream.router_error(e)),
+                Err(error) => last_error = upstream.router_error(error),
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Merges the kinds supported by the settling upstreams, in upstream order, without duplicates.
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        let mut kinds: Vec<SupportedPaymentKind> = Vec::new();
+        let mut last_error = None;
+        for upstream in self.upstreams.iter().filter(|u| !u.verify_only) {
+            let supported = match upstream.facilitator.supported().await {
+                Ok(supported) => supported,
+                Err(error) => {
+                    tracing::warn!(upstream = %upstream.name, error = %error.message, "Failed to fetch supported kinds");
+                    last_error = Some(upstream.router_error(error));
+                    continue;
+                }
+            };
+            for kind in supported.kinds {
+                let known = kinds
+                    .iter()
+                    .any(|k| k.scheme == kind.scheme && k.network == kind.network);
+                if upstream.serves_kind(&kind) && !known {
+                    kinds.push(kind);
+                }
+            }
+        }
+        match last_error {
+            Some(error) if kinds.is_empty() => Err(error),
+            _ => Ok(SupportedPaymentKindsResponse { kinds }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_fixtures::settle_request;
+    use crate::types::{MixedAddress, X402Version};
+    use solana_sdk::pubkey::Pubkey;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Settles successfully, or fails with the error of `failure` if set.
+    struct TestFacilitator {
+        failure: Option<fn() -> FacilitatorLocalError>,
+        settled: AtomicUsize,
+    }
+
+    impl TestFacilitator {
+        fn new(failure: Option<fn() -> FacilitatorLocalError>) -> Arc<Self> {
+            Arc::new(Self {
+                failure,
+                settled: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    fn rpc_error() -> FacilitatorLocalError {
+        FacilitatorLocalError::ContractCall("connection reset".to_string())
+    }
+
+    impl Facilitator for TestFacilitator {
+        type Error = FacilitatorLocalError;
+
+        async fn verify(&self, _request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+            Ok(VerifyResponse::valid(MixedAddress::Solana(
+                Pubkey::default(),
+            )))
+        }
+
+        async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+            if let Some(failure) = self.failure {
+                return Err(failure());
+            }
+            self.settled.fetch_add(1, Ordering::SeqCst);
+            Ok(SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: MixedAddress::Solana(Pubkey::default()),
+                transaction: None,
+                network: request.network(),
+            })
+        }
+
+        async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+            Ok(SupportedPaymentKindsResponse {
+                kinds: vec![SupportedPaymentKind {
+                    x402_version: X402Version::V1,
+                    scheme: Scheme::Exact,
+                    network: Network::SolanaDevnet.to_string(),
+                    extra: None,
+                }],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_settle_fails_over_to_backup() {
+        let primary = TestFacilitator::new(Some(|| FacilitatorLocalError::ShuttingDown));
+        let backup = TestFacilitator::new(None);
+        let router = FacilitatorRouter::new()
+            .with_upstream(Upstream::new("primary", primary.clone()))
+            .with_upstream(Upstream::new("backup", backup.clone()));
+
+        for _ in 0..UNHEALTHY_AFTER_FAILURES {
+            let response = router.settle(&settle_request(1_000)).await.unwrap();
+            assert!(response.success);
+        }
+        assert_eq!(backup.settled.load(Ordering::SeqCst), 3);
+        assert!(!router.upstreams[0].health.is_healthy());
+        assert_eq!(
+            router.candidates(Network::SolanaDevnet, Scheme::Exact, true)[0].name(),
+            "backup"
+        );
+
+        let kinds = router.supported().await.unwrap().kinds;
+        assert_eq!(kinds.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_verify_only_upstream_does_not_settle() {
+        let router = FacilitatorRouter::new()
+            .with_upstream(Upstream::new("verifier", TestFacilitator::new(None)).verify_only())
+            .with_upstream(
+                Upstream::new("monad", TestFacilitator::new(None)).for_network(Network::Monad),
+            );
+
+        assert!(router.verify(&settle_request(1_000)).await.is_ok());
+        let error = router.settle(&settle_request(1_000)).await.unwrap_err();
+        assert!(matches!(error, FacilitatorRouterError::NoRoute(..)));
+    }
+
+    #[tokio::test]
+    async fn test_settle_does_not_fail_over_after_possible_broadcast() {
+        let primary = TestFacilitator::new(Some(rpc_error));
+        let backup = TestFacilitator::new(None);
+        let router = FacilitatorRouter::new()
+            .with_upstream(Upstream::new("primary", primary.clone()))
+            .with_upstream(Upstream::new("backup", backup.clone()));
+
+        let error = router.settle(&settle_request(1_000)).await.unwrap_err();
+        assert!(matches!(
+            error,
+            FacilitatorRouterError::Upstream { upstream, .. } if upstream == "primary"
+        ));
+        assert_eq!(backup.settled.load(Ordering::SeqCst), 0);
+        // Verification has no side effect, so it still fails over on RPC errors
+        assert!(rpc_error().should_failover());
+    }
+
+    #[tokio::test]
+    async fn test_settle_fails_over_when_the_primary_signer_is_drained() {
+        let primary = TestFacilitator::new(Some(|| {
+            FacilitatorLocalError::NotBroadcast(
+                "insufficient funds for gas * price + value".to_string(),
+            )
+        }));
+        let backup = TestFacilitator::new(None);
+        let router = FacilitatorRouter::new()
+            .with_upstream(Upstream::new("primary", primary.clone()))
+            .with_upstream(Upstream::new("backup", backup.clone()));
+
+        let response = router.settle(&settle_request(1_000)).await.unwrap();
+        assert!(response.success);
+        assert_eq!(primary.settled.load(Ordering::SeqCst), 0);
+        assert_eq!(backup.settled.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_client_errors_fail_over_settlements_only_before_broadcast() {
+        let status = |status: StatusCode| FacilitatorClientError::Status {
+            status,
+            error: String::new(),
+        };
+        assert!(status(StatusCode::SERVICE_UNAVAILABLE).should_failover_settle());
+        assert!(status(StatusCode::BAD_GATEWAY).should_failover());
+        assert!(!status(StatusCode::BAD_GATEWAY).should_failover_settle());
+        assert!(!status(StatusCode::INTERNAL_SERVER_ERROR).should_failover_settle());
+        assert!(!FacilitatorClientError::Decoding(String::new()).should_failover_settle());
+        assert!(
+            !FacilitatorClientError::Rejected(FacilitatorErrorReason::InvalidScheme)
+                .should_failover_settle()
+        );
+        assert!(FacilitatorClientError::InvalidHeader(String::new()).should_failover_settle());
+    }
+}
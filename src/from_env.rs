@@ -1,10 +1,14 @@
+use crate::chain::rpc_pool::{RpcEndpoint, parse_rpc_endpoints};
 use crate::network::Network;
+use crate::types::{FacilitatorFee, MixedAddress};
 use alloy::network::EthereumWallet;
 use alloy::signers::local::PrivateKeySigner;
 use serde::Deserialize;
 use serde::Serialize;
 use solana_sdk::signature::Keypair;
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub const ENV_SIGNER_TYPE: &str = "SIGNER_TYPE";
@@ -25,6 +29,95 @@ pub fn rpc_env_name_from_network(network: Network) -> &'static str {
     }
 }
 
+/// Reads the RPC endpoints of `network` from its `RPC_URL_*` variable: a comma-separated list of
+/// URLs, each optionally followed by `|<weight>`. Returns `None` if the variable is not set.
+pub fn rpc_endpoints_from_env(
+    network: Network,
+) -> Result<Option<Vec<RpcEndpoint>>, Box<dyn std::error::Error>> {
+    let env_var = rpc_env_name_from_network(network);
+    match env::var(env_var) {
+        Ok(value) => {
+            let endpoints =
+                parse_rpc_endpoints(&value).map_err(|e| format!("env {env_var}: {e}"))?;
+            Ok(Some(endpoints))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Name of the variable holding the block confirmations settlements wait for on EVM `network`,
+/// like `X402_EVM_CONFIRMATIONS_MONAD_TESTNET`.
+pub fn evm_confirmations_env_name(network: Network) -> String {
+    let suffix = network.to_string().replace('-', "_").to_uppercase();
+    format!("X402_EVM_CONFIRMATIONS_{suffix}")
+}
+
+/// Reads the block confirmations settlements wait for on EVM `network`, 1 if not set.
+pub fn evm_confirmations_from_env(network: Network) -> Result<u64, Box<dyn std::error::Error>> {
+    let env_var = evm_confirmations_env_name(network);
+    match env::var(&env_var) {
+        Ok(value) => Ok(value.parse().map_err(|e| format!("env {env_var}: {e}"))?),
+        Err(_) => Ok(1),
+    }
+}
+
+/// Name of the variable holding the highest gas price, in wei, settlements pay on EVM `network`,
+/// like `X402_EVM_MAX_GAS_PRICE_MONAD_TESTNET`.
+pub fn evm_max_gas_price_env_name(network: Network) -> String {
+    let suffix = network.to_string().replace('-', "_").to_uppercase();
+    format!("X402_EVM_MAX_GAS_PRICE_{suffix}")
+}
+
+/// Name of the variable holding the `setting` (`BPS`, `FLAT` or `RECIPIENT`) of the facilitator
+/// fee charged on `network`, like `X402_FEE_BPS_SOLANA_DEVNET`.
+pub fn fee_env_name(setting: &str, network: Network) -> String {
+    let suffix = network.to_string().replace('-', "_").to_uppercase();
+    format!("X402_FEE_{setting}_{suffix}")
+}
+
+/// Reads the facilitator fee charged on `network` from its `X402_FEE_BPS_*` and `X402_FEE_FLAT_*`
+/// variables, paid to `X402_FEE_RECIPIENT_*`, or else to `default_recipient`.
+/// Returns `None` if no fee is set.
+pub fn facilitator_fee_from_env(
+    network: Network,
+    default_recipient: MixedAddress,
+) -> Result<Option<FacilitatorFee>, Box<dyn std::error::Error>> {
+    let var = |setting: &str| {
+        let env_var = fee_env_name(setting, network);
+        env::var(&env_var).ok().map(|value| (env_var, value))
+    };
+    let bps = match var("BPS") {
+        Some((env_var, value)) => Some(
+            value
+                .trim()
+                .parse::<u16>()
+                .map_err(|e| format!("env {env_var}: {e}"))?,
+        ),
+        None => None,
+    };
+    let flat = match var("FLAT") {
+        Some((env_var, value)) => {
+            FacilitatorFee::parse_flat(&value).map_err(|e| format!("env {env_var}: {e}"))?
+        }
+        None => BTreeMap::new(),
+    };
+    if bps.is_none() && flat.is_empty() {
+        return Ok(None);
+    }
+    let recipient = match var("RECIPIENT") {
+        Some((env_var, value)) => {
+            MixedAddress::from_str(value.trim()).map_err(|e| format!("env {env_var}: {e}"))?
+        }
+        None => default_recipient,
+    };
+    Ok(Some(FacilitatorFee {
+        recipient,
+        bps,
+        flat,
+        exempt: Vec::new(),
+    }))
+}
+
 /// Supported methods for constructing an Ethereum wallet from environment variables.
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SignerType {
@@ -33,15 +126,31 @@ pub enum SignerType {
     PrivateKey,
 }
 
+impl FromStr for SignerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "private-key" => Ok(SignerType::PrivateKey),
+            _ => Err(format!("Unknown signer type {s}")),
+        }
+    }
+}
+
+impl Display for SignerType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignerType::PrivateKey => write!(f, "private-key"),
+        }
+    }
+}
+
 impl SignerType {
     /// Parse the signer type from the `SIGNER_TYPE` environment variable.
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
         let signer_type_string =
             env::var(ENV_SIGNER_TYPE).map_err(|_| format!("env {ENV_SIGNER_TYPE} not set"))?;
-        match signer_type_string.as_str() {
-            "private-key" => Ok(SignerType::PrivateKey),
-            _ => Err(format!("Unknown signer type {signer_type_string}").into()),
-        }
+        Ok(SignerType::from_str(&signer_type_string)?)
     }
 
     /// Constructs an [`EthereumWallet`] based on the [`SignerType`] selected from environment.
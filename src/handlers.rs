@@ -9,18 +9,27 @@
 //! Each endpoint consumes or produces structured JSON payloads defined in `x402-facilitator`,
 //! and is compatible with official x402 client SDKs.
 
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
 use axum::response::Response;
 use axum::routing::{get, post};
 use axum::{Json, Router, response::IntoResponse};
+use futures::StreamExt;
+use serde::Deserialize;
 use serde_json::json;
 use tracing::instrument;
 
+use crate::admin_auth::{self, AdminToken};
 use crate::chain::FacilitatorLocalError;
+use crate::config::ConfigReloader;
 use crate::facilitator::Facilitator;
 use crate::facilitator_local::FacilitatorLocal;
+use crate::network::Network;
 use crate::provider_cache::ProviderCache;
+use crate::settlement_ledger::{self, ExportFormat, SettlementDetails, SettlementFilter};
+use crate::settlement_stats;
+use crate::timestamp::UnixTimestamp;
 use crate::types::{
     ErrorResponse, FacilitatorErrorReason, MixedAddress, SettleRequest, TransactionHash,
     VerifyRequest, VerifyResponse,
@@ -75,44 +84,46 @@ where
         .route("/supported", get(get_supported::<A>))
 }
 
-/// Routes specifically for FacilitatorLocal with transaction status support.
+/// Public routes of a [`FacilitatorLocal`]: the protocol [`routes`], plus transaction status
+/// and priority fee lookups.
 pub fn routes_with_transaction_status() -> Router<std::sync::Arc<FacilitatorLocal<ProviderCache>>> {
-    Router::new()
-        .route("/", get(get_root))
-        .route("/verify", get(get_verify_info))
-        .route("/verify", post(post_verify_facilitator_local))
-        .route("/settle", get(get_settle_info))
-        .route("/settle", post(post_settle_facilitator_local))
-        .route("/health", get(get_health_facilitator_local))
-        .route("/supported", get(get_supported_facilitator_local))
-        .route("/transaction/:tx_hash", get(get_transaction_status))
-}
-
-/// Wrapper handlers for FacilitatorLocal<ProviderCache>
-async fn post_verify_facilitator_local(
-    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
-    Json(body): Json<VerifyRequest>,
-) -> impl IntoResponse {
-    post_verify(State(facilitator), Json(body)).await
+    routes()
+        .route("/transaction/{tx_hash}", get(get_transaction_status))
+        .route(
+            "/transaction/{network}/{tx_hash}",
+            get(get_network_transaction_status),
+        )
+        .route("/priority-fee/{network}", get(get_priority_fee))
 }
 
-async fn post_settle_facilitator_local(
-    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
-    Json(body): Json<SettleRequest>,
-) -> impl IntoResponse {
-    post_settle(State(facilitator), Json(body)).await
+/// Operator routes of a [`FacilitatorLocal`], over its settlement history.
+pub fn admin_routes() -> Router<std::sync::Arc<FacilitatorLocal<ProviderCache>>> {
+    Router::new()
+        .route("/settlements", get(get_settlements))
+        .route("/settlements/export", get(get_settlements_export))
+        .route("/settlements/{id}", get(get_settlement))
+        .route("/stats/daily", get(get_daily_stats))
+        .route("/stats/failures", get(get_failure_stats))
 }
 
-async fn get_health_facilitator_local(
-    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
-) -> impl IntoResponse {
-    get_health(State(facilitator)).await
+/// Operator route reloading the configuration of a running facilitator, see [`ConfigReloader`].
+pub fn reload_routes() -> Router<std::sync::Arc<ConfigReloader>> {
+    Router::new().route("/config/reload", post(post_config_reload))
 }
 
-async fn get_supported_facilitator_local(
-    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
-) -> impl IntoResponse {
-    get_supported(State(facilitator)).await
+/// The [`admin_routes`] and [`reload_routes`], served only to requests with the admin `token`.
+pub fn operator_routes(
+    facilitator: std::sync::Arc<FacilitatorLocal<ProviderCache>>,
+    reloader: std::sync::Arc<ConfigReloader>,
+    token: std::sync::Arc<AdminToken>,
+) -> Router {
+    Router::new()
+        .merge(admin_routes().with_state(facilitator))
+        .merge(reload_routes().with_state(reloader))
+        .route_layer(axum::middleware::from_fn_with_state(
+            token,
+            admin_auth::require_admin_token,
+        ))
 }
 
 /// `GET /`: Returns a simple greeting message from the facilitator.
@@ -203,18 +214,49 @@ where
     }
 }
 
-/// `GET /transaction/:tx_hash`: Query the status of a transaction by its hash.
+/// Query parameters of `GET /transaction/{tx_hash}`.
+#[derive(Debug, Deserialize)]
+pub struct TransactionStatusQuery {
+    /// Network to look the transaction up on. All networks of the hash's family if omitted.
+    pub network: Option<Network>,
+}
+
+/// `GET /transaction/{tx_hash}`: Query the status of a transaction by its hash.
 ///
 /// This endpoint allows clients to check the status of a previously settled payment transaction.
-/// It returns the current status (pending, confirmed, failed, or not found), along with
+/// It returns the current status (pending, confirmed, failed, reorged, or not found), along with
 /// block number, confirmations, and any error information.
 ///
 /// The transaction hash can be in EVM format (0x-prefixed hex) or Solana format (base58).
+/// Without a `network` query parameter, every configured network of that format is queried.
 #[instrument(skip_all, fields(tx_hash = %tx_hash_str))]
 pub async fn get_transaction_status(
     State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
     Path(tx_hash_str): Path<String>,
+    Query(query): Query<TransactionStatusQuery>,
 ) -> impl IntoResponse {
+    transaction_status(&facilitator, query.network, &tx_hash_str).await
+}
+
+/// `GET /transaction/{network}/{tx_hash}`: Query the status of a transaction on one network.
+#[instrument(skip_all, fields(network = %network, tx_hash = %tx_hash_str))]
+pub async fn get_network_transaction_status(
+    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
+    Path((network, tx_hash_str)): Path<(Network, String)>,
+) -> impl IntoResponse {
+    transaction_status(&facilitator, Some(network), &tx_hash_str).await
+}
+
+/// Answers a transaction status query, on `network` if given.
+///
+/// A network that is not configured, or of another family than the hash, is a `400 Bad Request`;
+/// a network whose RPC fails to answer is a `502 Bad Gateway`. A transaction found on several
+/// networks is a `409 Conflict`, to be looked up again on one of them.
+async fn transaction_status(
+    facilitator: &FacilitatorLocal<ProviderCache>,
+    network: Option<Network>,
+    tx_hash_str: &str,
+) -> Response {
     // Parse transaction hash from string using serde_json (which uses the Deserialize impl)
     let tx_hash = match serde_json::from_str::<TransactionHash>(&format!("\"{}\"", tx_hash_str)) {
         Ok(hash) => hash,
@@ -234,16 +276,51 @@ pub async fn get_transaction_status(
         }
     };
 
-    match facilitator.get_transaction_status(&tx_hash).await {
+    let status = match network {
+        Some(network) => {
+            facilitator
+                .get_network_transaction_status(network, &tx_hash)
+                .await
+        }
+        None => facilitator.get_transaction_status(&tx_hash).await,
+    };
+    match status {
         Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+        Err(
+            error @ (FacilitatorLocalError::UnsupportedNetwork(_)
+            | FacilitatorLocalError::DecodingError(_)),
+        ) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+            }),
+        )
+            .into_response(),
+        Err(FacilitatorLocalError::AmbiguousTransaction(networks)) => {
+            let networks: Vec<String> = networks.iter().map(Network::to_string).collect();
+            (
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: format!(
+                        "Transaction found on networks {}, query one with the network parameter",
+                        networks.join(", ")
+                    ),
+                }),
+            )
+                .into_response()
+        }
         Err(error) => {
             tracing::warn!(
                 error = ?error,
                 tx_hash = %tx_hash_str,
                 "Failed to query transaction status"
             );
+            let status = match error {
+                FacilitatorLocalError::ContractCall(_) => StatusCode::BAD_GATEWAY,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                status,
                 Json(ErrorResponse {
                     error: format!("Failed to query transaction status: {}", error),
                 }),
@@ -253,10 +330,254 @@ pub async fn get_transaction_status(
     }
 }
 
+/// Query parameters of `GET /priority-fee/{network}`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityFeeQuery {
+    /// Token mint being paid. Defaults to the network's USDC mint.
+    pub asset: Option<MixedAddress>,
+    /// Payment recipient; its associated token account is included in the estimate.
+    pub pay_to: Option<MixedAddress>,
+}
+
+/// `GET /priority-fee/{network}`: Recommend a compute unit price for a Solana payment.
+///
+/// The recommendation is a percentile of recent prioritization fees for the accounts the payment
+/// writes, bounded by the per-network floor and the facilitator's maximum compute unit price.
+#[instrument(skip_all, fields(network = %network))]
+pub async fn get_priority_fee(
+    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
+    Path(network): Path<Network>,
+    Query(query): Query<PriorityFeeQuery>,
+) -> impl IntoResponse {
+    match facilitator
+        .priority_fee(network, query.asset, query.pay_to)
+        .await
+    {
+        Ok(priority_fee) => (StatusCode::OK, Json(priority_fee)).into_response(),
+        Err(
+            error @ (FacilitatorLocalError::UnsupportedNetwork(_)
+            | FacilitatorLocalError::InvalidAddress(_)),
+        ) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+            }),
+        )
+            .into_response(),
+        Err(error) => {
+            tracing::warn!(error = ?error, "Failed to estimate priority fee");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to estimate priority fee: {}", error),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Settlements per page of `GET /settlements`, unless `limit` is set.
+const DEFAULT_SETTLEMENTS_PAGE: usize = 50;
+
+/// Maximum settlements per page of `GET /settlements`.
+const MAX_SETTLEMENTS_PAGE: usize = 500;
+
+/// Pagination parameters of `GET /settlements`.
+#[derive(Debug, Deserialize)]
+pub struct SettlementPageQuery {
+    /// `nextCursor` of the previous page.
+    pub cursor: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+/// `GET /settlements`: Search the settlement history, newest first.
+///
+/// Filters are the [`SettlementFilter`] fields as query parameters: `payTo`, `payer`, `network`,
+/// `asset`, `status`, `from`, `to` (Unix seconds) and `resource`. The `nextCursor` of a page is
+/// passed as `cursor` to get the next one.
+#[instrument(skip_all)]
+pub async fn get_settlements(
+    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
+    Query(filter): Query<SettlementFilter>,
+    Query(page): Query<SettlementPageQuery>,
+) -> impl IntoResponse {
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_SETTLEMENTS_PAGE)
+        .clamp(1, MAX_SETTLEMENTS_PAGE);
+    Json(facilitator.ledger().search(&filter, page.cursor, limit))
+}
+
+/// `GET /settlements/{id}`: A settlement with its full request and response, and the current
+/// status of its transaction.
+#[instrument(skip_all, fields(id = id))]
+pub async fn get_settlement(
+    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    let Some(settlement) = facilitator.ledger().get(id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Settlement {id} not found"),
+            }),
+        )
+            .into_response();
+    };
+    let transaction_status = match &settlement.response.transaction {
+        Some(transaction) => facilitator
+            .get_network_transaction_status(settlement.response.network, transaction)
+            .await
+            .inspect_err(|e| tracing::debug!(error = %e, "Failed to query settlement transaction"))
+            .ok(),
+        None => None,
+    };
+    (
+        StatusCode::OK,
+        Json(SettlementDetails {
+            settlement,
+            transaction_status,
+        }),
+    )
+        .into_response()
+}
+
+/// Format parameter of `GET /settlements/export`.
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// `csv` (default) or `ndjson`.
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// `GET /settlements/export`: Export the settlements matching the `GET /settlements` filters,
+/// oldest first, as a CSV or NDJSON attachment streamed a page at a time.
+#[instrument(skip_all)]
+pub async fn get_settlements_export(
+    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
+    Query(filter): Query<SettlementFilter>,
+    Query(export): Query<ExportQuery>,
+) -> impl IntoResponse {
+    let format = export.format;
+    let rows = settlement_ledger::export_stream(facilitator.ledger().clone(), filter, format)
+        .map(Ok::<_, std::convert::Infallible>);
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"settlements.{}\"",
+                    format.extension()
+                ),
+            ),
+        ],
+        Body::from_stream(rows),
+    )
+}
+
+/// Range of `GET /stats/*` without `from`, in seconds before `to`, or now.
+const DEFAULT_STATS_RANGE: u64 = 30 * 86_400;
+
+/// Longest range of `GET /stats/*`, in seconds.
+const MAX_STATS_RANGE: u64 = 366 * 86_400;
+
+/// `filter` with `from` set to [`DEFAULT_STATS_RANGE`] before `to` if unset, or an error if it
+/// spans more than [`MAX_STATS_RANGE`].
+fn stats_filter(mut filter: SettlementFilter) -> Result<SettlementFilter, String> {
+    let to = filter
+        .to
+        .map(|to| to.seconds_since_epoch())
+        .unwrap_or_else(|| {
+            UnixTimestamp::try_now()
+                .map(|now| now.seconds_since_epoch())
+                .unwrap_or(0)
+        });
+    let from = *filter
+        .from
+        .get_or_insert(UnixTimestamp(to.saturating_sub(DEFAULT_STATS_RANGE)));
+    if to.saturating_sub(from.seconds_since_epoch()) > MAX_STATS_RANGE {
+        return Err(format!(
+            "Statistics span at most {} days, narrow from and to",
+            MAX_STATS_RANGE / 86_400
+        ));
+    }
+    Ok(filter)
+}
+
+/// `GET /stats/daily`: Settled amounts, payment counts, failures and gas spent per recipient,
+/// asset, network and UTC day, over the settlements matching the `GET /settlements` filters.
+///
+/// Without `from`, covers the [`DEFAULT_STATS_RANGE`] before `to`; `from` and `to` span at most
+/// [`MAX_STATS_RANGE`].
+#[instrument(skip_all)]
+pub async fn get_daily_stats(
+    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
+    Query(filter): Query<SettlementFilter>,
+) -> Response {
+    match stats_filter(filter) {
+        Ok(filter) => Json(
+            facilitator
+                .ledger()
+                .aggregate(&filter, |settlements| settlement_stats::daily(settlements)),
+        )
+        .into_response(),
+        Err(error) => (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response(),
+    }
+}
+
+/// `GET /stats/failures`: Failure rates by reason, over the settlements matching the
+/// `GET /settlements` filters, in the same range as `GET /stats/daily`.
+#[instrument(skip_all)]
+pub async fn get_failure_stats(
+    State(facilitator): State<std::sync::Arc<FacilitatorLocal<ProviderCache>>>,
+    Query(filter): Query<SettlementFilter>,
+) -> Response {
+    match stats_filter(filter) {
+        Ok(filter) => Json(facilitator.ledger().aggregate(&filter, |settlements| {
+            settlement_stats::failures(settlements)
+        }))
+        .into_response(),
+        Err(error) => (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response(),
+    }
+}
+
 fn invalid_schema(payer: Option<MixedAddress>) -> VerifyResponse {
     VerifyResponse::invalid(payer, FacilitatorErrorReason::InvalidScheme)
 }
 
+/// `POST /config/reload`: Reloads the configuration, as SIGHUP does.
+///
+/// Responds with whether some changes only apply on restart, or with `422` and the reason the
+/// configuration was rejected, in which case the current one is kept.
+#[instrument(skip_all)]
+pub async fn post_config_reload(
+    State(reloader): State<std::sync::Arc<ConfigReloader>>,
+) -> impl IntoResponse {
+    match reloader.reload() {
+        Ok(restart_required) => {
+            tracing::info!(restart_required, "Configuration reloaded");
+            (
+                StatusCode::OK,
+                Json(json!({ "restartRequired": restart_required })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Rejected reloaded configuration, keeping the current one");
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
 impl IntoResponse for FacilitatorLocalError {
     fn into_response(self) -> Response {
         let error = self;
@@ -290,7 +611,8 @@ impl IntoResponse for FacilitatorLocalError {
                 .into_response(),
             FacilitatorLocalError::ContractCall(..)
             | FacilitatorLocalError::InvalidAddress(..)
-            | FacilitatorLocalError::ClockError(_) => bad_request,
+            | FacilitatorLocalError::ClockError(_)
+            | FacilitatorLocalError::AmbiguousTransaction(_) => bad_request,
             FacilitatorLocalError::DecodingError(reason) => (
                 StatusCode::OK,
                 Json(VerifyResponse::invalid(
@@ -307,6 +629,234 @@ impl IntoResponse for FacilitatorLocalError {
                 )),
             )
                 .into_response(),
+            FacilitatorLocalError::InvalidFee(payer, _) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::InvalidFee,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::ShuttingDown => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse {
+                    error: "Facilitator is shutting down".to_string(),
+                }),
+            )
+                .into_response(),
+            FacilitatorLocalError::NotBroadcast(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse {
+                    error: "Facilitator can not settle now".to_string(),
+                }),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::NetworkProvider;
+    use crate::chain::evm::EvmProvider;
+    use alloy::primitives::{B256, U64};
+    use alloy::transports::mock::Asserter;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    const TX_HASH: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+
+    /// Answers a receipt lookup with a successful transaction mined in block 10.
+    fn push_mined(asserter: &Asserter) {
+        asserter.push_success(&serde_json::json!({
+            "type": "0x2",
+            "status": "0x1",
+            "cumulativeGasUsed": "0x5208",
+            "logs": [],
+            "logsBloom": alloy::primitives::Bloom::ZERO,
+            "transactionHash": TX_HASH,
+            "transactionIndex": "0x0",
+            "blockHash": B256::repeat_byte(0x01),
+            "blockNumber": "0xa",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x1",
+            "from": "0x0000000000000000000000000000000000000001",
+            "to": "0x0000000000000000000000000000000000000002",
+            "contractAddress": null,
+        }));
+        asserter.push_success(&U64::from(12));
+    }
+
+    /// Answers the receipt and transaction lookups of an unknown transaction.
+    fn push_not_found(asserter: &Asserter) {
+        asserter.push_success(&None::<()>);
+        asserter.push_success(&None::<()>);
+    }
+
+    /// Transaction status routes over Monad and Monad testnet providers, with their RPC mocks.
+    fn app() -> (Router, Asserter, Asserter) {
+        let (monad, testnet) = (Asserter::new(), Asserter::new());
+        let providers = ProviderCache::from_iter([
+            (
+                Network::Monad,
+                NetworkProvider::Evm(EvmProvider::mocked(Network::Monad, monad.clone())),
+            ),
+            (
+                Network::MonadTestnet,
+                NetworkProvider::Evm(EvmProvider::mocked(Network::MonadTestnet, testnet.clone())),
+            ),
+        ]);
+        let facilitator = std::sync::Arc::new(FacilitatorLocal::new(providers));
+        let app = routes_with_transaction_status().with_state(facilitator);
+        (app, monad, testnet)
+    }
+
+    async fn get_status(app: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_operator_routes_require_the_admin_token() {
+        let facilitator = std::sync::Arc::new(FacilitatorLocal::new(ProviderCache::from_iter([])));
+        let reloader = std::sync::Arc::new(ConfigReloader::new(
+            crate::config::ConfigLoader::new(None),
+            crate::config::Config::default(),
+            facilitator.clone(),
+            std::sync::Arc::new(crate::rate_limit::RateLimiter::new(None)),
+        ));
+        let token = std::sync::Arc::new(AdminToken::new("s3cret").unwrap());
+        let app = operator_routes(facilitator, reloader, token);
+
+        let routes = [
+            ("GET", "/settlements"),
+            ("GET", "/settlements/1"),
+            ("GET", "/settlements/export?format=ndjson"),
+            ("GET", "/stats/daily"),
+            ("GET", "/stats/failures"),
+            ("POST", "/config/reload"),
+        ];
+        for (method, uri) in routes {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, "Bearer wrong")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
         }
+
+        let request = Request::get("/settlements/export")
+            .header(header::AUTHORIZATION, "Bearer s3cret")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.starts_with(b"id,settledAt,"));
+    }
+
+    #[tokio::test]
+    async fn test_transaction_status_fans_out_over_networks_of_the_hash() {
+        let (app, monad, testnet) = app();
+        push_not_found(&monad);
+        push_mined(&testnet);
+
+        let (status, body) = get_status(app, &format!("/transaction/{TX_HASH}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "confirmed");
+        assert_eq!(body["network"], "monad-testnet");
+        assert_eq!(body["confirmations"], 2);
+        assert!(monad.read_q().is_empty() && testnet.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_status_not_found_has_no_network() {
+        let (app, monad, testnet) = app();
+        push_not_found(&monad);
+        push_not_found(&testnet);
+
+        let (status, body) = get_status(app, &format!("/transaction/{TX_HASH}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "notfound");
+        assert!(body.get("network").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_status_found_on_several_networks_is_a_conflict() {
+        let (app, monad, testnet) = app();
+        push_mined(&monad);
+        push_mined(&testnet);
+
+        let (status, body) = get_status(app.clone(), &format!("/transaction/{TX_HASH}")).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let error = body["error"].as_str().unwrap();
+        assert!(error.contains("monad, monad-testnet") || error.contains("monad-testnet, monad"));
+
+        // Scoping the lookup to one network resolves it
+        push_mined(&monad);
+        let uri = format!("/transaction/{TX_HASH}?network=monad");
+        let (status, body) = get_status(app, &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["network"], "monad");
+        assert!(testnet.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_network_scoped_transaction_status_queries_one_network() {
+        let (app, monad, testnet) = app();
+
+        // Looked up on the testnet only, through either route
+        push_not_found(&testnet);
+        let uri = format!("/transaction/monad-testnet/{TX_HASH}");
+        let (status, body) = get_status(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "notfound");
+        assert_eq!(body["network"], "monad-testnet");
+
+        push_not_found(&testnet);
+        let uri = format!("/transaction/{TX_HASH}?network=monad-testnet");
+        let (status, body) = get_status(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["network"], "monad-testnet");
+        assert!(monad.read_q().is_empty() && testnet.read_q().is_empty());
+
+        // A network that is not configured, or of another family than the hash
+        let uri = format!("/transaction/solana/{TX_HASH}");
+        let (status, _) = get_status(app, &uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_stats_cover_a_bounded_range() {
+        let to = UnixTimestamp(1_700_000_000);
+        let filter = stats_filter(SettlementFilter {
+            to: Some(to),
+            ..SettlementFilter::default()
+        })
+        .unwrap();
+        assert_eq!(
+            filter.from,
+            Some(UnixTimestamp(1_700_000_000 - DEFAULT_STATS_RANGE))
+        );
+
+        let filter = stats_filter(SettlementFilter::default()).unwrap();
+        assert!(filter.from.is_some());
+
+        let too_long = SettlementFilter {
+            from: Some(UnixTimestamp(0)),
+            to: Some(to),
+            ..SettlementFilter::default()
+        };
+        assert!(stats_filter(too_long).is_err());
     }
 }
@@ -6,23 +6,43 @@
 //! It is designed for reuse across all x402 roles:
 //! - _Facilitator_: a server that verifies and settles x402 payments (see [`facilitator`] and [`facilitator_local`])
 //! - _Seller_: a payment-gated service that consumes shared types from [`types`]
-//! - _Buyer_: a client that constructs and submits x402-compliant payments
+//! - _Buyer_: a client that constructs and submits x402-compliant payments (see [`buyer`])
 //!
 //! Modules:
+//! - [`admin_auth`] — bearer token authentication of the operator routes.
+//! - [`buyer`] — buyer-side construction of signed x402 payment payloads for EVM and Solana.
+//! - [`config`] — typed TOML/YAML configuration file, overlaid with environment variables.
 //! - [`facilitator`] — defines the [`facilitator::Facilitator`] trait used to validate and settle x402 payments.
 //! - [`facilitator_local`] — a concrete implementation of [`facilitator::Facilitator`].
+//! - [`facilitator_client`] — a [`facilitator::Facilitator`] backed by a remote facilitator over HTTP.
+//! - [`facilitator_router`] — a [`facilitator::Facilitator`] failing over across local and remote facilitators.
+//! - [`middleware`] — seller-side [`middleware::X402Layer`] gating axum/tower services behind x402 payments.
 //! - [`network`] — enumerates supported Ethereum-compatible networks and known token deployments.
+//! - [`pending_settlements`] — tracking of in-flight settlements, drained and persisted on shutdown.
 //! - [`provider_cache`] — dynamic initialization and caching of Ethereum JSON-RPC providers.
+//! - [`rate_limit`] — per-client rate limiting of the facilitator endpoints, reloadable at runtime.
+//! - [`settlement_ledger`] — searchable history of settlements, watched for finality and reorganizations.
+//! - [`settlement_stats`] — revenue and usage aggregates of the settlement history.
 //! - [`telemetry`] — OpenTelemetry instrumentation setup for tracing and observability.
 //! - [`types`] — all shared x402 protocol structures and payload formats.
 
+pub mod admin_auth;
+pub mod buyer;
 pub mod chain;
+pub mod config;
 pub mod facilitator;
+pub mod facilitator_client;
 pub mod facilitator_local;
+pub mod facilitator_router;
 pub mod from_env;
 pub mod handlers;
+pub mod middleware;
 pub mod network;
+pub mod pending_settlements;
 pub mod provider_cache;
+pub mod rate_limit;
+pub mod settlement_ledger;
+pub mod settlement_stats;
 pub mod sig_down;
 pub mod telemetry;
 pub mod timestamp;
@@ -9,56 +9,120 @@
 //! - `GET /settle` – Supported settlement schema
 //! - `POST /settle` – Settle an accepted payment payload on-chain
 //! - `GET /supported` – List supported payment kinds (version/scheme/network)
+//! - `POST /config/reload` – Reload the rate limits and network policies
 //!
 //! This server includes:
 //! - OpenTelemetry tracing via `TraceLayer`
 //! - CORS support for cross-origin clients
+//! - Per-client rate limiting, see [`rate_limit`]
 //! - Ethereum provider cache for per-network RPC routing
 //!
 //! Environment:
 //! - `.env` values loaded at startup
+//! - `--config <file>` reads a TOML or YAML configuration file, see [`config`]; env vars override it
 //! - `HOST`, `PORT` control binding address
 //! - `OTEL_*` variables enable tracing to systems like Honeycomb
+//!
+//! Other subcommands serve operators, see [`cli`]; `x402-facilitator config check` for one
+//! validates the configuration and prints it, secrets redacted.
 
 use axum::Router;
 use axum::http::Method;
+use clap::Parser;
 use dotenvy::dotenv;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_http::cors;
 
+use crate::admin_auth::AdminToken;
+use crate::cli::{Cli, Command, ConfigCommand};
+use crate::config::{Config, ConfigLoader, ConfigReloader};
 use crate::facilitator_local::FacilitatorLocal;
+use crate::pending_settlements::{SettlementNotifier, SettlementTracker};
 use crate::provider_cache::ProviderCache;
-use crate::rate_limit::RateLimitConfig;
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::settlement_ledger::SettlementLedger;
 use crate::sig_down::SigDown;
 use crate::telemetry::Telemetry;
 
+mod admin_auth;
 mod chain;
+mod cli;
+mod config;
 mod facilitator;
 mod facilitator_local;
 mod from_env;
 mod handlers;
 mod network;
+mod pending_settlements;
 mod provider_cache;
 mod rate_limit;
+mod settlement_ledger;
+mod settlement_stats;
 mod sig_down;
 mod telemetry;
 mod timestamp;
 mod types;
 
+/// Loads `.env` variables and the configuration, then runs the command, by default the server.
+///
+/// The configuration is exported to the environment before the Tokio runtime starts any thread.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Load .env variables
+    dotenv().ok();
+
+    let cli = Cli::parse();
+    let config_loader = ConfigLoader::new(cli.config);
+    let config = match config_loader.load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Config(ConfigCommand::Check) => {
+            print!("{}", config.to_redacted_toml()?);
+            Ok(())
+        }
+        Command::DecodePayment { payment } => cli::decode_payment(&payment),
+        Command::Serve => {
+            config.export_env();
+            runtime()?.block_on(serve(config_loader, config))
+        }
+        Command::Provider(command) => {
+            config.export_env();
+            runtime()?.block_on(cli::run(command))
+        }
+    }
+}
+
+fn runtime() -> std::io::Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+}
+
 /// Initializes the x402 facilitator server.
 ///
-/// - Loads `.env` variables.
 /// - Initializes OpenTelemetry tracing.
 /// - Connects to Ethereum providers for supported networks.
 /// - Starts an Axum HTTP server with the x402 protocol handlers.
 ///
-/// Binds to the address specified by the `HOST` and `PORT` env vars.
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Load .env variables
-    dotenv().ok();
-
+/// Binds to the address specified by the `HOST` and `PORT` env vars. Requests are rate limited
+/// per client, see [`rate_limit`]. On SIGHUP or `POST /config/reload`, reloads the configuration
+/// with `config_loader` and applies the rate limits and network policies, see [`ConfigReloader`].
+///
+/// Settlements left pending by a previous run are reconciled before serving. Settlements are
+/// recorded in the history at `SETTLEMENT_LEDGER_PATH`, and successful ones watched until final,
+/// see [`settlement_ledger`]. On shutdown, drains
+/// in-flight settlements up to `SETTLE_DRAIN_TIMEOUT_SECS`; those still pending stay in the
+/// journal, see [`pending_settlements`].
+async fn serve(
+    config_loader: ConfigLoader,
+    config: Config,
+) -> Result<(), Box<dyn std::error::Error>> {
     let telemetry = Telemetry::new()
         .with_name(env!("CARGO_PKG_NAME"))
         .with_version(env!("CARGO_PKG_VERSION"))
@@ -73,11 +137,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(1);
         }
     };
-    let facilitator = FacilitatorLocal::new(provider_cache);
+    // Settle what a previous run left pending before accepting new settlements
+    let settlements = SettlementTracker::open(&pending_settlements::pending_settlements_path())?;
+    let notifier = SettlementNotifier::from_env()?;
+    pending_settlements::reconcile(&provider_cache, &settlements, &notifier).await;
+
+    let ledger = SettlementLedger::open(&settlement_ledger::settlement_ledger_path())?;
+
+    let facilitator = FacilitatorLocal::new(provider_cache)
+        .with_settlements(settlements.clone())
+        .with_ledger(ledger.clone());
     let axum_state = Arc::new(facilitator);
+    let watch_state = axum_state.clone();
 
     // Load rate limiting configuration
-    let rate_limit_config = RateLimitConfig::from_env();
+    let rate_limit_config = RateLimitConfig::from_env()?;
     if let Some(ref config) = rate_limit_config {
         tracing::info!(
             verify_per_minute = config.verify_per_minute,
@@ -89,20 +163,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     } else {
         tracing::info!("Rate limiting disabled");
     }
+    let rate_limiter = Arc::new(RateLimiter::new(rate_limit_config));
+    let reloader = Arc::new(ConfigReloader::new(
+        config_loader,
+        config,
+        axum_state.clone(),
+        rate_limiter.clone(),
+    ));
+    let sighup_reloader = reloader.clone();
+
+    let mut http_endpoints = Router::new();
+    match AdminToken::from_env()? {
+        Some(token) => {
+            http_endpoints = http_endpoints.merge(handlers::operator_routes(
+                axum_state.clone(),
+                reloader,
+                Arc::new(token),
+            ));
+        }
+        None => tracing::warn!(
+            "{} is not set, operator routes are disabled",
+            admin_auth::ENV_ADMIN_API_TOKEN
+        ),
+    }
 
     // Build routes with rate limiting
-    let mut http_endpoints = Router::new()
-        .merge(handlers::routes_with_transaction_status().with_state(axum_state));
-
-    // TODO: Rate limiting is currently disabled due to Clone trait bound issues with RateLimit.
-    // The tower::limit::RateLimitLayer's service type (RateLimit) doesn't implement Clone,
-    // which is required by axum's Router::layer(). This needs to be addressed with an
-    // axum-compatible rate limiting solution or by wrapping the rate limiter appropriately.
-    // if let Some(config) = rate_limit_config {
-    //     http_endpoints = http_endpoints.layer(config.general_layer());
-    // }
-
-    http_endpoints = http_endpoints
+    let http_endpoints = http_endpoints
+        .merge(handlers::routes_with_transaction_status().with_state(axum_state))
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limiter,
+            rate_limit::rate_limit,
+        ))
         .layer(telemetry.http_tracing())
         .layer(
             cors::CorsLayer::new()
@@ -127,12 +218,63 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(1);
         });
 
+    tokio::spawn(async move {
+        settlement_ledger::watch_settlements(watch_state.ledger(), watch_state.provider_map()).await
+    });
+
     let sig_down = SigDown::try_new()?;
+    let reloads = sig_down.reloads();
+    tokio::spawn(async move {
+        loop {
+            reloads.notified().await;
+            match sighup_reloader.reload() {
+                Ok(restart_required) => {
+                    if restart_required {
+                        tracing::warn!(
+                            "Configuration changes beyond rate limits and network policies apply on restart"
+                        );
+                    }
+                    tracing::info!("Configuration reloaded");
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Rejected reloaded configuration, keeping the current one");
+                }
+            }
+        }
+    });
     let axum_cancellation_token = sig_down.cancellation_token();
     let axum_graceful_shutdown = async move { axum_cancellation_token.cancelled().await };
-    axum::serve(listener, http_endpoints)
+    let server = axum::serve(
+        listener,
+        http_endpoints.into_make_service_with_connect_info::<SocketAddr>(),
+    )
         .with_graceful_shutdown(axum_graceful_shutdown)
-        .await?;
+        .into_future();
+    tokio::pin!(server);
+    let shutdown = sig_down.cancellation_token();
+    // On shutdown both are ready: settlements are drained and the ledger flushed either way
+    let stopped = tokio::select! {
+        biased;
+        _ = shutdown.cancelled() => None,
+        result = &mut server => Some(result),
+    };
+
+    // Connections keep serving the requests in flight, while new settlements get a 503
+    let pending = settlements
+        .drain(pending_settlements::drain_timeout())
+        .await;
+    if !pending.is_empty() {
+        tracing::warn!(
+            count = pending.len(),
+            "Settlements left pending, to reconcile at next start"
+        );
+    }
+    let result = match stopped {
+        Some(result) => result,
+        None if settlements.in_flight() == 0 => server.await,
+        None => Ok(()),
+    };
+    ledger.flush().await;
 
-    Ok(())
+    Ok(result?)
 }
@@ -0,0 +1,312 @@
+//! Seller-side x402 middleware for axum/tower services.
+//!
+//! [`X402Layer`] gates the wrapped service behind an x402 payment:
+//!
+//! 1. Requests without a valid `X-PAYMENT` header get `402 Payment Required` with a
+//!    [`PaymentRequiredResponse`] listing the accepted [`PaymentRequirements`].
+//! 2. The payment is verified through a [`Facilitator`] before the inner service runs.
+//! 3. If the inner service responds with a success status, the payment is settled and the
+//!    base64-encoded [`SettleResponse`] is returned in the `X-PAYMENT-RESPONSE` header.
+//!    Error responses are passed through without settling.
+//!
+//! ```ignore
+//! let app = Router::new()
+//!     .route("/weather", get(weather))
+//!     .layer(X402Layer::new(facilitator).with_requirements(requirements));
+//! ```
+
+use axum::Json;
+use axum::body::Body;
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use crate::facilitator::Facilitator;
+use crate::types::{
+    Base64Bytes, PaymentPayload, PaymentRequiredResponse, PaymentRequirements, SettleResponse,
+    VerifyRequest, VerifyResponse, X402Version,
+};
+
+/// Request header carrying the base64-encoded [`PaymentPayload`].
+pub const X_PAYMENT: &str = "X-PAYMENT";
+/// Response header carrying the base64-encoded [`SettleResponse`].
+pub const X_PAYMENT_RESPONSE: &str = "X-PAYMENT-RESPONSE";
+
+/// A [`Layer`] that requires an x402 payment for every request to the wrapped service.
+///
+/// The price is configured through the accepted [`PaymentRequirements`]:
+/// `max_amount_required` of `asset` on `network`, paid to `pay_to`.
+pub struct X402Layer<F> {
+    facilitator: Arc<F>,
+    accepts: Arc<Vec<PaymentRequirements>>,
+}
+
+impl<F> Clone for X402Layer<F> {
+    fn clone(&self) -> Self {
+        Self {
+            facilitator: Arc::clone(&self.facilitator),
+            accepts: Arc::clone(&self.accepts),
+        }
+    }
+}
+
+impl<F> X402Layer<F> {
+    /// Creates a layer that verifies and settles payments through `facilitator`.
+    ///
+    /// At least one set of requirements must be added with [`Self::with_requirements`].
+    pub fn new(facilitator: F) -> Self {
+        Self {
+            facilitator: Arc::new(facilitator),
+            accepts: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Adds an accepted way to pay, e.g. USDC on one network.
+    pub fn with_requirements(mut self, requirements: PaymentRequirements) -> Self {
+        Arc::make_mut(&mut self.accepts).push(requirements);
+        self
+    }
+}
+
+impl<S, F> Layer<S> for X402Layer<F> {
+    type Service = X402Service<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        X402Service {
+            inner,
+            facilitator: Arc::clone(&self.facilitator),
+            accepts: Arc::clone(&self.accepts),
+        }
+    }
+}
+
+/// The [`Service`] produced by [`X402Layer`].
+pub struct X402Service<S, F> {
+    inner: S,
+    facilitator: Arc<F>,
+    accepts: Arc<Vec<PaymentRequirements>>,
+}
+
+impl<S: Clone, F> Clone for X402Service<S, F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            facilitator: Arc::clone(&self.facilitator),
+            accepts: Arc::clone(&self.accepts),
+        }
+    }
+}
+
+impl<S, F> Service<Request<Body>> for X402Service<S, F>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send,
+    F: Facilitator + Send + Sync + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Take the service that was driven to readiness, leaving a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let facilitator = Arc::clone(&self.facilitator);
+        let accepts = Arc::clone(&self.accepts);
+        Box::pin(async move {
+            let payment_request = match payment_request(&request, &accepts) {
+                Ok(payment_request) => payment_request,
+                Err(error) => return Ok(payment_required(error, &accepts)),
+            };
+            match facilitator.verify(&payment_request).await {
+                Ok(VerifyResponse::Valid { .. }) => {}
+                Ok(VerifyResponse::Invalid { reason, .. }) => {
+                    return Ok(payment_required(reason.to_string(), &accepts));
+                }
+                Err(error) => {
+                    tracing::warn!(error = %error, "x402 payment verification failed");
+                    return Ok(payment_required(error.to_string(), &accepts));
+                }
+            }
+
+            let mut response = inner.call(request).await?;
+            if !response.status().is_success() {
+                return Ok(response);
+            }
+
+            let settlement = match facilitator.settle(&payment_request).await {
+                Ok(settlement) if settlement.success => settlement,
+                Ok(settlement) => {
+                    let error = settlement
+                        .error_reason
+                        .map(|reason| reason.to_string())
+                        .unwrap_or_else(|| "Settlement failed".to_string());
+                    return Ok(payment_required(error, &accepts));
+                }
+                Err(error) => {
+                    tracing::warn!(error = %error, "x402 payment settlement failed");
+                    return Ok(payment_required(error.to_string(), &accepts));
+                }
+            };
+            match settlement_header(settlement) {
+                Some(header) => {
+                    response.headers_mut().insert(X_PAYMENT_RESPONSE, header);
+                }
+                None => tracing::warn!("failed to encode x402 settlement response header"),
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Builds the facilitator request from the `X-PAYMENT` header and the matching requirements.
+fn payment_request(
+    request: &Request<Body>,
+    accepts: &[PaymentRequirements],
+) -> Result<VerifyRequest, String> {
+    let header = request
+        .headers()
+        .get(X_PAYMENT)
+        .ok_or_else(|| "X-PAYMENT header is required".to_string())?;
+    let payment_payload = PaymentPayload::try_from(Base64Bytes::from(header.as_bytes()))
+        .map_err(|e| format!("Invalid or malformed payment header: {e}"))?;
+    let payment_requirements = accepts
+        .iter()
+        .find(|requirements| {
+            requirements.scheme == payment_payload.scheme
+                && requirements.network == payment_payload.network
+        })
+        .cloned()
+        .ok_or_else(|| "Unable to find matching payment requirements".to_string())?;
+    Ok(VerifyRequest {
+        x402_version: payment_payload.x402_version,
+        payment_payload,
+        payment_requirements,
+    })
+}
+
+fn payment_required(error: String, accepts: &[PaymentRequirements]) -> Response {
+    let body = PaymentRequiredResponse {
+        error,
+        accepts: accepts.to_vec(),
+        x402_version: X402Version::V1,
+    };
+    (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+}
+
+fn settlement_header(settlement: SettleResponse) -> Option<HeaderValue> {
+    let encoded: Base64Bytes = settlement.try_into().ok()?;
+    HeaderValue::from_bytes(encoded.as_ref()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_fixtures::settle_request;
+    use crate::types::{MixedAddress, SettleRequest, SupportedPaymentKindsResponse};
+    use axum::Router;
+    use axum::routing::get;
+    use solana_sdk::pubkey::Pubkey;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    #[derive(Default)]
+    struct MockFacilitator {
+        settled: AtomicUsize,
+    }
+
+    impl Facilitator for MockFacilitator {
+        type Error = String;
+
+        async fn verify(&self, _request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+            Ok(VerifyResponse::valid(MixedAddress::Solana(
+                Pubkey::default(),
+            )))
+        }
+
+        async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+            self.settled.fetch_add(1, Ordering::SeqCst);
+            Ok(SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: MixedAddress::Solana(Pubkey::default()),
+                transaction: None,
+                network: request.payment_requirements.network,
+            })
+        }
+
+        async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+            Ok(SupportedPaymentKindsResponse { kinds: vec![] })
+        }
+    }
+
+    fn app(facilitator: Arc<MockFacilitator>) -> Router {
+        Router::new()
+            .route("/paid", get(|| async { "paid content" }))
+            .route(
+                "/broken",
+                get(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+            )
+            .layer(
+                X402Layer::new(facilitator)
+                    .with_requirements(settle_request(1_000).payment_requirements),
+            )
+    }
+
+    fn payment_header() -> String {
+        let payload = serde_json::json!({
+            "x402Version": 1,
+            "scheme": "exact",
+            "network": "solana-devnet",
+            "payload": { "transaction": "AA==" },
+        });
+        let encoded = Base64Bytes::encode(payload.to_string());
+        String::from_utf8(encoded.0.into_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_missing_payment_header_returns_402() {
+        let facilitator = Arc::new(MockFacilitator::default());
+        let request = Request::get("/paid").body(Body::empty()).unwrap();
+        let response = app(facilitator.clone()).oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(facilitator.settled.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_valid_payment_is_settled_after_handler() {
+        let facilitator = Arc::new(MockFacilitator::default());
+        let request = Request::get("/paid")
+            .header(X_PAYMENT, payment_header())
+            .body(Body::empty())
+            .unwrap();
+        let response = app(facilitator.clone()).oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().contains_key(X_PAYMENT_RESPONSE));
+        assert_eq!(facilitator.settled.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_handler_is_not_settled() {
+        let facilitator = Arc::new(MockFacilitator::default());
+        let request = Request::get("/broken")
+            .header(X_PAYMENT, payment_header())
+            .body(Body::empty())
+            .unwrap();
+        let response = app(facilitator.clone()).oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!response.headers().contains_key(X_PAYMENT_RESPONSE));
+        assert_eq!(facilitator.settled.load(Ordering::SeqCst), 0);
+    }
+}
@@ -43,7 +43,20 @@ impl Display for Network {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl FromStr for Network {
+    type Err = String;
+
+    /// Parses a network by its name, like `solana-devnet`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Network::variants()
+            .iter()
+            .find(|network| network.to_string() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown network: {s}"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NetworkFamily {
     Evm,
     Solana,
@@ -70,6 +83,14 @@ impl Network {
             Network::SolanaDevnet,
         ]
     }
+
+    /// Decimals of the native coin: 18 for MON, 9 for SOL.
+    pub fn native_decimals(&self) -> u8 {
+        match NetworkFamily::from(*self) {
+            NetworkFamily::Evm => 18,
+            NetworkFamily::Solana => 9,
+        }
+    }
 }
 
 /// Lazily initialized known USDC deployment on Monad mainnet as [`USDCDeployment`].
@@ -0,0 +1,968 @@
+//! Tracking of in-flight settlements, so that neither a shutdown nor a crash loses them.
+//!
+//! [`SettlementTracker`] counts settlements from the moment the facilitator accepts them until it
+//! responds. Once a provider broadcasts the settlement transaction, it records a
+//! [`PendingSettlement`] with [`broadcast`], until it gets the receipt or an error and calls
+//! [`settled`]. Only a settlement cut short by a shutdown stays pending.
+//! A tracker opened with [`SettlementTracker::open`] appends the changes to its pending settlements
+//! to a journal file, from a background thread.
+//!
+//! On shutdown, [`SettlementTracker::drain`] refuses new settlements, and gives the in-flight ones
+//! up to a deadline to complete. At the next start, [`reconcile`] looks up the outcome of every
+//! settlement left in the journal, notifies it with a [`SettlementNotifier`], and reseeds the
+//! nonces of the EVM signers.
+//!
+//! Environment:
+//! - `SETTLE_DRAIN_TIMEOUT_SECS` – how long a shutdown waits for in-flight settlements, 30 by default
+//! - `PENDING_SETTLEMENTS_PATH` – journal of pending settlements, `pending-settlements.ndjson` by default
+//! - `SETTLEMENT_WEBHOOK_URL` – where reconciled settlements are posted, if set
+
+use alloy::primitives::Address;
+use dashmap::DashMap;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio_util::task::TaskTracker;
+use url::Url;
+
+use crate::chain::{NetworkProvider, TransactionStatusQuery};
+use crate::network::Network;
+use crate::provider_cache::ProviderMap;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    MixedAddress, SettleRequest, TransactionHash, TransactionStatus, TransactionStatusResponse,
+};
+
+pub const ENV_SETTLE_DRAIN_TIMEOUT_SECS: &str = "SETTLE_DRAIN_TIMEOUT_SECS";
+pub const ENV_PENDING_SETTLEMENTS_PATH: &str = "PENDING_SETTLEMENTS_PATH";
+pub const ENV_SETTLEMENT_WEBHOOK_URL: &str = "SETTLEMENT_WEBHOOK_URL";
+
+/// How long after its broadcast a transaction unknown to the node is considered dropped.
+///
+/// A Solana transaction expires with its blockhash within about two minutes, unless it uses a
+/// durable nonce; an EVM transaction still unknown after this long has left the mempool.
+const DROPPED_AFTER: Duration = Duration::from_secs(600);
+
+/// A settlement transaction that was broadcast, and whose receipt was not received.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSettlement {
+    pub network: Network,
+    pub transaction: TransactionHash,
+    pub signer: MixedAddress,
+    /// Nonce of the transaction, on EVM networks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    pub request: SettleRequest,
+    pub broadcast_at: UnixTimestamp,
+}
+
+/// Number of records appended to the journal file after which it is compacted.
+const COMPACT_AFTER: usize = 1_000;
+
+/// A change to the pending settlements, as appended to the journal file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum JournalRecord {
+    Broadcast(Box<PendingSettlement>),
+    Settled(TransactionHash),
+}
+
+/// A request to the thread writing the journal file.
+enum JournalWrite {
+    Append(JournalRecord),
+    /// Answered once the writes requested before are done.
+    Flush(tokio::sync::oneshot::Sender<()>),
+}
+
+/// Pending settlements by transaction, written through to a journal file when it has a writer.
+struct Journal {
+    entries: Arc<DashMap<String, PendingSettlement>>,
+    writer: Option<std::sync::mpsc::Sender<JournalWrite>>,
+}
+
+impl Journal {
+    fn insert(&self, settlement: PendingSettlement) {
+        self.entries
+            .insert(settlement.transaction.to_string(), settlement.clone());
+        self.write(JournalWrite::Append(JournalRecord::Broadcast(Box::new(
+            settlement,
+        ))));
+    }
+
+    fn remove(&self, transaction: &TransactionHash) {
+        if self.entries.remove(&transaction.to_string()).is_some() {
+            self.write(JournalWrite::Append(JournalRecord::Settled(
+                transaction.clone(),
+            )));
+        }
+    }
+
+    fn snapshot(&self) -> Vec<PendingSettlement> {
+        snapshot(&self.entries)
+    }
+
+    fn write(&self, write: JournalWrite) {
+        if let Some(writer) = &self.writer {
+            // The writer thread only stops once the journal is dropped
+            let _ = writer.send(write);
+        }
+    }
+
+    /// Waits for the writes of the journal file requested so far.
+    async fn flush(&self) {
+        if self.writer.is_some() {
+            let (flushed, on_flushed) = tokio::sync::oneshot::channel();
+            self.write(JournalWrite::Flush(flushed));
+            let _ = on_flushed.await;
+        }
+    }
+}
+
+fn snapshot(entries: &DashMap<String, PendingSettlement>) -> Vec<PendingSettlement> {
+    let mut settlements: Vec<_> = entries.iter().map(|entry| entry.value().clone()).collect();
+    settlements.sort_by_key(|settlement| settlement.broadcast_at);
+    settlements
+}
+
+fn append(file: &mut std::fs::File, record: &JournalRecord) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    file.write_all(&line)
+}
+
+/// Appends the records sent to `writes` to the journal at `path`, off the async runtime.
+///
+/// Once it holds [`COMPACT_AFTER`] records more than `entries`, the file is rewritten with one
+/// record per entry. Records of changes made meanwhile are appended after, and replay to the same
+/// entries.
+fn write_journal(
+    path: PathBuf,
+    entries: Arc<DashMap<String, PendingSettlement>>,
+    writes: std::sync::mpsc::Receiver<JournalWrite>,
+) {
+    let open = |path: &Path| {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+    };
+    let mut file = open(&path);
+    let mut appended = 0;
+    for write in writes {
+        let record = match write {
+            JournalWrite::Append(record) => record,
+            JournalWrite::Flush(flushed) => {
+                let _ = flushed.send(());
+                continue;
+            }
+        };
+        let result = match &mut file {
+            Ok(file) => append(file, &record),
+            Err(e) => Err(std::io::Error::new(e.kind(), e.to_string())),
+        };
+        if let Err(e) = result {
+            tracing::error!(error = %e, path = %path.display(), "Failed to persist pending settlements");
+            // Compacting rewrites every entry, the one just lost included
+            appended = COMPACT_AFTER;
+        }
+        appended += 1;
+        if appended >= COMPACT_AFTER {
+            match save(&path, &snapshot(&entries)) {
+                Ok(()) => appended = 0,
+                Err(e) => {
+                    tracing::error!(error = %e, path = %path.display(), "Failed to compact pending settlements");
+                }
+            }
+            file = open(&path);
+        }
+    }
+}
+
+/// The settlement being processed by the current task, see [`SettlementTracker::track`].
+#[derive(Clone)]
+struct CurrentSettlement {
+    request: SettleRequest,
+    journal: Arc<Journal>,
+}
+
+tokio::task_local! {
+    static CURRENT_SETTLEMENT: CurrentSettlement;
+}
+
+/// Keeps count of in-flight settlements, and of their broadcast transactions.
+#[derive(Clone)]
+pub struct SettlementTracker {
+    draining: Arc<AtomicBool>,
+    tasks: TaskTracker,
+    journal: Arc<Journal>,
+}
+
+impl Default for SettlementTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettlementTracker {
+    /// A tracker keeping its pending settlements in memory only.
+    pub fn new() -> Self {
+        Self::from_journal(Journal {
+            entries: Arc::new(DashMap::new()),
+            writer: None,
+        })
+    }
+
+    /// A tracker writing its pending settlements through to the journal at `path`.
+    ///
+    /// Settlements already in the journal, left by a previous run, are loaded as pending, and the
+    /// journal compacted to them. Changes are then appended to it by a background thread.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let pending = load(path)?;
+        save(path, &pending)?;
+        let entries: Arc<DashMap<_, _>> = Arc::new(
+            pending
+                .into_iter()
+                .map(|settlement| (settlement.transaction.to_string(), settlement))
+                .collect(),
+        );
+        let (writer, writes) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("pending-settlements".to_string())
+            .spawn({
+                let path = path.to_path_buf();
+                let entries = entries.clone();
+                move || write_journal(path, entries, writes)
+            })?;
+        Ok(Self::from_journal(Journal {
+            entries,
+            writer: Some(writer),
+        }))
+    }
+
+    fn from_journal(journal: Journal) -> Self {
+        Self {
+            draining: Arc::new(AtomicBool::new(false)),
+            tasks: TaskTracker::new(),
+            journal: Arc::new(journal),
+        }
+    }
+
+    /// Runs `settle`, the settlement of `request`, as an in-flight settlement.
+    ///
+    /// Returns `None` without running it if the tracker is draining.
+    pub async fn track<F: Future>(&self, request: &SettleRequest, settle: F) -> Option<F::Output> {
+        let current = CurrentSettlement {
+            request: request.clone(),
+            journal: self.journal.clone(),
+        };
+        // Counted before checking the flag, so that a drain can not miss a settlement it let in.
+        let tracked = self
+            .tasks
+            .track_future(CURRENT_SETTLEMENT.scope(current, settle));
+        if self.draining.load(Ordering::SeqCst) {
+            return None;
+        }
+        Some(tracked.await)
+    }
+
+    /// Number of settlements in flight.
+    pub fn in_flight(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Settlements broadcast and not settled yet, oldest first.
+    pub fn pending(&self) -> Vec<PendingSettlement> {
+        self.journal.snapshot()
+    }
+
+    /// Waits for the changes to the pending settlements so far to be written to the journal.
+    pub async fn flush(&self) {
+        self.journal.flush().await
+    }
+
+    /// Refuses new settlements, and waits up to `timeout` for the in-flight ones to complete.
+    ///
+    /// Returns the settlements still pending afterwards.
+    pub async fn drain(&self, timeout: Duration) -> Vec<PendingSettlement> {
+        self.draining.store(true, Ordering::SeqCst);
+        self.tasks.close();
+        tracing::info!(in_flight = self.tasks.len(), "Draining settlements");
+        if tokio::time::timeout(timeout, self.tasks.wait())
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                in_flight = self.tasks.len(),
+                "Settlements still in flight at the drain deadline"
+            );
+        }
+        self.flush().await;
+        self.pending()
+    }
+}
+
+/// Records that the settlement of the current task broadcast `transaction`.
+///
+/// Does nothing outside of [`SettlementTracker::track`].
+pub fn broadcast(
+    network: Network,
+    transaction: TransactionHash,
+    signer: MixedAddress,
+    nonce: Option<u64>,
+) {
+    let _ = CURRENT_SETTLEMENT.try_with(|current| {
+        let broadcast_at = UnixTimestamp::try_now().unwrap_or(UnixTimestamp(0));
+        current.journal.insert(PendingSettlement {
+            network,
+            transaction,
+            signer,
+            nonce,
+            request: current.request.clone(),
+            broadcast_at,
+        });
+    });
+}
+
+/// Records that the settlement of `transaction`, recorded with [`broadcast`], has an outcome:
+/// its receipt, or an error.
+pub fn settled(transaction: &TransactionHash) {
+    let _ = CURRENT_SETTLEMENT.try_with(|current| current.journal.remove(transaction));
+}
+
+/// Writes `pending` to the journal at `path`, replacing the file atomically.
+pub fn save(path: &Path, pending: &[PendingSettlement]) -> std::io::Result<()> {
+    let mut ndjson = Vec::new();
+    for settlement in pending {
+        let record = JournalRecord::Broadcast(Box::new(settlement.clone()));
+        serde_json::to_writer(&mut ndjson, &record)?;
+        ndjson.push(b'\n');
+    }
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, ndjson)?;
+    std::fs::rename(&partial, path)
+}
+
+/// Replays the journal at `path` into the settlements still pending. A missing file has none.
+///
+/// A last record cut short, as by a crash, is skipped.
+pub fn load(path: &Path) -> std::io::Result<Vec<PendingSettlement>> {
+    let ndjson = match std::fs::read(path) {
+        Ok(ndjson) => ndjson,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let entries = DashMap::new();
+    let mut lines = ndjson
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .peekable();
+    while let Some(line) = lines.next() {
+        match serde_json::from_slice(line) {
+            Ok(JournalRecord::Broadcast(settlement)) => {
+                entries.insert(settlement.transaction.to_string(), *settlement);
+            }
+            Ok(JournalRecord::Settled(transaction)) => {
+                entries.remove(&transaction.to_string());
+            }
+            Err(e) if lines.peek().is_none() => {
+                tracing::warn!(error = %e, path = %path.display(), "Skipped truncated pending settlement record");
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(snapshot(&entries))
+}
+
+/// A pending settlement whose outcome was found by [`reconcile`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciledSettlement {
+    pub settlement: PendingSettlement,
+    /// Either [`TransactionStatus::Confirmed`] or [`TransactionStatus::Failed`].
+    pub status: TransactionStatusResponse,
+}
+
+/// Looks up the outcome of the settlements pending in `tracker`, typically left by a previous run.
+///
+/// Settlements are looked up concurrently. Confirmed and failed settlements leave the journal, and
+/// are passed to `notifier`; others stay pending, to be reconciled at the next start. The nonces of
+/// the EVM signers found in the journal are then reseeded to follow their transactions still
+/// pending, see [`in_flight_nonces`].
+pub async fn reconcile<A>(
+    provider_map: &A,
+    tracker: &SettlementTracker,
+    notifier: &SettlementNotifier,
+) where
+    A: ProviderMap<Value = NetworkProvider>,
+{
+    let pending = tracker.pending();
+    if pending.is_empty() {
+        return;
+    }
+    tracing::info!(count = pending.len(), "Reconciling pending settlements");
+    let now = UnixTimestamp::try_now().unwrap_or(UnixTimestamp(0));
+    let outcomes = join_all(
+        pending
+            .iter()
+            .map(|settlement| reconcile_settlement(provider_map, settlement, now)),
+    )
+    .await;
+    let mut reconciled = Vec::new();
+    for (settlement, status) in pending.iter().zip(outcomes) {
+        if let Some(status) = status {
+            tracker.journal.remove(&settlement.transaction);
+            reconciled.push(ReconciledSettlement {
+                settlement: settlement.clone(),
+                status,
+            });
+        }
+    }
+    join_all(
+        reconciled
+            .iter()
+            .map(|reconciled| notifier.notify(reconciled)),
+    )
+    .await;
+
+    let in_flight = in_flight_nonces(&pending, &tracker.pending());
+    join_all(
+        in_flight
+            .into_iter()
+            .map(|((network, signer), in_flight)| reseed(provider_map, network, signer, in_flight)),
+    )
+    .await;
+    tracker.flush().await;
+}
+
+/// Reseeds the nonce of an EVM `signer` of `network` to follow its `in_flight` transactions.
+async fn reseed<A>(provider_map: &A, network: Network, signer: Address, in_flight: Vec<u64>)
+where
+    A: ProviderMap<Value = NetworkProvider>,
+{
+    let Some(NetworkProvider::Evm(provider)) = provider_map.by_network(network) else {
+        return;
+    };
+    // Relayed native payments are signed by their payer, whose nonce is not managed here
+    if !provider.is_signer(signer) {
+        return;
+    }
+    match provider.reseed_nonce(signer, in_flight).await {
+        Ok(next_nonce) => {
+            tracing::info!(%network, %signer, next_nonce, "Reseeded signer nonce")
+        }
+        Err(e) => {
+            tracing::warn!(%network, %signer, error = %e, "Can not reseed signer nonce")
+        }
+    }
+}
+
+/// The final status of a pending settlement, or `None` while it may still land.
+///
+/// A transaction unknown to its network counts as failed once its nonce is taken by another
+/// transaction, on EVM networks, once its durable nonce is advanced without it, on Solana, or
+/// else [`DROPPED_AFTER`] its broadcast. On EVM networks its nonce is then left to the next
+/// settlement, which rules out that it lands later.
+async fn reconcile_settlement<A>(
+    provider_map: &A,
+    settlement: &PendingSettlement,
+    now: UnixTimestamp,
+) -> Option<TransactionStatusResponse>
+where
+    A: ProviderMap<Value = NetworkProvider>,
+{
+    let Some(provider) = provider_map.by_network(settlement.network) else {
+        tracing::warn!(
+            network = %settlement.network,
+            transaction = %settlement.transaction,
+            "Network of pending settlement is not configured"
+        );
+        return None;
+    };
+    let status = match provider
+        .get_transaction_status(&settlement.transaction)
+        .await
+    {
+        Ok(status) => status,
+        Err(e) => {
+            tracing::warn!(
+                transaction = %settlement.transaction,
+                error = %e,
+                "Can not reconcile pending settlement"
+            );
+            return None;
+        }
+    };
+    let status = match status.status {
+        TransactionStatus::Confirmed | TransactionStatus::Failed => status,
+        TransactionStatus::NotFound => match dropped(provider, settlement, now).await {
+            Some(error) => TransactionStatusResponse {
+                status: TransactionStatus::Failed,
+                error: Some(error.to_string()),
+                ..status
+            },
+            None => {
+                tracing::info!(
+                    transaction = %settlement.transaction,
+                    "Settlement not found yet, still pending"
+                );
+                return None;
+            }
+        },
+        TransactionStatus::Pending | TransactionStatus::Reorged => {
+            tracing::info!(
+                transaction = %settlement.transaction,
+                status = ?status.status,
+                "Settlement still pending"
+            );
+            return None;
+        }
+    };
+    tracing::info!(
+        network = %settlement.network,
+        transaction = %settlement.transaction,
+        status = ?status.status,
+        "Reconciled pending settlement"
+    );
+    Some(status)
+}
+
+/// Why the transaction of `settlement`, unknown to its network, will not land, if it will not.
+async fn dropped(
+    provider: &NetworkProvider,
+    settlement: &PendingSettlement,
+    now: UnixTimestamp,
+) -> Option<&'static str> {
+    if let (NetworkProvider::Evm(provider), MixedAddress::Evm(signer), Some(nonce)) =
+        (provider, &settlement.signer, settlement.nonce)
+    {
+        match provider.mined_nonce(signer.0).await {
+            Ok(mined_nonce) if mined_nonce > nonce => {
+                return Some("Transaction replaced by another with its nonce");
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(
+                    transaction = %settlement.transaction,
+                    error = %e,
+                    "Can not check the nonce of pending settlement"
+                );
+                return None;
+            }
+        }
+    }
+    if let NetworkProvider::Solana(provider) = provider {
+        match provider.durable_nonce_unexpired(&settlement.request).await {
+            Ok(Some(true)) => return None,
+            // The transaction itself advances the nonce: it may have landed since it was looked up
+            Ok(Some(false)) => {
+                return match provider
+                    .get_transaction_status(&settlement.transaction)
+                    .await
+                {
+                    Ok(status) if status.status == TransactionStatus::NotFound => {
+                        Some("Durable nonce advanced without the transaction")
+                    }
+                    Ok(_) | Err(_) => None,
+                };
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(
+                    transaction = %settlement.transaction,
+                    error = %e,
+                    "Can not check the durable nonce of pending settlement"
+                );
+                return None;
+            }
+        }
+    }
+    let expired = now.seconds_since_epoch()
+        > settlement.broadcast_at.seconds_since_epoch() + DROPPED_AFTER.as_secs();
+    expired.then_some("Transaction dropped before landing")
+}
+
+/// Nonces of the EVM transactions `still_pending`, for every network and signer of `journaled`.
+///
+/// Signers whose transactions all reached an outcome get no nonce, for their next one to follow
+/// the transaction count of the network.
+fn in_flight_nonces(
+    journaled: &[PendingSettlement],
+    still_pending: &[PendingSettlement],
+) -> HashMap<(Network, Address), Vec<u64>> {
+    let mut in_flight = HashMap::new();
+    for settlement in journaled {
+        if let MixedAddress::Evm(signer) = settlement.signer {
+            in_flight.insert((settlement.network, signer.0), Vec::new());
+        }
+    }
+    for settlement in still_pending {
+        if let (MixedAddress::Evm(signer), Some(nonce)) = (&settlement.signer, settlement.nonce) {
+            if let Some(nonces) = in_flight.get_mut(&(settlement.network, signer.0)) {
+                nonces.push(nonce);
+            }
+        }
+    }
+    in_flight
+}
+
+/// Posts each [`ReconciledSettlement`] as JSON to a webhook, if one is configured.
+#[derive(Clone, Default)]
+pub struct SettlementNotifier {
+    webhook: Option<Url>,
+    client: reqwest::Client,
+}
+
+impl SettlementNotifier {
+    /// Reads the webhook from `SETTLEMENT_WEBHOOK_URL`; without it, notifications are skipped.
+    pub fn from_env() -> Result<Self, url::ParseError> {
+        let webhook = match std::env::var(ENV_SETTLEMENT_WEBHOOK_URL) {
+            Ok(url) => Some(Url::parse(&url)?),
+            Err(_) => None,
+        };
+        Ok(Self {
+            webhook,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    pub async fn notify(&self, reconciled: &ReconciledSettlement) {
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        let result = self
+            .client
+            .post(webhook.clone())
+            .timeout(Duration::from_secs(10))
+            .json(reconciled)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            tracing::warn!(
+                transaction = %reconciled.settlement.transaction,
+                error = %e,
+                "Failed to notify reconciled settlement"
+            );
+        }
+    }
+}
+
+/// How long a shutdown waits for in-flight settlements, from `SETTLE_DRAIN_TIMEOUT_SECS`.
+pub fn drain_timeout() -> Duration {
+    Duration::from_secs(
+        std::env::var(ENV_SETTLE_DRAIN_TIMEOUT_SECS)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(30),
+    )
+}
+
+/// Where pending settlements are persisted, from `PENDING_SETTLEMENTS_PATH`.
+pub fn pending_settlements_path() -> PathBuf {
+    std::env::var(ENV_PENDING_SETTLEMENTS_PATH)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("pending-settlements.ndjson"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::NetworkProviderOps;
+    use crate::chain::evm::EvmProvider;
+    use crate::provider_cache::ProviderCache;
+    use crate::types::test_fixtures::settle_request;
+
+    #[tokio::test]
+    async fn test_drain_refuses_new_settlements_and_returns_pending() {
+        let tracker = SettlementTracker::new();
+        let request = settle_request(1_000);
+        let signature = TransactionHash::Solana([7; 64]);
+        let signer = MixedAddress::Solana(solana_sdk::pubkey::Pubkey::new_unique());
+
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let in_flight = tokio::spawn({
+            let tracker = tracker.clone();
+            let request = request.clone();
+            let signature = signature.clone();
+            async move {
+                tracker
+                    .track(&request, async move {
+                        broadcast(Network::SolanaDevnet, signature, signer, None);
+                        let _ = released.await;
+                    })
+                    .await
+            }
+        });
+        while tracker.pending().is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let pending = tracker.drain(Duration::from_millis(10)).await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].transaction, signature);
+        assert!(tracker.track(&request, async {}).await.is_none());
+
+        release.send(()).unwrap();
+        assert!(in_flight.await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_journal_survives_restart() {
+        let path = std::env::temp_dir().join(format!(
+            "x402-pending-settlements-{}.ndjson",
+            std::process::id()
+        ));
+        let confirmed = TransactionHash::Evm([1; 32]);
+        let unconfirmed = TransactionHash::Evm([2; 32]);
+        let signer = MixedAddress::Evm(crate::types::EvmAddress(
+            alloy::primitives::Address::repeat_byte(3),
+        ));
+
+        let tracker = SettlementTracker::open(&path).unwrap();
+        tracker
+            .track(&settle_request(1_000), async {
+                broadcast(
+                    Network::MonadTestnet,
+                    confirmed.clone(),
+                    signer.clone(),
+                    Some(4),
+                );
+                broadcast(Network::MonadTestnet, unconfirmed.clone(), signer, Some(5));
+                settled(&confirmed);
+            })
+            .await
+            .unwrap();
+
+        tracker.flush().await;
+        // A record cut short by a crash is skipped
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"settled\":")
+            .unwrap();
+
+        let reopened = SettlementTracker::open(&path).unwrap();
+        let pending = reopened.pending();
+        let journal = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].transaction, unconfirmed);
+        assert_eq!(pending[0].nonce, Some(5));
+        // Compacted on open
+        assert_eq!(journal.lines().count(), 1);
+    }
+
+    fn pending_evm_settlement(signer: Address, nonce: u64) -> PendingSettlement {
+        PendingSettlement {
+            network: Network::MonadTestnet,
+            transaction: TransactionHash::Evm([nonce as u8; 32]),
+            signer: MixedAddress::Evm(crate::types::EvmAddress(signer)),
+            nonce: Some(nonce),
+            request: settle_request(1_000),
+            broadcast_at: UnixTimestamp::try_now().unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_fails_evm_transactions_whose_nonce_is_taken() {
+        use alloy::primitives::U64;
+
+        let asserter = alloy::transports::mock::Asserter::new();
+        let provider = EvmProvider::mocked(Network::MonadTestnet, asserter.clone());
+        let MixedAddress::Evm(signer) = provider.signer_address() else {
+            unreachable!()
+        };
+        let signer = signer.0;
+        let providers =
+            ProviderCache::from_iter([(Network::MonadTestnet, NetworkProvider::Evm(provider))]);
+        let tracker = SettlementTracker::new();
+        tracker.journal.insert(pending_evm_settlement(signer, 4));
+
+        // Unknown to the node, but its nonce is mined: it was replaced, and will not land
+        asserter.push_success(&None::<()>);
+        asserter.push_success(&None::<()>);
+        asserter.push_success(&U64::from(5));
+        asserter.push_success(&U64::from(6));
+        reconcile(&providers, &tracker, &SettlementNotifier::default()).await;
+        assert!(tracker.pending().is_empty());
+        assert!(asserter.read_q().is_empty());
+
+        // Unknown with its nonce free, it may still land until dropped
+        tracker.journal.insert(pending_evm_settlement(signer, 6));
+        asserter.push_success(&None::<()>);
+        asserter.push_success(&None::<()>);
+        asserter.push_success(&U64::from(6));
+        asserter.push_success(&U64::from(6));
+        reconcile(&providers, &tracker, &SettlementNotifier::default()).await;
+        assert_eq!(tracker.pending().len(), 1);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_leaves_the_nonce_of_native_payers_alone() {
+        use alloy::primitives::U64;
+
+        let asserter = alloy::transports::mock::Asserter::new();
+        let provider = EvmProvider::mocked(Network::MonadTestnet, asserter.clone());
+        let providers =
+            ProviderCache::from_iter([(Network::MonadTestnet, NetworkProvider::Evm(provider))]);
+        let payer = Address::repeat_byte(3);
+        let tracker = SettlementTracker::new();
+        tracker.journal.insert(pending_evm_settlement(payer, 4));
+
+        // A relayed native payment is reconciled by the nonce of its payer, never reseeded
+        asserter.push_success(&None::<()>);
+        asserter.push_success(&None::<()>);
+        asserter.push_success(&U64::from(5));
+        asserter.push_success(&U64::from(5));
+        reconcile(&providers, &tracker, &SettlementNotifier::default()).await;
+        assert!(tracker.pending().is_empty());
+        assert_eq!(asserter.read_q().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_keeps_durable_nonce_transactions_until_the_nonce_advances() {
+        use crate::chain::rpc_pool::RpcEndpoint;
+        use crate::chain::solana::{MintExtensionPolicy, PriorityFeePolicy, SolanaProvider};
+        use crate::types::{Base64Bytes, ExactPaymentPayload, ExactSolanaPayload};
+        use solana_client::nonblocking::rpc_client::RpcClient;
+        use solana_client::rpc_request::RpcRequest;
+        use solana_nonce::state::{DurableNonce, State as NonceState};
+        use solana_nonce::versions::Versions as NonceVersions;
+        use solana_rpc_client::mock_sender::MocksMap;
+        use solana_sdk::hash::Hash;
+        use solana_sdk::instruction::{AccountMeta, Instruction};
+        use solana_sdk::message::{Message, VersionedMessage};
+        use solana_sdk::pubkey::Pubkey;
+        use solana_sdk::signature::Keypair;
+        use solana_sdk::transaction::VersionedTransaction;
+        use solana_system_interface::instruction::SystemInstruction;
+
+        let (nonce_account, nonce_authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+        let advance_nonce = Instruction::new_with_bincode(
+            solana_system_interface::program::ID,
+            &SystemInstruction::AdvanceNonceAccount,
+            vec![
+                AccountMeta::new(nonce_account, false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(nonce_authority, true),
+            ],
+        );
+        let mut message = Message::new(&[advance_nonce], Some(&Pubkey::new_unique()));
+        message.recent_blockhash = *durable_nonce.as_hash();
+        let transaction = VersionedTransaction {
+            signatures: vec![],
+            message: VersionedMessage::Legacy(message),
+        };
+        let transaction = Base64Bytes::encode(bincode::serialize(&transaction).unwrap());
+        let mut request = settle_request(1_000);
+        request.payment_payload.payload = ExactPaymentPayload::Solana(ExactSolanaPayload {
+            transaction: String::from_utf8(transaction.0.into_owned()).unwrap(),
+        });
+        let settlement = PendingSettlement {
+            network: Network::SolanaDevnet,
+            transaction: TransactionHash::Solana([9; 64]),
+            signer: MixedAddress::Solana(Pubkey::new_unique()),
+            nonce: None,
+            request,
+            // Long past the expiry of any recent blockhash
+            broadcast_at: UnixTimestamp(0),
+        };
+        let nonce_account_holding = |durable_nonce: DurableNonce| {
+            let state = NonceState::new_initialized(&nonce_authority, durable_nonce, 5_000);
+            let data = bincode::serialize(&NonceVersions::new(state)).unwrap();
+            let space = data.len();
+            let data = String::from_utf8(Base64Bytes::encode(data).0.into_owned()).unwrap();
+            serde_json::json!({
+                "context": {"slot": 1},
+                "value": {
+                    "lamports": 1_000_000,
+                    "data": [data, "base64"],
+                    "owner": solana_system_interface::program::ID.to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                    "space": space,
+                },
+            })
+        };
+        // The transaction is unknown to the node, as long as no lookup falls back to the mock's
+        let providers = |mocks: Vec<(RpcRequest, serde_json::Value)>| {
+            let provider = SolanaProvider::try_new(
+                Keypair::new(),
+                &[RpcEndpoint::new("http://localhost:8899")],
+                Network::SolanaDevnet,
+                200_000,
+                100_000,
+                MintExtensionPolicy::default(),
+                PriorityFeePolicy::default(),
+            )
+            .unwrap()
+            .with_rpc_client(RpcClient::new_mock_with_mocks_map(
+                "sig_not_found".to_string(),
+                MocksMap::from_iter(mocks),
+            ));
+            ProviderCache::from_iter([(Network::SolanaDevnet, NetworkProvider::Solana(provider))])
+        };
+        let not_found = (RpcRequest::GetTransaction, serde_json::Value::Null);
+        let tracker = SettlementTracker::new();
+        tracker.journal.insert(settlement);
+
+        // Unknown, but its nonce still holds its blockhash: it may still land
+        let mocks = vec![
+            not_found.clone(),
+            (
+                RpcRequest::GetAccountInfo,
+                nonce_account_holding(durable_nonce),
+            ),
+        ];
+        reconcile(&providers(mocks), &tracker, &SettlementNotifier::default()).await;
+        assert_eq!(tracker.pending().len(), 1);
+
+        // The nonce advanced as the transaction landed after its lookup, found when looked up again
+        let advanced = DurableNonce::from_blockhash(&Hash::new_unique());
+        let mocks = vec![
+            not_found.clone(),
+            (RpcRequest::GetAccountInfo, nonce_account_holding(advanced)),
+        ];
+        reconcile(&providers(mocks), &tracker, &SettlementNotifier::default()).await;
+        assert_eq!(tracker.pending().len(), 1);
+
+        // The nonce advanced without the transaction: it will never land
+        let mocks = vec![
+            not_found.clone(),
+            (RpcRequest::GetAccountInfo, nonce_account_holding(advanced)),
+            not_found,
+        ];
+        reconcile(&providers(mocks), &tracker, &SettlementNotifier::default()).await;
+        assert!(tracker.pending().is_empty());
+    }
+
+    #[test]
+    fn test_in_flight_nonces_follow_still_pending_transactions() {
+        let settled_signer = Address::repeat_byte(1);
+        let pending_signer = Address::repeat_byte(2);
+        let journaled = [
+            pending_evm_settlement(settled_signer, 1),
+            pending_evm_settlement(pending_signer, 4),
+            pending_evm_settlement(pending_signer, 5),
+        ];
+        let still_pending = [journaled[2].clone()];
+
+        let in_flight = in_flight_nonces(&journaled, &still_pending);
+        assert_eq!(in_flight.len(), 2);
+        assert_eq!(
+            in_flight[&(Network::MonadTestnet, settled_signer)],
+            Vec::<u64>::new()
+        );
+        assert_eq!(in_flight[&(Network::MonadTestnet, pending_signer)], vec![5]);
+    }
+}
@@ -23,6 +23,9 @@ use std::collections::HashMap;
 
 use crate::chain::FromEnvByNetworkBuild;
 use crate::chain::NetworkProvider;
+use crate::chain::evm::EvmPolicy;
+use crate::chain::solana::SolanaPolicy;
+use crate::config::Config;
 use crate::network::Network;
 
 /// A cache of pre-initialized [`EthereumProvider`] instances keyed by network.
@@ -77,6 +80,48 @@ impl ProviderCache {
         }
         Ok(Self { providers })
     }
+
+    /// Applies the reloadable part of `config` to the running providers: the [`SolanaPolicy`]
+    /// of every Solana network and the [`EvmPolicy`] of every EVM network.
+    ///
+    /// All policies are built before any is swapped in, so an invalid `config` changes nothing.
+    pub fn apply_policies(&self, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+        let env = config.env_vars();
+        let lookup = |name: &str| env.get(name).cloned();
+        let mut solana_policies = Vec::new();
+        let mut evm_policies = Vec::new();
+        for (network, provider) in self {
+            match provider {
+                NetworkProvider::Solana(provider) => {
+                    let policy = SolanaPolicy::from_lookup(*network, lookup)?;
+                    solana_policies.push((provider, policy));
+                }
+                NetworkProvider::Evm(provider) => {
+                    let policy = EvmPolicy::from_lookup(*network, lookup)?;
+                    evm_policies.push((provider, policy));
+                }
+            }
+        }
+        for (provider, policy) in solana_policies {
+            if *provider.policy() != policy {
+                provider.set_policy(policy);
+            }
+        }
+        for (provider, policy) in evm_policies {
+            if provider.policy() != policy {
+                provider.set_policy(policy);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromIterator<(Network, NetworkProvider)> for ProviderCache {
+    fn from_iter<I: IntoIterator<Item = (Network, NetworkProvider)>>(iter: I) -> Self {
+        Self {
+            providers: iter.into_iter().collect(),
+        }
+    }
 }
 
 impl ProviderMap for ProviderCache {
@@ -90,3 +135,80 @@ impl ProviderMap for ProviderCache {
         self.providers.values()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::evm::EvmProvider;
+    use crate::chain::rpc_pool::RpcEndpoint;
+    use crate::chain::solana::{MintExtensionPolicy, PriorityFeePolicy, SolanaProvider};
+    use alloy::network::EthereumWallet;
+    use alloy::signers::local::PrivateKeySigner;
+    use solana_sdk::signature::Keypair;
+
+    async fn provider_cache() -> ProviderCache {
+        let evm = EvmProvider::try_new(
+            EthereumWallet::from(PrivateKeySigner::random()),
+            &[RpcEndpoint::new("http://localhost:8545")],
+            true,
+            Network::MonadTestnet,
+        )
+        .await
+        .unwrap();
+        let solana = SolanaProvider::try_new(
+            Keypair::new(),
+            &[RpcEndpoint::new("http://localhost:8899")],
+            Network::SolanaDevnet,
+            200_000,
+            100_000,
+            MintExtensionPolicy::default(),
+            PriorityFeePolicy::default(),
+        )
+        .unwrap();
+        [
+            (Network::MonadTestnet, NetworkProvider::Evm(evm)),
+            (Network::SolanaDevnet, NetworkProvider::Solana(solana)),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn policies(cache: &ProviderCache) -> (EvmPolicy, SolanaPolicy) {
+        let evm = match cache.by_network(Network::MonadTestnet) {
+            Some(NetworkProvider::Evm(provider)) => provider.policy(),
+            _ => unreachable!(),
+        };
+        let solana = match cache.by_network(Network::SolanaDevnet) {
+            Some(NetworkProvider::Solana(provider)) => SolanaPolicy::clone(&provider.policy()),
+            _ => unreachable!(),
+        };
+        (evm, solana)
+    }
+
+    #[tokio::test]
+    async fn test_apply_policies_updates_evm_and_solana_providers() {
+        let cache = provider_cache().await;
+        let mut config = Config::default();
+        config.networks.monad_testnet.max_gas_price = Some(50_000_000_000);
+        config.networks.solana_devnet.max_compute_unit_price = Some(5_000);
+        config.networks.solana_devnet.commitment = Some("finalized".to_string());
+        cache.apply_policies(&config).unwrap();
+
+        let (evm, solana) = policies(&cache);
+        assert_eq!(evm.max_gas_price, Some(50_000_000_000));
+        assert_eq!(solana.max_compute_unit_price, 5_000);
+        assert_eq!(solana.max_compute_unit_limit, 200_000);
+        assert!(solana.settle_commitment.is_finalized());
+    }
+
+    #[tokio::test]
+    async fn test_apply_policies_changes_nothing_on_invalid_config() {
+        let cache = provider_cache().await;
+        let before = policies(&cache);
+        let mut config = Config::default();
+        config.networks.monad_testnet.max_gas_price = Some(50_000_000_000);
+        config.networks.solana_devnet.commitment = Some("eventually".to_string());
+        assert!(cache.apply_policies(&config).is_err());
+        assert_eq!(policies(&cache), before);
+    }
+}
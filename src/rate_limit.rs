@@ -3,13 +3,30 @@
 //! This module provides configurable rate limiting to protect the facilitator
 //! from abuse and DoS attacks. Rate limits are applied per IP address and
 //! can be configured separately for different endpoint types.
+//!
+//! The limits are held by a [`RateLimiter`], applied by the [`rate_limit`] middleware, and can be
+//! swapped at runtime on configuration reload, see [`RateLimiter::set_config`].
+
+use axum::Json;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use dashmap::DashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::types::ErrorResponse;
 
-use std::time::Duration;
-use tower::limit::RateLimitLayer;
-use tower::ServiceBuilder;
+/// Length of a rate limiting window.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Number of tracked windows past which expired ones are pruned.
+const PRUNE_THRESHOLD: usize = 10_000;
 
 /// Rate limiting configuration loaded from environment variables.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RateLimitConfig {
     /// Maximum requests per minute for verification endpoints.
     pub verify_per_minute: u32,
@@ -38,76 +55,222 @@ impl RateLimitConfig {
     /// Environment variables:
     /// - `RATE_LIMIT_VERIFY_PER_MINUTE`: Requests per minute for `/verify` (default: 60)
     /// - `RATE_LIMIT_SETTLE_PER_MINUTE`: Requests per minute for `/settle` (default: 30)
-    /// - `RATE_LIMIT_TRANSACTION_STATUS_PER_MINUTE`: Requests per minute for `/transaction/:tx_hash` (default: 120)
+    /// - `RATE_LIMIT_TRANSACTION_STATUS_PER_MINUTE`: Requests per minute for `/transaction/{tx_hash}` (default: 120)
     /// - `RATE_LIMIT_GENERAL_PER_MINUTE`: Requests per minute for other endpoints (default: 300)
     ///
-    /// If rate limiting is disabled (all values set to 0), returns None.
-    pub fn from_env() -> Option<Self> {
-        let verify = std::env::var("RATE_LIMIT_VERIFY_PER_MINUTE")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(60);
-
-        let settle = std::env::var("RATE_LIMIT_SETTLE_PER_MINUTE")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(30);
-
-        let transaction_status = std::env::var("RATE_LIMIT_TRANSACTION_STATUS_PER_MINUTE")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(120);
-
-        let general = std::env::var("RATE_LIMIT_GENERAL_PER_MINUTE")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(300);
+    /// A limit of 0 disables rate limiting of its endpoints. If rate limiting is disabled (all
+    /// values set to 0), returns None.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Reads the configuration from the variables returned by `lookup`, see [`Self::from_env`].
+    ///
+    /// Fails on values that are not a number of requests.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let var = |name: &str, default: u32| match lookup(name) {
+            Some(value) => value
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| format!("env {name}: {e}")),
+            None => Ok(default),
+        };
+        let config = Self {
+            verify_per_minute: var("RATE_LIMIT_VERIFY_PER_MINUTE", 60)?,
+            settle_per_minute: var("RATE_LIMIT_SETTLE_PER_MINUTE", 30)?,
+            transaction_status_per_minute: var("RATE_LIMIT_TRANSACTION_STATUS_PER_MINUTE", 120)?,
+            general_per_minute: var("RATE_LIMIT_GENERAL_PER_MINUTE", 300)?,
+        };
 
         // If all limits are 0, rate limiting is disabled
-        if verify == 0 && settle == 0 && transaction_status == 0 && general == 0 {
-            return None;
+        let is_disabled = config.verify_per_minute == 0
+            && config.settle_per_minute == 0
+            && config.transaction_status_per_minute == 0
+            && config.general_per_minute == 0;
+        Ok((!is_disabled).then_some(config))
+    }
+
+    /// Requests per minute allowed on `endpoint`, 0 for no limit.
+    fn limit(&self, endpoint: Endpoint) -> u32 {
+        match endpoint {
+            Endpoint::Verify => self.verify_per_minute,
+            Endpoint::Settle => self.settle_per_minute,
+            Endpoint::TransactionStatus => self.transaction_status_per_minute,
+            Endpoint::General => self.general_per_minute,
         }
+    }
+}
 
-        Some(Self {
-            verify_per_minute: verify,
-            settle_per_minute: settle,
-            transaction_status_per_minute: transaction_status,
-            general_per_minute: general,
-        })
+/// Endpoint types, limited separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Endpoint {
+    Verify,
+    Settle,
+    TransactionStatus,
+    General,
+}
+
+impl Endpoint {
+    fn from_path(path: &str) -> Self {
+        match path {
+            "/verify" => Endpoint::Verify,
+            "/settle" => Endpoint::Settle,
+            path if path.starts_with("/transaction/") => Endpoint::TransactionStatus,
+            _ => Endpoint::General,
+        }
     }
+}
+
+/// Requests counted in the current window of a client.
+#[derive(Debug)]
+struct Window {
+    started_at: Instant,
+    requests: u32,
+}
 
-    /// Create a rate limit layer for verification endpoints.
-    pub fn verify_layer(&self) -> RateLimitLayer {
-        RateLimitLayer::new(self.verify_per_minute as u64, Duration::from_secs(60))
+/// Fixed-window request counter per client IP address and endpoint type.
+///
+/// The configuration is swapped atomically on reload; windows in progress keep their counts and
+/// are checked against the new limits.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RwLock<Option<RateLimitConfig>>,
+    windows: DashMap<(IpAddr, Endpoint), Window>,
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter enforcing `config`, or nothing if `None`.
+    pub fn new(config: Option<RateLimitConfig>) -> Self {
+        Self {
+            config: RwLock::new(config),
+            windows: DashMap::new(),
+        }
     }
 
-    /// Create a rate limit layer for settlement endpoints.
-    pub fn settle_layer(&self) -> RateLimitLayer {
-        RateLimitLayer::new(self.settle_per_minute as u64, Duration::from_secs(60))
+    /// The limits currently enforced, if any.
+    pub fn config(&self) -> Option<RateLimitConfig> {
+        self.config
+            .read()
+            .expect("rate limit lock poisoned")
+            .clone()
     }
 
-    /// Create a rate limit layer for transaction status endpoints.
-    pub fn transaction_status_layer(&self) -> RateLimitLayer {
-        RateLimitLayer::new(self.transaction_status_per_minute as u64, Duration::from_secs(60))
+    /// Swaps in new limits, as on configuration reload.
+    pub fn set_config(&self, config: Option<RateLimitConfig>) {
+        tracing::info!(config = ?config, "Updated rate limits");
+        *self.config.write().expect("rate limit lock poisoned") = config;
     }
 
-    /// Create a rate limit layer for general endpoints.
-    pub fn general_layer(&self) -> RateLimitLayer {
-        RateLimitLayer::new(self.general_per_minute as u64, Duration::from_secs(60))
+    /// Counts a request of `ip` to `path`, and tells whether it is within the limits.
+    fn try_acquire(&self, ip: IpAddr, path: &str) -> bool {
+        let endpoint = Endpoint::from_path(path);
+        let limit = match self.config() {
+            Some(config) => config.limit(endpoint),
+            None => return true,
+        };
+        if limit == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        if self.windows.len() > PRUNE_THRESHOLD {
+            self.windows
+                .retain(|_, window| now.duration_since(window.started_at) < WINDOW);
+        }
+        let mut window = self.windows.entry((ip, endpoint)).or_insert(Window {
+            started_at: now,
+            requests: 0,
+        });
+        if now.duration_since(window.started_at) >= WINDOW {
+            window.started_at = now;
+            window.requests = 0;
+        }
+        if window.requests >= limit {
+            return false;
+        }
+        window.requests += 1;
+        true
     }
 }
 
-/// Create a service builder with rate limiting applied.
+/// Middleware rejecting requests over the limits of a [`RateLimiter`] with
+/// `429 Too Many Requests`.
 ///
-/// This applies rate limiting based on the configuration, using a simple
-/// in-memory rate limiter that tracks requests per IP address.
-pub fn create_rate_limited_service_builder(
-    config: Option<&RateLimitConfig>,
-) -> ServiceBuilder<tower::layer::util::Stack<RateLimitLayer, tower::layer::util::Identity>> {
-    let limit = config
-        .map(|c| c.general_per_minute as u64)
-        .unwrap_or(u32::MAX as u64);
-    
-    ServiceBuilder::new().layer(RateLimitLayer::new(limit, Duration::from_secs(60)))
+/// Needs the client address, so the router must be served with
+/// `into_make_service_with_connect_info::<SocketAddr>()`.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if limiter.try_acquire(client.ip(), request.uri().path()) {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ErrorResponse {
+                error: "Rate limit exceeded".to_string(),
+            }),
+        )
+            .into_response()
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_limits_per_client_and_endpoint() {
+        let limiter = RateLimiter::new(Some(RateLimitConfig {
+            verify_per_minute: 2,
+            settle_per_minute: 0,
+            ..RateLimitConfig::default()
+        }));
+        let client: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        assert!(limiter.try_acquire(client, "/verify"));
+        assert!(limiter.try_acquire(client, "/verify"));
+        assert!(!limiter.try_acquire(client, "/verify"));
+        assert!(limiter.try_acquire(other, "/verify"));
+        assert!(limiter.try_acquire(client, "/supported"));
+        // A limit of 0 leaves its endpoints unlimited
+        for _ in 0..100 {
+            assert!(limiter.try_acquire(client, "/settle"));
+        }
+
+        // Reloaded limits apply to the windows in progress
+        limiter.set_config(Some(RateLimitConfig {
+            verify_per_minute: 3,
+            ..RateLimitConfig::default()
+        }));
+        assert!(limiter.try_acquire(client, "/verify"));
+        assert!(!limiter.try_acquire(client, "/verify"));
+        limiter.set_config(None);
+        assert!(limiter.try_acquire(client, "/verify"));
+    }
+
+    #[test]
+    fn test_rate_limit_config_rejects_invalid_values() {
+        let config = RateLimitConfig::from_lookup(|name| {
+            (name == "RATE_LIMIT_SETTLE_PER_MINUTE").then(|| "10".to_string())
+        });
+        assert_eq!(
+            config,
+            Ok(Some(RateLimitConfig {
+                settle_per_minute: 10,
+                ..RateLimitConfig::default()
+            }))
+        );
+        let disabled = RateLimitConfig::from_lookup(|_| Some("0".to_string()));
+        assert_eq!(disabled, Ok(None));
+        let error = RateLimitConfig::from_lookup(|name| {
+            (name == "RATE_LIMIT_VERIFY_PER_MINUTE").then(|| "ten".to_string())
+        })
+        .unwrap_err();
+        assert!(
+            error.starts_with("env RATE_LIMIT_VERIFY_PER_MINUTE"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,974 @@
+//! History of settled payments, watched until their transactions are final.
+//!
+//! [`FacilitatorLocal`](crate::facilitator_local::FacilitatorLocal) records the outcome of every
+//! settlement it answers, successful or not, in a [`SettlementLedger`], under an increasing id. A
+//! ledger opened with [`SettlementLedger::open`] appends its records to a file, one JSON object
+//! per line, from a background thread, and reads them back at the next start. The file is
+//! compacted to the last record of every id at startup, and once updates piled up. Records are
+//! searched with a [`SettlementFilter`], newest first, and exported as CSV or NDJSON, a page at a
+//! time.
+//!
+//! [`watch_settlements`] re-checks the transactions of successful settlements every
+//! [`SETTLEMENT_CHECK_INTERVAL`], a few at a time, until they are final, and flags those
+//! reorganized out of the chain as [`Finality::Reorged`]. It also records the native coin the
+//! facilitator spent on every settlement transaction. A settlement is no longer checked after
+//! [`SETTLEMENT_WATCH_WINDOW`].
+//!
+//! Environment:
+//! - `SETTLEMENT_LEDGER_PATH` – file of the settlement history, `settlements.ndjson` by default
+
+use dashmap::DashMap;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+use url::Url;
+
+use crate::chain::{FinalityQuery, NetworkProvider, PaymentAmount, SettlementCostQuery};
+use crate::network::Network;
+use crate::provider_cache::ProviderMap;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    Finality, MixedAddress, SettleRequest, SettleResponse, TokenAmount, TransactionHash,
+    TransactionStatusResponse,
+};
+
+pub const ENV_SETTLEMENT_LEDGER_PATH: &str = "SETTLEMENT_LEDGER_PATH";
+
+/// How often the finality and the cost of recent settlements are checked.
+pub const SETTLEMENT_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long after its settlement a transaction is checked for finality and cost.
+pub const SETTLEMENT_WATCH_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Number of superseded records in the ledger file after which it is compacted.
+const COMPACT_AFTER: usize = 1_000;
+
+/// Number of watched settlements looked up at the same time.
+const SETTLEMENT_CHECKS_IN_FLIGHT: usize = 16;
+
+/// Number of settlements read from the ledger at a time by an export.
+const EXPORT_PAGE: usize = 500;
+
+/// A settlement, and the finality of its transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementRecord {
+    pub id: u64,
+    pub request: SettleRequest,
+    pub response: SettleResponse,
+    pub settled_at: UnixTimestamp,
+    /// Finality of the transaction of a successful settlement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finality: Option<Finality>,
+    /// Native coin the facilitator spent on the transaction, in wei or lamports, once known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_spent: Option<TokenAmount>,
+    /// Facilitator fee paid along a successful settlement, in the payment asset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<TokenAmount>,
+    /// Amount the payment transferred, in the smallest unit of the asset, as decoded from it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<TokenAmount>,
+    /// Decimals of the payment asset, if they could be resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+}
+
+/// Outcome of a settlement, as searched with [`SettlementFilter::status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettlementStatus {
+    /// Successful, with a transaction not final yet.
+    Settled,
+    /// Successful, with a final transaction.
+    Finalized,
+    /// Successful, then reorganized out of the chain.
+    Reorged,
+    /// The settlement transaction failed.
+    Failed,
+}
+
+impl SettlementStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SettlementStatus::Settled => "settled",
+            SettlementStatus::Finalized => "finalized",
+            SettlementStatus::Reorged => "reorged",
+            SettlementStatus::Failed => "failed",
+        }
+    }
+}
+
+impl SettlementRecord {
+    /// Amount the payment transferred, the maximum it required if it was not decoded.
+    pub fn paid_amount(&self) -> TokenAmount {
+        self.amount
+            .unwrap_or(self.request.payment_requirements.max_amount_required)
+    }
+
+    pub fn status(&self) -> SettlementStatus {
+        match (self.response.success, self.finality) {
+            (false, _) => SettlementStatus::Failed,
+            (true, Some(Finality::Finalized)) => SettlementStatus::Finalized,
+            (true, Some(Finality::Reorged)) => SettlementStatus::Reorged,
+            (true, _) => SettlementStatus::Settled,
+        }
+    }
+}
+
+/// Criteria a settlement must all meet to be searched. Unset criteria match any settlement.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementFilter {
+    pub pay_to: Option<MixedAddress>,
+    pub payer: Option<MixedAddress>,
+    pub network: Option<Network>,
+    pub asset: Option<MixedAddress>,
+    pub status: Option<SettlementStatus>,
+    /// Settled at or after this time.
+    pub from: Option<UnixTimestamp>,
+    /// Settled before this time.
+    pub to: Option<UnixTimestamp>,
+    pub resource: Option<Url>,
+}
+
+impl SettlementFilter {
+    pub fn matches(&self, record: &SettlementRecord) -> bool {
+        let requirements = &record.request.payment_requirements;
+        self.pay_to
+            .as_ref()
+            .is_none_or(|pay_to| *pay_to == requirements.pay_to)
+            && self
+                .payer
+                .as_ref()
+                .is_none_or(|payer| *payer == record.response.payer)
+            && self
+                .network
+                .is_none_or(|network| network == record.response.network)
+            && self
+                .asset
+                .as_ref()
+                .is_none_or(|asset| *asset == requirements.asset)
+            && self.status.is_none_or(|status| status == record.status())
+            && self.from.is_none_or(|from| record.settled_at >= from)
+            && self.to.is_none_or(|to| record.settled_at < to)
+            && self
+                .resource
+                .as_ref()
+                .is_none_or(|resource| *resource == requirements.resource)
+    }
+}
+
+/// A page of settlements, newest first.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementPage {
+    pub settlements: Vec<SettlementRecord>,
+    /// Cursor of the next page, if there may be one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<u64>,
+}
+
+/// A settlement, with the current status of its transaction if it could be queried.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementDetails {
+    #[serde(flatten)]
+    pub settlement: SettlementRecord,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_status: Option<TransactionStatusResponse>,
+}
+
+type Records = Arc<RwLock<BTreeMap<u64, SettlementRecord>>>;
+
+/// A settlement [`SettlementLedger::check_settlements`] looks up.
+struct WatchedSettlement {
+    id: u64,
+    network: Network,
+    transaction: TransactionHash,
+    check_cost: bool,
+    check_finality: bool,
+}
+
+impl WatchedSettlement {
+    /// Looks up the cost and the finality of the transaction, as needed, at the same time.
+    async fn check(
+        self,
+        provider: &NetworkProvider,
+    ) -> (Self, Option<TokenAmount>, Option<Finality>) {
+        let (network, transaction) = (self.network, &self.transaction);
+        let cost = async {
+            if !self.check_cost {
+                return None;
+            }
+            provider
+                .get_settlement_cost(transaction)
+                .await
+                .inspect_err(|e| tracing::debug!(%network, %transaction, error = %e, "Settlement cost check failed"))
+                .ok()
+        };
+        let finality = async {
+            if !self.check_finality {
+                return None;
+            }
+            provider
+                .get_finality(transaction)
+                .await
+                .inspect_err(|e| tracing::debug!(%network, %transaction, error = %e, "Finality check failed"))
+                .ok()
+        };
+        let (cost, finality) = tokio::join!(cost, finality);
+        (self, cost, finality)
+    }
+}
+
+/// A request to the thread writing the ledger file.
+enum LedgerWrite {
+    Append(Box<SettlementRecord>),
+    /// Answered once the writes requested before are done.
+    Flush(tokio::sync::oneshot::Sender<()>),
+}
+
+struct Ledger {
+    records: Records,
+    /// Ids of the successful settlements, by transaction.
+    by_transaction: DashMap<String, u64>,
+    next_id: AtomicU64,
+    /// Thread the records are sent to, to be appended to the ledger file, if persisted.
+    writer: Option<std::sync::mpsc::Sender<LedgerWrite>>,
+}
+
+/// Settlements by id, appended to a file when opened with [`SettlementLedger::open`].
+#[derive(Clone)]
+pub struct SettlementLedger {
+    inner: Arc<Ledger>,
+}
+
+impl Default for SettlementLedger {
+    fn default() -> Self {
+        Self::from_records(Records::default(), None)
+    }
+}
+
+impl SettlementLedger {
+    /// A ledger kept in memory only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A ledger appending its records to the file at `path`, with the records already there.
+    ///
+    /// The last line written for an id wins. Lines that fail to parse, like one cut short by a
+    /// crash, are skipped. The file is compacted to the records loaded, then appended to by a
+    /// background thread.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mut records = BTreeMap::new();
+        match std::fs::read_to_string(path) {
+            Ok(lines) => {
+                for line in lines.lines().filter(|line| !line.trim().is_empty()) {
+                    match serde_json::from_str::<SettlementRecord>(line) {
+                        Ok(record) => {
+                            records.insert(record.id, record);
+                        }
+                        Err(e) => {
+                            tracing::warn!(error = %e, path = %path.display(), "Skipping unreadable settlement record")
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        save(path, records.values())?;
+        let records = Arc::new(RwLock::new(records));
+        let (writer, writes) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("settlement-ledger".to_string())
+            .spawn({
+                let path = path.to_path_buf();
+                let records = records.clone();
+                move || write_ledger(path, records, writes)
+            })?;
+        Ok(Self::from_records(records, Some(writer)))
+    }
+
+    fn from_records(
+        records: Records,
+        writer: Option<std::sync::mpsc::Sender<LedgerWrite>>,
+    ) -> Self {
+        let loaded = records.read().unwrap_or_else(PoisonError::into_inner);
+        let next_id = loaded.keys().next_back().map_or(1, |id| id + 1);
+        let by_transaction = loaded
+            .values()
+            .filter(|record| record.response.success)
+            .filter_map(|record| {
+                let transaction = record.response.transaction.as_ref()?;
+                Some((transaction.to_string(), record.id))
+            })
+            .collect();
+        drop(loaded);
+        Self {
+            inner: Arc::new(Ledger {
+                records,
+                by_transaction,
+                next_id: AtomicU64::new(next_id),
+                writer,
+            }),
+        }
+    }
+
+    /// Records `response` to `request`, with the facilitator `fee` and the `amount` it paid; a
+    /// successful settlement as [`Finality::Unfinalized`].
+    pub fn record(
+        &self,
+        request: &SettleRequest,
+        response: &SettleResponse,
+        fee: Option<TokenAmount>,
+        amount: Option<PaymentAmount>,
+    ) {
+        let record = SettlementRecord {
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+            request: request.clone(),
+            response: response.clone(),
+            settled_at: UnixTimestamp::try_now().unwrap_or(UnixTimestamp(0)),
+            finality: response.success.then_some(Finality::Unfinalized),
+            gas_spent: None,
+            fee,
+            amount: amount.map(|amount| amount.amount),
+            decimals: amount.and_then(|amount| amount.decimals),
+        };
+        let id = record.id;
+        // Inserted before its append is queued, for a compaction in between to keep it
+        self.write_records().insert(id, record.clone());
+        self.append(&record);
+        if let Some(transaction) = response.transaction.as_ref().filter(|_| response.success) {
+            self.inner
+                .by_transaction
+                .insert(transaction.to_string(), id);
+        }
+    }
+
+    /// The settlement with `id`, if recorded.
+    pub fn get(&self, id: u64) -> Option<SettlementRecord> {
+        self.read_records().get(&id).cloned()
+    }
+
+    /// The successful settlement of `transaction`, if recorded.
+    pub fn by_transaction(&self, transaction: &TransactionHash) -> Option<SettlementRecord> {
+        let id = *self.inner.by_transaction.get(&transaction.to_string())?;
+        self.get(id)
+    }
+
+    /// Up to `limit` settlements matching `filter`, newest first, older than the `cursor` of a
+    /// previous page if given.
+    pub fn search(
+        &self,
+        filter: &SettlementFilter,
+        cursor: Option<u64>,
+        limit: usize,
+    ) -> SettlementPage {
+        let records = self.read_records();
+        let settlements: Vec<_> = records
+            .range(..cursor.unwrap_or(u64::MAX))
+            .rev()
+            .map(|(_, record)| record)
+            .filter(|record| filter.matches(record))
+            .take(limit)
+            .cloned()
+            .collect();
+        let next_cursor = settlements
+            .last()
+            .filter(|_| settlements.len() == limit)
+            .map(|record| record.id);
+        SettlementPage {
+            settlements,
+            next_cursor,
+        }
+    }
+
+    /// Up to `limit` settlements matching `filter`, oldest first, newer than the settlement with
+    /// id `after` if given.
+    pub fn matching_after(
+        &self,
+        filter: &SettlementFilter,
+        after: Option<u64>,
+        limit: usize,
+    ) -> Vec<SettlementRecord> {
+        let from = after.map_or(0, |id| id.saturating_add(1));
+        self.read_records()
+            .range(from..)
+            .map(|(_, record)| record)
+            .filter(|record| filter.matches(record))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Runs `aggregate` over the settlements matching `filter`, newest first, without copying
+    /// them.
+    ///
+    /// The settlements are read under the ledger lock, which `aggregate` should not hold long:
+    /// the walk stops at the first settlement older than the `from` of `filter`.
+    pub fn aggregate<T>(
+        &self,
+        filter: &SettlementFilter,
+        aggregate: impl FnOnce(&mut dyn Iterator<Item = &SettlementRecord>) -> T,
+    ) -> T {
+        let records = self.read_records();
+        let mut matching = records
+            .values()
+            .rev()
+            .take_while(|record| filter.from.is_none_or(|from| record.settled_at >= from))
+            .filter(|record| filter.matches(record));
+        aggregate(&mut matching)
+    }
+
+    /// Settlements with a transaction not final yet or of unknown cost, settled within
+    /// [`SETTLEMENT_WATCH_WINDOW`] before `now`.
+    fn watched(&self, now: UnixTimestamp) -> Vec<WatchedSettlement> {
+        let since = now
+            .seconds_since_epoch()
+            .saturating_sub(SETTLEMENT_WATCH_WINDOW.as_secs());
+        self.read_records()
+            .values()
+            .rev()
+            .take_while(|record| record.settled_at.seconds_since_epoch() >= since)
+            .filter_map(|record| {
+                let unfinalized = record
+                    .finality
+                    .is_some_and(|finality| finality != Finality::Finalized);
+                if record.gas_spent.is_some() && !unfinalized {
+                    return None;
+                }
+                Some(WatchedSettlement {
+                    id: record.id,
+                    network: record.response.network,
+                    transaction: record.response.transaction.clone()?,
+                    check_cost: record.gas_spent.is_none(),
+                    check_finality: unfinalized,
+                })
+            })
+            .collect()
+    }
+
+    /// Checks the finality and the cost of the watched settlements once, concurrently, and
+    /// records changes.
+    ///
+    /// Records are updated in place. One is appended to the ledger file when its finality
+    /// changes, or once it is final with a known cost: a cost alone is not written.
+    pub async fn check_settlements<A>(&self, provider_map: &A)
+    where
+        A: ProviderMap<Value = NetworkProvider>,
+    {
+        let now = UnixTimestamp::try_now().unwrap_or(UnixTimestamp(0));
+        let checks = self.watched(now).into_iter().filter_map(|watched| {
+            let provider = provider_map.by_network(watched.network)?;
+            Some(watched.check(provider))
+        });
+        let checked: Vec<_> = futures::stream::iter(checks)
+            .buffer_unordered(SETTLEMENT_CHECKS_IN_FLIGHT)
+            .collect()
+            .await;
+        let mut updated = Vec::new();
+        let mut records = self.write_records();
+        for (watched, gas_spent, finality) in checked {
+            let Some(record) = records.get_mut(&watched.id) else {
+                continue;
+            };
+            let (network, transaction) = (watched.network, &watched.transaction);
+            let finality_changed =
+                finality.is_some_and(|finality| record.finality != Some(finality));
+            if finality_changed {
+                match finality {
+                    Some(Finality::Reorged) => {
+                        tracing::warn!(%network, %transaction, "Settlement reorganized out of the chain")
+                    }
+                    Some(Finality::Unfinalized) => {
+                        tracing::info!(%network, %transaction, "Reorganized settlement included again")
+                    }
+                    Some(Finality::Finalized) | None => {
+                        tracing::debug!(%network, %transaction, "Settlement finalized")
+                    }
+                }
+                record.finality = finality;
+            }
+            if gas_spent.is_some() {
+                record.gas_spent = gas_spent;
+            }
+            let done = record.gas_spent.is_some() && record.finality == Some(Finality::Finalized);
+            if finality_changed || (gas_spent.is_some() && done) {
+                updated.push(record.clone());
+            }
+        }
+        drop(records);
+        for record in &updated {
+            self.append(record);
+        }
+    }
+
+    /// Sends `record` to be appended to the ledger file, if any.
+    fn append(&self, record: &SettlementRecord) {
+        self.write(LedgerWrite::Append(Box::new(record.clone())));
+    }
+
+    fn write(&self, write: LedgerWrite) {
+        if let Some(writer) = &self.inner.writer {
+            // The writer thread only stops once the ledger is dropped
+            let _ = writer.send(write);
+        }
+    }
+
+    /// Waits for the writes of the ledger file requested so far.
+    pub async fn flush(&self) {
+        if self.inner.writer.is_some() {
+            let (flushed, on_flushed) = tokio::sync::oneshot::channel();
+            self.write(LedgerWrite::Flush(flushed));
+            let _ = on_flushed.await;
+        }
+    }
+
+    fn read_records(&self) -> RwLockReadGuard<'_, BTreeMap<u64, SettlementRecord>> {
+        self.inner
+            .records
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_records(&self) -> RwLockWriteGuard<'_, BTreeMap<u64, SettlementRecord>> {
+        self.inner
+            .records
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Appends the records sent to `writes` to the ledger at `path`, off the async runtime.
+///
+/// Once the file holds [`COMPACT_AFTER`] superseded records, it is rewritten with the last record
+/// of every id. Records updated meanwhile are appended after, and replay to the same records.
+fn write_ledger(path: PathBuf, records: Records, writes: std::sync::mpsc::Receiver<LedgerWrite>) {
+    let open = |path: &Path| OpenOptions::new().create(true).append(true).open(path);
+    let count = |records: &Records| records.read().unwrap_or_else(PoisonError::into_inner).len();
+    let mut file = open(&path);
+    let mut lines = count(&records);
+    for write in writes {
+        let record = match write {
+            LedgerWrite::Append(record) => record,
+            LedgerWrite::Flush(flushed) => {
+                let _ = flushed.send(());
+                continue;
+            }
+        };
+        let result = match &mut file {
+            Ok(file) => serde_json::to_vec(&record)
+                .map_err(std::io::Error::from)
+                .and_then(|mut line| {
+                    line.push(b'\n');
+                    file.write_all(&line)
+                }),
+            Err(e) => Err(std::io::Error::new(e.kind(), e.to_string())),
+        };
+        lines += 1;
+        let live = count(&records);
+        if let Err(e) = &result {
+            tracing::error!(error = %e, id = record.id, "Failed to persist settlement record");
+        }
+        if result.is_err() || lines >= live + COMPACT_AFTER {
+            // Compacting rewrites every record, the one just lost included
+            let snapshot: Vec<_> = records
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .values()
+                .cloned()
+                .collect();
+            match save(&path, snapshot.iter()) {
+                Ok(()) => lines = snapshot.len(),
+                Err(e) => {
+                    tracing::error!(error = %e, path = %path.display(), "Failed to compact settlement ledger");
+                }
+            }
+            file = open(&path);
+        }
+    }
+}
+
+/// Writes `records` to the ledger at `path`, replacing it at once.
+fn save<'a>(
+    path: &Path,
+    records: impl Iterator<Item = &'a SettlementRecord>,
+) -> std::io::Result<()> {
+    let partial = path.with_extension("partial");
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+    for record in records {
+        serde_json::to_writer(&mut file, record)?;
+        file.write_all(b"\n")?;
+    }
+    file.into_inner()?.sync_all()?;
+    std::fs::rename(&partial, path)
+}
+
+/// Checks the finality and the cost of the settlements in `ledger` every
+/// [`SETTLEMENT_CHECK_INTERVAL`], forever.
+pub async fn watch_settlements<A>(ledger: &SettlementLedger, provider_map: &A)
+where
+    A: ProviderMap<Value = NetworkProvider>,
+{
+    let mut interval = tokio::time::interval(SETTLEMENT_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        ledger.check_settlements(provider_map).await;
+    }
+}
+
+/// Where the settlement history is persisted, from `SETTLEMENT_LEDGER_PATH`.
+pub fn settlement_ledger_path() -> PathBuf {
+    std::env::var(ENV_SETTLEMENT_LEDGER_PATH)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("settlements.ndjson"))
+}
+
+/// Format of a settlement export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Columns of a CSV export.
+const CSV_HEADER: &str =
+    "id,settledAt,network,status,payer,payTo,asset,amount,fee,transaction,resource,errorReason";
+
+/// The settlements of `ledger` matching `filter`, oldest first, written in `format`: one row per
+/// settlement in CSV, or one record per line in NDJSON.
+///
+/// Settlements are read [`EXPORT_PAGE`] at a time, so that the whole export is never held in
+/// memory.
+pub fn export_stream(
+    ledger: SettlementLedger,
+    filter: SettlementFilter,
+    format: ExportFormat,
+) -> impl futures::Stream<Item = String> {
+    let header = futures::stream::iter([export_header(format)]);
+    let pages = futures::stream::unfold(Some(None), move |after| {
+        let page = after.map(|after| ledger.matching_after(&filter, after, EXPORT_PAGE));
+        async move {
+            let page = page?;
+            let next = page
+                .last()
+                .filter(|_| page.len() == EXPORT_PAGE)
+                .map(|record| Some(record.id));
+            Some((export_rows(&page, format), next))
+        }
+    });
+    header.chain(pages)
+}
+
+/// The header line of an export in `format`, if it has one.
+fn export_header(format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Csv => format!("{CSV_HEADER}\n"),
+        ExportFormat::Ndjson => String::new(),
+    }
+}
+
+/// Writes `records` in `format`, without the header.
+fn export_rows(records: &[SettlementRecord], format: ExportFormat) -> String {
+    let mut output = String::new();
+    match format {
+        ExportFormat::Csv => {
+            for record in records {
+                let requirements = &record.request.payment_requirements;
+                let response = &record.response;
+                let row = [
+                    record.id.to_string(),
+                    record.settled_at.to_string(),
+                    response.network.to_string(),
+                    record.status().as_str().to_string(),
+                    response.payer.to_string(),
+                    requirements.pay_to.to_string(),
+                    requirements.asset.to_string(),
+                    record.paid_amount().to_string(),
+                    record.fee.map(|fee| fee.to_string()).unwrap_or_default(),
+                    response
+                        .transaction
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default(),
+                    requirements.resource.to_string(),
+                    response
+                        .error_reason
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default(),
+                ];
+                let row: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+                output.push_str(&row.join(","));
+                output.push('\n');
+            }
+        }
+        ExportFormat::Ndjson => {
+            for record in records {
+                if let Ok(line) = serde_json::to_string(record) {
+                    output.push_str(&line);
+                    output.push('\n');
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Quotes `field` if it holds a comma, a quote or a line break.
+///
+/// A field starting like a spreadsheet formula, with `=`, `+`, `-`, `@`, a tab or a carriage
+/// return, is prefixed with `'` so that it is read as text.
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}")
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FacilitatorErrorReason;
+    use crate::types::test_fixtures::settle_request;
+
+    fn settle_response(success: bool, byte: u8) -> SettleResponse {
+        SettleResponse {
+            success,
+            error_reason: (!success).then_some(FacilitatorErrorReason::TransactionFailed),
+            payer: MixedAddress::Solana(solana_sdk::pubkey::Pubkey::new_unique()),
+            transaction: Some(TransactionHash::Solana([byte; 64])),
+            network: Network::SolanaDevnet,
+        }
+    }
+
+    #[test]
+    fn test_ledger_watches_recent_settlements() {
+        let request = settle_request(1_000);
+        let ledger = SettlementLedger::new();
+        ledger.record(&request, &settle_response(true, 1), None, None);
+        ledger.record(&request, &settle_response(false, 2), None, None);
+
+        assert!(
+            ledger
+                .by_transaction(&TransactionHash::Solana([2; 64]))
+                .is_none()
+        );
+        let settled = ledger
+            .by_transaction(&TransactionHash::Solana([1; 64]))
+            .unwrap();
+        assert_eq!(settled.finality, Some(Finality::Unfinalized));
+
+        let now = settled.settled_at;
+        // Both transactions are of unknown cost, and the successful one is not final yet
+        assert_eq!(ledger.watched(now).len(), 2);
+        assert!(
+            ledger
+                .watched(now + SETTLEMENT_WATCH_WINDOW.as_secs() + 1)
+                .is_empty()
+        );
+
+        let failed = SettlementFilter {
+            status: Some(SettlementStatus::Failed),
+            ..SettlementFilter::default()
+        };
+        let ids: Vec<_> =
+            ledger.aggregate(&failed, |records| records.map(|record| record.id).collect());
+        assert_eq!(ids, vec![2]);
+        let later = SettlementFilter {
+            from: Some(now + 1),
+            ..SettlementFilter::default()
+        };
+        assert_eq!(ledger.aggregate(&later, |records| records.count()), 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_pages_newest_first_and_survives_restart() {
+        let path = std::env::temp_dir().join(format!(
+            "x402-settlement-ledger-{}.ndjson",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let request = settle_request(1_000);
+        let ledger = SettlementLedger::open(&path).unwrap();
+        for byte in 1..=5 {
+            ledger.record(&request, &settle_response(byte != 3, byte), None, None);
+        }
+        let mut finalized = ledger.get(1).unwrap();
+        finalized.finality = Some(Finality::Finalized);
+        ledger.append(&finalized);
+        ledger.write_records().insert(1, finalized);
+        ledger.flush().await;
+        drop(ledger);
+
+        // Compacted to the last record of every id
+        let reopened = SettlementLedger::open(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 5);
+        assert_eq!(reopened.get(1).unwrap().finality, Some(Finality::Finalized));
+        let settled = SettlementFilter {
+            status: Some(SettlementStatus::Settled),
+            ..SettlementFilter::default()
+        };
+        let first = reopened.search(&settled, None, 2);
+        let ids: Vec<_> = first.settlements.iter().map(|record| record.id).collect();
+        assert_eq!(ids, vec![5, 4]);
+        let second = reopened.search(&settled, first.next_cursor, 2);
+        let ids: Vec<_> = second.settlements.iter().map(|record| record.id).collect();
+        assert_eq!(ids, vec![2]);
+
+        let failed = SettlementFilter {
+            status: Some(SettlementStatus::Failed),
+            ..SettlementFilter::default()
+        };
+        let csv: String = export_stream(reopened.clone(), failed, ExportFormat::Csv)
+            .collect()
+            .await;
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("3,"));
+        assert!(lines[1].ends_with(",transaction_failed"));
+
+        reopened.record(&request, &settle_response(true, 6), None, None);
+        assert_eq!(reopened.get(6).unwrap().id, 6);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_export_streams_pages_and_neutralises_formulas() {
+        let ledger = SettlementLedger::new();
+        let request = settle_request(1_000);
+        for n in 0..=EXPORT_PAGE {
+            ledger.record(&request, &settle_response(true, n as u8), None, None);
+        }
+        let pages: Vec<String> = export_stream(
+            ledger.clone(),
+            SettlementFilter::default(),
+            ExportFormat::Ndjson,
+        )
+        .collect()
+        .await;
+        // No header, a full page, then the last settlement
+        let rows: Vec<_> = pages.iter().map(|page| page.lines().count()).collect();
+        assert_eq!(rows, vec![0, EXPORT_PAGE, 1]);
+
+        assert_eq!(csv_field("=1+2"), "'=1+2");
+        assert_eq!(csv_field("-1,2"), "\"'-1,2\"");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("transaction_failed"), "transaction_failed");
+    }
+
+    #[tokio::test]
+    async fn test_checks_update_records_in_place_and_persist_finality_changes() {
+        use alloy::primitives::B256;
+
+        let path = std::env::temp_dir().join(format!(
+            "x402-settlement-checks-{}.ndjson",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let asserter = alloy::transports::mock::Asserter::new();
+        let provider =
+            crate::chain::evm::EvmProvider::mocked(Network::MonadTestnet, asserter.clone());
+        let providers = crate::provider_cache::ProviderCache::from_iter([(
+            Network::MonadTestnet,
+            NetworkProvider::Evm(provider),
+        )]);
+        let ledger = SettlementLedger::open(&path).unwrap();
+        let response = SettleResponse {
+            transaction: Some(TransactionHash::Evm([0xaa; 32])),
+            network: Network::MonadTestnet,
+            ..settle_response(true, 1)
+        };
+        ledger.record(&settle_request(1_000), &response, None, None);
+        let included = B256::repeat_byte(0x01);
+        let receipt = serde_json::json!({
+            "type": "0x2",
+            "status": "0x1",
+            "cumulativeGasUsed": "0x5208",
+            "logs": [],
+            "logsBloom": alloy::primitives::Bloom::ZERO,
+            "transactionHash": B256::repeat_byte(0xaa),
+            "transactionIndex": "0x0",
+            "blockHash": included,
+            "blockNumber": "0xa",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x2",
+            "from": alloy::primitives::Address::repeat_byte(0x01),
+            "to": alloy::primitives::Address::repeat_byte(0x02),
+            "contractAddress": null,
+        });
+        let block = |number: u64, hash: B256| {
+            let mut block = alloy::rpc::types::Block::<alloy::rpc::types::Transaction>::default();
+            block.header.hash = hash;
+            block.header.inner.number = number;
+            block
+        };
+        let lines = |path: &Path| std::fs::read_to_string(path).unwrap().lines().count();
+
+        // The cost and the finality are looked up together: the cost is kept in memory only
+        asserter.push_success(&receipt);
+        asserter.push_success(&receipt);
+        asserter.push_success(&block(10, included));
+        asserter.push_success(&block(9, B256::repeat_byte(0x09)));
+        ledger.check_settlements(&providers).await;
+        ledger.flush().await;
+        let record = ledger.get(1).unwrap();
+        assert_eq!(record.gas_spent, Some(TokenAmount::from(42_000u64)));
+        assert_eq!(record.finality, Some(Finality::Unfinalized));
+        assert_eq!(lines(&path), 1);
+
+        // Only the finality is looked up again, and its change is appended
+        asserter.push_success(&receipt);
+        asserter.push_success(&block(10, included));
+        asserter.push_success(&block(12, B256::repeat_byte(0x0c)));
+        ledger.check_settlements(&providers).await;
+        ledger.flush().await;
+        assert!(asserter.read_q().is_empty());
+        assert_eq!(ledger.get(1).unwrap().finality, Some(Finality::Finalized));
+        assert!(ledger.watched(record.settled_at).is_empty());
+        assert_eq!(lines(&path), 2);
+        drop(ledger);
+        let reopened = SettlementLedger::open(&path).unwrap();
+        assert_eq!(
+            reopened.get(1).unwrap().gas_spent,
+            Some(TokenAmount::from(42_000u64))
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
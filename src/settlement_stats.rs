@@ -0,0 +1,326 @@
+//! Revenue and usage aggregates of the settlement history.
+//!
+//! [`daily`] totals the settlements of the
+//! [`SettlementLedger`](crate::settlement_ledger::SettlementLedger) per recipient, asset, network
+//! and UTC day: payment counts, settled amount, facilitator fees collected, failures by
+//! [`FacilitatorErrorReason`], and the native coin the facilitator spent on their transactions.
+//! [`failures`] gives the failure rates by reason over all of them.
+//!
+//! Amounts are the ones the payments transferred, as decoded from them, summed as [`TokenAmount`]s
+//! in the smallest unit. They are also given in whole units: with the decimals of the asset
+//! recorded along the settlements, those of the [`USDCDeployment`] for older records, and the
+//! native coin decimals for gas.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::network::{Network, USDCDeployment};
+use crate::settlement_ledger::{SettlementRecord, SettlementStatus};
+use crate::timestamp::UnixTimestamp;
+use crate::types::{FacilitatorErrorReason, MixedAddress, TokenAmount};
+
+/// Aggregates of the settlements paying one recipient, in one asset on one network, during one
+/// UTC day.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyStats {
+    /// As `YYYY-MM-DD`.
+    pub day: String,
+    pub pay_to: MixedAddress,
+    pub asset: MixedAddress,
+    pub network: Network,
+    /// Settlements answered, successful or not.
+    pub payments: u64,
+    /// Successful settlements, still in the chain.
+    pub settled: u64,
+    /// Successful settlements since reorganized out of the chain.
+    pub reorged: u64,
+    pub failed: u64,
+    /// Failed settlements by reason.
+    pub failures: BTreeMap<String, u64>,
+    /// Amount of the settled payments, in the smallest unit of the asset.
+    pub settled_amount: TokenAmount,
+    /// Amount of the settled payments in whole units, for assets of known decimals.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settled_amount_units: Option<String>,
+    /// Facilitator fees paid along the settled payments, in the smallest unit of the asset.
+    pub fees: TokenAmount,
+    /// Facilitator fees in whole units, for assets of known decimals.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees_units: Option<String>,
+    /// Decimals of the asset, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    /// Native coin spent on the settlement transactions, in wei or lamports, as far as known.
+    pub gas_spent: TokenAmount,
+    /// Native coin spent on the settlement transactions, in whole coins.
+    pub gas_spent_units: String,
+}
+
+/// Failures of one [`FacilitatorErrorReason`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureReasonStats {
+    pub reason: FacilitatorErrorReason,
+    pub count: u64,
+    /// Share of all payments failed for this reason, from 0 to 1.
+    pub rate: f64,
+}
+
+/// Failure rates of settlements, overall and by reason.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureStats {
+    pub payments: u64,
+    pub failed: u64,
+    /// Share of payments that failed, from 0 to 1.
+    pub failure_rate: f64,
+    /// Most frequent first.
+    pub reasons: Vec<FailureReasonStats>,
+}
+
+/// Totals `records` per UTC day, recipient, asset and network, in that order.
+pub fn daily<'a>(records: impl IntoIterator<Item = &'a SettlementRecord>) -> Vec<DailyStats> {
+    let mut groups: BTreeMap<(String, String, String, String), DailyStats> = BTreeMap::new();
+    for record in records {
+        let requirements = &record.request.payment_requirements;
+        let network = record.response.network;
+        let day = utc_day(record.settled_at);
+        let key = (
+            day.clone(),
+            requirements.pay_to.to_string(),
+            requirements.asset.to_string(),
+            network.to_string(),
+        );
+        let stats = groups.entry(key).or_insert_with(|| DailyStats {
+            day,
+            pay_to: requirements.pay_to.clone(),
+            asset: requirements.asset.clone(),
+            network,
+            payments: 0,
+            settled: 0,
+            reorged: 0,
+            failed: 0,
+            failures: BTreeMap::new(),
+            settled_amount: TokenAmount::from(0u64),
+            settled_amount_units: None,
+            fees: TokenAmount::from(0u64),
+            fees_units: None,
+            decimals: None,
+            gas_spent: TokenAmount::from(0u64),
+            gas_spent_units: String::new(),
+        });
+        stats.payments += 1;
+        stats.decimals = stats.decimals.or(record.decimals);
+        match record.status() {
+            SettlementStatus::Settled | SettlementStatus::Finalized => {
+                stats.settled += 1;
+                stats.settled_amount = stats.settled_amount.saturating_add(record.paid_amount());
+                if let Some(fee) = record.fee {
+                    stats.fees = stats.fees.saturating_add(fee);
+                }
+            }
+            SettlementStatus::Reorged => stats.reorged += 1,
+            SettlementStatus::Failed => {
+                stats.failed += 1;
+                *stats
+                    .failures
+                    .entry(failure_reason(record).to_string())
+                    .or_default() += 1;
+            }
+        }
+        if let Some(gas_spent) = record.gas_spent {
+            stats.gas_spent = stats.gas_spent.saturating_add(gas_spent);
+        }
+    }
+    groups
+        .into_values()
+        .map(|mut stats| {
+            let decimals = stats
+                .decimals
+                .or_else(|| token_decimals(stats.network, &stats.asset));
+            stats.decimals = decimals;
+            stats.settled_amount_units =
+                decimals.map(|decimals| stats.settled_amount.format_units(decimals));
+            stats.fees_units = decimals.map(|decimals| stats.fees.format_units(decimals));
+            stats.gas_spent_units = stats
+                .gas_spent
+                .format_units(stats.network.native_decimals());
+            stats
+        })
+        .collect()
+}
+
+/// Failure rates of `records`, by reason.
+pub fn failures<'a>(records: impl IntoIterator<Item = &'a SettlementRecord>) -> FailureStats {
+    let mut payments = 0;
+    let mut reasons: BTreeMap<String, (FacilitatorErrorReason, u64)> = BTreeMap::new();
+    for record in records {
+        payments += 1;
+        if record.status() == SettlementStatus::Failed {
+            let reason = failure_reason(record);
+            reasons.entry(reason.to_string()).or_insert((reason, 0)).1 += 1;
+        }
+    }
+    let rate = |count: u64| {
+        if payments == 0 {
+            0.0
+        } else {
+            count as f64 / payments as f64
+        }
+    };
+    let mut reasons: Vec<_> = reasons
+        .into_values()
+        .map(|(reason, count)| FailureReasonStats {
+            reason,
+            count,
+            rate: rate(count),
+        })
+        .collect();
+    reasons.sort_by_key(|reason| std::cmp::Reverse(reason.count));
+    let failed = reasons.iter().map(|reason| reason.count).sum();
+    FailureStats {
+        payments,
+        failed,
+        failure_rate: rate(failed),
+        reasons,
+    }
+}
+
+/// Why the settlement of `record` failed, [`FacilitatorErrorReason::UnexpectedSettleError`] if
+/// not told.
+fn failure_reason(record: &SettlementRecord) -> FacilitatorErrorReason {
+    record
+        .response
+        .error_reason
+        .clone()
+        .unwrap_or(FacilitatorErrorReason::UnexpectedSettleError)
+}
+
+/// Decimals of `asset` on `network`, if it is the known USDC deployment, for records without
+/// decimals.
+fn token_decimals(network: Network, asset: &MixedAddress) -> Option<u8> {
+    let deployment = USDCDeployment::by_network(network);
+    (deployment.address() == *asset).then_some(deployment.decimals)
+}
+
+/// The UTC date of `timestamp`, as `YYYY-MM-DD`.
+fn utc_day(timestamp: UnixTimestamp) -> String {
+    // Days since the epoch to a Gregorian date, after Howard Hinnant's `civil_from_days`
+    let days = timestamp.seconds_since_epoch() / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_fixtures::settle_request;
+    use crate::types::{SettleResponse, TransactionHash};
+
+    #[test]
+    fn test_utc_day_and_units() {
+        assert_eq!(utc_day(UnixTimestamp(0)), "1970-01-01");
+        assert_eq!(utc_day(UnixTimestamp(951_782_400)), "2000-02-29");
+        assert_eq!(utc_day(UnixTimestamp(1_700_000_000)), "2023-11-14");
+        assert_eq!(TokenAmount::from(1_500_000u64).format_units(6), "1.5");
+        assert_eq!(TokenAmount::from(25u64).format_units(6), "0.000025");
+        assert_eq!(TokenAmount::from(2_000_000u64).format_units(6), "2");
+    }
+
+    #[test]
+    fn test_daily_totals_per_recipient_and_day() {
+        let request = settle_request(1_500_000);
+        let record = |id: u64, success: bool, settled_at: u64| SettlementRecord {
+            id,
+            request: request.clone(),
+            response: SettleResponse {
+                success,
+                error_reason: (!success).then_some(FacilitatorErrorReason::TransactionFailed),
+                payer: MixedAddress::Solana(solana_sdk::pubkey::Pubkey::new_unique()),
+                transaction: Some(TransactionHash::Solana([id as u8; 64])),
+                network: Network::SolanaDevnet,
+            },
+            settled_at: UnixTimestamp(settled_at),
+            finality: None,
+            gas_spent: Some(TokenAmount::from(5_000u64)),
+            fee: success.then_some(TokenAmount::from(3_750u64)),
+            amount: None,
+            decimals: None,
+        };
+        // The second payment transferred less than the maximum required
+        let mut paid_less = record(2, true, 1_700_000_100);
+        paid_less.amount = Some(TokenAmount::from(1_000_000u64));
+        let records = [
+            record(1, true, 1_700_000_000),
+            paid_less,
+            record(3, false, 1_700_000_200),
+            record(4, true, 1_700_100_000),
+        ];
+
+        let stats = daily(&records);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].day, "2023-11-14");
+        assert_eq!(
+            (stats[0].payments, stats[0].settled, stats[0].failed),
+            (3, 2, 1)
+        );
+        assert_eq!(stats[0].failures.get("transaction_failed"), Some(&1));
+        assert_eq!(stats[0].settled_amount_units.as_deref(), Some("2.5"));
+        assert_eq!(stats[0].fees_units.as_deref(), Some("0.0075"));
+        assert_eq!(stats[0].gas_spent_units, "0.000015");
+        assert_eq!(stats[1].day, "2023-11-16");
+
+        let failures = failures(records.iter().rev());
+        assert_eq!(failures.failed, 1);
+        assert_eq!(failures.failure_rate, 0.25);
+        assert_eq!(
+            failures.reasons[0].reason,
+            FacilitatorErrorReason::TransactionFailed
+        );
+    }
+
+    #[test]
+    fn test_daily_units_use_the_recorded_decimals() {
+        let mut request = settle_request(2_000_000_000);
+        request.payment_requirements.asset =
+            MixedAddress::Solana(solana_sdk::pubkey::Pubkey::new_unique());
+        let record = |id: u64, decimals: Option<u8>| SettlementRecord {
+            id,
+            request: request.clone(),
+            response: SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: MixedAddress::Solana(solana_sdk::pubkey::Pubkey::new_unique()),
+                transaction: Some(TransactionHash::Solana([id as u8; 64])),
+                network: Network::SolanaDevnet,
+            },
+            settled_at: UnixTimestamp(1_700_000_000 + id),
+            finality: None,
+            gas_spent: None,
+            fee: None,
+            amount: Some(TokenAmount::from(2_000_000_000u64)),
+            decimals,
+        };
+
+        let stats = daily(&[record(1, None)]);
+        assert_eq!(stats[0].settled_amount, TokenAmount::from(2_000_000_000u64));
+        assert_eq!(stats[0].settled_amount_units, None);
+
+        let stats = daily(&[record(1, None), record(2, Some(9))]);
+        assert_eq!(stats[0].decimals, Some(9));
+        assert_eq!(stats[0].settled_amount_units.as_deref(), Some("4"));
+    }
+}
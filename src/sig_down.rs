@@ -1,15 +1,18 @@
+use std::sync::Arc;
 use tokio::signal::unix::SignalKind;
 use tokio::signal::unix::signal;
+use tokio::sync::Notify;
 use tokio_util::sync::CancellationToken;
 use tokio_util::task::TaskTracker;
 
-/// Handles graceful shutdown on SIGTERM and SIGINT signals.
+/// Handles graceful shutdown on SIGTERM and SIGINT signals, and reload requests on SIGHUP.
 ///
-/// Spawns a background task that listens for shutdown signals and triggers
-/// a cancellation token when received.
+/// Spawns a background task that listens for signals: a shutdown signal triggers a cancellation
+/// token, and SIGHUP notifies whoever waits on [`SigDown::reloads`].
 pub struct SigDown {
     _task_tracker: TaskTracker,
     cancellation_token: CancellationToken,
+    reloads: Arc<Notify>,
 }
 
 impl SigDown {
@@ -19,16 +22,26 @@ impl SigDown {
     pub fn try_new() -> Result<Self, std::io::Error> {
         let mut sigterm = signal(SignalKind::terminate())?;
         let mut sigint = signal(SignalKind::interrupt())?;
+        let mut sighup = signal(SignalKind::hangup())?;
         let inner = CancellationToken::new();
         let outer = inner.clone();
+        let reloads = Arc::new(Notify::new());
+        let reloads_inner = reloads.clone();
         let task_tracker = TaskTracker::new();
         task_tracker.spawn(async move {
-            tokio::select! {
-                _ = sigterm.recv() => {
-                    inner.cancel();
-                },
-                _ = sigint.recv() => {
-                    inner.cancel();
+            loop {
+                tokio::select! {
+                    _ = sigterm.recv() => {
+                        inner.cancel();
+                        break;
+                    },
+                    _ = sigint.recv() => {
+                        inner.cancel();
+                        break;
+                    },
+                    _ = sighup.recv() => {
+                        reloads_inner.notify_one();
+                    }
                 }
             }
         });
@@ -36,9 +49,15 @@ impl SigDown {
         Ok(Self {
             _task_tracker: task_tracker,
             cancellation_token: outer,
+            reloads,
         })
     }
 
+    /// Notified on SIGHUP. A signal received while nobody waits is kept for the next waiter.
+    pub fn reloads(&self) -> Arc<Notify> {
+        self.reloads.clone()
+    }
+
     /// Returns a clone of the cancellation token for distributing to subsystems.
     pub fn cancellation_token(&self) -> CancellationToken {
         self.cancellation_token.clone()
@@ -51,3 +70,24 @@ impl SigDown {
         self._task_tracker.wait().await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_sighup_notifies_reloads_without_shutdown() {
+        let sig_down = SigDown::try_new().unwrap();
+        let reloads = sig_down.reloads();
+        let status = std::process::Command::new("kill")
+            .args(["-HUP", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        tokio::time::timeout(Duration::from_secs(5), reloads.notified())
+            .await
+            .expect("SIGHUP notifies a reload");
+        assert!(!sig_down.cancellation_token().is_cancelled());
+    }
+}
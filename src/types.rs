@@ -20,13 +20,14 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use solana_sdk::bs58;
 use solana_sdk::pubkey::Pubkey;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Add, Div, Mul, Rem, Sub};
 use std::str::FromStr;
 use url::Url;
 
-use crate::network::Network;
+use crate::network::{Network, NetworkFamily};
 use crate::timestamp::UnixTimestamp;
 
 /// Represents the protocol version. Currently only version 1 is supported.
@@ -273,6 +274,18 @@ pub struct ExactEvmPayloadAuthorization {
 pub struct ExactEvmPayload {
     pub signature: EvmSignature,
     pub authorization: ExactEvmPayloadAuthorization,
+    /// Authorization of the facilitator fee, when the facilitator charges one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<Box<ExactEvmFeePayload>>,
+}
+
+/// A second ERC-3009 authorization from the payer, transferring the [`FacilitatorFee`] to its
+/// recipient. It is settled in the same transaction as the payment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExactEvmFeePayload {
+    pub signature: EvmSignature,
+    pub authorization: ExactEvmPayloadAuthorization,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -281,11 +294,21 @@ pub struct ExactSolanaPayload {
     pub transaction: String,
 }
 
+/// Payload for a native coin (MON/ETH) payment on an EVM network:
+/// a raw EIP-2718 transaction, signed by the buyer, transferring value to `payTo`.
+/// The facilitator verifies it and relays it as-is, so the buyer pays the gas.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExactEvmNativePayload {
+    pub raw_transaction: Bytes,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ExactPaymentPayload {
     Evm(ExactEvmPayload),
     Solana(ExactSolanaPayload),
+    EvmNative(ExactEvmNativePayload),
 }
 
 /// Describes a signed request to transfer a specific amount of funds on-chain.
@@ -333,6 +356,23 @@ impl TryFrom<Base64Bytes<'_>> for PaymentPayload {
 pub struct TokenAmount(pub U256);
 
 impl TokenAmount {
+    /// Formats `self` in whole units of a token with `decimals`: `1500000` with 6 decimals is `1.5`.
+    pub fn format_units(&self, decimals: u8) -> String {
+        let digits = self.0.to_string();
+        let decimals = decimals as usize;
+        if decimals == 0 {
+            return digits;
+        }
+        let digits = format!("{digits:0>width$}", width = decimals + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - decimals);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{whole}.{fraction}")
+        }
+    }
+
     /// Computes the absolute difference between `self` and `other`.
     ///
     /// Returns $\left\vert \mathtt{self} - \mathtt{other} \right\vert$.
@@ -722,30 +762,38 @@ impl Display for MixedAddress {
     }
 }
 
-impl<'de> Deserialize<'de> for MixedAddress {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
+impl FromStr for MixedAddress {
+    type Err = MixedAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         static OFFCHAIN_ADDRESS_REGEX: Lazy<Regex> = Lazy::new(|| {
             Regex::new(r"^[A-Za-z0-9][A-Za-z0-9-]{0,34}[A-Za-z0-9]$")
                 .expect("Invalid regex for offchain address")
         });
 
-        let s = String::deserialize(deserializer)?;
         // 1) EVM address (e.g., 0x... 20 bytes, hex)
-        if let Ok(addr) = EvmAddress::from_str(&s) {
+        if let Ok(addr) = EvmAddress::from_str(s) {
             return Ok(MixedAddress::Evm(addr));
         }
         // 2) Solana Pubkey (base58, 32 bytes)
-        if let Ok(pk) = Pubkey::from_str(&s) {
+        if let Ok(pk) = Pubkey::from_str(s) {
             return Ok(MixedAddress::Solana(pk));
         }
         // 3) Off-chain address by regex
-        if OFFCHAIN_ADDRESS_REGEX.is_match(&s) {
-            return Ok(MixedAddress::Offchain(s));
+        if OFFCHAIN_ADDRESS_REGEX.is_match(s) {
+            return Ok(MixedAddress::Offchain(s.to_string()));
         }
-        Err(serde::de::Error::custom("Invalid address format"))
+        Err(MixedAddressError::InvalidAddressFormat)
+    }
+}
+
+impl<'de> Deserialize<'de> for MixedAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        MixedAddress::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
 
@@ -798,6 +846,15 @@ impl<'de> Deserialize<'de> for TransactionHash {
     }
 }
 
+impl From<&TransactionHash> for NetworkFamily {
+    fn from(value: &TransactionHash) -> Self {
+        match value {
+            TransactionHash::Evm(_) => NetworkFamily::Evm,
+            TransactionHash::Solana(_) => NetworkFamily::Solana,
+        }
+    }
+}
+
 impl Serialize for TransactionHash {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         match self {
@@ -870,6 +927,12 @@ impl PaymentRequirements {
             network: self.network,
         }
     }
+
+    /// The facilitator fee passed in the `fee` of `extra`, if any and well-formed.
+    pub fn facilitator_fee(&self) -> Option<FacilitatorFee> {
+        let fee = self.extra.as_ref()?.get("fee")?.clone();
+        serde_json::from_value(fee).ok()
+    }
 }
 
 /// Wrapper for a payment payload and requirements sent by the client to a facilitator
@@ -902,32 +965,63 @@ impl VerifyRequest {
 /// to be used for settlement.
 pub type SettleRequest = VerifyRequest;
 
-#[derive(Debug, Serialize, Deserialize, thiserror::Error)]
-#[serde(untagged, rename_all = "camelCase")]
+/// Reason a payment was rejected or failed to settle.
+///
+/// Serialized as a plain string: the snake_case code for known reasons, or the message itself for
+/// [`FacilitatorErrorReason::FreeForm`]. Unknown codes deserialize to `FreeForm`.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
 pub enum FacilitatorErrorReason {
     /// Payer doesn't have sufficient funds.
     #[error("insufficient_funds")]
-    #[serde(rename = "insufficient_funds")]
     InsufficientFunds,
     /// The scheme in PaymentPayload didn't match expected (e.g., not 'exact'), or settlement failed.
     #[error("invalid_scheme")]
-    #[serde(rename = "invalid_scheme")]
     InvalidScheme,
     /// Network in PaymentPayload didn't match a facilitator's expected network.
     #[error("invalid_network")]
-    #[serde(rename = "invalid_network")]
     InvalidNetwork,
     /// Unexpected settle error
     #[error("unexpected_settle_error")]
-    #[serde(rename = "unexpected_settle_error")]
     UnexpectedSettleError,
+    /// The transaction's blockhash expired before it landed on-chain.
+    #[error("blockhash_expired")]
+    BlockhashExpired,
+    /// The settlement transaction landed on-chain but its execution failed.
+    #[error("transaction_failed")]
+    TransactionFailed,
+    /// The payment does not pay the facilitator fee as required.
+    #[error("invalid_fee")]
+    InvalidFee,
     #[error("{0}")]
     FreeForm(String),
 }
 
+impl Serialize for FacilitatorErrorReason {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FacilitatorErrorReason {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let reason = match s.as_str() {
+            "insufficient_funds" => FacilitatorErrorReason::InsufficientFunds,
+            "invalid_scheme" => FacilitatorErrorReason::InvalidScheme,
+            "invalid_network" => FacilitatorErrorReason::InvalidNetwork,
+            "unexpected_settle_error" => FacilitatorErrorReason::UnexpectedSettleError,
+            "blockhash_expired" => FacilitatorErrorReason::BlockhashExpired,
+            "transaction_failed" => FacilitatorErrorReason::TransactionFailed,
+            "invalid_fee" => FacilitatorErrorReason::InvalidFee,
+            _ => FacilitatorErrorReason::FreeForm(s),
+        };
+        Ok(reason)
+    }
+}
+
 /// Returned from a facilitator after attempting to settle a payment on-chain.
 /// Indicates success/failure, transaction hash, and payer identity.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SettleResponse {
     pub success: bool,
@@ -965,6 +1059,25 @@ impl TryInto<Base64Bytes<'static>> for SettleResponse {
     }
 }
 
+/// Additional facts established during verification, reported alongside a valid [`VerifyResponse`].
+///
+/// Present when the amount the recipient receives may differ from the transferred amount,
+/// e.g. for Solana Token-2022 mints with a transfer fee.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyDetails {
+    /// Amount moved out of the payer's account, in token base units.
+    pub amount: TokenAmount,
+    /// Amount credited to the `payTo` recipient after token-level fees.
+    pub net_amount: TokenAmount,
+    /// Token-level fee withheld by the token program, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer_fee: Option<TokenAmount>,
+    /// Token extensions present on the mint and accepted by the facilitator policy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub token_extensions: Vec<String>,
+}
+
 /// Result returned by a facilitator after verifying a [`PaymentPayload`] against the provided [`PaymentRequirements`].
 ///
 /// This response indicates whether the payment authorization is valid and identifies the payer. If invalid,
@@ -972,7 +1085,10 @@ impl TryInto<Base64Bytes<'static>> for SettleResponse {
 #[derive(Debug)]
 pub enum VerifyResponse {
     /// The payload matches the requirements and passes all checks.
-    Valid { payer: MixedAddress },
+    Valid {
+        payer: MixedAddress,
+        details: Option<VerifyDetails>,
+    },
     /// The payload was well-formed but failed verification due to the specified [`FacilitatorErrorReason`]
     Invalid {
         reason: FacilitatorErrorReason,
@@ -985,7 +1101,18 @@ impl VerifyResponse {
     ///
     /// Indicates that the provided payment payload has been validated against the payment requirements.
     pub fn valid(payer: MixedAddress) -> Self {
-        VerifyResponse::Valid { payer }
+        VerifyResponse::Valid {
+            payer,
+            details: None,
+        }
+    }
+
+    /// Constructs a successful verification response carrying additional [`VerifyDetails`].
+    pub fn valid_with_details(payer: MixedAddress, details: VerifyDetails) -> Self {
+        VerifyResponse::Valid {
+            payer,
+            details: Some(details),
+        }
     }
 
     /// Constructs a failed verification response with the given `payer` address and error `reason`.
@@ -1003,14 +1130,17 @@ impl Serialize for VerifyResponse {
         S: Serializer,
     {
         let mut s = match self {
-            VerifyResponse::Valid { .. } => serializer.serialize_struct("VerifyResponse", 2)?,
+            VerifyResponse::Valid { .. } => serializer.serialize_struct("VerifyResponse", 3)?,
             VerifyResponse::Invalid { .. } => serializer.serialize_struct("VerifyResponse", 3)?,
         };
 
         match self {
-            VerifyResponse::Valid { payer } => {
+            VerifyResponse::Valid { payer, details } => {
                 s.serialize_field("isValid", &true)?;
                 s.serialize_field("payer", payer)?;
+                if let Some(details) = details {
+                    s.serialize_field("details", details)?
+                }
             }
             VerifyResponse::Invalid { reason, payer } => {
                 s.serialize_field("isValid", &false)?;
@@ -1038,6 +1168,8 @@ impl<'de> Deserialize<'de> for VerifyResponse {
             payer: Option<MixedAddress>,
             #[serde(default)]
             invalid_reason: Option<FacilitatorErrorReason>,
+            #[serde(default)]
+            details: Option<VerifyDetails>,
         }
 
         let raw = Raw::deserialize(deserializer)?;
@@ -1047,7 +1179,10 @@ impl<'de> Deserialize<'de> for VerifyResponse {
                 None => Err(serde::de::Error::custom(
                     "`payer` must be present when `isValid` is true",
                 )),
-                Some(payer) => Ok(VerifyResponse::Valid { payer }),
+                Some(payer) => Ok(VerifyResponse::Valid {
+                    payer,
+                    details: raw.details,
+                }),
             },
             (false, Some(reason)) => Ok(VerifyResponse::Invalid {
                 payer: raw.payer,
@@ -1407,7 +1542,102 @@ pub struct SupportedPaymentKind {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SupportedPaymentKindExtra {
-    pub fee_payer: MixedAddress,
+    /// Solana account paying the transaction fees, which co-signs the payment transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_payer: Option<MixedAddress>,
+    /// Recommended Solana compute unit price in micro-lamports, from recent prioritization fees.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_compute_unit_price: Option<u64>,
+    /// Service fee the facilitator charges on every payment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FacilitatorFee>,
+}
+
+/// Service fee a facilitator charges on every payment it settles, to recover what it spends on
+/// settlement transactions.
+///
+/// The buyer pays it on top of `maxAmountRequired`, in the payment asset, with a second transfer
+/// to `recipient` in the same payment: a second `TransferChecked` (or system transfer for native
+/// SOL) at the end of a Solana transaction, an [`ExactEvmFeePayload`] on EVM networks. Sellers
+/// pass it to buyers in the `fee` of the requirements' `extra`. Buyer-signed native coin payments
+/// on EVM networks, where the buyer pays the gas, are not charged: EVM facilitators list the
+/// native coin in `exempt`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FacilitatorFee {
+    /// Receives the fee: the owner of the receiving token account on Solana.
+    pub recipient: MixedAddress,
+    /// Share of `maxAmountRequired` charged, in basis points, rounded up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bps: Option<u16>,
+    /// Flat fee by asset, in its smallest unit. The `*` entry applies to any other asset.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub flat: BTreeMap<String, TokenAmount>,
+    /// Assets whose payments are not charged, like the native coin of EVM networks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exempt: Vec<MixedAddress>,
+}
+
+impl FacilitatorFee {
+    /// Key of the [`FacilitatorFee::flat`] entry applying to any asset.
+    pub const ANY_ASSET: &str = "*";
+
+    /// Fee due on a payment of `amount` of `asset`: the flat fee of the asset plus the basis
+    /// points of the amount, or nothing for an exempt asset.
+    pub fn amount(&self, asset: &MixedAddress, amount: TokenAmount) -> TokenAmount {
+        if self.exempt.contains(asset) {
+            return TokenAmount::zero();
+        }
+        let flat = self
+            .flat
+            .get(&asset.to_string())
+            .or_else(|| self.flat.get(Self::ANY_ASSET))
+            .copied()
+            .unwrap_or(TokenAmount::zero());
+        let share = match self.bps {
+            Some(bps) => amount
+                .saturating_mul(TokenAmount::from(u64::from(bps)))
+                .div_ceil(TokenAmount::from(10_000u64)),
+            None => TokenAmount::zero(),
+        };
+        flat.saturating_add(share)
+    }
+
+    /// Parses flat fees as a comma-separated list of `<asset>=<amount>` entries, in the smallest
+    /// unit of the asset. An entry without an asset applies to any other asset.
+    pub fn parse_flat(value: &str) -> Result<BTreeMap<String, TokenAmount>, String> {
+        let mut flat = BTreeMap::new();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (asset, amount) = match entry.split_once('=') {
+                Some((asset, amount)) => {
+                    let asset = MixedAddress::from_str(asset.trim())
+                        .map_err(|e| format!("{e}: {asset}"))?;
+                    (asset.to_string(), amount.trim())
+                }
+                None => (Self::ANY_ASSET.to_string(), entry),
+            };
+            let amount =
+                U256::from_str(amount).map_err(|e| format!("invalid fee amount {amount}: {e}"))?;
+            if flat.insert(asset.clone(), TokenAmount(amount)).is_some() {
+                return Err(format!("duplicate flat fee for {asset}"));
+            }
+        }
+        Ok(flat)
+    }
+}
+
+/// Recommended Solana priority fee for a payment, returned by `GET /priority-fee/{network}`.
+///
+/// Clients put `compute_unit_price` into the `SetComputeUnitPrice` instruction of the payment
+/// transaction. It never exceeds `max_compute_unit_price`, the cap enforced by the facilitator.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityFeeResponse {
+    pub network: Network,
+    /// Recommended compute unit price in micro-lamports.
+    pub compute_unit_price: u64,
+    /// Maximum compute unit price in micro-lamports accepted by the facilitator.
+    pub max_compute_unit_price: u64,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -1450,6 +1680,20 @@ pub enum TransactionStatus {
     Failed,
     /// Transaction not found (may have been dropped or invalid hash).
     NotFound,
+    /// Transaction was settled, then reorganized out of the chain.
+    Reorged,
+}
+
+/// Whether a settled transaction may still be reverted by a chain reorganization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Finality {
+    /// Included in the chain, not final yet.
+    Unfinalized,
+    /// Included in a finalized block, or rooted slot on Solana.
+    Finalized,
+    /// No longer included in the chain since a reorganization.
+    Reorged,
 }
 
 /// Response containing the status and details of a transaction.
@@ -1460,8 +1704,9 @@ pub struct TransactionStatusResponse {
     pub transaction_hash: TransactionHash,
     /// Current status of the transaction.
     pub status: TransactionStatus,
-    /// Network where the transaction was submitted.
-    pub network: Network,
+    /// Network where the transaction was submitted; unknown if not found on any network.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
     /// Block number where the transaction was included (if confirmed).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub block_number: Option<u64>,
@@ -1472,3 +1717,67 @@ pub struct TransactionStatusResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
+
+/// Fixtures shared by the tests of several modules.
+#[cfg(test)]
+pub(crate) mod test_fixtures {
+    use super::SettleRequest;
+
+    /// A settle request of `max_amount_required` USDC units on Solana devnet, with a placeholder
+    /// transaction.
+    pub fn settle_request(max_amount_required: u64) -> SettleRequest {
+        serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "solana-devnet",
+                "payload": {"transaction": "AAAA"}
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "solana-devnet",
+                "maxAmountRequired": max_amount_required.to_string(),
+                "resource": "https://example.com/resource",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "11111111111111111111111111111111",
+                "maxTimeoutSeconds": 60,
+                "asset": "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"
+            }
+        }))
+        .unwrap()
+    }
+
+    /// Data of a Token-2022 mint with 6 decimals, taking a transfer fee of `basis_points`,
+    /// capped at `maximum_fee`.
+    pub fn transfer_fee_mint(basis_points: u16, maximum_fee: u64) -> Vec<u8> {
+        use spl_token_2022::extension::transfer_fee::{TransferFee, TransferFeeConfig};
+        use spl_token_2022::extension::{
+            BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        };
+        use spl_token_2022::state::Mint;
+
+        let len =
+            ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig])
+                .unwrap();
+        let mut data = vec![0; len];
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        let fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: maximum_fee.into(),
+            transfer_fee_basis_points: basis_points.into(),
+        };
+        let config = mint.init_extension::<TransferFeeConfig>(true).unwrap();
+        config.older_transfer_fee = fee;
+        config.newer_transfer_fee = fee;
+        mint.base = Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        data
+    }
+}